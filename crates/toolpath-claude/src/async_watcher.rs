@@ -2,16 +2,25 @@
 //!
 //! Provides event-driven file watching for Claude conversation JSONL files.
 //! Uses the `notify` crate for filesystem events with a periodic fallback poll.
+//!
+//! When Claude Code rotates a session to a new file (plan-mode exit, context
+//! overflow), the watcher reads the old file to the end, then follows the
+//! successor it finds in the same directory by its bridge entry, the way
+//! [`ConversationWatcher`](crate::ConversationWatcher) does.
 
+use crate::chain::{self, ChainIndex};
+use crate::checkpoint;
 use crate::error::Result;
 use crate::reader::ConversationReader;
 use crate::types::ConversationEntry;
 use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Mutex;
 use tokio::sync::mpsc;
+use toolpath_convo::{SessionCursor, WatcherCheckpoint};
 
 /// Configuration for the async watcher
 #[derive(Debug, Clone)]
@@ -31,17 +40,84 @@ impl Default for WatcherConfig {
     }
 }
 
+/// How far a watcher has read: the file it is on and the byte offset in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchPosition {
+    pub file_path: PathBuf,
+    pub offset: u64,
+}
+
+/// What a watcher started with [`AsyncConversationWatcher::start_events`]
+/// sends.
+#[derive(Debug, Clone)]
+pub enum WatchEvent {
+    /// New entries from the current file.
+    Entries(Vec<ConversationEntry>),
+    /// The session rotated to a new file; entries after this come from
+    /// `to`. Both are session IDs (file stems).
+    Rotated { from: String, to: String },
+}
+
 /// Async conversation watcher that uses filesystem events
 /// with a periodic fallback poll for reliability.
 pub struct AsyncConversationWatcher {
-    /// Path to the JSONL conversation file
-    file_path: PathBuf,
-    /// Current byte offset in the file
-    byte_offset: Arc<Mutex<u64>>,
+    state: Arc<Mutex<State>>,
     /// Configuration
     config: WatcherConfig,
 }
 
+struct State {
+    cursor: Cursor,
+    /// Successor files seen in the watched file's directory.
+    chain: ChainIndex,
+}
+
+#[derive(Debug, Clone)]
+struct Cursor {
+    position: WatchPosition,
+    /// Sessions rotated away from, oldest first. When non-empty, the
+    /// current file's bridge entries are skipped.
+    chain: Vec<String>,
+    /// The last entry read from the current file.
+    last_uuid: Option<String>,
+}
+
+impl Cursor {
+    fn checkpoint(&self) -> Result<WatcherCheckpoint> {
+        let path = &self.position.file_path;
+        let (project, session_id) = file_session(path);
+        let file = if path.exists() {
+            Some(checkpoint::identify(path, self.position.offset)?)
+        } else {
+            None
+        };
+        Ok(WatcherCheckpoint {
+            sessions: vec![SessionCursor {
+                project,
+                session_id,
+                chain: self.chain.clone(),
+                offset: self.position.offset,
+                last_uuid: self.last_uuid.clone(),
+                file,
+            }],
+        })
+    }
+}
+
+/// The (directory, session ID) a session log is checkpointed under.
+fn file_session(path: &Path) -> (String, String) {
+    let dir = path
+        .parent()
+        .map(|d| d.display().to_string())
+        .unwrap_or_default();
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default()
+        .to_string();
+    (dir, stem)
+}
+
 impl AsyncConversationWatcher {
     /// Create a new async watcher for a conversation file.
     ///
@@ -49,78 +125,156 @@ impl AsyncConversationWatcher {
     /// * `file_path` - Path to the JSONL conversation file
     /// * `config` - Optional configuration (uses defaults if None)
     pub fn new(file_path: PathBuf, config: Option<WatcherConfig>) -> Self {
-        Self {
-            file_path,
-            byte_offset: Arc::new(Mutex::new(0)),
-            config: config.unwrap_or_default(),
-        }
+        Self::with_offset(file_path, 0, config)
     }
 
     /// Create a watcher starting from a specific byte offset.
     /// Useful for resuming watching after a restart.
     pub fn with_offset(file_path: PathBuf, offset: u64, config: Option<WatcherConfig>) -> Self {
+        let cursor = Cursor {
+            position: WatchPosition { file_path, offset },
+            chain: Vec::new(),
+            last_uuid: None,
+        };
+        Self::from_cursor(cursor, config)
+    }
+
+    fn from_cursor(cursor: Cursor, config: Option<WatcherConfig>) -> Self {
         Self {
-            file_path,
-            byte_offset: Arc::new(Mutex::new(offset)),
+            state: Arc::new(Mutex::new(State {
+                cursor,
+                chain: ChainIndex::new(),
+            })),
             config: config.unwrap_or_default(),
         }
     }
 
+    /// Create a watcher for `file_path` resuming from `checkpoint`, which may
+    /// have followed the session to a later file. Without a cursor for the
+    /// session this is [`Self::new`].
+    ///
+    /// The cursor's project is the directory of the session file.
+    pub fn resume(
+        file_path: PathBuf,
+        checkpoint: &WatcherCheckpoint,
+        config: Option<WatcherConfig>,
+    ) -> Result<Self> {
+        let (dir, stem) = file_session(&file_path);
+        let Some(saved) = checkpoint.session(&dir, &stem) else {
+            return Ok(Self::new(file_path, config));
+        };
+        let file_path = file_path.with_file_name(format!("{}.jsonl", saved.session_id));
+        let offset = if file_path.exists() {
+            checkpoint::resume_offset(&file_path, saved)?
+        } else {
+            0
+        };
+        let cursor = Cursor {
+            position: WatchPosition { file_path, offset },
+            chain: saved.chain.clone(),
+            last_uuid: saved.last_uuid.clone(),
+        };
+        Ok(Self::from_cursor(cursor, config))
+    }
+
+    /// The watcher's position as a checkpoint, to save and
+    /// [`resume`](Self::resume) from.
+    pub async fn checkpoint(&self) -> Result<WatcherCheckpoint> {
+        self.state.lock().await.cursor.checkpoint()
+    }
+
     /// Get the current byte offset
     pub async fn offset(&self) -> u64 {
-        *self.byte_offset.lock().await
+        self.state.lock().await.cursor.position.offset
+    }
+
+    /// The current file and byte offset. The file changes as rotations are
+    /// followed.
+    pub async fn position(&self) -> WatchPosition {
+        self.state.lock().await.cursor.position.clone()
     }
 
     /// Check for new entries since last read (non-blocking poll).
-    /// Returns new entries and updates internal offset.
+    /// Returns new entries and updates internal offset. Rotations are
+    /// followed silently; use [`Self::poll_events`] to see them.
     pub async fn poll(&self) -> Result<Vec<ConversationEntry>> {
-        let mut offset = self.byte_offset.lock().await;
-        let (entries, new_offset) = ConversationReader::read_from_offset(&self.file_path, *offset)?;
-        *offset = new_offset;
-        Ok(entries)
+        Ok(self
+            .poll_events()
+            .await?
+            .into_iter()
+            .filter_map(|event| match event {
+                WatchEvent::Entries(entries) => Some(entries),
+                WatchEvent::Rotated { .. } => None,
+            })
+            .flatten()
+            .collect())
+    }
+
+    /// Check for new entries and rotations since last read.
+    pub async fn poll_events(&self) -> Result<Vec<WatchEvent>> {
+        let mut state = self.state.lock().await;
+        let pending = state.pending()?;
+        Ok(pending
+            .into_iter()
+            .map(|(event, cursor)| {
+                state.cursor = cursor;
+                event
+            })
+            .collect())
     }
 
     /// Start watching the file and send new entries to the provided channel.
     /// This spawns a background task that:
-    /// 1. Watches for filesystem modify events
+    /// 1. Watches for filesystem events in the file's directory
     /// 2. Polls periodically as a safety fallback
     ///
-    /// Returns a handle that can be used to stop the watcher.
+    /// Rotations are followed silently. Returns a handle that can be used
+    /// to stop the watcher.
     pub async fn start(self, tx: mpsc::Sender<Vec<ConversationEntry>>) -> Result<WatcherHandle> {
+        self.spawn(Sink::Entries(tx))
+    }
+
+    /// Like [`Self::start`], but sends [`WatchEvent`]s, so the receiver
+    /// learns when the session rotates to a new file.
+    pub async fn start_events(self, tx: mpsc::Sender<WatchEvent>) -> Result<WatcherHandle> {
+        self.spawn(Sink::Events(tx))
+    }
+
+    fn spawn(self, sink: Sink) -> Result<WatcherHandle> {
         let (stop_tx, mut stop_rx) = mpsc::channel::<()>(1);
-        let file_path = self.file_path.clone();
-        let byte_offset = self.byte_offset.clone();
+        let state = self.state.clone();
         let poll_interval = self.config.poll_interval;
         let debounce = self.config.debounce;
 
         // Channel for filesystem events
         let (event_tx, mut event_rx) = mpsc::channel::<()>(16);
 
-        // Set up the filesystem watcher
-        let event_tx_clone = event_tx.clone();
-        let file_path_clone = file_path.clone();
-
-        // Create the watcher in a blocking context since notify isn't async
+        // Create the watcher in a blocking context since notify isn't async.
+        // Any session log in the directory counts: a rotation shows up as a
+        // new file next to the current one.
         let watcher_result: std::result::Result<RecommendedWatcher, notify::Error> =
             notify::recommended_watcher(move |res: std::result::Result<Event, notify::Error>| {
-                if let Ok(event) = res {
-                    // Only trigger on modify events for our file
-                    if event.kind.is_modify() {
-                        for path in &event.paths {
-                            if path == &file_path_clone {
-                                let _ = event_tx_clone.blocking_send(());
-                                break;
-                            }
-                        }
-                    }
+                if let Ok(event) = res
+                    && (event.kind.is_modify() || event.kind.is_create())
+                    && event
+                        .paths
+                        .iter()
+                        .any(|p| p.extension().is_some_and(|ext| ext == "jsonl"))
+                {
+                    let _ = event_tx.try_send(());
                 }
             });
 
         // Watch the parent directory (notify works better with directories)
+        let dir = self
+            .state
+            .try_lock()
+            .ok()
+            .and_then(|s| s.cursor.position.file_path.parent().map(PathBuf::from));
         let mut watcher = match watcher_result {
             Ok(mut w) => {
-                if let Some(parent) = file_path.parent() {
-                    let _ = w.watch(parent, RecursiveMode::NonRecursive);
+                if let Some(dir) = &dir {
+                    let _ = w.watch(dir, RecursiveMode::NonRecursive);
                 }
                 Some(w)
             }
@@ -134,38 +288,32 @@ impl AsyncConversationWatcher {
         };
 
         // Spawn the main watch loop
-        let handle = tokio::spawn(async move {
+        let task_state = state.clone();
+        let task = tokio::spawn(async move {
             let mut poll_timer = tokio::time::interval(poll_interval);
             let mut last_event = std::time::Instant::now();
 
             loop {
-                tokio::select! {
+                let flow = tokio::select! {
                     // Stop signal
-                    _ = stop_rx.recv() => {
-                        break;
-                    }
+                    _ = stop_rx.recv() => Flow::Stop,
 
                     // Filesystem event (debounced)
                     Some(()) = event_rx.recv() => {
                         let now = std::time::Instant::now();
                         if now.duration_since(last_event) >= debounce {
                             last_event = now;
-                            if let Ok(entries) = read_new_entries(&file_path, &byte_offset).await
-                                && !entries.is_empty() && tx.send(entries).await.is_err()
-                            {
-                                break; // Receiver dropped
-                            }
+                            deliver(&task_state, &sink, &mut stop_rx).await
+                        } else {
+                            Flow::Continue
                         }
                     }
 
                     // Periodic fallback poll
-                    _ = poll_timer.tick() => {
-                        if let Ok(entries) = read_new_entries(&file_path, &byte_offset).await
-                            && !entries.is_empty() && tx.send(entries).await.is_err()
-                        {
-                            break; // Receiver dropped
-                        }
-                    }
+                    _ = poll_timer.tick() => deliver(&task_state, &sink, &mut stop_rx).await,
+                };
+                if flow == Flow::Stop {
+                    break;
                 }
             }
 
@@ -175,32 +323,147 @@ impl AsyncConversationWatcher {
 
         Ok(WatcherHandle {
             stop_tx,
-            _task: handle,
+            task,
+            state,
         })
     }
 }
 
-/// Read new entries from offset and update the offset
-async fn read_new_entries(
-    file_path: &PathBuf,
-    byte_offset: &Arc<Mutex<u64>>,
-) -> Result<Vec<ConversationEntry>> {
-    let mut offset = byte_offset.lock().await;
-    let (entries, new_offset) = ConversationReader::read_from_offset(file_path, *offset)?;
-    *offset = new_offset;
-    Ok(entries)
+impl State {
+    /// Events since the cursor, each with the cursor just after it. Reads
+    /// the current file to the end; only then follows a rotation, and keeps
+    /// following until a file has new entries.
+    fn pending(&mut self) -> Result<Vec<(WatchEvent, Cursor)>> {
+        let mut cursor = self.cursor.clone();
+        let mut events = Vec::new();
+        let mut visited = HashSet::new();
+        loop {
+            let position = &mut cursor.position;
+            let (entries, end) =
+                ConversationReader::read_from_offset(&position.file_path, position.offset)?;
+            position.offset = end;
+            if let Some(last) = entries.last() {
+                cursor.last_uuid = Some(last.uuid.clone());
+            }
+            let Some(stem) = cursor
+                .position
+                .file_path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .map(str::to_string)
+            else {
+                break;
+            };
+            let entries: Vec<ConversationEntry> = entries
+                .into_iter()
+                .filter(|e| cursor.chain.is_empty() || !chain::is_bridge_entry(e, &stem))
+                .collect();
+            if !entries.is_empty() {
+                events.push((WatchEvent::Entries(entries), cursor.clone()));
+                break;
+            }
+
+            // Caught up with this file — has the session moved on?
+            let Some(dir) = cursor.position.file_path.parent().map(PathBuf::from) else {
+                break;
+            };
+            self.chain.refresh_dir(&dir)?;
+            let Some(next) = self.chain.successor_of(&stem).map(str::to_string) else {
+                break;
+            };
+            if !visited.insert(stem.clone()) {
+                break; // cycle
+            }
+            let mut chain = std::mem::take(&mut cursor.chain);
+            chain.push(stem.clone());
+            cursor = Cursor {
+                position: WatchPosition {
+                    file_path: dir.join(format!("{}.jsonl", next)),
+                    offset: 0,
+                },
+                chain,
+                last_uuid: None,
+            };
+            events.push((
+                WatchEvent::Rotated {
+                    from: stem,
+                    to: next,
+                },
+                cursor.clone(),
+            ));
+        }
+        Ok(events)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Flow {
+    Continue,
+    Stop,
+}
+
+/// Where a started watcher sends what it reads.
+enum Sink {
+    Entries(mpsc::Sender<Vec<ConversationEntry>>),
+    Events(mpsc::Sender<WatchEvent>),
+}
+
+impl Sink {
+    /// Send `event`, if this sink takes it. `false` once the receiver is gone.
+    async fn send(&self, event: WatchEvent) -> bool {
+        match (self, event) {
+            (Sink::Entries(tx), WatchEvent::Entries(entries)) => tx.send(entries).await.is_ok(),
+            (Sink::Entries(_), WatchEvent::Rotated { .. }) => true,
+            (Sink::Events(tx), event) => tx.send(event).await.is_ok(),
+        }
+    }
+}
+
+/// Send what's new, advancing the cursor past each event as it is
+/// delivered. A stop signal that arrives while a send is blocked leaves the
+/// cursor after the last delivered event, even midway through a rotation.
+async fn deliver(state: &Mutex<State>, sink: &Sink, stop_rx: &mut mpsc::Receiver<()>) -> Flow {
+    let pending = match state.lock().await.pending() {
+        Ok(pending) => pending,
+        Err(_) => return Flow::Continue,
+    };
+    for (event, cursor) in pending {
+        tokio::select! {
+            biased;
+            _ = stop_rx.recv() => return Flow::Stop,
+            delivered = sink.send(event) => {
+                if !delivered {
+                    return Flow::Stop; // Receiver dropped
+                }
+            }
+        }
+        state.lock().await.cursor = cursor;
+    }
+    Flow::Continue
 }
 
 /// Handle to control a running watcher
 pub struct WatcherHandle {
     stop_tx: mpsc::Sender<()>,
-    _task: tokio::task::JoinHandle<()>,
+    task: tokio::task::JoinHandle<()>,
+    state: Arc<Mutex<State>>,
 }
 
 impl WatcherHandle {
-    /// Stop the watcher
-    pub async fn stop(self) {
+    /// Stop the watcher and wait for it to finish. Returns the position
+    /// after the last event delivered, from which
+    /// [`AsyncConversationWatcher::with_offset`] resumes without losing or
+    /// repeating entries.
+    pub async fn stop(self) -> WatchPosition {
         let _ = self.stop_tx.send(()).await;
+        let _ = self.task.await;
+        self.state.lock().await.cursor.position.clone()
+    }
+
+    /// The position after the last event delivered, as a checkpoint. Save
+    /// it while the watcher runs to survive a crash.
+    pub async fn checkpoint(&self) -> Result<WatcherCheckpoint> {
+        self.state.lock().await.cursor.checkpoint()
     }
 }
 
@@ -308,4 +571,167 @@ mod tests {
         assert_eq!(entries.len(), 1);
         assert_eq!(entries[0].uuid, "456");
     }
+
+    fn entry(uuid: &str, session: &str) -> String {
+        format!(
+            r#"{{"type":"user","uuid":"{uuid}","timestamp":"2024-01-01T00:00:00Z","sessionId":"{session}","message":{{"role":"user","content":"Hello"}}}}"#
+        )
+    }
+
+    /// A project dir where `session-a` rotated to `session-b`, whose first
+    /// entry is the bridge from `session-a`.
+    fn rotated_dir() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("session-a.jsonl"),
+            format!(
+                "{}\n{}\n",
+                entry("a1", "session-a"),
+                entry("a2", "session-a")
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("session-b.jsonl"),
+            format!(
+                "{}\n{}\n",
+                entry("bridge", "session-a"),
+                entry("b1", "session-b")
+            ),
+        )
+        .unwrap();
+        dir
+    }
+
+    fn uuids(entries: &[ConversationEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.uuid.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_poll_events_follows_rotation() {
+        let dir = rotated_dir();
+        let watcher = AsyncConversationWatcher::new(dir.path().join("session-a.jsonl"), None);
+
+        let events = watcher.poll_events().await.unwrap();
+        assert!(matches!(&events[..], [WatchEvent::Entries(e)] if uuids(e) == ["a1", "a2"]));
+
+        let events = watcher.poll_events().await.unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            &events[0],
+            WatchEvent::Rotated { from, to } if from == "session-a" && to == "session-b"
+        ));
+        assert!(matches!(&events[1], WatchEvent::Entries(e) if uuids(e) == ["b1"]));
+
+        let position = watcher.position().await;
+        assert_eq!(position.file_path, dir.path().join("session-b.jsonl"));
+        assert!(watcher.poll_events().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_poll_skips_rotation_events() {
+        let dir = rotated_dir();
+        let watcher = AsyncConversationWatcher::new(dir.path().join("session-a.jsonl"), None);
+        let _ = watcher.poll().await.unwrap();
+        assert_eq!(uuids(&watcher.poll().await.unwrap()), ["b1"]);
+    }
+
+    #[tokio::test]
+    async fn test_start_events_reports_rotation() {
+        let dir = rotated_dir();
+        let config = WatcherConfig {
+            poll_interval: Duration::from_millis(20),
+            debounce: Duration::from_millis(10),
+        };
+        let watcher =
+            AsyncConversationWatcher::new(dir.path().join("session-a.jsonl"), Some(config));
+        let (tx, mut rx) = mpsc::channel(16);
+        let handle = watcher.start_events(tx).await.unwrap();
+
+        let mut received = Vec::new();
+        while received.len() < 3 {
+            let event = timeout(Duration::from_secs(1), rx.recv())
+                .await
+                .expect("timeout")
+                .expect("channel closed");
+            received.push(event);
+        }
+        assert!(matches!(&received[1], WatchEvent::Rotated { to, .. } if to == "session-b"));
+        assert!(matches!(&received[2], WatchEvent::Entries(e) if uuids(e) == ["b1"]));
+
+        let position = handle.stop().await;
+        assert_eq!(position.file_path, dir.path().join("session-b.jsonl"));
+    }
+
+    #[tokio::test]
+    async fn test_stop_mid_rotation_resumes_cleanly() {
+        let dir = rotated_dir();
+        let config = WatcherConfig {
+            poll_interval: Duration::from_millis(20),
+            debounce: Duration::from_millis(10),
+        };
+        let watcher =
+            AsyncConversationWatcher::new(dir.path().join("session-a.jsonl"), Some(config));
+        // Room for one event: the rotation blocks behind the first batch.
+        let (tx, mut rx) = mpsc::channel(1);
+        let handle = watcher.start_events(tx).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let position = handle.stop().await;
+        assert_eq!(position.file_path, dir.path().join("session-a.jsonl"));
+        assert!(matches!(rx.recv().await, Some(WatchEvent::Entries(_))));
+        assert!(rx.recv().await.is_none());
+
+        let resumed =
+            AsyncConversationWatcher::with_offset(position.file_path, position.offset, None);
+        let events = resumed.poll_events().await.unwrap();
+        assert!(matches!(&events[0], WatchEvent::Rotated { .. }));
+        assert!(matches!(&events[1], WatchEvent::Entries(e) if uuids(e) == ["b1"]));
+    }
+
+    #[tokio::test]
+    async fn test_resume_from_checkpoint() {
+        let dir = rotated_dir();
+        let head = dir.path().join("session-a.jsonl");
+        let watcher = AsyncConversationWatcher::new(head.clone(), None);
+        watcher.poll().await.unwrap();
+        watcher.poll().await.unwrap();
+        let checkpoint = watcher.checkpoint().await.unwrap();
+        assert_eq!(checkpoint.sessions[0].session_id, "session-b");
+        assert_eq!(checkpoint.sessions[0].last_uuid.as_deref(), Some("b1"));
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(dir.path().join("session-b.jsonl"))
+            .unwrap();
+        writeln!(file, "{}", entry("b2", "session-b")).unwrap();
+
+        let resumed = AsyncConversationWatcher::resume(head, &checkpoint, None).unwrap();
+        assert_eq!(uuids(&resumed.poll().await.unwrap()), ["b2"]);
+    }
+
+    #[tokio::test]
+    async fn test_resume_after_rewrite() {
+        let dir = rotated_dir();
+        let path = dir.path().join("session-a.jsonl");
+        let watcher = AsyncConversationWatcher::new(path.clone(), None);
+        watcher.poll().await.unwrap();
+        let checkpoint = watcher.checkpoint().await.unwrap();
+
+        // Rewritten with a new first line: the old offset would land
+        // mid-line, so resume after the last entry read instead
+        std::fs::write(
+            &path,
+            format!(
+                "{}\n{}\n{}\n{}\n",
+                entry("a0-with-a-longer-id", "session-a"),
+                entry("a1", "session-a"),
+                entry("a2", "session-a"),
+                entry("a3", "session-a"),
+            ),
+        )
+        .unwrap();
+        let resumed = AsyncConversationWatcher::resume(path, &checkpoint, None).unwrap();
+        assert_eq!(uuids(&resumed.poll().await.unwrap()), ["a3"]);
+    }
 }
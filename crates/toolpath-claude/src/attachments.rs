@@ -0,0 +1,148 @@
+//! Images and documents attached to a conversation.
+//!
+//! Attachments are identified by the SHA-256 of their decoded bytes. Derived
+//! paths record each one as an `attachment://sha256/<hex>` artifact; the
+//! bytes themselves stay out of the document and can be written out with
+//! [`extract`].
+
+use crate::error::Result;
+use crate::types::{Conversation, MediaSource};
+use base64::Engine;
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// The artifact key for an attachment with the given `sha256:<hex>` digest.
+pub fn artifact_uri(sha256: &str) -> String {
+    format!(
+        "attachment://sha256/{}",
+        sha256.strip_prefix("sha256:").unwrap_or(sha256)
+    )
+}
+
+/// File extension for a media type, for naming extracted attachments.
+pub fn extension(media_type: Option<&str>) -> &'static str {
+    match media_type.unwrap_or("") {
+        "image/png" => "png",
+        "image/jpeg" | "image/jpg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "application/pdf" => "pdf",
+        "text/plain" => "txt",
+        "text/markdown" => "md",
+        "text/html" => "html",
+        "application/json" => "json",
+        _ => "bin",
+    }
+}
+
+/// Write the bytes of every inline image and document in `conversation` to
+/// `dir` as `<hex>.<ext>`, named by SHA-256 so repeats are written once.
+/// Returns the files, in order of first appearance; existing files are left
+/// as they are.
+pub fn extract(conversation: &Conversation, dir: &Path) -> Result<Vec<PathBuf>> {
+    let sources = conversation
+        .entries
+        .iter()
+        .filter_map(|e| e.message.as_ref())
+        .flat_map(|m| m.media().into_iter().map(|media| media.source.clone()));
+    write_all(sources, dir)
+}
+
+/// [`extract`] for any sources, such as images pasted into prompts.
+pub fn write_all(
+    sources: impl IntoIterator<Item = MediaSource>,
+    dir: &Path,
+) -> Result<Vec<PathBuf>> {
+    let mut seen = HashSet::new();
+    let mut written = Vec::new();
+    for source in sources {
+        let Some(bytes) = source.bytes() else {
+            continue;
+        };
+        let digest = crate::digest::sha256(&bytes);
+        if !seen.insert(digest.clone()) {
+            continue;
+        }
+        let hex = digest.trim_start_matches("sha256:");
+        let file = dir.join(format!(
+            "{}.{}",
+            hex,
+            extension(source.media_type.as_deref())
+        ));
+        if !file.exists() {
+            std::fs::create_dir_all(dir)?;
+            crate::io::write_atomic(&file, &bytes)?;
+        }
+        written.push(file);
+    }
+    Ok(written)
+}
+
+/// Decode standard base64, with or without padding. Whitespace is ignored;
+/// `None` for any other character outside the alphabet.
+pub(crate) fn decode_base64(data: &str) -> Option<Vec<u8>> {
+    const ENGINE: GeneralPurpose = GeneralPurpose::new(
+        &alphabet::STANDARD,
+        GeneralPurposeConfig::new()
+            .with_decode_padding_mode(DecodePaddingMode::Indifferent)
+            .with_decode_allow_trailing_bits(true),
+    );
+    let data: Cow<[u8]> = if data.bytes().any(|b| b.is_ascii_whitespace()) {
+        Cow::Owned(data.bytes().filter(|b| !b.is_ascii_whitespace()).collect())
+    } else {
+        Cow::Borrowed(data.as_bytes())
+    };
+    ENGINE.decode(data).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reader::ConversationReader;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_decode_base64() {
+        assert_eq!(decode_base64("").unwrap(), b"");
+        assert_eq!(decode_base64("Zg==").unwrap(), b"f");
+        assert_eq!(decode_base64("Zm8=").unwrap(), b"fo");
+        assert_eq!(decode_base64("Zm9v").unwrap(), b"foo");
+        assert_eq!(decode_base64("Zm9v\nYmFy").unwrap(), b"foobar");
+        assert_eq!(decode_base64("Zm9vYg").unwrap(), b"foob");
+        assert!(decode_base64("Zm9v!").is_none());
+    }
+
+    #[test]
+    fn test_artifact_uri() {
+        assert_eq!(artifact_uri("sha256:abc"), "attachment://sha256/abc");
+    }
+
+    #[test]
+    fn test_extract() {
+        let temp = TempDir::new().unwrap();
+        let log = temp.path().join("s.jsonl");
+        let image = |uuid: &str| {
+            format!(
+                r#"{{"type":"user","uuid":"{uuid}","timestamp":"2024-01-01T00:00:00Z","sessionId":"s","message":{{"role":"user","content":[{{"type":"image","source":{{"type":"base64","media_type":"image/png","data":"Zm9v"}}}},{{"type":"image","source":{{"type":"url","url":"https://example.com/a.png"}}}}]}}}}"#
+            )
+        };
+        fs::write(&log, format!("{}\n{}\n", image("u1"), image("u2"))).unwrap();
+        let convo = ConversationReader::read_conversation(&log).unwrap();
+
+        let dir = temp.path().join("out");
+        let files = extract(&convo, &dir).unwrap();
+        let digest = crate::digest::sha256(b"foo");
+        assert_eq!(
+            files,
+            vec![dir.join(format!("{}.png", digest.trim_start_matches("sha256:")))]
+        );
+        assert_eq!(fs::read(&files[0]).unwrap(), b"foo");
+        // Written through a temporary file that doesn't stay behind.
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    }
+}
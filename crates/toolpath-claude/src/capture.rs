@@ -0,0 +1,268 @@
+//! How much of a conversation's text derived documents record.
+//!
+//! Each kind of text — what was said, thinking, tool output, compaction
+//! summaries — has its own [`Capture`] in a [`CapturePolicy`]. Text that is
+//! shortened or left out is identified by its digest, under
+//! `<field>_sha256`; with a [`BlobDir`], the full text is also written there
+//! as `<hex>.txt`, so a compact document can be shared while the complete
+//! text is archived alongside it.
+
+use crate::derive::truncate;
+use crate::digest::sha256;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// How one kind of text is recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capture {
+    /// The text as it is.
+    Full,
+    /// At most this many characters, ending in `...` when shortened and
+    /// there is room for it.
+    Truncate(usize),
+    /// Only the text's digest.
+    Digest,
+    /// Nothing at all.
+    Omit,
+}
+
+impl std::str::FromStr for Capture {
+    type Err = String;
+
+    /// `full`, `truncate:<n>`, `digest` or `omit`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(Capture::Full),
+            "digest" => Ok(Capture::Digest),
+            "omit" => Ok(Capture::Omit),
+            _ => s
+                .strip_prefix("truncate:")
+                .and_then(|n| n.parse().ok())
+                .filter(|&n| n >= 3)
+                .map(Capture::Truncate)
+                .ok_or_else(|| {
+                    format!(
+                        "invalid capture {:?}: expected full, truncate:<n> (n >= 3), digest or omit",
+                        s
+                    )
+                }),
+        }
+    }
+}
+
+/// A [`Capture`] for each kind of text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapturePolicy {
+    /// Turn text, under `text` in `conversation.append`.
+    pub text: Capture,
+    /// Thinking blocks, under `thinking` in `conversation.append`.
+    pub thinking: Capture,
+    /// Tool output: `output` in `mcp.call`, `stdout` and `stderr` in
+    /// `shell.exec`, and `feedback` in `plan.reject`.
+    pub tool_output: Capture,
+    /// Context compaction summaries, under `summary` in `conversation.compact`.
+    pub summary: Capture,
+}
+
+impl Default for CapturePolicy {
+    fn default() -> Self {
+        Self {
+            text: Capture::Truncate(2000),
+            thinking: Capture::Omit,
+            tool_output: Capture::Truncate(2000),
+            summary: Capture::Full,
+        }
+    }
+}
+
+impl CapturePolicy {
+    /// Every kind of text in full, for an archival document.
+    pub fn full() -> Self {
+        Self {
+            text: Capture::Full,
+            thinking: Capture::Full,
+            tool_output: Capture::Full,
+            summary: Capture::Full,
+        }
+    }
+
+    /// Set the capture for a field by name: `text`, `thinking`,
+    /// `tool_output` or `summary`.
+    pub fn set(&mut self, field: &str, capture: Capture) -> Result<(), String> {
+        let slot = match field {
+            "text" => &mut self.text,
+            "thinking" => &mut self.thinking,
+            "tool_output" => &mut self.tool_output,
+            "summary" => &mut self.summary,
+            _ => {
+                return Err(format!(
+                    "unknown capture field {:?}: expected text, thinking, tool_output or summary",
+                    field
+                ));
+            }
+        };
+        *slot = capture;
+        Ok(())
+    }
+}
+
+/// A sidecar directory for the full text of fields that a [`CapturePolicy`]
+/// shortens or reduces to a digest, each written as `<hex>.txt`.
+///
+/// Derivation can't fail, so write errors are kept for [`BlobDir::take_error`]
+/// rather than returned.
+#[derive(Debug)]
+pub struct BlobDir {
+    dir: PathBuf,
+    error: Mutex<Option<std::io::Error>>,
+}
+
+impl BlobDir {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            error: Mutex::new(None),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The file holding the text with the given `sha256:<hex>` digest.
+    pub fn file(&self, sha256: &str) -> PathBuf {
+        self.dir.join(format!(
+            "{}.txt",
+            sha256.strip_prefix("sha256:").unwrap_or(sha256)
+        ))
+    }
+
+    /// The first error writing a blob since the last call, if any.
+    pub fn take_error(&self) -> Option<std::io::Error> {
+        self.error.lock().ok()?.take()
+    }
+
+    fn put(&self, digest: &str, text: &str) {
+        let file = self.file(digest);
+        if file.exists() {
+            return;
+        }
+        let written = std::fs::create_dir_all(&self.dir).and_then(|_| std::fs::write(&file, text));
+        if let Err(e) = written
+            && let Ok(mut error) = self.error.lock()
+        {
+            error.get_or_insert(e);
+        }
+    }
+}
+
+/// Record `text` under `field` in `extra` as `capture` says, adding
+/// `<field>_sha256` (and writing to `blobs`) when it isn't recorded whole.
+pub(crate) fn record(
+    extra: &mut HashMap<String, Value>,
+    field: &str,
+    text: &str,
+    capture: Capture,
+    blobs: Option<&BlobDir>,
+) {
+    let kept = match capture {
+        Capture::Omit => return,
+        Capture::Full => {
+            extra.insert(field.to_string(), json!(text));
+            return;
+        }
+        Capture::Truncate(max) => {
+            let kept = truncate(text, max);
+            extra.insert(field.to_string(), json!(kept));
+            kept
+        }
+        Capture::Digest => String::new(),
+    };
+    if kept == text {
+        return;
+    }
+    let digest = sha256(text.as_bytes());
+    if let Some(blobs) = blobs {
+        blobs.put(&digest, text);
+    }
+    extra.insert(format!("{}_sha256", field), json!(digest));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_capture() {
+        assert_eq!("full".parse(), Ok(Capture::Full));
+        assert_eq!("truncate:500".parse(), Ok(Capture::Truncate(500)));
+        assert_eq!("digest".parse(), Ok(Capture::Digest));
+        assert_eq!("omit".parse(), Ok(Capture::Omit));
+        assert!("truncate:2".parse::<Capture>().is_err());
+        assert!("truncate".parse::<Capture>().is_err());
+        assert!("some".parse::<Capture>().is_err());
+    }
+
+    #[test]
+    fn test_policy_set() {
+        let mut policy = CapturePolicy::default();
+        policy.set("thinking", Capture::Full).unwrap();
+        assert_eq!(policy.thinking, Capture::Full);
+        assert!(policy.set("output", Capture::Full).is_err());
+    }
+
+    #[test]
+    fn test_record() {
+        let text = "hello world";
+        let digest = sha256(text.as_bytes());
+        let record = |capture| {
+            let mut extra = HashMap::new();
+            super::record(&mut extra, "text", text, capture, None);
+            extra
+        };
+
+        assert_eq!(
+            record(Capture::Full),
+            HashMap::from([("text".into(), json!(text))])
+        );
+        assert!(record(Capture::Omit).is_empty());
+        assert_eq!(
+            record(Capture::Digest),
+            HashMap::from([("text_sha256".into(), json!(digest))])
+        );
+        assert_eq!(
+            record(Capture::Truncate(8)),
+            HashMap::from([
+                ("text".into(), json!("hello...")),
+                ("text_sha256".into(), json!(digest)),
+            ])
+        );
+        assert_eq!(
+            record(Capture::Truncate(100)),
+            HashMap::from([("text".into(), json!(text))])
+        );
+    }
+
+    #[test]
+    fn test_blobs() {
+        let temp = TempDir::new().unwrap();
+        let blobs = BlobDir::new(temp.path().join("blobs"));
+        let mut extra = HashMap::new();
+        record(
+            &mut extra,
+            "text",
+            "hello world",
+            Capture::Digest,
+            Some(&blobs),
+        );
+        record(&mut extra, "summary", "short", Capture::Full, Some(&blobs));
+
+        let file = blobs.file(extra["text_sha256"].as_str().unwrap());
+        assert_eq!(std::fs::read_to_string(file).unwrap(), "hello world");
+        assert_eq!(std::fs::read_dir(blobs.dir()).unwrap().count(), 1);
+        assert!(blobs.take_error().is_none());
+    }
+}
@@ -7,10 +7,11 @@
 //! conversation as a single logical session.
 
 use crate::error::Result;
-use crate::paths::PathResolver;
+use crate::paths::{self, PathResolver};
 use crate::reader::ConversationReader;
 use crate::types::ConversationEntry;
 use std::collections::{HashMap, HashSet};
+use std::path::Path;
 
 /// Resolve a chain using a pre-built succession map.
 ///
@@ -82,7 +83,12 @@ impl ChainIndex {
     /// Scan for new files and classify them. Files already in
     /// `known_files` are skipped entirely.
     pub(crate) fn refresh(&mut self, resolver: &PathResolver, project_path: &str) -> Result<()> {
-        let sessions = resolver.list_conversations(project_path)?;
+        self.refresh_dir(&resolver.project_dir(project_path)?)
+    }
+
+    /// [`Self::refresh`] for the session logs in `dir`.
+    pub(crate) fn refresh_dir(&mut self, dir: &Path) -> Result<()> {
+        let sessions = paths::session_stems(dir)?;
 
         for file_stem in &sessions {
             if self.known_files.contains(file_stem.as_str()) {
@@ -90,7 +96,7 @@ impl ChainIndex {
             }
             self.known_files.insert(file_stem.clone());
 
-            let path = resolver.conversation_file(project_path, file_stem)?;
+            let path = dir.join(format!("{}.jsonl", file_stem));
             if let Some(first_sid) = ConversationReader::read_first_session_id(&path) {
                 if first_sid != *file_stem {
                     // This file is a successor of first_sid
@@ -128,6 +134,12 @@ impl ChainIndex {
         self.succession.get(session_id).map(|s| s.as_str())
     }
 
+    /// Immediate predecessor of the file `file_stem`, if it is a successor.
+    #[cfg(any(feature = "watcher", test))]
+    pub(crate) fn predecessor_of(&self, file_stem: &str) -> Option<&str> {
+        self.reverse.get(file_stem).map(|s| s.as_str())
+    }
+
     /// All chain heads — file stems that are not successors of another.
     pub(crate) fn chain_heads(&self) -> Vec<String> {
         self.known_files
@@ -0,0 +1,140 @@
+//! File identity for watcher checkpoints.
+//!
+//! A checkpoint stores a byte offset into a session log. Before trusting it
+//! again, [`resume_offset`] checks the file is still the one the offset was
+//! taken from: same inode, and the same bytes just before the offset. If
+//! not — the log was truncated or rewritten — it looks for the last entry
+//! read and resumes after it, or starts over.
+
+use crate::digest::sha256;
+use crate::error::Result;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use toolpath_convo::{FileIdentity, SessionCursor};
+
+/// Bytes before the offset covered by [`FileIdentity::digest`].
+const WINDOW: u64 = 4096;
+
+/// Fingerprint `path` as of `offset`.
+pub(crate) fn identify(path: &Path, offset: u64) -> Result<FileIdentity> {
+    let mut file = File::open(path)?;
+    let metadata = file.metadata()?;
+    let end = offset.min(metadata.len());
+    let start = end.saturating_sub(WINDOW);
+    file.seek(SeekFrom::Start(start))?;
+    let mut window = Vec::new();
+    file.take(end - start).read_to_end(&mut window)?;
+    Ok(FileIdentity {
+        inode: inode(&metadata),
+        digest: sha256(&window),
+    })
+}
+
+#[cfg(unix)]
+fn inode(metadata: &std::fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &std::fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// Where to resume reading `path` for `cursor`: its offset if the file is
+/// unchanged up to there, else just after its last entry, else the start.
+pub(crate) fn resume_offset(path: &Path, cursor: &SessionCursor) -> Result<u64> {
+    let len = std::fs::metadata(path)?.len();
+    if cursor.offset <= len {
+        let unchanged = match &cursor.file {
+            Some(identity) => identify(path, cursor.offset)? == *identity,
+            None => true,
+        };
+        if unchanged {
+            return Ok(cursor.offset);
+        }
+    }
+    if let Some(uuid) = &cursor.last_uuid
+        && let Some(offset) = offset_after(path, uuid)?
+    {
+        return Ok(offset);
+    }
+    Ok(0)
+}
+
+/// Byte offset just past the line holding entry `uuid`, counted the way
+/// [`ConversationReader::read_from_offset`](crate::ConversationReader::read_from_offset)
+/// counts.
+pub(crate) fn offset_after(path: &Path, uuid: &str) -> Result<Option<u64>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut offset = 0;
+    let mut found = None;
+    for line in reader.lines() {
+        let line = line?;
+        offset += line.len() as u64 + 1;
+        if line.contains(uuid)
+            && serde_json::from_str::<serde_json::Value>(&line)
+                .ok()
+                .is_some_and(|v| v.get("uuid").and_then(|u| u.as_str()) == Some(uuid))
+        {
+            found = Some(offset);
+        }
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn line(uuid: &str) -> String {
+        format!(
+            r#"{{"type":"user","uuid":"{uuid}","timestamp":"2024-01-01T00:00:00Z","message":{{"role":"user","content":"Hi"}}}}"#
+        ) + "\n"
+    }
+
+    fn cursor(path: &Path, offset: u64, last_uuid: &str) -> SessionCursor {
+        SessionCursor {
+            project: "/p".into(),
+            session_id: "s".into(),
+            chain: Vec::new(),
+            offset,
+            last_uuid: Some(last_uuid.into()),
+            file: Some(identify(path, offset).unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_resume_unchanged_file() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("s.jsonl");
+        fs::write(&path, line("a") + &line("b")).unwrap();
+        let offset = offset_after(&path, "a").unwrap().unwrap();
+        assert_eq!(offset, line("a").len() as u64);
+
+        let cursor = cursor(&path, offset, "a");
+        fs::write(&path, line("a") + &line("b") + &line("c")).unwrap();
+        assert_eq!(resume_offset(&path, &cursor).unwrap(), offset);
+    }
+
+    #[test]
+    fn test_resume_rewritten_file() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("s.jsonl");
+        fs::write(&path, line("a") + &line("b")).unwrap();
+        let cursor = cursor(&path, offset_after(&path, "b").unwrap().unwrap(), "b");
+
+        // Rewritten with an extra entry before `b`: resume after `b`
+        let rewritten = line("x") + &line("a") + &line("b") + &line("c");
+        fs::write(&path, &rewritten).unwrap();
+        let expected = (line("x") + &line("a") + &line("b")).len() as u64;
+        assert_eq!(resume_offset(&path, &cursor).unwrap(), expected);
+
+        // Truncated past `b`: start over
+        fs::write(&path, line("a")).unwrap();
+        assert_eq!(resume_offset(&path, &cursor).unwrap(), 0);
+    }
+}
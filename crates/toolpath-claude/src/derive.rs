@@ -2,24 +2,107 @@
 //!
 //! The conversation itself is treated as an artifact under change. Each turn
 //! appends to `claude://<session-id>` via a `conversation.append` structural
-//! operation. File mutations from tool use (Write, Edit) appear as sibling
-//! artifacts in the same step's `change` map.
-
-use crate::types::{ContentPart, Conversation, MessageContent, MessageRole};
-use serde_json::json;
-use std::collections::HashMap;
+//! operation. File mutations from tool use (Write, Edit, MultiEdit,
+//! NotebookEdit) appear as sibling artifacts in the same step's `change` map,
+//! with `raw` unified diffs synthesized from the tool inputs and results.
+//! A step whose `Bash` tool use ran `git commit` carries the new commit in
+//! `meta.source`, so it correlates with git-derived paths.
+//!
+//! Plans from `ExitPlanMode` and lists from `TodoWrite` are artifacts too:
+//! `claude://<session-id>/plan` and `claude://<session-id>/todos`.
+//!
+//! Each `Bash` tool use is a `shell.exec` structural change on
+//! `shell://<tool-use-id>`. A formatter or linter run is split into a step
+//! of its own, by a `tool:<binary>` actor, parented on the turn's step.
+//!
+//! A call to an MCP tool (`mcp__<server>__<tool>`) is an `mcp.call`
+//! structural change on `mcp://<server>/<tool-use-id>`, in a step of its own
+//! by a `tool:mcp:<server>` actor, after the turn's step.
+//!
+//! Inline images and documents are `attachment.add` changes on
+//! `attachment://sha256/<hex>`, listed under `attachments` in the turn's
+//! `conversation.append`. Their bytes are not recorded; see
+//! [`attachments::extract`](crate::attachments::extract).
+//!
+//! How much conversation text is recorded — in full, truncated, as a digest,
+//! or not at all — is set per field by [`DeriveConfig::capture`]; see
+//! [`capture`](crate::capture).
+
+use crate::attachments;
+use crate::capture::{self, BlobDir, Capture, CapturePolicy};
+use crate::digest;
+use crate::edits::{self, FileTracker};
+use crate::paths::PathResolver;
+use crate::plans::{self, PlanOutcome, PlanTracker};
+use crate::provider::{ToolCategories, parse_mcp_tool};
+use crate::subagents::Subagent;
+use crate::types::{
+    CompactMetadata, ContentPart, Conversation, ConversationEntry, FileSnapshot, HistoryEntry,
+    MediaSource, Message, MessageContent, MessageRole, ToolResultContent,
+};
+use crate::usage::{PriceTable, StepUsage, UsageTotals};
+use crate::{shell, vcs};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::{HashMap, HashSet};
+use toolpath::v1::ext::Extension;
+use toolpath::v1::patch::{self, UnifiedDiff};
 use toolpath::v1::{
-    ActorDefinition, ArtifactChange, Base, Identity, Path, PathIdentity, PathMeta, Step,
-    StepIdentity, StructuralChange,
+    ActorDefinition, ArtifactChange, Base, Graph, GraphMeta, Identity, Path, PathIdentity,
+    PathMeta, PathOrRef, Ref, Step, StepIdentity, StructuralChange, VcsSource,
 };
+use toolpath_convo::ToolCategory;
 
 /// Configuration for deriving Toolpath documents from Claude conversations.
 #[derive(Default)]
 pub struct DeriveConfig {
     /// Override the project path used for `path.base.uri`.
     pub project_path: Option<String>,
-    /// Include thinking blocks in the conversation artifact.
-    pub include_thinking: bool,
+    /// How much of each kind of text to record. By default turn text and
+    /// tool output are truncated to 2000 characters and thinking is left out.
+    pub capture: CapturePolicy,
+    /// Write the full text of fields that [`DeriveConfig::capture`] shortens
+    /// here, referenced by the `<field>_sha256` digest.
+    pub blobs: Option<BlobDir>,
+    /// Read pre-edit file content from the `file-history` backups under this
+    /// Claude data dir, for files whose content is otherwise unknown.
+    pub file_history: Option<PathResolver>,
+    /// Record a [`BaseDigests`] extension on each step with file diffs.
+    pub base_digests: bool,
+    /// Resolve commits made during the session to full revisions. Without
+    /// one, steps are stamped with the abbreviated hash from git's output.
+    pub repository: Option<Box<dyn RevisionResolver>>,
+    /// Attribute file changes to `Bash` commands from later evidence: a
+    /// `Read` showing different content than last known, or a `git status`
+    /// listing a file the previous one didn't. Only applied when exactly one
+    /// command could be responsible. [`derive_path`] only; an
+    /// [`IncrementalDeriver`] has already emitted the command's step.
+    pub infer_shell_changes: bool,
+    /// Estimate the cost of each assistant step's [`StepUsage`].
+    pub prices: Option<PriceTable>,
+    /// Categories for MCP tools, recorded on their `mcp.call` changes.
+    pub tool_categories: ToolCategories,
+}
+
+/// Resolves abbreviated commit hashes to full revisions — typically backed
+/// by the project's local repository. `Send`, so a [`DeriveConfig`] can move
+/// into a task.
+pub trait RevisionResolver: Send {
+    /// The full revision `short` abbreviates, or `None` if there is no such
+    /// commit (the match was likely a false positive).
+    fn resolve(&self, short: &str) -> Option<String>;
+}
+
+/// SHA-256 digests (`sha256:<hex>`) of the content each file's `raw` diff
+/// applies to, keyed by file path. Stored in step `meta` under
+/// `claude.base_digests` when [`DeriveConfig::base_digests`] is set, so a
+/// reader can check a diff against their own copy before applying it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct BaseDigests(pub HashMap<String, String>);
+
+impl Extension for BaseDigests {
+    const KEY: &'static str = "claude.base_digests";
 }
 
 /// Derive a single Toolpath Path from a Claude conversation.
@@ -28,27 +111,176 @@ pub struct DeriveConfig {
 /// Each user or assistant turn produces a step whose `change` map contains
 /// a `conversation.append` structural change on that artifact, plus any
 /// file-level artifacts touched by tool use.
+///
+/// File changes carry a `raw` diff whenever one can be built: against the
+/// file's prior content (from the tool result's `originalFile`, tracked
+/// across earlier `Read`/`Write`/`Edit` calls in the session, or read from
+/// `file-history` backups when [`DeriveConfig::file_history`] is set), else
+/// from the result's `structuredPatch`, else from the edit strings alone. Tool uses
+/// whose result is an error are not recorded as changes.
+///
+/// A step whose `Bash` tool use created a git commit gets `meta.source` set
+/// to it, resolved through [`DeriveConfig::repository`] when given.
 pub fn derive_path(conversation: &Conversation, config: &DeriveConfig) -> Path {
+    derive_session(conversation, config).0
+}
+
+/// [`derive_path`], also returning the state it ended with.
+fn derive_session(conversation: &Conversation, config: &DeriveConfig) -> (Path, DeriveState) {
+    let results = tool_results(conversation);
+    let mut state = DeriveState::new();
+    let mut steps: Vec<Step> = conversation
+        .entries
+        .iter()
+        .flat_map(|entry| state.derive(entry, conversation, &results, config))
+        .collect();
+    for (step_id, path, change) in std::mem::take(&mut state.inferred) {
+        if let Some(step) = steps.iter_mut().find(|s| s.step.id == step_id) {
+            step.change.entry(path).or_insert(change);
+        }
+    }
+
     let session_short = safe_prefix(&conversation.session_id, 8);
-    let convo_artifact = format!("claude://{}", conversation.session_id);
+    let head = state
+        .last_step_id
+        .clone()
+        .unwrap_or_else(|| "empty".to_string());
+    let base_uri = config
+        .project_path
+        .as_deref()
+        .or(conversation.project_path.as_deref())
+        .map(|p| format!("file://{}", p));
 
-    let mut steps = Vec::new();
-    let mut last_step_id: Option<String> = None;
-    let mut actors: HashMap<String, ActorDefinition> = HashMap::new();
+    let mut meta = PathMeta {
+        title: Some(format!("Claude session: {}", session_short)),
+        source: Some("claude-code".to_string()),
+        actors: if state.actors.is_empty() {
+            None
+        } else {
+            Some(std::mem::take(&mut state.actors))
+        },
+        ..Default::default()
+    };
+    if let Some(totals) = UsageTotals::of(&steps) {
+        // Serializing plain numbers cannot fail.
+        let _ = meta.set_ext(totals);
+    }
 
-    for entry in &conversation.entries {
-        if entry.uuid.is_empty() {
-            continue;
+    let path = Path {
+        version: None,
+        path: PathIdentity {
+            id: path_id(&conversation.session_id),
+            base: base_uri.map(|uri| Base { uri, ref_str: None }),
+            head,
+        },
+        steps,
+        meta: Some(meta),
+    };
+    (path, state)
+}
+
+/// The ID of the path derived from a session.
+fn path_id(session_id: &str) -> String {
+    format!("path-claude-{}", safe_prefix(session_id, 8))
+}
+
+/// Derivation state carried from one entry to the next.
+struct DeriveState {
+    files: FileTracker,
+    /// Each entry's parent link, by UUID. The log is a tree: rewinds and
+    /// edited prompts start sibling branches via `parentUuid`. Steps follow
+    /// those links, bridging over entries that emit no step, so abandoned
+    /// branches become dead ends.
+    parent_of: HashMap<String, Option<String>>,
+    /// Step IDs of the entries that emitted a step, by UUID.
+    emitted: HashMap<String, String>,
+    /// Each entry's position in the log so far, by UUID, for finding the
+    /// file-history backups in effect at an entry.
+    positions: HashMap<String, usize>,
+    last_step_id: Option<String>,
+    actors: HashMap<String, ActorDefinition>,
+    plans: PlanTracker,
+    /// Tool uses seen so far; orders the evidence below.
+    tool_seq: usize,
+    /// When each file's content was last established by a tool use.
+    known_at: HashMap<String, usize>,
+    /// When each file was last changed by a file-editing tool use.
+    edited_at: HashMap<String, usize>,
+    /// Commands that may have changed the working tree.
+    shells: Vec<ShellRun>,
+    /// The last `git status`: when it ran and the paths it listed.
+    last_status: Option<(usize, HashSet<String>)>,
+    /// File changes inferred for earlier steps, as (step ID, path, change).
+    inferred: Vec<(String, String, ArtifactChange)>,
+    /// API responses whose usage is already on a step, by message ID.
+    counted: HashSet<String>,
+    /// The last `compact_boundary` not yet followed by its summary.
+    compact_boundary: Option<CompactMetadata>,
+    /// Main-line turn steps since the last compaction summary.
+    since_compaction: usize,
+}
+
+/// A `Bash` command that may have changed the working tree.
+struct ShellRun {
+    seq: usize,
+    tool_use_id: String,
+    /// The step its `shell.exec` change is recorded on.
+    step_id: String,
+}
+
+impl DeriveState {
+    fn new() -> Self {
+        Self {
+            files: FileTracker::new(),
+            parent_of: HashMap::new(),
+            emitted: HashMap::new(),
+            positions: HashMap::new(),
+            last_step_id: None,
+            actors: HashMap::new(),
+            plans: PlanTracker::new(),
+            tool_seq: 0,
+            known_at: HashMap::new(),
+            edited_at: HashMap::new(),
+            shells: Vec::new(),
+            last_status: None,
+            inferred: Vec::new(),
+            counted: HashSet::new(),
+            compact_boundary: None,
+            since_compaction: 0,
         }
+    }
 
-        let message = match &entry.message {
-            Some(m) => m,
-            None => continue,
+    /// The steps for `entry`: none, its own, or its own followed by one per
+    /// formatter or linter command it ran. Entries must be passed in log
+    /// order; `conversation` and `results` need only cover the log up to
+    /// `entry` and the results of its tool uses.
+    fn derive(
+        &mut self,
+        entry: &ConversationEntry,
+        conversation: &Conversation,
+        results: &HashMap<&str, ToolOutcome<'_>>,
+        config: &DeriveConfig,
+    ) -> Vec<Step> {
+        if entry.uuid.is_empty() {
+            return Vec::new();
+        }
+        self.parent_of
+            .insert(entry.uuid.clone(), parent_link(entry).map(str::to_string));
+        let position = self.positions.len();
+        self.positions.insert(entry.uuid.clone(), position);
+
+        let Some(message) = entry.message.as_ref() else {
+            if let Some(metadata) = entry.compact_metadata() {
+                self.compact_boundary = Some(metadata);
+            }
+            return Vec::new();
         };
-
+        if entry.is_compact_summary() {
+            return self.derive_compaction(entry, message, conversation, config);
+        }
         let (actor, role_str) = match message.role {
             MessageRole::User => {
-                actors
+                self.actors
                     .entry("human:user".to_string())
                     .or_insert_with(|| ActorDefinition {
                         name: Some("User".to_string()),
@@ -62,7 +294,7 @@ pub fn derive_path(conversation: &Conversation, config: &DeriveConfig) -> Path {
                 } else {
                     ("agent:claude-code".to_string(), "claude-code".to_string())
                 };
-                actors.entry(actor_key.clone()).or_insert_with(|| {
+                self.actors.entry(actor_key.clone()).or_insert_with(|| {
                     let mut identities = vec![Identity {
                         system: "anthropic".to_string(),
                         id: model_str.clone(),
@@ -83,43 +315,195 @@ pub fn derive_path(conversation: &Conversation, config: &DeriveConfig) -> Path {
                 });
                 (actor_key, "assistant")
             }
-            MessageRole::System => continue,
+            MessageRole::System => return Vec::new(),
         };
 
         // Collect conversation text and file changes from this turn
         let mut file_changes: HashMap<String, ArtifactChange> = HashMap::new();
+        let mut base_digests: HashMap<String, String> = HashMap::new();
+        // Each file's content before its first change in this turn, if known
+        let mut turn_bases: HashMap<String, Option<String>> = HashMap::new();
         let mut text_parts: Vec<String> = Vec::new();
+        let mut thinking_parts: Vec<&str> = Vec::new();
         let mut tool_uses: Vec<String> = Vec::new();
+        let mut revision: Option<String> = None;
+        // `Bash` runs as (tool use ID, formatter or linter, `shell.exec`)
+        let mut shell_runs: Vec<(String, Option<&str>, StructuralChange)> = Vec::new();
+        // MCP tool uses as (tool use ID, server, `mcp.call`)
+        let mut mcp_calls: Vec<(String, &str, StructuralChange)> = Vec::new();
+        let step_id = step_id(&entry.uuid);
 
         match &message.content {
             Some(MessageContent::Parts(parts)) => {
                 for part in parts {
                     match part {
-                        ContentPart::Text { text } => {
-                            if !text.trim().is_empty() {
-                                text_parts.push(text.clone());
-                            }
+                        ContentPart::Text { text } if !text.trim().is_empty() => {
+                            text_parts.push(text.clone());
                         }
-                        ContentPart::Thinking { thinking, .. } => {
-                            if config.include_thinking && !thinking.trim().is_empty() {
-                                text_parts.push(format!("[thinking] {}", thinking));
-                            }
+                        ContentPart::Thinking { thinking, .. }
+                            if config.capture.thinking != Capture::Omit
+                                && !thinking.trim().is_empty() =>
+                        {
+                            thinking_parts.push(thinking);
                         }
-                        ContentPart::ToolUse { name, input, .. } => {
+                        ContentPart::ToolUse { id, name, input } => {
                             tool_uses.push(name.clone());
-                            if let Some(file_path) = input.get("file_path").and_then(|v| v.as_str())
-                            {
-                                match name.as_str() {
-                                    "Write" | "Edit" => {
-                                        file_changes.insert(
-                                            file_path.to_string(),
-                                            ArtifactChange {
-                                                raw: None,
-                                                structural: None,
-                                            },
-                                        );
+                            let outcome = results.get(id.as_str()).copied().unwrap_or_default();
+                            let ToolOutcome {
+                                result, is_error, ..
+                            } = outcome;
+                            self.tool_seq += 1;
+                            if let Some((server, tool)) = parse_mcp_tool(name) {
+                                let mut change = mcp_change(
+                                    server,
+                                    tool,
+                                    input,
+                                    is_error,
+                                    config.tool_categories.category(name),
+                                );
+                                if let Some(output) = outcome.content.map(ToolResultContent::text) {
+                                    capture::record(
+                                        &mut change.extra,
+                                        "output",
+                                        &output,
+                                        config.capture.tool_output,
+                                        config.blobs.as_ref(),
+                                    );
+                                }
+                                mcp_calls.push((id.clone(), server, change));
+                                continue;
+                            }
+                            if name == "Bash" {
+                                let command =
+                                    input.get("command").and_then(Value::as_str).unwrap_or("");
+                                let binary = shell::tool_binary(command);
+                                let output = outcome.content.map(ToolResultContent::text);
+                                shell_runs.push((
+                                    id.clone(),
+                                    binary,
+                                    shell::exec_change(
+                                        input,
+                                        result,
+                                        output.as_deref(),
+                                        is_error,
+                                        entry.cwd.as_deref(),
+                                        config.capture.tool_output,
+                                        config.blobs.as_ref(),
+                                    ),
+                                ));
+                                if is_error {
+                                    continue;
+                                }
+                                if let Some(rev) =
+                                    commit_revision(input, outcome, config.repository.as_deref())
+                                {
+                                    revision = Some(rev);
+                                }
+                                if config.infer_shell_changes {
+                                    let run_step = match binary {
+                                        Some(binary) => {
+                                            let n = shell_runs
+                                                .iter()
+                                                .filter(|(_, b, _)| *b == Some(binary))
+                                                .count();
+                                            tool_step_id(&step_id, binary, n)
+                                        }
+                                        None => step_id.clone(),
+                                    };
+                                    self.observe_shell(
+                                        id,
+                                        command,
+                                        output.as_deref().unwrap_or(""),
+                                        run_step,
+                                        entry.cwd.as_deref(),
+                                    );
+                                }
+                                continue;
+                            }
+                            if name == "ExitPlanMode" {
+                                let answered = result.is_some() || outcome.content.is_some();
+                                let plan_outcome = match (answered, is_error) {
+                                    (_, true) => PlanOutcome::Rejected,
+                                    (true, false) => PlanOutcome::Accepted,
+                                    (false, false) => PlanOutcome::Pending,
+                                };
+                                let feedback = outcome.content.map(ToolResultContent::text);
+                                if let Some(change) = self.plans.plan_change(
+                                    input,
+                                    plan_outcome,
+                                    feedback.as_deref(),
+                                    config.capture.tool_output,
+                                    config.blobs.as_ref(),
+                                ) {
+                                    let key = format!("claude://{}/plan", conversation.session_id);
+                                    add_plan_change(&mut file_changes, key, change);
+                                }
+                                continue;
+                            }
+                            if is_error {
+                                continue;
+                            }
+                            if name == "TodoWrite" {
+                                if let Some(change) = self.plans.todos_change(input) {
+                                    let key = format!("claude://{}/todos", conversation.session_id);
+                                    add_plan_change(&mut file_changes, key, change);
+                                }
+                            } else if name == "Read" {
+                                if let Some(result) = result
+                                    && let Some((path, previous)) = self.files.observe_read(result)
+                                {
+                                    if config.infer_shell_changes
+                                        && let Some(previous) = previous
+                                    {
+                                        self.infer_from_read(&path, &previous);
+                                    }
+                                    self.known_at.insert(path, self.tool_seq);
+                                }
+                            } else {
+                                if let Some(resolver) = &config.file_history {
+                                    seed_from_backup(
+                                        &mut self.files,
+                                        conversation,
+                                        &self.positions,
+                                        &entry.uuid,
+                                        input,
+                                        result,
+                                        resolver,
+                                    );
+                                }
+                                if let Some(fc) = self.files.change_for(name, input, result) {
+                                    self.known_at.insert(fc.path.clone(), self.tool_seq);
+                                    self.edited_at.insert(fc.path.clone(), self.tool_seq);
+                                    if let Some(base) = &fc.base {
+                                        base_digests
+                                            .entry(fc.path.clone())
+                                            .or_insert_with(|| digest::sha256(base.as_bytes()));
                                     }
-                                    _ => {}
+                                    let before = turn_bases
+                                        .entry(fc.path.clone())
+                                        .or_insert_with(|| fc.base.clone());
+                                    let change = match file_changes.remove(&fc.path) {
+                                        Some(earlier) => {
+                                            let contents =
+                                                before.as_deref().zip(self.files.content(&fc.path));
+                                            // A file change without `raw` is one whose
+                                            // diff is unknown, so the other's diff alone
+                                            // doesn't describe the turn.
+                                            let unknown =
+                                                earlier.raw.is_none() || fc.change.raw.is_none();
+                                            let mut merged =
+                                                merge_changes(earlier, fc.change, contents);
+                                            if unknown {
+                                                merged.raw = contents.map(|(before, after)| {
+                                                    edits::whole_file_diff(before, after)
+                                                        .to_string()
+                                                });
+                                            }
+                                            merged
+                                        }
+                                        None => fc.change,
+                                    };
+                                    file_changes.insert(fc.path, change);
                                 }
                             }
                         }
@@ -127,29 +511,60 @@ pub fn derive_path(conversation: &Conversation, config: &DeriveConfig) -> Path {
                     }
                 }
             }
-            Some(MessageContent::Text(text)) => {
-                if !text.trim().is_empty() {
-                    text_parts.push(text.clone());
+            Some(MessageContent::Text(text)) if !text.trim().is_empty() => {
+                text_parts.push(text.clone());
+            }
+            _ => {}
+        }
+
+        let mut attachments: Vec<String> = Vec::new();
+        for media in message.media() {
+            if let Some((key, change)) =
+                attachment_change(media.kind.as_str(), media.source, media.title)
+            {
+                if !attachments.contains(&key) {
+                    attachments.push(key.clone());
                 }
+                file_changes.entry(key).or_insert(change);
             }
-            None => {}
         }
 
         // Skip entries with no conversation content and no file changes
-        if text_parts.is_empty() && tool_uses.is_empty() && file_changes.is_empty() {
-            continue;
+        if text_parts.is_empty()
+            && thinking_parts.is_empty()
+            && tool_uses.is_empty()
+            && file_changes.is_empty()
+        {
+            return Vec::new();
         }
 
         // Build the conversation artifact change
         let mut convo_extra = HashMap::new();
         convo_extra.insert("role".to_string(), json!(role_str));
         if !text_parts.is_empty() {
-            let combined = text_parts.join("\n\n");
-            convo_extra.insert("text".to_string(), json!(truncate(&combined, 2000)));
+            capture::record(
+                &mut convo_extra,
+                "text",
+                &text_parts.join("\n\n"),
+                config.capture.text,
+                config.blobs.as_ref(),
+            );
+        }
+        if !thinking_parts.is_empty() {
+            capture::record(
+                &mut convo_extra,
+                "thinking",
+                &thinking_parts.join("\n\n"),
+                config.capture.thinking,
+                config.blobs.as_ref(),
+            );
         }
         if !tool_uses.is_empty() {
             convo_extra.insert("tool_uses".to_string(), json!(tool_uses.clone()));
         }
+        if !attachments.is_empty() {
+            convo_extra.insert("attachments".to_string(), json!(attachments));
+        }
 
         let convo_change = ArtifactChange {
             raw: None,
@@ -160,485 +575,2832 @@ pub fn derive_path(conversation: &Conversation, config: &DeriveConfig) -> Path {
         };
 
         let mut changes = HashMap::new();
-        changes.insert(convo_artifact.clone(), convo_change);
+        changes.insert(
+            format!("claude://{}", conversation.session_id),
+            convo_change,
+        );
         changes.extend(file_changes);
 
+        // Formatter and linter runs become steps of their own, below.
+        let mut tool_runs = Vec::new();
+        for (id, binary, exec) in shell_runs {
+            match binary {
+                Some(binary) => tool_runs.push((id, binary, exec)),
+                None => {
+                    changes.insert(format!("shell://{}", id), structural(exec));
+                }
+            }
+        }
+
         // Build step — no meta.intent; the conversation content already
         // lives in the structural change and adding it again is redundant.
-        let step_id = format!("step-{}", safe_prefix(&entry.uuid, 8));
-        let parents = if entry.is_sidechain {
-            entry
-                .parent_uuid
-                .as_ref()
-                .map(|p| vec![format!("step-{}", safe_prefix(p, 8))])
-                .unwrap_or_default()
-        } else {
-            last_step_id.iter().cloned().collect()
-        };
-
-        let step = Step {
+        let mut step = Step {
+            version: None,
             step: StepIdentity {
                 id: step_id.clone(),
-                parents,
+                parents: self.parents(entry),
                 actor,
                 timestamp: entry.timestamp.clone(),
             },
             change: changes,
             meta: None,
         };
+        if let Some(revision) = revision {
+            step.meta.get_or_insert_with(Default::default).source = Some(VcsSource {
+                vcs_type: "git".to_string(),
+                revision,
+                change_id: None,
+                extra: HashMap::new(),
+            });
+        }
+        if message.role == MessageRole::Assistant {
+            let response = message.id.clone().or_else(|| entry.request_id.clone());
+            if response.is_none_or(|id| self.counted.insert(id))
+                && let Some(usage) = StepUsage::from_entry(entry, config.prices.as_ref())
+            {
+                // Serializing plain numbers and strings cannot fail.
+                let _ = step
+                    .meta
+                    .get_or_insert_with(Default::default)
+                    .set_ext(usage);
+            }
+        }
+        if config.base_digests && !base_digests.is_empty() {
+            // Serializing a map of strings cannot fail.
+            let _ = step
+                .meta
+                .get_or_insert_with(Default::default)
+                .set_ext(BaseDigests(base_digests));
+        }
 
-        if !entry.is_sidechain {
-            last_step_id = Some(step_id);
+        let mut steps = vec![step];
+        let mut runs_of: HashMap<&str, usize> = HashMap::new();
+        for (id, binary, exec) in tool_runs {
+            let n = runs_of.entry(binary).or_default();
+            *n += 1;
+            let tool_id = tool_step_id(&step_id, binary, *n);
+            let actor = format!("tool:{}", binary);
+            self.actors
+                .entry(actor.clone())
+                .or_insert_with(|| ActorDefinition {
+                    name: Some(binary.to_string()),
+                    ..Default::default()
+                });
+            let parent = steps.last().map(|s| s.step.id.clone());
+            steps.push(Step {
+                version: None,
+                step: StepIdentity {
+                    id: tool_id.clone(),
+                    parents: parent.into_iter().collect(),
+                    actor,
+                    timestamp: entry.timestamp.clone(),
+                },
+                change: HashMap::from([(format!("shell://{}", id), structural(exec))]),
+                meta: None,
+            });
         }
-        steps.push(step);
-    }
 
-    let head = last_step_id.unwrap_or_else(|| "empty".to_string());
-    let base_uri = config
-        .project_path
-        .as_deref()
-        .or(conversation.project_path.as_deref())
-        .map(|p| format!("file://{}", p));
+        // MCP calls become steps by their server, after any tool runs.
+        let mut calls_to: HashMap<&str, usize> = HashMap::new();
+        for (id, server, call) in mcp_calls {
+            let n = calls_to.entry(server).or_default();
+            *n += 1;
+            let actor = format!("tool:mcp:{}", server);
+            self.actors
+                .entry(actor.clone())
+                .or_insert_with(|| ActorDefinition {
+                    name: Some(server.to_string()),
+                    identities: vec![Identity {
+                        system: "mcp".to_string(),
+                        id: server.to_string(),
+                    }],
+                    ..Default::default()
+                });
+            let parent = steps.last().map(|s| s.step.id.clone());
+            steps.push(Step {
+                version: None,
+                step: StepIdentity {
+                    id: tool_step_id(&step_id, &format!("mcp-{}", server), *n),
+                    parents: parent.into_iter().collect(),
+                    actor,
+                    timestamp: entry.timestamp.clone(),
+                },
+                change: HashMap::from([(format!("mcp://{}/{}", server, id), structural(call))]),
+                meta: None,
+            });
+        }
 
-    Path {
-        path: PathIdentity {
-            id: format!("path-claude-{}", session_short),
-            base: base_uri.map(|uri| Base { uri, ref_str: None }),
-            head,
-        },
-        steps,
-        meta: Some(PathMeta {
-            title: Some(format!("Claude session: {}", session_short)),
-            source: Some("claude-code".to_string()),
-            actors: if actors.is_empty() {
-                None
-            } else {
-                Some(actors)
-            },
-            ..Default::default()
-        }),
+        if !entry.is_sidechain {
+            self.since_compaction += 1;
+        }
+        self.emit(entry, steps)
     }
-}
 
-/// Derive Toolpath Paths from multiple conversations in a project.
-pub fn derive_project(conversations: &[Conversation], config: &DeriveConfig) -> Vec<Path> {
-    conversations
-        .iter()
-        .map(|c| derive_path(c, config))
-        .collect()
-}
+    /// The parents of `entry`'s step.
+    fn parents(&self, entry: &ConversationEntry) -> Vec<String> {
+        match nearest_emitted(&entry.uuid, &self.parent_of, &self.emitted) {
+            Some(parent) => vec![parent],
+            // Main-line entries that reach no emitted ancestor — the session
+            // start, or a link into a filtered chain bridge — continue from
+            // the previous main-line step.
+            None if !entry.is_sidechain => self.last_step_id.iter().cloned().collect(),
+            None => Vec::new(),
+        }
+    }
 
-/// Truncate a string to at most `max` characters (not bytes), appending "..."
-/// if truncated. Always cuts on a char boundary.
-fn truncate(s: &str, max: usize) -> String {
-    let char_count = s.chars().count();
-    if char_count <= max {
-        s.to_string()
-    } else {
-        let truncated: String = s.chars().take(max - 3).collect();
-        format!("{}...", truncated)
+    /// Record that `entry` produced `steps`: later entries continue from
+    /// the last of them.
+    fn emit(&mut self, entry: &ConversationEntry, steps: Vec<Step>) -> Vec<Step> {
+        if let Some(last) = steps.last().map(|s| s.step.id.clone()) {
+            self.emitted.insert(entry.uuid.clone(), last.clone());
+            if !entry.is_sidechain {
+                self.last_step_id = Some(last);
+            }
+        }
+        steps
     }
-}
 
-/// Return the first `n` characters of a string, safe for any UTF-8 content.
-fn safe_prefix(s: &str, n: usize) -> String {
-    s.chars().take(n).collect()
-}
+    /// The step for a compaction summary: a `conversation.compact` change,
+    /// by Claude Code, recording the summary and the turns it replaced.
+    fn derive_compaction(
+        &mut self,
+        entry: &ConversationEntry,
+        message: &Message,
+        conversation: &Conversation,
+        config: &DeriveConfig,
+    ) -> Vec<Step> {
+        let actor = "agent:claude-code".to_string();
+        self.actors.entry(actor.clone()).or_insert_with(|| {
+            let identities = entry
+                .version
+                .iter()
+                .map(|version| Identity {
+                    system: "claude-code".to_string(),
+                    id: version.clone(),
+                })
+                .collect();
+            ActorDefinition {
+                name: Some("Claude Code".to_string()),
+                provider: Some("anthropic".to_string()),
+                identities,
+                ..Default::default()
+            }
+        });
+
+        let metadata = self.compact_boundary.take().unwrap_or_default();
+        let mut extra =
+            HashMap::from([("replaced_turns".to_string(), json!(self.since_compaction))]);
+        capture::record(
+            &mut extra,
+            "summary",
+            &message.text(),
+            config.capture.summary,
+            config.blobs.as_ref(),
+        );
+        if let Some(trigger) = metadata.trigger {
+            extra.insert("trigger".to_string(), json!(trigger));
+        }
+        if let Some(pre_tokens) = metadata.pre_tokens {
+            extra.insert("pre_tokens".to_string(), json!(pre_tokens));
+        }
+        self.since_compaction = 0;
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::types::{ContentPart, ConversationEntry, Message, MessageContent};
+        let step = Step {
+            version: None,
+            step: StepIdentity {
+                id: step_id(&entry.uuid),
+                parents: self.parents(entry),
+                actor,
+                timestamp: entry.timestamp.clone(),
+            },
+            change: HashMap::from([(
+                format!("claude://{}", conversation.session_id),
+                structural(StructuralChange {
+                    change_type: "conversation.compact".to_string(),
+                    extra,
+                }),
+            )]),
+            meta: None,
+        };
+        self.emit(entry, vec![step])
+    }
 
-    fn make_entry(
-        uuid: &str,
-        role: MessageRole,
-        content: &str,
-        timestamp: &str,
-    ) -> ConversationEntry {
-        ConversationEntry {
-            parent_uuid: None,
-            is_sidechain: false,
-            entry_type: match role {
-                MessageRole::User => "user",
-                MessageRole::Assistant => "assistant",
-                MessageRole::System => "system",
+    /// Note a successful `Bash` command for change inference: a `git status`
+    /// is compared with the previous one, and a command that isn't a git
+    /// query becomes a candidate for later evidence.
+    fn observe_shell(
+        &mut self,
+        tool_use_id: &str,
+        command: &str,
+        output: &str,
+        step_id: String,
+        cwd: Option<&str>,
+    ) {
+        if let Some(paths) = shell::git_status_paths(command, output) {
+            let paths: HashSet<String> = paths
+                .into_iter()
+                .map(|p| match cwd {
+                    Some(cwd) if !p.starts_with('/') => {
+                        format!("{}/{}", cwd.trim_end_matches('/'), p)
+                    }
+                    _ => p,
+                })
+                .collect();
+            if let Some((since, previous)) = &self.last_status
+                && let Some(run) = self.sole_shell_since(*since)
+            {
+                let inferred: Vec<_> = paths
+                    .difference(previous)
+                    .filter(|path| self.edited_at.get(*path).is_none_or(|at| at <= since))
+                    .map(|path| {
+                        let change = inferred_change(None, &run.tool_use_id, "git-status");
+                        (run.step_id.clone(), path.clone(), change)
+                    })
+                    .collect();
+                self.inferred.extend(inferred);
             }
-            .to_string(),
-            uuid: uuid.to_string(),
-            timestamp: timestamp.to_string(),
-            session_id: Some("test-session".to_string()),
-            cwd: None,
-            git_branch: None,
-            version: None,
-            message: Some(Message {
-                role,
-                content: Some(MessageContent::Text(content.to_string())),
-                model: None,
-                id: None,
-                message_type: None,
-                stop_reason: None,
-                stop_sequence: None,
-                usage: None,
-            }),
-            user_type: None,
-            request_id: None,
-            tool_use_result: None,
-            snapshot: None,
-            message_id: None,
-            extra: Default::default(),
+            self.last_status = Some((self.tool_seq, paths));
+        }
+        if !shell::is_git_query(command) {
+            self.shells.push(ShellRun {
+                seq: self.tool_seq,
+                tool_use_id: tool_use_id.to_string(),
+                step_id,
+            });
         }
     }
 
-    fn make_conversation(entries: Vec<ConversationEntry>) -> Conversation {
-        let mut convo = Conversation::new("test-session-12345678".to_string());
-        for entry in entries {
-            convo.add_entry(entry);
+    /// A full `Read` found `path` changed from `previous`; attribute the
+    /// change to the command that must have made it.
+    fn infer_from_read(&mut self, path: &str, previous: &str) {
+        let since = self.known_at.get(path).copied().unwrap_or(0);
+        let (Some(run), Some(current)) = (self.sole_shell_since(since), self.files.content(path))
+        else {
+            return;
+        };
+        let raw = UnifiedDiff::between(previous, current, edits::CONTEXT).to_string();
+        let change = inferred_change(Some(raw), &run.tool_use_id, "read");
+        self.inferred
+            .push((run.step_id.clone(), path.to_string(), change));
+    }
+
+    /// The only command run after tool use `seq`, if exactly one was.
+    fn sole_shell_since(&self, seq: usize) -> Option<&ShellRun> {
+        match self
+            .shells
+            .iter()
+            .rev()
+            .take_while(|r| r.seq > seq)
+            .collect::<Vec<_>>()[..]
+        {
+            [run] => Some(run),
+            _ => None,
         }
-        convo
     }
+}
 
-    // ── truncate ───────────────────────────────────────────────────────
+/// ID for the `n`th run of `binary` split off `step_id`, counting from 1.
+fn tool_step_id(step_id: &str, binary: &str, n: usize) -> String {
+    match n {
+        0 | 1 => format!("{}-{}", step_id, binary),
+        n => format!("{}-{}-{}", step_id, binary, n),
+    }
+}
 
-    #[test]
-    fn test_truncate_short() {
-        assert_eq!(truncate("hello", 10), "hello");
+fn structural(change: StructuralChange) -> ArtifactChange {
+    ArtifactChange {
+        raw: None,
+        structural: Some(change),
     }
+}
 
-    #[test]
-    fn test_truncate_exact() {
-        assert_eq!(truncate("hello", 5), "hello");
+/// The `mcp.call` change for a call to `tool` on MCP server `server`.
+fn mcp_change(
+    server: &str,
+    tool: &str,
+    input: &Value,
+    is_error: bool,
+    category: Option<ToolCategory>,
+) -> StructuralChange {
+    let mut extra = HashMap::from([
+        ("server".to_string(), json!(server)),
+        ("tool".to_string(), json!(tool)),
+        ("input".to_string(), input.clone()),
+        ("is_error".to_string(), json!(is_error)),
+    ]);
+    if let Some(category) = category {
+        extra.insert("category".to_string(), json!(category));
     }
+    StructuralChange {
+        change_type: "mcp.call".to_string(),
+        extra,
+    }
+}
 
-    #[test]
-    fn test_truncate_long() {
-        let result = truncate("hello world, this is long", 10);
-        assert!(result.ends_with("..."));
-        assert_eq!(result.chars().count(), 10);
+/// An `attachment.add` change on `attachment://sha256/<hex>`, for a source
+/// with inline data.
+fn attachment_change(
+    kind: &str,
+    source: &MediaSource,
+    title: Option<&str>,
+) -> Option<(String, ArtifactChange)> {
+    let bytes = source.bytes()?;
+    let mut extra = HashMap::from([
+        ("kind".to_string(), json!(kind)),
+        ("size".to_string(), json!(bytes.len())),
+    ]);
+    if let Some(media_type) = &source.media_type {
+        extra.insert("media_type".to_string(), json!(media_type));
+    }
+    if let Some(title) = title {
+        extra.insert("title".to_string(), json!(title));
     }
+    let change = StructuralChange {
+        change_type: "attachment.add".to_string(),
+        extra,
+    };
+    Some((
+        attachments::artifact_uri(&digest::sha256(&bytes)),
+        structural(change),
+    ))
+}
 
-    #[test]
-    fn test_truncate_multibyte() {
-        // Should not panic on multi-byte characters
-        let s = "café résumé naïve";
-        let result = truncate(s, 8);
-        assert!(result.ends_with("..."));
-        assert_eq!(result.chars().count(), 8);
+/// A file change attributed to a command after the fact.
+fn inferred_change(raw: Option<String>, tool_use_id: &str, evidence: &str) -> ArtifactChange {
+    ArtifactChange {
+        raw,
+        structural: Some(StructuralChange {
+            change_type: "shell.effect".to_string(),
+            extra: HashMap::from([
+                ("tool_use_id".to_string(), json!(tool_use_id)),
+                ("evidence".to_string(), json!(evidence)),
+            ]),
+        }),
     }
+}
 
-    // ── safe_prefix ────────────────────────────────────────────────────
+/// `parentUuid`, or `logicalParentUuid` across a compaction boundary.
+fn parent_link(entry: &ConversationEntry) -> Option<&str> {
+    entry
+        .parent_uuid
+        .as_deref()
+        .or_else(|| entry.extra.get("logicalParentUuid").and_then(Value::as_str))
+}
 
-    #[test]
-    fn test_safe_prefix_normal() {
-        assert_eq!(safe_prefix("abcdef1234", 8), "abcdef12");
+/// Walk `uuid`'s ancestry to the closest entry that emitted a step. `None`
+/// when the ancestry ends, or leaves the conversation, first.
+fn nearest_emitted(
+    uuid: &str,
+    parent_of: &HashMap<String, Option<String>>,
+    emitted: &HashMap<String, String>,
+) -> Option<String> {
+    let mut current = uuid;
+    // Bounded by the number of entries, in case of a malformed cycle.
+    for _ in 0..=parent_of.len() {
+        let parent = parent_of.get(current)?.as_deref()?;
+        if let Some(step) = emitted.get(parent) {
+            return Some(step.clone());
+        }
+        current = parent;
     }
+    None
+}
 
-    #[test]
-    fn test_safe_prefix_short() {
-        assert_eq!(safe_prefix("abc", 8), "abc");
+/// Seed the tracker with a file's backed-up content when nothing better is
+/// known: the tool result carries no prior content and no earlier tool call
+/// revealed it.
+fn seed_from_backup(
+    files: &mut FileTracker,
+    conversation: &Conversation,
+    positions: &HashMap<String, usize>,
+    uuid: &str,
+    input: &Value,
+    result: Option<&Value>,
+    resolver: &PathResolver,
+) {
+    let Some(path) = input.get("file_path").and_then(Value::as_str) else {
+        return;
+    };
+    let has_original = result.is_some_and(|r| {
+        r.get("originalFile").is_some_and(Value::is_string)
+            || r.get("originalFileContents").is_some_and(Value::is_string)
+    });
+    if has_original || files.knows(path) {
+        return;
+    }
+    if let Ok(Some(content)) = conversation.file_content_in(positions, uuid, path, resolver) {
+        files.seed(path, content);
     }
+}
 
-    #[test]
-    fn test_safe_prefix_unicode() {
-        assert_eq!(safe_prefix("日本語テスト", 3), "日本語");
+/// Record a plan or todo change, after any earlier one in the same turn.
+fn add_plan_change(
+    changes: &mut HashMap<String, ArtifactChange>,
+    key: String,
+    mut change: ArtifactChange,
+) {
+    if let Some(earlier) = changes.remove(&key) {
+        if let Some(structural) = change.structural.as_mut() {
+            plans::append_operations(earlier.structural.as_ref(), structural);
+        }
+        change = merge_changes(earlier, change, None);
     }
+    changes.insert(key, change);
+}
 
-    // ── derive_path ────────────────────────────────────────────────────
+/// Combine two changes to the same file within one turn. Raw diffs are
+/// composed when they line up; otherwise the diff is rebuilt from the
+/// file's `contents` before and after the turn, and dropped when those
+/// aren't known, since no single diff would be valid.
+fn merge_changes(
+    earlier: ArtifactChange,
+    later: ArtifactChange,
+    contents: Option<(&str, &str)>,
+) -> ArtifactChange {
+    let raw = match (earlier.raw, later.raw) {
+        (Some(a), Some(b)) => match (UnifiedDiff::parse(&a), UnifiedDiff::parse(&b)) {
+            (Ok(da), Ok(db)) => patch::compose(&da, &db).ok().map(|d| d.to_string()),
+            _ => None,
+        }
+        .or_else(|| {
+            contents.map(|(before, after)| edits::whole_file_diff(before, after).to_string())
+        }),
+        (a, b) => b.or(a),
+    };
+    ArtifactChange {
+        raw,
+        structural: later.structural.or(earlier.structural),
+    }
+}
 
-    #[test]
-    fn test_derive_path_basic() {
-        let entries = vec![
-            make_entry(
-                "uuid-1111-aaaa",
-                MessageRole::User,
-                "Hello",
+/// Whether `entry` is a prompt typed by the user, rather than tool results.
+fn is_prompt(entry: &ConversationEntry) -> bool {
+    entry.message.as_ref().is_some_and(|m| {
+        m.role == MessageRole::User && m.tool_results().is_empty() && !m.text().trim().is_empty()
+    }) && !entry.is_compact_summary()
+}
+
+/// The outcome of a tool use, as recorded in the entry that answered it.
+#[derive(Clone, Copy, Default)]
+struct ToolOutcome<'a> {
+    /// The entry's `toolUseResult` payload, if any.
+    result: Option<&'a Value>,
+    /// The `tool_result` content returned to the model.
+    content: Option<&'a ToolResultContent>,
+    is_error: bool,
+}
+
+/// Tool use outcomes by `tool_use_id`.
+fn tool_results(conversation: &Conversation) -> HashMap<&str, ToolOutcome<'_>> {
+    let mut results = HashMap::new();
+    for entry in &conversation.entries {
+        results.extend(entry_results(entry));
+    }
+    results
+}
+
+/// The outcomes of the tool uses `entry` answers, by `tool_use_id`.
+fn entry_results(entry: &ConversationEntry) -> impl Iterator<Item = (&str, ToolOutcome<'_>)> {
+    let results = entry
+        .message
+        .as_ref()
+        .map(|m| m.tool_results())
+        .unwrap_or_default();
+    results.into_iter().map(|r| {
+        (
+            r.tool_use_id,
+            ToolOutcome {
+                result: entry.tool_use_result.as_ref(),
+                content: Some(r.content),
+                is_error: r.is_error,
+            },
+        )
+    })
+}
+
+/// The revision of a commit a `Bash` tool use created, resolved through
+/// `repository` when one is given.
+fn commit_revision(
+    input: &Value,
+    outcome: ToolOutcome<'_>,
+    repository: Option<&dyn RevisionResolver>,
+) -> Option<String> {
+    let command = input.get("command").and_then(Value::as_str)?;
+    let output = match outcome
+        .result
+        .and_then(|r| r.get("stdout"))
+        .and_then(Value::as_str)
+    {
+        Some(stdout) => stdout.to_string(),
+        None => outcome.content?.text(),
+    };
+    let short = vcs::commit_hash(command, &output)?;
+    match repository {
+        Some(repo) => repo.resolve(&short),
+        None => Some(short),
+    }
+}
+
+/// Derive a Graph from a Claude conversation and its subagents (see
+/// [`crate::ClaudeConvo::read_subagents`]).
+///
+/// The first path is the session itself, without the subagents' sidechain
+/// entries. Each subagent follows as its own path, based on the step that
+/// delegated to it (`Base::toolpath`). The delegating step carries a
+/// `delegated-to` ref to the subagent's path, and the subagent's path a
+/// `delegated-from` ref back. Subagents whose `Task` tool use produced no
+/// step are left out.
+pub fn derive_graph(
+    conversation: &Conversation,
+    subagents: &[Subagent],
+    config: &DeriveConfig,
+) -> Graph {
+    let delegated: HashSet<&str> = subagents
+        .iter()
+        .flat_map(|s| s.conversation.entries.iter().map(|e| e.uuid.as_str()))
+        .collect();
+    let mut main_convo = conversation.clone();
+    main_convo
+        .entries
+        .retain(|e| !delegated.contains(e.uuid.as_str()));
+    let mut main = derive_path(&main_convo, config);
+    let main_id = main.path.id.clone();
+
+    // Task tool use ID → (delegating step, task description)
+    let mut tasks: HashMap<&str, (String, Option<&str>)> = HashMap::new();
+    for entry in &main_convo.entries {
+        for tu in entry.tool_uses().into_iter().filter(|tu| tu.name == "Task") {
+            let description = tu.input.get("description").and_then(Value::as_str);
+            let step_id = step_id(&entry.uuid);
+            tasks.insert(tu.id, (step_id, description));
+        }
+    }
+
+    let mut sub_paths = Vec::new();
+    for sub in subagents {
+        let Some((step_id, description)) = tasks.get(sub.tool_use_id.as_str()) else {
+            continue;
+        };
+        let Some(step) = main.steps.iter_mut().find(|s| &s.step.id == step_id) else {
+            continue;
+        };
+
+        let mut path = derive_path(&sub.conversation, config);
+        path.path.id = format!("{}-agent-{}", main_id, sub.conversation.session_id);
+        path.path.base = Some(Base::toolpath(&main_id, step_id));
+        let meta = path.meta.get_or_insert_with(Default::default);
+        meta.title = Some(match description {
+            Some(d) => format!("Claude subagent: {}", d),
+            None => format!(
+                "Claude subagent: {}",
+                safe_prefix(&sub.conversation.session_id, 8)
+            ),
+        });
+        meta.refs.push(Ref {
+            rel: "delegated-from".to_string(),
+            href: format!("toolpath:{}/{}", main_id, step_id),
+        });
+        step.meta
+            .get_or_insert_with(Default::default)
+            .refs
+            .push(Ref {
+                rel: "delegated-to".to_string(),
+                href: format!("toolpath:{}", path.path.id),
+            });
+        sub_paths.push(path);
+    }
+
+    let session_short = safe_prefix(&conversation.session_id, 8);
+    let mut graph = Graph::new(format!("graph-claude-{}", session_short));
+    graph.meta = Some(GraphMeta {
+        title: Some(format!("Claude session: {}", session_short)),
+        ..Default::default()
+    });
+    graph.paths = std::iter::once(main)
+        .chain(sub_paths)
+        .map(|p| PathOrRef::Path(Box::new(p)))
+        .collect();
+    graph
+}
+
+/// Derives steps from a session as it grows, typically fed from a
+/// [`ConversationWatcher`](crate::ConversationWatcher) poll loop.
+///
+/// State — the last step, actors, reconstructed file contents — carries
+/// across [`push`](Self::push) calls, so the steps match what
+/// [`derive_path`] produces for the finished log. An entry whose tool uses
+/// have no results yet is held back (along with everything after it) until
+/// they arrive, or until a later user prompt shows they never will;
+/// [`flush`](Self::flush) derives whatever is still waiting.
+///
+/// Only entries not yet derived are kept, so memory stays bounded by what is
+/// held back rather than growing with the session.
+pub struct IncrementalDeriver {
+    config: DeriveConfig,
+    /// The session, holding the entries not yet derived.
+    conversation: Conversation,
+    state: DeriveState,
+    /// Index into `conversation.entries` of the first entry not yet derived;
+    /// always 0 between calls.
+    next: usize,
+    /// Index into `conversation.entries` of the entry answering each tool
+    /// use, by `tool_use_id`.
+    answered: HashMap<String, usize>,
+}
+
+impl IncrementalDeriver {
+    /// Start deriving `session_id`. The conversation artifact and path ID
+    /// are those [`derive_path`] would use for the same session.
+    pub fn new(session_id: impl Into<String>, config: DeriveConfig) -> Self {
+        let mut conversation = Conversation::new(session_id.into());
+        conversation.project_path = config.project_path.clone();
+        Self {
+            config,
+            conversation,
+            state: DeriveState::new(),
+            next: 0,
+            answered: HashMap::new(),
+        }
+    }
+
+    /// The ID of the path the steps belong to.
+    pub fn path_id(&self) -> String {
+        path_id(&self.conversation.session_id)
+    }
+
+    /// The configuration steps are derived with, e.g. to check its
+    /// [`BlobDir`](crate::capture::BlobDir) for write errors.
+    pub fn config(&self) -> &DeriveConfig {
+        &self.config
+    }
+
+    /// The most recent main-line step, once there is one.
+    pub fn head(&self) -> Option<&str> {
+        self.state.last_step_id.as_deref()
+    }
+
+    /// The session so far, with the entries not yet derived.
+    pub fn conversation(&self) -> &Conversation {
+        &self.conversation
+    }
+
+    /// Whether entries are held back waiting for tool results.
+    pub fn is_waiting(&self) -> bool {
+        !self.conversation.entries.is_empty()
+    }
+
+    /// Add newly seen entries, returning the steps now ready.
+    pub fn push(&mut self, entries: impl IntoIterator<Item = ConversationEntry>) -> Vec<Step> {
+        for entry in entries {
+            let index = self.conversation.entries.len();
+            for (id, _) in entry_results(&entry) {
+                self.answered.insert(id.to_string(), index);
+            }
+            self.conversation.add_entry(entry);
+        }
+        self.derive_ready(false)
+    }
+
+    /// Fold in `file-history` snapshots read alongside the entries, for
+    /// [`DeriveConfig::file_history`].
+    pub fn merge_snapshots(&mut self, snapshots: impl IntoIterator<Item = FileSnapshot>) {
+        for snapshot in snapshots {
+            self.conversation.merge_file_snapshot(snapshot);
+        }
+    }
+
+    /// Record a session rotation, as reported by the watcher's
+    /// `take_pending_rotations`. Steps continue on the same path.
+    pub fn rotate(&mut self, from: &str, to: &str) {
+        let ids = &mut self.conversation.session_ids;
+        if ids.is_empty() {
+            ids.push(from.to_string());
+        }
+        if !ids.iter().any(|id| id == to) {
+            ids.push(to.to_string());
+        }
+    }
+
+    /// Derive every held-back entry, even those still waiting on tool
+    /// results.
+    pub fn flush(&mut self) -> Vec<Step> {
+        self.derive_ready(true)
+    }
+
+    fn derive_ready(&mut self, force: bool) -> Vec<Step> {
+        let Self {
+            config,
+            conversation,
+            state,
+            next,
+            answered,
+        } = self;
+        let mut steps = Vec::new();
+        while let Some(entry) = conversation.entries.get(*next) {
+            let mut results = HashMap::new();
+            let mut waiting = false;
+            for tu in entry.tool_uses() {
+                let outcome = answered.get(tu.id).and_then(|&i| {
+                    entry_results(&conversation.entries[i])
+                        .filter(|(id, _)| *id == tu.id)
+                        .last()
+                });
+                match outcome {
+                    Some((id, outcome)) => {
+                        results.insert(id, outcome);
+                    }
+                    None => waiting = true,
+                }
+            }
+            // A later prompt means the turn ended without the results
+            // (interrupted or crashed mid-tool), so they won't arrive.
+            let abandoned = || conversation.entries[*next + 1..].iter().any(is_prompt);
+            if waiting && !force && !abandoned() {
+                break;
+            }
+            steps.extend(state.derive(entry, conversation, &results, config));
+            *next += 1;
+        }
+        // Inferred changes belong to steps already handed out.
+        state.inferred.clear();
+
+        // Derived entries are no longer needed; the results of tool uses
+        // still waiting are all in later entries.
+        conversation.entries.drain(..*next);
+        answered.retain(|_, i| {
+            let kept = *i >= *next;
+            *i = i.saturating_sub(*next);
+            kept
+        });
+        *next = 0;
+        steps
+    }
+}
+
+/// Derive Toolpath Paths from multiple conversations in a project.
+pub fn derive_project(conversations: &[Conversation], config: &DeriveConfig) -> Vec<Path> {
+    conversations
+        .iter()
+        .map(|c| derive_path(c, config))
+        .collect()
+}
+
+/// A session resumed from another: the first entries of `successor` link
+/// (by `parentUuid` or `logicalParentUuid`) to entry `from_uuid` of
+/// `predecessor`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionLink {
+    pub predecessor: String,
+    pub successor: String,
+    pub from_uuid: String,
+}
+
+/// Links between the logical sessions of a project. Each session links to
+/// at most one predecessor: the session holding the first out-of-session
+/// entry its log refers back to.
+pub fn session_links(conversations: &[Conversation]) -> Vec<SessionLink> {
+    let owner: HashMap<&str, &str> = conversations
+        .iter()
+        .flat_map(|c| {
+            c.entries
+                .iter()
+                .map(move |e| (e.uuid.as_str(), c.session_id.as_str()))
+        })
+        .collect();
+
+    conversations
+        .iter()
+        .filter_map(|c| {
+            let own: HashSet<&str> = c.entries.iter().map(|e| e.uuid.as_str()).collect();
+            c.entries.iter().find_map(|entry| {
+                let parent = parent_link(entry).filter(|p| !own.contains(p))?;
+                let predecessor = owner.get(parent)?;
+                Some(SessionLink {
+                    predecessor: predecessor.to_string(),
+                    successor: c.session_id.clone(),
+                    from_uuid: parent.to_string(),
+                })
+            })
+        })
+        .collect()
+}
+
+/// Project-wide facts recorded on a project graph.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProjectSummary {
+    /// Timestamp of the earliest step.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<String>,
+    /// Timestamp of the latest step.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ended_at: Option<String>,
+    /// Every file changed in any session, sorted.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files_changed: Vec<String>,
+}
+
+impl Extension for ProjectSummary {
+    const KEY: &'static str = "claude.project";
+}
+
+/// Derive a Graph of a whole project: one path per logical session
+/// (rotated files already merged, as by
+/// [`ClaudeConvo::read_conversation`](crate::ClaudeConvo::read_conversation)),
+/// plus one per session that only survives in `history`.
+///
+/// A resumed session's path is based on the step it continued from, and
+/// the two carry `predecessor` / `successor` refs (see [`session_links`]).
+/// The graph's meta collects every path's actors and a [`ProjectSummary`].
+///
+/// `history` should hold the project's `history.jsonl` prompts, e.g. from
+/// [`ClaudeConvo::read_history`](crate::ClaudeConvo::read_history). Prompts
+/// of sessions that have a log are ignored; the rest become paths of
+/// `human:user` steps, since the prompts are all that is left of them.
+pub fn derive_project_graph(
+    conversations: &[Conversation],
+    history: &[HistoryEntry],
+    config: &DeriveConfig,
+) -> Graph {
+    let mut sessions: Vec<&Conversation> = conversations.iter().collect();
+    sessions.sort_by_key(|c| c.started_at);
+
+    let mut paths = Vec::new();
+    let mut states = HashMap::new();
+    for convo in &sessions {
+        let (path, state) = derive_session(convo, config);
+        states.insert(convo.session_id.as_str(), (path.path.id.clone(), state));
+        paths.push(path);
+    }
+
+    for link in session_links(conversations) {
+        let Some((from_path, state)) = states.get(link.predecessor.as_str()) else {
+            continue;
+        };
+        let from_step = state
+            .emitted
+            .get(&link.from_uuid)
+            .cloned()
+            .or_else(|| nearest_emitted(&link.from_uuid, &state.parent_of, &state.emitted));
+        let Some(to_index) = sessions.iter().position(|c| c.session_id == link.successor) else {
+            continue;
+        };
+        let to_path = paths[to_index].path.id.clone();
+        if let Some(step) = &from_step {
+            paths[to_index].path.base = Some(Base::toolpath(from_path, step));
+        }
+        paths[to_index]
+            .meta
+            .get_or_insert_with(Default::default)
+            .refs
+            .push(Ref {
+                rel: "predecessor".to_string(),
+                href: match &from_step {
+                    Some(step) => format!("toolpath:{}/{}", from_path, step),
+                    None => format!("toolpath:{}", from_path),
+                },
+            });
+        if let Some(from) = paths.iter_mut().find(|p| &p.path.id == from_path) {
+            from.meta
+                .get_or_insert_with(Default::default)
+                .refs
+                .push(Ref {
+                    rel: "successor".to_string(),
+                    href: format!("toolpath:{}", to_path),
+                });
+        }
+    }
+
+    // Sessions whose logs are gone, in order of their first prompt.
+    let known: HashSet<&str> = sessions
+        .iter()
+        .flat_map(|c| {
+            std::iter::once(c.session_id.as_str()).chain(c.session_ids.iter().map(String::as_str))
+        })
+        .collect();
+    let mut lost: Vec<(&str, Vec<&HistoryEntry>)> = Vec::new();
+    for entry in history {
+        let Some(session) = entry.session_id.as_deref().filter(|s| !known.contains(s)) else {
+            continue;
+        };
+        match lost.iter_mut().find(|(s, _)| *s == session) {
+            Some((_, prompts)) => prompts.push(entry),
+            None => lost.push((session, vec![entry])),
+        }
+    }
+    for (session, mut prompts) in lost {
+        prompts.sort_by_key(|e| e.timestamp);
+        paths.push(history_path(session, &prompts, config));
+    }
+
+    let project = config
+        .project_path
+        .as_deref()
+        .or_else(|| sessions.iter().find_map(|c| c.project_path.as_deref()));
+    let name = project
+        .and_then(|p| p.trim_end_matches('/').rsplit('/').next())
+        .filter(|n| !n.is_empty())
+        .unwrap_or("project");
+
+    let mut actors = HashMap::new();
+    for path in &paths {
+        if let Some(path_actors) = path.meta.as_ref().and_then(|m| m.actors.as_ref()) {
+            for (key, actor) in path_actors {
+                actors.entry(key.clone()).or_insert_with(|| actor.clone());
+            }
+        }
+    }
+    let mut meta = GraphMeta {
+        title: Some(format!("Claude project: {}", project.unwrap_or(name))),
+        actors: (!actors.is_empty()).then_some(actors),
+        ..Default::default()
+    };
+    // Serializing strings cannot fail.
+    let _ = meta.set_ext(project_summary(&paths));
+
+    let mut graph = Graph::new(format!("graph-claude-{}", name));
+    graph.meta = Some(meta);
+    graph.paths = paths
+        .into_iter()
+        .map(|p| PathOrRef::Path(Box::new(p)))
+        .collect();
+    graph
+}
+
+/// A path for a session known only from its `history.jsonl` prompts.
+fn history_path(session_id: &str, prompts: &[&HistoryEntry], config: &DeriveConfig) -> Path {
+    let artifact = format!("claude://{}", session_id);
+    let mut steps: Vec<Step> = Vec::new();
+    for (i, prompt) in prompts.iter().enumerate() {
+        let timestamp = chrono::DateTime::from_timestamp_millis(prompt.timestamp)
+            .map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+            .unwrap_or_default();
+        let mut change = StructuralChange {
+            change_type: "conversation.append".to_string(),
+            extra: HashMap::from([
+                ("role".to_string(), json!("user")),
+                ("source".to_string(), json!("history")),
+            ]),
+        };
+        capture::record(
+            &mut change.extra,
+            "text",
+            &prompt.display,
+            config.capture.text,
+            config.blobs.as_ref(),
+        );
+        let mut changes = HashMap::new();
+        let mut attachments = Vec::new();
+        for (_, image) in prompt.images() {
+            if let Some((key, image_change)) = attachment_change("image", &image, None) {
+                attachments.push(key.clone());
+                changes.entry(key).or_insert(image_change);
+            }
+        }
+        if !attachments.is_empty() {
+            change
+                .extra
+                .insert("attachments".to_string(), json!(attachments));
+        }
+        changes.insert(artifact.clone(), structural(change));
+        steps.push(Step {
+            version: None,
+            step: StepIdentity {
+                id: format!("step-history-{}", i + 1),
+                parents: steps
+                    .last()
+                    .map(|s| s.step.id.clone())
+                    .into_iter()
+                    .collect(),
+                actor: "human:user".to_string(),
+                timestamp,
+            },
+            change: changes,
+            meta: None,
+        });
+    }
+
+    let project = config
+        .project_path
+        .as_deref()
+        .or_else(|| prompts.iter().find_map(|p| p.project.as_deref()));
+    Path {
+        version: None,
+        path: PathIdentity {
+            id: path_id(session_id),
+            base: project.map(|p| Base {
+                uri: format!("file://{}", p),
+                ref_str: None,
+            }),
+            head: steps
+                .last()
+                .map(|s| s.step.id.clone())
+                .unwrap_or_else(|| "empty".to_string()),
+        },
+        steps,
+        meta: Some(PathMeta {
+            title: Some(format!(
+                "Claude session: {} (history only)",
+                safe_prefix(session_id, 8)
+            )),
+            source: Some("claude-code".to_string()),
+            actors: Some(HashMap::from([(
+                "human:user".to_string(),
+                ActorDefinition {
+                    name: Some("User".to_string()),
+                    ..Default::default()
+                },
+            )])),
+            ..Default::default()
+        }),
+    }
+}
+
+/// Time range and changed files across `paths`.
+fn project_summary(paths: &[Path]) -> ProjectSummary {
+    let mut times: Vec<(chrono::DateTime<chrono::FixedOffset>, &str)> = paths
+        .iter()
+        .flat_map(|p| &p.steps)
+        .filter_map(|s| {
+            let ts = s.step.timestamp.as_str();
+            chrono::DateTime::parse_from_rfc3339(ts)
+                .ok()
+                .map(|t| (t, ts))
+        })
+        .collect();
+    times.sort();
+    let files: std::collections::BTreeSet<&str> = paths
+        .iter()
+        .flat_map(|p| &p.steps)
+        .flat_map(|s| s.change.keys())
+        .filter(|k| !k.contains("://"))
+        .map(String::as_str)
+        .collect();
+    ProjectSummary {
+        started_at: times.first().map(|(_, ts)| ts.to_string()),
+        ended_at: times.last().map(|(_, ts)| ts.to_string()),
+        files_changed: files.into_iter().map(str::to_string).collect(),
+    }
+}
+
+/// Truncate a string to at most `max` characters (not bytes), appending "..."
+/// if truncated and there is room for it. Always cuts on a char boundary.
+pub(crate) fn truncate(s: &str, max: usize) -> String {
+    let char_count = s.chars().count();
+    if char_count <= max {
+        s.to_string()
+    } else if max < 3 {
+        s.chars().take(max).collect()
+    } else {
+        let truncated: String = s.chars().take(max - 3).collect();
+        format!("{}...", truncated)
+    }
+}
+
+/// ID of the step derived from the entry with `uuid`.
+pub(crate) fn step_id(uuid: &str) -> String {
+    format!("step-{}", safe_prefix(uuid, 8))
+}
+
+/// Return the first `n` characters of a string, safe for any UTF-8 content.
+fn safe_prefix(s: &str, n: usize) -> String {
+    s.chars().take(n).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ContentPart, ConversationEntry, Message, MessageContent};
+
+    fn make_entry(
+        uuid: &str,
+        role: MessageRole,
+        content: &str,
+        timestamp: &str,
+    ) -> ConversationEntry {
+        ConversationEntry {
+            parent_uuid: None,
+            is_sidechain: false,
+            entry_type: match role {
+                MessageRole::User => "user",
+                MessageRole::Assistant => "assistant",
+                MessageRole::System => "system",
+            }
+            .to_string(),
+            uuid: uuid.to_string(),
+            timestamp: timestamp.to_string(),
+            session_id: Some("test-session".to_string()),
+            cwd: None,
+            git_branch: None,
+            version: None,
+            message: Some(Message {
+                role,
+                content: Some(MessageContent::Text(content.to_string())),
+                model: None,
+                id: None,
+                message_type: None,
+                stop_reason: None,
+                stop_sequence: None,
+                usage: None,
+            }),
+            user_type: None,
+            request_id: None,
+            tool_use_result: None,
+            snapshot: None,
+            message_id: None,
+            extra: Default::default(),
+        }
+    }
+
+    fn make_conversation(entries: Vec<ConversationEntry>) -> Conversation {
+        let mut convo = Conversation::new("test-session-12345678".to_string());
+        for entry in entries {
+            convo.add_entry(entry);
+        }
+        convo
+    }
+
+    // ── truncate ───────────────────────────────────────────────────────
+
+    #[test]
+    fn test_truncate_short() {
+        assert_eq!(truncate("hello", 10), "hello");
+    }
+
+    #[test]
+    fn test_truncate_exact() {
+        assert_eq!(truncate("hello", 5), "hello");
+    }
+
+    #[test]
+    fn test_truncate_long() {
+        let result = truncate("hello world, this is long", 10);
+        assert!(result.ends_with("..."));
+        assert_eq!(result.chars().count(), 10);
+    }
+
+    #[test]
+    fn test_truncate_no_room_for_ellipsis() {
+        assert_eq!(truncate("hello", 2), "he");
+        assert_eq!(truncate("hello", 0), "");
+    }
+
+    #[test]
+    fn test_truncate_multibyte() {
+        // Should not panic on multi-byte characters
+        let s = "café résumé naïve";
+        let result = truncate(s, 8);
+        assert!(result.ends_with("..."));
+        assert_eq!(result.chars().count(), 8);
+    }
+
+    // ── safe_prefix ────────────────────────────────────────────────────
+
+    #[test]
+    fn test_safe_prefix_normal() {
+        assert_eq!(safe_prefix("abcdef1234", 8), "abcdef12");
+    }
+
+    #[test]
+    fn test_safe_prefix_short() {
+        assert_eq!(safe_prefix("abc", 8), "abc");
+    }
+
+    #[test]
+    fn test_safe_prefix_unicode() {
+        assert_eq!(safe_prefix("日本語テスト", 3), "日本語");
+    }
+
+    // ── derive_path ────────────────────────────────────────────────────
+
+    #[test]
+    fn test_derive_path_basic() {
+        let entries = vec![
+            make_entry(
+                "uuid-1111-aaaa",
+                MessageRole::User,
+                "Hello",
+                "2024-01-01T00:00:00Z",
+            ),
+            make_entry(
+                "uuid-2222-bbbb",
+                MessageRole::Assistant,
+                "Hi there",
+                "2024-01-01T00:00:01Z",
+            ),
+        ];
+        let convo = make_conversation(entries);
+        let config = DeriveConfig::default();
+
+        let path = derive_path(&convo, &config);
+
+        assert!(path.path.id.starts_with("path-claude-"));
+        assert_eq!(path.steps.len(), 2);
+        assert_eq!(path.steps[0].step.actor, "human:user");
+        assert!(path.steps[1].step.actor.starts_with("agent:"));
+    }
+
+    #[test]
+    fn test_derive_path_step_parents() {
+        let entries = vec![
+            make_entry(
+                "uuid-1111",
+                MessageRole::User,
+                "Hello",
+                "2024-01-01T00:00:00Z",
+            ),
+            make_entry(
+                "uuid-2222",
+                MessageRole::Assistant,
+                "Hi",
+                "2024-01-01T00:00:01Z",
+            ),
+            make_entry(
+                "uuid-3333",
+                MessageRole::User,
+                "More",
+                "2024-01-01T00:00:02Z",
+            ),
+        ];
+        let convo = make_conversation(entries);
+        let config = DeriveConfig::default();
+
+        let path = derive_path(&convo, &config);
+
+        // Second step should have first as parent
+        assert!(path.steps[1].step.parents.contains(&path.steps[0].step.id));
+        // Third step should have second as parent
+        assert!(path.steps[2].step.parents.contains(&path.steps[1].step.id));
+    }
+
+    #[test]
+    fn test_derive_path_conversation_artifact() {
+        let entries = vec![make_entry(
+            "uuid-1111",
+            MessageRole::User,
+            "Hello",
+            "2024-01-01T00:00:00Z",
+        )];
+        let convo = make_conversation(entries);
+        let config = DeriveConfig::default();
+
+        let path = derive_path(&convo, &config);
+
+        // Each step should have the conversation artifact
+        let convo_key = format!("claude://{}", convo.session_id);
+        assert!(path.steps[0].change.contains_key(&convo_key));
+
+        let change = &path.steps[0].change[&convo_key];
+        let structural = change.structural.as_ref().unwrap();
+        assert_eq!(structural.change_type, "conversation.append");
+        assert_eq!(structural.extra["role"], "user");
+    }
+
+    #[test]
+    fn test_derive_path_no_meta_intent() {
+        let entries = vec![make_entry(
+            "uuid-1111",
+            MessageRole::User,
+            "Hello",
+            "2024-01-01T00:00:00Z",
+        )];
+        let convo = make_conversation(entries);
+        let config = DeriveConfig::default();
+
+        let path = derive_path(&convo, &config);
+
+        // meta.intent should NOT be set (we removed it as redundant)
+        assert!(path.steps[0].meta.is_none());
+    }
+
+    #[test]
+    fn test_derive_path_actors() {
+        let entries = vec![
+            make_entry(
+                "uuid-1111",
+                MessageRole::User,
+                "Hello",
+                "2024-01-01T00:00:00Z",
+            ),
+            make_entry(
+                "uuid-2222",
+                MessageRole::Assistant,
+                "Hi",
+                "2024-01-01T00:00:01Z",
+            ),
+        ];
+        let convo = make_conversation(entries);
+        let config = DeriveConfig::default();
+
+        let path = derive_path(&convo, &config);
+        let actors = path.meta.as_ref().unwrap().actors.as_ref().unwrap();
+
+        assert!(actors.contains_key("human:user"));
+        // Assistant actor depends on model (None in our test)
+        assert!(actors.contains_key("agent:claude-code"));
+    }
+
+    #[test]
+    fn test_derive_path_with_project_path_config() {
+        let convo = make_conversation(vec![make_entry(
+            "uuid-1",
+            MessageRole::User,
+            "Hello",
+            "2024-01-01T00:00:00Z",
+        )]);
+        let config = DeriveConfig {
+            project_path: Some("/my/project".to_string()),
+            ..Default::default()
+        };
+
+        let path = derive_path(&convo, &config);
+        assert_eq!(path.path.base.as_ref().unwrap().uri, "file:///my/project");
+    }
+
+    #[test]
+    fn test_derive_path_skips_empty_content() {
+        let mut entry = make_entry("uuid-1111", MessageRole::User, "", "2024-01-01T00:00:00Z");
+        // Empty text, no tool uses, no file changes → should be skipped
+        entry.message.as_mut().unwrap().content = Some(MessageContent::Text("   ".to_string()));
+
+        let convo = make_conversation(vec![entry]);
+        let config = DeriveConfig::default();
+
+        let path = derive_path(&convo, &config);
+        assert!(path.steps.is_empty());
+    }
+
+    #[test]
+    fn test_derive_path_skips_system_messages() {
+        let entries = vec![
+            make_entry(
+                "uuid-1111",
+                MessageRole::System,
+                "System prompt",
+                "2024-01-01T00:00:00Z",
+            ),
+            make_entry(
+                "uuid-2222",
+                MessageRole::User,
+                "Hello",
+                "2024-01-01T00:00:01Z",
+            ),
+        ];
+        let convo = make_conversation(entries);
+        let config = DeriveConfig::default();
+
+        let path = derive_path(&convo, &config);
+        // System message should be skipped
+        assert_eq!(path.steps.len(), 1);
+        assert_eq!(path.steps[0].step.actor, "human:user");
+    }
+
+    #[test]
+    fn test_derive_path_with_tool_use() {
+        let mut convo = Conversation::new("test-session-12345678".to_string());
+        let entry = ConversationEntry {
+            parent_uuid: None,
+            is_sidechain: false,
+            entry_type: "assistant".to_string(),
+            uuid: "uuid-tool".to_string(),
+            timestamp: "2024-01-01T00:00:00Z".to_string(),
+            session_id: Some("test-session".to_string()),
+            message: Some(Message {
+                role: MessageRole::Assistant,
+                content: Some(MessageContent::Parts(vec![
+                    ContentPart::Text {
+                        text: "Let me write that".to_string(),
+                    },
+                    ContentPart::ToolUse {
+                        id: "t1".to_string(),
+                        name: "Write".to_string(),
+                        input: serde_json::json!({"file_path": "/tmp/test.rs"}),
+                    },
+                ])),
+                model: Some("claude-sonnet-4-5-20250929".to_string()),
+                id: None,
+                message_type: None,
+                stop_reason: None,
+                stop_sequence: None,
+                usage: None,
+            }),
+            cwd: None,
+            git_branch: None,
+            version: None,
+            user_type: None,
+            request_id: None,
+            tool_use_result: None,
+            snapshot: None,
+            message_id: None,
+            extra: Default::default(),
+        };
+        convo.add_entry(entry);
+        let config = DeriveConfig::default();
+
+        let path = derive_path(&convo, &config);
+
+        assert_eq!(path.steps.len(), 1);
+        // Should have both the conversation artifact and the file artifact
+        assert!(path.steps[0].change.contains_key("/tmp/test.rs"));
+        let convo_key = format!("claude://{}", convo.session_id);
+        assert!(path.steps[0].change.contains_key(&convo_key));
+    }
+
+    fn thinking_conversation(text: &str) -> Conversation {
+        let mut entry = make_entry(
+            "uuid-think",
+            MessageRole::Assistant,
+            "",
+            "2024-01-01T00:00:00Z",
+        );
+        entry.message.as_mut().unwrap().content = Some(MessageContent::Parts(vec![
+            ContentPart::Thinking {
+                thinking: "Weighing the options".to_string(),
+                signature: None,
+            },
+            ContentPart::Text {
+                text: text.to_string(),
+            },
+        ]));
+        make_conversation(vec![entry])
+    }
+
+    fn append_extra(path: &Path) -> &HashMap<String, Value> {
+        &path.steps[0].change["claude://test-session-12345678"]
+            .structural
+            .as_ref()
+            .unwrap()
+            .extra
+    }
+
+    #[test]
+    fn test_derive_path_capture_default() {
+        let long = "x".repeat(2500);
+        let convo = thinking_conversation(&long);
+        let path = derive_path(&convo, &DeriveConfig::default());
+
+        let extra = append_extra(&path);
+        assert_eq!(extra["text"].as_str().unwrap().chars().count(), 2000);
+        assert_eq!(extra["text_sha256"], json!(digest::sha256(long.as_bytes())));
+        assert!(!extra.contains_key("thinking"));
+    }
+
+    #[test]
+    fn test_derive_path_capture_thinking_separately() {
+        let convo = thinking_conversation("Done");
+        let config = DeriveConfig {
+            capture: CapturePolicy {
+                thinking: Capture::Full,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let path = derive_path(&convo, &config);
+
+        let extra = append_extra(&path);
+        assert_eq!(extra["text"], json!("Done"));
+        assert_eq!(extra["thinking"], json!("Weighing the options"));
+    }
+
+    #[test]
+    fn test_derive_path_capture_blobs() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let long = "y".repeat(50);
+        let convo = thinking_conversation(&long);
+        let config = DeriveConfig {
+            capture: CapturePolicy {
+                text: Capture::Digest,
+                thinking: Capture::Truncate(10),
+                ..Default::default()
+            },
+            blobs: Some(BlobDir::new(temp.path())),
+            ..Default::default()
+        };
+        let path = derive_path(&convo, &config);
+
+        let extra = append_extra(&path);
+        assert!(!extra.contains_key("text"));
+        assert_eq!(extra["thinking"], json!("Weighin..."));
+        let blobs = config.blobs.as_ref().unwrap();
+        for field in ["text_sha256", "thinking_sha256"] {
+            assert!(blobs.file(extra[field].as_str().unwrap()).exists());
+        }
+        let text = std::fs::read_to_string(blobs.file(extra["text_sha256"].as_str().unwrap()));
+        assert_eq!(text.unwrap(), long);
+        assert!(blobs.take_error().is_none());
+    }
+
+    #[test]
+    fn test_derive_path_sidechain_uses_parent_uuid() {
+        let mut convo = Conversation::new("test-session-12345678".to_string());
+
+        let e1 = make_entry(
+            "uuid-main-11",
+            MessageRole::User,
+            "Hello",
+            "2024-01-01T00:00:00Z",
+        );
+        let e2 = make_entry(
+            "uuid-main-22",
+            MessageRole::Assistant,
+            "Hi",
+            "2024-01-01T00:00:01Z",
+        );
+        let mut e3 = make_entry(
+            "uuid-side-33",
+            MessageRole::User,
+            "Side",
+            "2024-01-01T00:00:02Z",
+        );
+        e3.is_sidechain = true;
+        e3.parent_uuid = Some("uuid-main-11".to_string());
+
+        convo.add_entry(e1);
+        convo.add_entry(e2);
+        convo.add_entry(e3);
+
+        let config = DeriveConfig::default();
+        let path = derive_path(&convo, &config);
+
+        assert_eq!(path.steps.len(), 3);
+        // Sidechain step should reference e1 as parent, not e2
+        let sidechain_step = &path.steps[2];
+        let expected_parent = format!("step-{}", safe_prefix("uuid-main-11", 8));
+        assert!(sidechain_step.step.parents.contains(&expected_parent));
+    }
+
+    fn parts_entry(
+        uuid: &str,
+        role: MessageRole,
+        parts: Vec<ContentPart>,
+        timestamp: &str,
+    ) -> ConversationEntry {
+        let mut entry = make_entry(uuid, role, "", timestamp);
+        entry.message.as_mut().unwrap().content = Some(MessageContent::Parts(parts));
+        entry
+    }
+
+    fn tool_use(id: &str, name: &str, input: serde_json::Value) -> ContentPart {
+        ContentPart::ToolUse {
+            id: id.to_string(),
+            name: name.to_string(),
+            input,
+        }
+    }
+
+    fn tool_result_entry(
+        uuid: &str,
+        tool_use_id: &str,
+        result: serde_json::Value,
+        is_error: bool,
+    ) -> ConversationEntry {
+        let mut entry = parts_entry(
+            uuid,
+            MessageRole::User,
+            vec![ContentPart::ToolResult {
+                tool_use_id: tool_use_id.to_string(),
+                content: crate::types::ToolResultContent::Text("ok".to_string()),
+                is_error,
+            }],
+            "2024-01-01T00:00:01Z",
+        );
+        entry.tool_use_result = Some(result);
+        entry
+    }
+
+    #[test]
+    fn test_derive_path_edit_has_raw_diff() {
+        let convo = make_conversation(vec![
+            parts_entry(
+                "uuid-edit",
+                MessageRole::Assistant,
+                vec![tool_use(
+                    "t1",
+                    "Edit",
+                    serde_json::json!({"file_path": "/p/src/lib.rs", "old_string": "b", "new_string": "B"}),
+                )],
+                "2024-01-01T00:00:00Z",
+            ),
+            tool_result_entry(
+                "uuid-result",
+                "t1",
+                serde_json::json!({"filePath": "/p/src/lib.rs", "originalFile": "a\nb\nc\n"}),
+                false,
+            ),
+        ]);
+
+        let path = derive_path(&convo, &DeriveConfig::default());
+        let change = &path.steps[0].change["/p/src/lib.rs"];
+        assert_eq!(
+            change.raw.as_deref(),
+            Some("@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n")
+        );
+    }
+
+    #[test]
+    fn test_derive_path_records_attachments() {
+        let image = |source: serde_json::Value| ContentPart::Image {
+            source: serde_json::from_value(source).unwrap(),
+        };
+        let convo = make_conversation(vec![parts_entry(
+            "uuid-image",
+            MessageRole::User,
+            vec![
+                image(json!({"type": "base64", "media_type": "image/png", "data": "Zm9v"})),
+                image(json!({"type": "url", "url": "https://example.com/a.png"})),
+            ],
+            "2024-01-01T00:00:00Z",
+        )]);
+
+        let path = derive_path(&convo, &DeriveConfig::default());
+        assert_eq!(path.steps.len(), 1);
+        let key = format!(
+            "attachment://sha256/{}",
+            digest::sha256(b"foo").trim_start_matches("sha256:")
+        );
+        let change = path.steps[0].change[&key].structural.as_ref().unwrap();
+        assert_eq!(change.change_type, "attachment.add");
+        assert_eq!(change.extra["kind"], "image");
+        assert_eq!(change.extra["media_type"], "image/png");
+        assert_eq!(change.extra["size"], 3);
+
+        let convo_change = path.steps[0].change["claude://test-session-12345678"]
+            .structural
+            .as_ref()
+            .unwrap();
+        assert_eq!(convo_change.extra["attachments"], json!([key]));
+        assert_eq!(path.steps[0].change.len(), 2);
+    }
+
+    #[test]
+    fn test_derive_path_mcp_calls() {
+        let convo = make_conversation(vec![
+            parts_entry(
+                "uuid-mcp",
+                MessageRole::Assistant,
+                vec![
+                    tool_use("t1", "mcp__postgres__query", json!({"sql": "select 1"})),
+                    tool_use("t2", "mcp__jira__get_issue", json!({"key": "OPS-1"})),
+                ],
+                "2024-01-01T00:00:00Z",
+            ),
+            tool_result_entry("uuid-result", "t1", json!({}), false),
+        ]);
+        let config = DeriveConfig {
+            tool_categories: ToolCategories {
+                servers: HashMap::from([("postgres".to_string(), ToolCategory::FileRead)]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let path = derive_path(&convo, &config);
+        let ids: Vec<&str> = path.steps.iter().map(|s| s.step.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "step-uuid-mcp",
+                "step-uuid-mcp-mcp-postgres",
+                "step-uuid-mcp-mcp-jira"
+            ]
+        );
+        assert_eq!(path.steps[1].step.actor, "tool:mcp:postgres");
+        assert_eq!(path.steps[1].step.parents, vec!["step-uuid-mcp"]);
+        assert_eq!(
+            path.steps[2].step.parents,
+            vec!["step-uuid-mcp-mcp-postgres"]
+        );
+        assert_eq!(path.path.head, "step-uuid-mcp-mcp-jira");
+
+        let call = path.steps[1].change["mcp://postgres/t1"]
+            .structural
+            .as_ref()
+            .unwrap();
+        assert_eq!(call.change_type, "mcp.call");
+        assert_eq!(call.extra["tool"], "query");
+        assert_eq!(call.extra["input"]["sql"], "select 1");
+        assert_eq!(call.extra["category"], "file_read");
+        assert_eq!(call.extra["output"], "ok");
+        let call = path.steps[2].change["mcp://jira/t2"]
+            .structural
+            .as_ref()
+            .unwrap();
+        assert!(!call.extra.contains_key("category"));
+        assert!(!call.extra.contains_key("output"));
+
+        let actors = path.meta.as_ref().unwrap().actors.as_ref().unwrap();
+        let postgres = &actors["tool:mcp:postgres"];
+        assert_eq!(postgres.name.as_deref(), Some("postgres"));
+        assert_eq!(postgres.identities[0].system, "mcp");
+    }
+
+    #[test]
+    fn test_derive_path_edits_in_one_turn_compose() {
+        let convo = make_conversation(vec![
+            parts_entry(
+                "uuid-write",
+                MessageRole::Assistant,
+                vec![
+                    tool_use(
+                        "t1",
+                        "Write",
+                        serde_json::json!({"file_path": "/p/a.txt", "content": "x\ny\n"}),
+                    ),
+                    tool_use(
+                        "t2",
+                        "Edit",
+                        serde_json::json!({"file_path": "/p/a.txt", "old_string": "y", "new_string": "z"}),
+                    ),
+                ],
+                "2024-01-01T00:00:00Z",
+            ),
+            tool_result_entry(
+                "uuid-result",
+                "t1",
+                serde_json::json!({"type": "create", "originalFile": null}),
+                false,
+            ),
+        ]);
+
+        let path = derive_path(&convo, &DeriveConfig::default());
+        let raw = path.steps[0].change["/p/a.txt"].raw.as_deref().unwrap();
+        let diff = UnifiedDiff::parse(raw).unwrap();
+        assert_eq!(diff.apply("").unwrap(), "x\nz\n");
+    }
+
+    #[test]
+    fn test_derive_path_unknown_change_in_turn_drops_raw() {
+        let convo = make_conversation(vec![parts_entry(
+            "uuid-write",
+            MessageRole::Assistant,
+            vec![
+                tool_use(
+                    "t1",
+                    "Write",
+                    serde_json::json!({"file_path": "/p/a.txt", "content": "x\ny\n"}),
+                ),
+                tool_use(
+                    "t2",
+                    "Edit",
+                    serde_json::json!({"file_path": "/p/a.txt", "old_string": "y", "new_string": "z"}),
+                ),
+            ],
+            "2024-01-01T00:00:00Z",
+        )]);
+
+        // The Write replaced unknown content, so the Edit's diff alone
+        // would misdescribe the turn.
+        let path = derive_path(&convo, &DeriveConfig::default());
+        let change = &path.steps[0].change["/p/a.txt"];
+        assert!(change.raw.is_none());
+        assert_eq!(
+            change.structural.as_ref().unwrap().change_type,
+            "file.write"
+        );
+    }
+
+    #[test]
+    fn test_deriver_is_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<DeriveConfig>();
+        assert_send::<IncrementalDeriver>();
+    }
+
+    #[test]
+    fn test_derive_path_edits_that_dont_compose() {
+        let edit = |id: &str, old: &str, new: &str| {
+            tool_use(
+                id,
+                "Edit",
+                serde_json::json!({"file_path": "/p/a.txt", "old_string": old, "new_string": new}),
+            )
+        };
+        // The file changed on disk between the two edits.
+        let convo = make_conversation(vec![
+            parts_entry(
+                "uuid-edit",
+                MessageRole::Assistant,
+                vec![edit("t1", "b", "B"), edit("t2", "b2", "c")],
+                "2024-01-01T00:00:00Z",
+            ),
+            tool_result_entry(
+                "uuid-r1",
+                "t1",
+                serde_json::json!({"filePath": "/p/a.txt", "originalFile": "a\nb\n"}),
+                false,
+            ),
+            tool_result_entry(
+                "uuid-r2",
+                "t2",
+                serde_json::json!({"filePath": "/p/a.txt", "originalFile": "a\nb2\n"}),
+                false,
+            ),
+        ]);
+        let path = derive_path(&convo, &DeriveConfig::default());
+        let raw = path.steps[0].change["/p/a.txt"].raw.as_deref().unwrap();
+        assert_eq!(
+            UnifiedDiff::parse(raw).unwrap().apply("a\nb\n").unwrap(),
+            "a\nc\n"
+        );
+
+        // Without either file's content, no single diff is valid.
+        let convo = make_conversation(vec![parts_entry(
+            "uuid-edit",
+            MessageRole::Assistant,
+            vec![edit("t1", "y", "z"), edit("t2", "q", "r")],
+            "2024-01-01T00:00:00Z",
+        )]);
+        let path = derive_path(&convo, &DeriveConfig::default());
+        assert_eq!(path.steps[0].change["/p/a.txt"].raw, None);
+    }
+
+    #[test]
+    fn test_derive_path_failed_tool_use_is_not_a_change() {
+        let convo = make_conversation(vec![
+            parts_entry(
+                "uuid-edit",
+                MessageRole::Assistant,
+                vec![tool_use(
+                    "t1",
+                    "Edit",
+                    serde_json::json!({"file_path": "/p/f", "old_string": "a", "new_string": "b"}),
+                )],
+                "2024-01-01T00:00:00Z",
+            ),
+            tool_result_entry("uuid-result", "t1", serde_json::json!("Error"), true),
+        ]);
+
+        let path = derive_path(&convo, &DeriveConfig::default());
+        assert!(!path.steps[0].change.contains_key("/p/f"));
+        assert_eq!(
+            path.steps[0].change[&format!("claude://{}", convo.session_id)]
+                .structural
+                .as_ref()
+                .unwrap()
+                .extra["tool_uses"],
+            serde_json::json!(["Edit"])
+        );
+    }
+
+    #[test]
+    fn test_derive_path_read_then_edit_uses_content() {
+        let convo = make_conversation(vec![
+            parts_entry(
+                "uuid-read",
+                MessageRole::Assistant,
+                vec![tool_use(
+                    "t1",
+                    "Read",
+                    serde_json::json!({"file_path": "/p/f"}),
+                )],
                 "2024-01-01T00:00:00Z",
             ),
+            tool_result_entry(
+                "uuid-read-result",
+                "t1",
+                serde_json::json!({"type": "text", "file": {"filePath": "/p/f", "content": "1\n2\n3\n4\n5\n6\n", "startLine": 1, "numLines": 6, "totalLines": 6}}),
+                false,
+            ),
+            parts_entry(
+                "uuid-edit",
+                MessageRole::Assistant,
+                vec![tool_use(
+                    "t2",
+                    "Edit",
+                    serde_json::json!({"file_path": "/p/f", "old_string": "6", "new_string": "six"}),
+                )],
+                "2024-01-01T00:00:02Z",
+            ),
+        ]);
+
+        let path = derive_path(&convo, &DeriveConfig::default());
+        let edit_step = path.steps.last().unwrap();
+        assert_eq!(
+            edit_step.change["/p/f"].raw.as_deref(),
+            Some("@@ -3,4 +3,4 @@\n 3\n 4\n 5\n-6\n+six\n")
+        );
+    }
+
+    #[test]
+    fn test_derive_path_uses_file_history_backup() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let resolver = PathResolver::new().with_claude_dir(temp.path());
+        let backups = resolver.file_history_dir("test-session-12345678").unwrap();
+        std::fs::create_dir_all(&backups).unwrap();
+        std::fs::write(backups.join("abc@v1"), "a\nb\nc\n").unwrap();
+
+        let mut convo = make_conversation(vec![
             make_entry(
-                "uuid-2222-bbbb",
+                "uuid-user",
+                MessageRole::User,
+                "Fix b",
+                "2024-01-01T00:00:00Z",
+            ),
+            parts_entry(
+                "uuid-edit",
                 MessageRole::Assistant,
-                "Hi there",
+                vec![tool_use(
+                    "t1",
+                    "Edit",
+                    serde_json::json!({"file_path": "/p/f", "old_string": "b", "new_string": "B"}),
+                )],
                 "2024-01-01T00:00:01Z",
             ),
-        ];
-        let convo = make_conversation(entries);
-        let config = DeriveConfig::default();
+        ]);
+        let snapshot: crate::types::ConversationEntry = serde_json::from_value(serde_json::json!({
+            "type": "file-history-snapshot",
+            "messageId": "uuid-user",
+            "snapshot": {
+                "messageId": "uuid-user",
+                "trackedFileBackups": {"/p/f": {"backupFileName": "abc@v1", "version": 1}}
+            }
+        }))
+        .unwrap();
+        let session = convo.session_id.clone();
+        convo.add_file_snapshot(&session, &snapshot);
+
+        // Without backups only the edit strings are known.
+        let plain = derive_path(&convo, &DeriveConfig::default());
+        let change = &plain.steps[1].change["/p/f"];
+        assert!(change.raw.is_none());
+        assert_eq!(change.structural.as_ref().unwrap().change_type, "file.edit");
+        assert!(plain.steps[1].meta.is_none());
 
+        let config = DeriveConfig {
+            file_history: Some(resolver),
+            base_digests: true,
+            ..Default::default()
+        };
         let path = derive_path(&convo, &config);
+        let step = &path.steps[1];
+        assert_eq!(
+            step.change["/p/f"].raw.as_deref(),
+            Some("@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n")
+        );
+        let digests = step
+            .meta
+            .as_ref()
+            .unwrap()
+            .get_ext::<BaseDigests>()
+            .unwrap()
+            .unwrap();
+        assert_eq!(digests.0["/p/f"], digest::sha256(b"a\nb\nc\n"));
+    }
 
-        assert!(path.path.id.starts_with("path-claude-"));
-        assert_eq!(path.steps.len(), 2);
-        assert_eq!(path.steps[0].step.actor, "human:user");
-        assert!(path.steps[1].step.actor.starts_with("agent:"));
+    // ── derive_project ─────────────────────────────────────────────────
+
+    #[test]
+    fn test_derive_project() {
+        let c1 = make_conversation(vec![make_entry(
+            "uuid-1",
+            MessageRole::User,
+            "Hello",
+            "2024-01-01T00:00:00Z",
+        )]);
+        let mut c2 = Conversation::new("session-2".to_string());
+        c2.add_entry(make_entry(
+            "uuid-2",
+            MessageRole::User,
+            "World",
+            "2024-01-02T00:00:00Z",
+        ));
+
+        let config = DeriveConfig::default();
+        let paths = derive_project(&[c1, c2], &config);
+
+        assert_eq!(paths.len(), 2);
     }
 
     #[test]
-    fn test_derive_path_step_parents() {
-        let entries = vec![
-            make_entry(
-                "uuid-1111",
+    fn test_derive_project_graph_links_resumed_sessions() {
+        let mut first = Conversation::new("aaaaaaaa-1".to_string());
+        first.add_entry(make_entry(
+            "uuid-a1",
+            MessageRole::User,
+            "Fix it",
+            "2024-01-01T00:00:00Z",
+        ));
+        let mut edit = parts_entry(
+            "uuid-a2",
+            MessageRole::Assistant,
+            vec![tool_use(
+                "t1",
+                "Write",
+                json!({"file_path": "/p/src/lib.rs", "content": "x\n"}),
+            )],
+            "2024-01-01T00:00:01Z",
+        );
+        edit.parent_uuid = Some("uuid-a1".to_string());
+        first.add_entry(edit);
+        let mut resumed = Conversation::new("bbbbbbbb-2".to_string());
+        resumed.add_entry(linked_entry(
+            "uuid-b1",
+            Some("uuid-a2"),
+            MessageRole::User,
+            "Continue",
+            "2024-01-02T00:00:00Z",
+        ));
+        let history: Vec<HistoryEntry> = serde_json::from_value(json!([
+            {"display": "Old prompt [Image #1]", "timestamp": 1700000000000i64, "project": "/p", "sessionId": "cccccccc-3",
+             "pastedContents": {"1": {"id": 1, "type": "image", "content": "Zm9v", "mediaType": "image/png"}}},
+            {"display": "Continue", "timestamp": 1704153600000i64, "project": "/p", "sessionId": "bbbbbbbb-2"},
+        ]))
+        .unwrap();
+
+        let convos = [resumed, first];
+        assert_eq!(
+            session_links(&convos),
+            vec![SessionLink {
+                predecessor: "aaaaaaaa-1".to_string(),
+                successor: "bbbbbbbb-2".to_string(),
+                from_uuid: "uuid-a2".to_string(),
+            }]
+        );
+
+        let config = DeriveConfig {
+            project_path: Some("/p".to_string()),
+            ..Default::default()
+        };
+        let graph = derive_project_graph(&convos, &history, &config);
+        let paths: Vec<&Path> = graph
+            .paths
+            .iter()
+            .map(|p| match p {
+                PathOrRef::Path(p) => p.as_ref(),
+                PathOrRef::Ref(_) => panic!("expected inline path"),
+            })
+            .collect();
+        assert_eq!(graph.graph.id, "graph-claude-p");
+        assert_eq!(
+            paths.iter().map(|p| p.path.id.as_str()).collect::<Vec<_>>(),
+            vec![
+                "path-claude-aaaaaaaa",
+                "path-claude-bbbbbbbb",
+                "path-claude-cccccccc"
+            ]
+        );
+
+        let (a, b, gone) = (paths[0], paths[1], paths[2]);
+        assert_eq!(
+            b.path.base.as_ref().unwrap().uri,
+            format!("toolpath:{}/step-uuid-a2", a.path.id)
+        );
+        assert_eq!(b.meta.as_ref().unwrap().refs[0].rel, "predecessor");
+        assert_eq!(a.meta.as_ref().unwrap().refs[0].rel, "successor");
+        assert_eq!(gone.steps.len(), 1);
+        assert_eq!(gone.steps[0].step.actor, "human:user");
+        assert_eq!(gone.steps[0].step.timestamp, "2023-11-14T22:13:20.000Z");
+        let image = attachments::artifact_uri(&digest::sha256(b"foo"));
+        assert_eq!(
+            gone.steps[0].change[&image]
+                .structural
+                .as_ref()
+                .unwrap()
+                .extra["media_type"],
+            "image/png"
+        );
+
+        let meta = graph.meta.as_ref().unwrap();
+        assert!(meta.actors.as_ref().unwrap().contains_key("human:user"));
+        let summary: ProjectSummary = meta.get_ext().unwrap().unwrap();
+        assert_eq!(summary.files_changed, vec!["/p/src/lib.rs"]);
+        assert_eq!(
+            summary.started_at.as_deref(),
+            Some("2023-11-14T22:13:20.000Z")
+        );
+        assert_eq!(summary.ended_at.as_deref(), Some("2024-01-02T00:00:00Z"));
+    }
+
+    fn linked_entry(
+        uuid: &str,
+        parent: Option<&str>,
+        role: MessageRole,
+        content: &str,
+        timestamp: &str,
+    ) -> ConversationEntry {
+        let mut entry = make_entry(uuid, role, content, timestamp);
+        entry.parent_uuid = parent.map(str::to_string);
+        entry
+    }
+
+    #[test]
+    fn test_derive_path_rewind_becomes_dead_end() {
+        let convo = make_conversation(vec![
+            linked_entry(
+                "uuid-aaaa",
+                None,
                 MessageRole::User,
                 "Hello",
                 "2024-01-01T00:00:00Z",
             ),
-            make_entry(
-                "uuid-2222",
+            linked_entry(
+                "uuid-bbbb",
+                Some("uuid-aaaa"),
                 MessageRole::Assistant,
                 "Hi",
                 "2024-01-01T00:00:01Z",
             ),
-            make_entry(
-                "uuid-3333",
+            linked_entry(
+                "uuid-cccc",
+                Some("uuid-bbbb"),
                 MessageRole::User,
-                "More",
+                "Do X",
                 "2024-01-01T00:00:02Z",
             ),
-        ];
-        let convo = make_conversation(entries);
-        let config = DeriveConfig::default();
+            linked_entry(
+                "uuid-dddd",
+                Some("uuid-cccc"),
+                MessageRole::Assistant,
+                "Did X",
+                "2024-01-01T00:00:03Z",
+            ),
+            // The user rewound to the assistant's greeting and asked again.
+            linked_entry(
+                "uuid-eeee",
+                Some("uuid-bbbb"),
+                MessageRole::User,
+                "Do Y",
+                "2024-01-01T00:00:04Z",
+            ),
+            linked_entry(
+                "uuid-ffff",
+                Some("uuid-eeee"),
+                MessageRole::Assistant,
+                "Did Y",
+                "2024-01-01T00:00:05Z",
+            ),
+        ]);
 
-        let path = derive_path(&convo, &config);
+        let path = derive_path(&convo, &DeriveConfig::default());
 
-        // Second step should have first as parent
-        assert!(path.steps[1].step.parents.contains(&path.steps[0].step.id));
-        // Third step should have second as parent
-        assert!(path.steps[2].step.parents.contains(&path.steps[1].step.id));
+        assert_eq!(path.steps.len(), 6);
+        assert_eq!(path.steps[2].step.parents, vec![step_id("uuid-bbbb")]);
+        assert_eq!(path.steps[4].step.parents, vec![step_id("uuid-bbbb")]);
+        assert_eq!(path.path.head, step_id("uuid-ffff"));
+
+        let dead: Vec<_> = toolpath::v1::query::dead_ends(&path.steps, &path.path.head)
+            .into_iter()
+            .map(|s| s.step.id.clone())
+            .collect();
+        assert_eq!(dead, vec![step_id("uuid-cccc"), step_id("uuid-dddd")]);
     }
 
     #[test]
-    fn test_derive_path_conversation_artifact() {
-        let entries = vec![make_entry(
-            "uuid-1111",
-            MessageRole::User,
-            "Hello",
-            "2024-01-01T00:00:00Z",
-        )];
-        let convo = make_conversation(entries);
-        let config = DeriveConfig::default();
-
-        let path = derive_path(&convo, &config);
+    fn test_derive_path_bridges_skipped_entries() {
+        let mut system = linked_entry(
+            "uuid-bbbb",
+            Some("uuid-aaaa"),
+            MessageRole::System,
+            "note",
+            "2024-01-01T00:00:01Z",
+        );
+        system.entry_type = "system".to_string();
+        let convo = make_conversation(vec![
+            linked_entry(
+                "uuid-aaaa",
+                None,
+                MessageRole::User,
+                "Hello",
+                "2024-01-01T00:00:00Z",
+            ),
+            system,
+            linked_entry(
+                "uuid-cccc",
+                Some("uuid-bbbb"),
+                MessageRole::Assistant,
+                "Hi",
+                "2024-01-01T00:00:02Z",
+            ),
+        ]);
 
-        // Each step should have the conversation artifact
-        let convo_key = format!("claude://{}", convo.session_id);
-        assert!(path.steps[0].change.contains_key(&convo_key));
+        let path = derive_path(&convo, &DeriveConfig::default());
 
-        let change = &path.steps[0].change[&convo_key];
-        let structural = change.structural.as_ref().unwrap();
-        assert_eq!(structural.change_type, "conversation.append");
-        assert_eq!(structural.extra["role"], "user");
+        assert_eq!(path.steps.len(), 2);
+        assert_eq!(path.steps[1].step.parents, vec![step_id("uuid-aaaa")]);
     }
 
     #[test]
-    fn test_derive_path_no_meta_intent() {
-        let entries = vec![make_entry(
-            "uuid-1111",
+    fn test_derive_path_follows_logical_parent() {
+        let mut after = linked_entry(
+            "uuid-cccc",
+            None,
             MessageRole::User,
-            "Hello",
-            "2024-01-01T00:00:00Z",
-        )];
-        let convo = make_conversation(entries);
-        let config = DeriveConfig::default();
+            "Continue",
+            "2024-01-01T00:00:02Z",
+        );
+        after
+            .extra
+            .insert("logicalParentUuid".to_string(), json!("uuid-aaaa"));
+        let convo = make_conversation(vec![
+            linked_entry(
+                "uuid-aaaa",
+                None,
+                MessageRole::User,
+                "Hello",
+                "2024-01-01T00:00:00Z",
+            ),
+            linked_entry(
+                "uuid-bbbb",
+                Some("uuid-aaaa"),
+                MessageRole::Assistant,
+                "Hi",
+                "2024-01-01T00:00:01Z",
+            ),
+            after,
+        ]);
 
-        let path = derive_path(&convo, &config);
+        let path = derive_path(&convo, &DeriveConfig::default());
 
-        // meta.intent should NOT be set (we removed it as redundant)
-        assert!(path.steps[0].meta.is_none());
+        assert_eq!(path.steps[2].step.parents, vec![step_id("uuid-aaaa")]);
+    }
+
+    #[test]
+    fn test_derive_path_compaction() {
+        let boundary: ConversationEntry = serde_json::from_value(json!({
+            "type": "system", "subtype": "compact_boundary", "uuid": "uuid-cccc",
+            "parentUuid": null, "logicalParentUuid": "uuid-bbbb",
+            "timestamp": "2024-01-01T00:00:02Z", "content": "Conversation compacted",
+            "compactMetadata": {"trigger": "auto", "preTokens": 155000}
+        }))
+        .unwrap();
+        let mut summary = linked_entry(
+            "uuid-dddd",
+            Some("uuid-cccc"),
+            MessageRole::User,
+            "This session is being continued. Summary: use tabs.",
+            "2024-01-01T00:00:03Z",
+        );
+        summary
+            .extra
+            .insert("isCompactSummary".to_string(), json!(true));
+        let convo = make_conversation(vec![
+            linked_entry(
+                "uuid-aaaa",
+                None,
+                MessageRole::User,
+                "Use tabs",
+                "2024-01-01T00:00:00Z",
+            ),
+            linked_entry(
+                "uuid-bbbb",
+                Some("uuid-aaaa"),
+                MessageRole::Assistant,
+                "OK",
+                "2024-01-01T00:00:01Z",
+            ),
+            boundary,
+            summary,
+            linked_entry(
+                "uuid-eeee",
+                Some("uuid-dddd"),
+                MessageRole::User,
+                "Indent this",
+                "2024-01-01T00:00:04Z",
+            ),
+        ]);
+
+        let path = derive_path(&convo, &DeriveConfig::default());
+        assert_eq!(path.steps.len(), 4);
+        let compact = &path.steps[2];
+        assert_eq!(compact.step.actor, "agent:claude-code");
+        assert_eq!(compact.step.parents, vec![step_id("uuid-bbbb")]);
+        let change = compact.change["claude://test-session-12345678"]
+            .structural
+            .as_ref()
+            .unwrap();
+        assert_eq!(change.change_type, "conversation.compact");
+        assert_eq!(
+            change.extra["summary"],
+            "This session is being continued. Summary: use tabs."
+        );
+        assert_eq!(change.extra["replaced_turns"], 2);
+        assert_eq!(change.extra["trigger"], "auto");
+        assert_eq!(change.extra["pre_tokens"], 155000);
+        assert_eq!(path.steps[3].step.parents, vec![step_id("uuid-dddd")]);
+        assert!(
+            path.meta.as_ref().unwrap().actors.as_ref().unwrap()["agent:claude-code"]
+                .name
+                .is_some()
+        );
     }
 
     #[test]
-    fn test_derive_path_actors() {
-        let entries = vec![
+    fn test_derive_graph_nests_subagents() {
+        let mut task = parts_entry(
+            "uuid-task",
+            MessageRole::Assistant,
+            vec![tool_use(
+                "task-1",
+                "Task",
+                json!({"prompt": "Find the bug", "description": "Bug hunt"}),
+            )],
+            "2024-01-01T00:00:01Z",
+        );
+        task.parent_uuid = Some("uuid-user".to_string());
+        let mut side1 = make_entry(
+            "uuid-side-1",
+            MessageRole::User,
+            "Find the bug",
+            "2024-01-01T00:00:02Z",
+        );
+        side1.is_sidechain = true;
+        let mut side2 = linked_entry(
+            "uuid-side-2",
+            Some("uuid-side-1"),
+            MessageRole::Assistant,
+            "In auth.rs",
+            "2024-01-01T00:00:03Z",
+        );
+        side2.is_sidechain = true;
+        let convo = make_conversation(vec![
             make_entry(
-                "uuid-1111",
+                "uuid-user",
                 MessageRole::User,
-                "Hello",
+                "Look for bugs",
                 "2024-01-01T00:00:00Z",
             ),
-            make_entry(
-                "uuid-2222",
+            task,
+            side1,
+            side2,
+        ]);
+        let subagents = crate::subagents::sidechains(&convo);
+
+        let graph = derive_graph(&convo, &subagents, &DeriveConfig::default());
+
+        assert_eq!(graph.paths.len(), 2);
+        let (PathOrRef::Path(main), PathOrRef::Path(sub)) = (&graph.paths[0], &graph.paths[1])
+        else {
+            panic!("Expected inline paths");
+        };
+        assert_eq!(main.steps.len(), 2);
+        let delegating = &main.steps[1];
+        let sub_id = format!("{}-agent-task-1", main.path.id);
+        assert_eq!(sub.path.id, sub_id);
+        assert_eq!(
+            delegating.meta.as_ref().unwrap().refs[0].href,
+            format!("toolpath:{}", sub_id)
+        );
+
+        assert_eq!(sub.steps.len(), 2);
+        assert!(sub.steps[0].step.parents.is_empty());
+        assert_eq!(sub.path.head, sub.steps[1].step.id);
+        assert_eq!(
+            sub.path.base.as_ref().unwrap().uri,
+            format!("toolpath:{}/{}", main.path.id, delegating.step.id)
+        );
+        let meta = sub.meta.as_ref().unwrap();
+        assert_eq!(meta.title.as_deref(), Some("Claude subagent: Bug hunt"));
+        assert_eq!(meta.refs[0].rel, "delegated-from");
+    }
+
+    fn commit_conversation() -> Conversation {
+        let mut result = tool_result_entry(
+            "uuid-result",
+            "bash-1",
+            json!({"stdout": "[main 1a2b3c4] Fix parser\n 1 file changed", "stderr": ""}),
+            false,
+        );
+        result.parent_uuid = Some("uuid-commit".to_string());
+        make_conversation(vec![
+            parts_entry(
+                "uuid-commit",
                 MessageRole::Assistant,
-                "Hi",
-                "2024-01-01T00:00:01Z",
+                vec![tool_use(
+                    "bash-1",
+                    "Bash",
+                    json!({"command": "git commit -am 'Fix parser'"}),
+                )],
+                "2024-01-01T00:00:00Z",
             ),
-        ];
-        let convo = make_conversation(entries);
-        let config = DeriveConfig::default();
+            result,
+        ])
+    }
 
-        let path = derive_path(&convo, &config);
-        let actors = path.meta.as_ref().unwrap().actors.as_ref().unwrap();
+    struct FakeRepo;
 
-        assert!(actors.contains_key("human:user"));
-        // Assistant actor depends on model (None in our test)
-        assert!(actors.contains_key("agent:claude-code"));
+    impl RevisionResolver for FakeRepo {
+        fn resolve(&self, short: &str) -> Option<String> {
+            (short == "1a2b3c4").then(|| format!("{}{}", short, "0".repeat(33)))
+        }
     }
 
     #[test]
-    fn test_derive_path_with_project_path_config() {
-        let convo = make_conversation(vec![make_entry(
-            "uuid-1",
-            MessageRole::User,
-            "Hello",
-            "2024-01-01T00:00:00Z",
-        )]);
+    fn test_derive_path_stamps_commit_revision() {
+        let path = derive_path(&commit_conversation(), &DeriveConfig::default());
+
+        let source = path.steps[0]
+            .meta
+            .as_ref()
+            .unwrap()
+            .source
+            .as_ref()
+            .unwrap();
+        assert_eq!(source.vcs_type, "git");
+        assert_eq!(source.revision, "1a2b3c4");
+    }
+
+    #[test]
+    fn test_derive_path_resolves_commit_revision() {
         let config = DeriveConfig {
-            project_path: Some("/my/project".to_string()),
+            repository: Some(Box::new(FakeRepo)),
             ..Default::default()
         };
-
-        let path = derive_path(&convo, &config);
-        assert_eq!(path.path.base.as_ref().unwrap().uri, "file:///my/project");
+        let path = derive_path(&commit_conversation(), &config);
+
+        let source = path.steps[0]
+            .meta
+            .as_ref()
+            .unwrap()
+            .source
+            .as_ref()
+            .unwrap();
+        assert_eq!(source.revision.len(), 40);
+        assert!(source.revision.starts_with("1a2b3c4"));
     }
 
     #[test]
-    fn test_derive_path_skips_empty_content() {
-        let mut entry = make_entry("uuid-1111", MessageRole::User, "", "2024-01-01T00:00:00Z");
-        // Empty text, no tool uses, no file changes → should be skipped
-        entry.message.as_mut().unwrap().content = Some(MessageContent::Text("   ".to_string()));
+    fn test_derive_path_unknown_commit_not_stamped() {
+        struct EmptyRepo;
+        impl RevisionResolver for EmptyRepo {
+            fn resolve(&self, _short: &str) -> Option<String> {
+                None
+            }
+        }
+        let config = DeriveConfig {
+            repository: Some(Box::new(EmptyRepo)),
+            ..Default::default()
+        };
+        let path = derive_path(&commit_conversation(), &config);
 
-        let convo = make_conversation(vec![entry]);
-        let config = DeriveConfig::default();
+        assert!(path.steps[0].meta.is_none());
+    }
 
-        let path = derive_path(&convo, &config);
-        assert!(path.steps.is_empty());
+    fn step_ids(steps: &[Step]) -> Vec<&str> {
+        steps.iter().map(|s| s.step.id.as_str()).collect()
     }
 
     #[test]
-    fn test_derive_path_skips_system_messages() {
-        let entries = vec![
-            make_entry(
-                "uuid-1111",
-                MessageRole::System,
-                "System prompt",
-                "2024-01-01T00:00:00Z",
-            ),
-            make_entry(
-                "uuid-2222",
-                MessageRole::User,
-                "Hello",
-                "2024-01-01T00:00:01Z",
-            ),
-        ];
-        let convo = make_conversation(entries);
-        let config = DeriveConfig::default();
+    fn test_incremental_matches_batch() {
+        let convo = commit_conversation();
+        let batch = derive_path(&convo, &DeriveConfig::default());
+
+        let mut deriver =
+            IncrementalDeriver::new(convo.session_id.clone(), DeriveConfig::default());
+        let mut steps = Vec::new();
+        for entry in convo.entries.clone() {
+            steps.extend(deriver.push([entry]));
+        }
 
-        let path = derive_path(&convo, &config);
-        // System message should be skipped
-        assert_eq!(path.steps.len(), 1);
-        assert_eq!(path.steps[0].step.actor, "human:user");
+        assert_eq!(deriver.path_id(), batch.path.id);
+        assert_eq!(deriver.head(), Some(batch.path.head.as_str()));
+        assert_eq!(
+            serde_json::to_value(&steps).unwrap(),
+            serde_json::to_value(&batch.steps).unwrap()
+        );
     }
 
     #[test]
-    fn test_derive_path_with_tool_use() {
-        let mut convo = Conversation::new("test-session-12345678".to_string());
-        let entry = ConversationEntry {
-            parent_uuid: None,
-            is_sidechain: false,
-            entry_type: "assistant".to_string(),
-            uuid: "uuid-tool".to_string(),
-            timestamp: "2024-01-01T00:00:00Z".to_string(),
-            session_id: Some("test-session".to_string()),
-            message: Some(Message {
-                role: MessageRole::Assistant,
-                content: Some(MessageContent::Parts(vec![
-                    ContentPart::Text {
-                        text: "Let me write that".to_string(),
-                    },
-                    ContentPart::ToolUse {
-                        id: "t1".to_string(),
-                        name: "Write".to_string(),
-                        input: serde_json::json!({"file_path": "/tmp/test.rs"}),
-                    },
-                ])),
-                model: Some("claude-sonnet-4-5-20250929".to_string()),
-                id: None,
-                message_type: None,
-                stop_reason: None,
-                stop_sequence: None,
-                usage: None,
-            }),
-            cwd: None,
-            git_branch: None,
-            version: None,
-            user_type: None,
-            request_id: None,
-            tool_use_result: None,
-            snapshot: None,
-            message_id: None,
-            extra: Default::default(),
-        };
-        convo.add_entry(entry);
-        let config = DeriveConfig::default();
-
-        let path = derive_path(&convo, &config);
+    fn test_incremental_waits_for_tool_results() {
+        let convo = commit_conversation();
+        let mut deriver =
+            IncrementalDeriver::new(convo.session_id.clone(), DeriveConfig::default());
+
+        // The Bash call is held back until its result arrives.
+        assert!(deriver.push([convo.entries[0].clone()]).is_empty());
+        let steps = deriver.push([convo.entries[1].clone()]);
+        assert_eq!(step_ids(&steps), vec!["step-uuid-com"]);
+        assert!(steps[0].meta.as_ref().unwrap().source.is_some());
+    }
 
-        assert_eq!(path.steps.len(), 1);
-        // Should have both the conversation artifact and the file artifact
-        assert!(path.steps[0].change.contains_key("/tmp/test.rs"));
-        let convo_key = format!("claude://{}", convo.session_id);
-        assert!(path.steps[0].change.contains_key(&convo_key));
+    #[test]
+    fn test_incremental_flush_emits_waiting() {
+        let convo = commit_conversation();
+        let mut deriver =
+            IncrementalDeriver::new(convo.session_id.clone(), DeriveConfig::default());
+
+        assert!(deriver.push([convo.entries[0].clone()]).is_empty());
+        let steps = deriver.flush();
+        assert_eq!(step_ids(&steps), vec!["step-uuid-com"]);
+        assert!(steps[0].meta.is_none());
+        assert!(deriver.flush().is_empty());
     }
 
     #[test]
-    fn test_derive_path_sidechain_uses_parent_uuid() {
-        let mut convo = Conversation::new("test-session-12345678".to_string());
+    fn test_incremental_releases_abandoned_tool_use() {
+        let convo = commit_conversation();
+        let mut deriver =
+            IncrementalDeriver::new(convo.session_id.clone(), DeriveConfig::default());
+
+        assert!(deriver.push([convo.entries[0].clone()]).is_empty());
+        assert!(deriver.is_waiting());
+        // The user moves on; the Bash result is never coming.
+        let steps = deriver.push([linked_entry(
+            "uuid-next",
+            Some(&convo.entries[0].uuid),
+            MessageRole::User,
+            "Never mind",
+            "2024-01-01T00:01:00Z",
+        )]);
+        assert_eq!(step_ids(&steps), vec!["step-uuid-com", "step-uuid-nex"]);
+        assert!(!deriver.is_waiting());
+        assert!(deriver.conversation().entries.is_empty());
+    }
 
-        let e1 = make_entry(
-            "uuid-main-11",
+    #[test]
+    fn test_incremental_continues_across_rotation() {
+        let mut deriver = IncrementalDeriver::new("session-a", DeriveConfig::default());
+        let first = deriver.push([make_entry(
+            "uuid-aaaa",
             MessageRole::User,
             "Hello",
             "2024-01-01T00:00:00Z",
-        );
-        let e2 = make_entry(
-            "uuid-main-22",
+        )]);
+        deriver.rotate("session-a", "session-b");
+        let second = deriver.push([linked_entry(
+            "uuid-bbbb",
+            Some("uuid-aaaa"),
             MessageRole::Assistant,
             "Hi",
             "2024-01-01T00:00:01Z",
+        )]);
+
+        assert_eq!(second[0].step.parents, vec![first[0].step.id.clone()]);
+        assert_eq!(
+            deriver.conversation().session_ids,
+            vec!["session-a", "session-b"]
         );
-        let mut e3 = make_entry(
-            "uuid-side-33",
+        assert!(second[0].change.contains_key("claude://session-a"));
+    }
+
+    fn bash_result(uuid: &str, tool_use_id: &str, stdout: &str) -> ConversationEntry {
+        let mut entry = parts_entry(
+            uuid,
             MessageRole::User,
-            "Side",
-            "2024-01-01T00:00:02Z",
+            vec![ContentPart::ToolResult {
+                tool_use_id: tool_use_id.to_string(),
+                content: crate::types::ToolResultContent::Text(stdout.to_string()),
+                is_error: false,
+            }],
+            "2024-01-01T00:00:01Z",
         );
-        e3.is_sidechain = true;
-        e3.parent_uuid = Some("uuid-main-11".to_string());
+        entry.tool_use_result = Some(json!({"stdout": stdout, "stderr": ""}));
+        entry
+    }
 
-        convo.add_entry(e1);
-        convo.add_entry(e2);
-        convo.add_entry(e3);
+    fn bash_entry(uuid: &str, tool_use_id: &str, command: &str) -> ConversationEntry {
+        parts_entry(
+            uuid,
+            MessageRole::Assistant,
+            vec![tool_use(tool_use_id, "Bash", json!({"command": command}))],
+            "2024-01-01T00:00:00Z",
+        )
+    }
 
-        let config = DeriveConfig::default();
+    fn read_entries(n: usize, path: &str, content: &str) -> Vec<ConversationEntry> {
+        let lines = content.lines().count();
+        vec![
+            parts_entry(
+                &format!("uuid-read-{}", n),
+                MessageRole::Assistant,
+                vec![tool_use(
+                    &format!("read-{}", n),
+                    "Read",
+                    json!({"file_path": path}),
+                )],
+                "2024-01-01T00:00:00Z",
+            ),
+            tool_result_entry(
+                &format!("uuid-read-result-{}", n),
+                &format!("read-{}", n),
+                json!({"type": "text", "file": {"filePath": path, "content": content, "startLine": 1, "numLines": lines, "totalLines": lines}}),
+                false,
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_derive_path_bash_is_shell_exec() {
+        let convo = make_conversation(vec![
+            bash_entry("uuid-test", "bash-1", "cargo test"),
+            bash_result("uuid-test-result", "bash-1", "test result: ok"),
+        ]);
+
+        let path = derive_path(&convo, &DeriveConfig::default());
+
+        let exec = path.steps[0].change["shell://bash-1"]
+            .structural
+            .as_ref()
+            .unwrap();
+        assert_eq!(exec.change_type, "shell.exec");
+        assert_eq!(exec.extra["command"], json!("cargo test"));
+        assert_eq!(exec.extra["exit_code"], json!(0));
+        assert_eq!(exec.extra["stdout"], json!("test result: ok"));
+    }
+
+    #[test]
+    fn test_derive_path_formatter_gets_own_step() {
+        let convo = make_conversation(vec![
+            bash_entry("uuid-fmt", "bash-1", "cargo fmt --all"),
+            bash_result("uuid-fmt-result", "bash-1", ""),
+            linked_entry(
+                "uuid-next",
+                Some("uuid-fmt-result"),
+                MessageRole::Assistant,
+                "Formatted.",
+                "2024-01-01T00:00:02Z",
+            ),
+        ]);
+
+        let path = derive_path(&convo, &DeriveConfig::default());
+
+        assert_eq!(
+            step_ids(&path.steps),
+            vec!["step-uuid-fmt", "step-uuid-fmt-rustfmt", "step-uuid-nex"]
+        );
+        let fmt = &path.steps[1];
+        assert_eq!(fmt.step.actor, "tool:rustfmt");
+        assert_eq!(fmt.step.parents, vec!["step-uuid-fmt"]);
+        assert!(fmt.change.contains_key("shell://bash-1"));
+        assert!(!path.steps[0].change.contains_key("shell://bash-1"));
+        assert_eq!(path.steps[2].step.parents, vec!["step-uuid-fmt-rustfmt"]);
+        assert!(
+            path.meta
+                .as_ref()
+                .unwrap()
+                .actors
+                .as_ref()
+                .unwrap()
+                .contains_key("tool:rustfmt")
+        );
+    }
+
+    #[test]
+    fn test_derive_path_infers_change_from_read() {
+        let mut entries = read_entries(1, "/p/f.rs", "fn  main(){}\n");
+        entries.push(bash_entry("uuid-fmt", "bash-1", "rustfmt /p/f.rs"));
+        entries.push(bash_result("uuid-fmt-result", "bash-1", ""));
+        entries.extend(read_entries(2, "/p/f.rs", "fn main() {}\n"));
+        let convo = make_conversation(entries);
+
+        let config = DeriveConfig {
+            infer_shell_changes: true,
+            ..Default::default()
+        };
         let path = derive_path(&convo, &config);
+        let fmt = path
+            .steps
+            .iter()
+            .find(|s| s.step.actor == "tool:rustfmt")
+            .unwrap();
+        let change = &fmt.change["/p/f.rs"];
+        assert_eq!(
+            change.raw.as_deref(),
+            Some("@@ -1 +1 @@\n-fn  main(){}\n+fn main() {}\n")
+        );
+        assert_eq!(
+            change.structural.as_ref().unwrap().extra["evidence"],
+            json!("read")
+        );
 
-        assert_eq!(path.steps.len(), 3);
-        // Sidechain step should reference e1 as parent, not e2
-        let sidechain_step = &path.steps[2];
-        let expected_parent = format!("step-{}", safe_prefix("uuid-main-11", 8));
-        assert!(sidechain_step.step.parents.contains(&expected_parent));
+        // Off by default.
+        let path = derive_path(&convo, &DeriveConfig::default());
+        assert!(path.steps.iter().all(|s| !s.change.contains_key("/p/f.rs")));
     }
 
-    // ── derive_project ─────────────────────────────────────────────────
+    #[test]
+    fn test_derive_path_infers_change_from_git_status() {
+        let mut entries = vec![
+            bash_entry("uuid-st1", "bash-1", "git status --short"),
+            bash_result("uuid-st1-result", "bash-1", " M src/lib.rs\n"),
+            bash_entry("uuid-gen", "bash-2", "make generate"),
+            bash_result("uuid-gen-result", "bash-2", ""),
+            bash_entry("uuid-st2", "bash-3", "git status --short"),
+            bash_result(
+                "uuid-st2-result",
+                "bash-3",
+                " M src/lib.rs\n?? src/gen.rs\n",
+            ),
+        ];
+        for entry in &mut entries {
+            entry.cwd = Some("/p".to_string());
+        }
+        let convo = make_conversation(entries);
+
+        let config = DeriveConfig {
+            infer_shell_changes: true,
+            ..Default::default()
+        };
+        let path = derive_path(&convo, &config);
+
+        let generate = path
+            .steps
+            .iter()
+            .find(|s| s.step.id == "step-uuid-gen")
+            .unwrap();
+        let change = &generate.change["/p/src/gen.rs"];
+        assert!(change.raw.is_none());
+        assert_eq!(
+            change.structural.as_ref().unwrap().extra["evidence"],
+            json!("git-status")
+        );
+        assert!(!generate.change.contains_key("/p/src/lib.rs"));
+    }
 
     #[test]
-    fn test_derive_project() {
-        let c1 = make_conversation(vec![make_entry(
-            "uuid-1",
-            MessageRole::User,
-            "Hello",
-            "2024-01-01T00:00:00Z",
-        )]);
-        let mut c2 = Conversation::new("session-2".to_string());
-        c2.add_entry(make_entry(
-            "uuid-2",
-            MessageRole::User,
-            "World",
-            "2024-01-02T00:00:00Z",
-        ));
+    fn test_derive_path_plan_and_todo_artifacts() {
+        let convo = make_conversation(vec![
+            parts_entry(
+                "uuid-plan",
+                MessageRole::Assistant,
+                vec![tool_use(
+                    "plan-1",
+                    "ExitPlanMode",
+                    json!({"plan": "1. Parse\n2. Test\n"}),
+                )],
+                "2024-01-01T00:00:00Z",
+            ),
+            tool_result_entry("uuid-plan-result", "plan-1", json!("Rejected"), true),
+            parts_entry(
+                "uuid-todo",
+                MessageRole::Assistant,
+                vec![
+                    tool_use(
+                        "todo-1",
+                        "TodoWrite",
+                        json!({"todos": [{"content": "Parse", "status": "in_progress"}]}),
+                    ),
+                    tool_use(
+                        "todo-2",
+                        "TodoWrite",
+                        json!({"todos": [{"content": "Parse", "status": "completed"}]}),
+                    ),
+                ],
+                "2024-01-01T00:00:02Z",
+            ),
+        ]);
+
+        let path = derive_path(&convo, &DeriveConfig::default());
+        let plan_key = format!("claude://{}/plan", convo.session_id);
+        let todos_key = format!("claude://{}/todos", convo.session_id);
+
+        let plan = path.steps[0].change[&plan_key].structural.as_ref().unwrap();
+        assert_eq!(plan.change_type, "plan.reject");
+        assert_eq!(plan.extra["feedback"], json!("ok"));
+
+        let todos = &path.steps.last().unwrap().change[&todos_key];
+        assert_eq!(
+            todos.structural.as_ref().unwrap().extra["operations"],
+            json!([
+                {"op": "add", "item": "Parse", "status": "in_progress"},
+                {"op": "complete", "item": "Parse"},
+            ])
+        );
+        let diff = UnifiedDiff::parse(todos.raw.as_deref().unwrap()).unwrap();
+        assert_eq!(diff.apply("").unwrap(), "- [x] Parse\n");
+    }
 
-        let config = DeriveConfig::default();
-        let paths = derive_project(&[c1, c2], &config);
+    #[test]
+    fn test_derive_path_usage_counted_once() {
+        let chunk = |uuid: &str, text: &str| -> ConversationEntry {
+            serde_json::from_value(json!({
+                "uuid": uuid,
+                "type": "assistant",
+                "timestamp": "2024-01-01T00:00:00Z",
+                "requestId": "req_1",
+                "message": {
+                    "id": "msg_1",
+                    "role": "assistant",
+                    "model": "claude-sonnet-4-5",
+                    "content": text,
+                    "usage": {"input_tokens": 10, "output_tokens": 20, "cache_read_input_tokens": 1000}
+                }
+            }))
+            .unwrap()
+        };
+        let convo = make_conversation(vec![chunk("uuid-aaaa", "One"), chunk("uuid-bbbb", "Two")]);
+        let config = DeriveConfig {
+            prices: Some(
+                PriceTable::from_toml("[models.claude]\ninput = 1.0\noutput = 1.0").unwrap(),
+            ),
+            ..Default::default()
+        };
 
-        assert_eq!(paths.len(), 2);
+        let path = derive_path(&convo, &config);
+
+        let usage: StepUsage = path.steps[0]
+            .meta
+            .as_ref()
+            .unwrap()
+            .get_ext()
+            .unwrap()
+            .unwrap();
+        assert_eq!(usage.output_tokens, 20);
+        assert_eq!(usage.request_id.as_deref(), Some("req_1"));
+        assert!(path.steps[1].meta.is_none());
+
+        let totals: UsageTotals = path.meta.as_ref().unwrap().get_ext().unwrap().unwrap();
+        assert_eq!(totals.input_tokens, 10);
+        assert_eq!(totals.cache_read_tokens, 1000);
+        assert!((totals.cost_usd.unwrap() - 0.00003).abs() < 1e-12);
     }
 
     #[test]
@@ -0,0 +1,581 @@
+//! Synthesize `raw` unified diffs for file-mutating tool uses.
+//!
+//! Claude Code logs the *inputs* of `Write`, `Edit` and `MultiEdit` on the
+//! assistant's tool-use part, and (in newer versions) a `toolUseResult`
+//! payload on the user entry that carries the tool result. That payload may
+//! include the file's prior content (`originalFile`) and a structured patch.
+//! [`FileTracker`] combines both with the content it has seen so far to
+//! produce the most precise diff available:
+//!
+//! 1. prior content from the result payload, or remembered from earlier
+//!    `Read`/`Write`/`Edit` calls — full diff with real line numbers;
+//! 2. the result's `structuredPatch` — Claude Code's own hunks;
+//! 3. neither — no `raw` diff, since its line numbers would be made up; the
+//!    change is recorded as a structural `file.edit` (with the edit strings)
+//!    or `file.write` instead.
+
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use toolpath::v1::patch::UnifiedDiff;
+use toolpath::v1::{ArtifactChange, StructuralChange};
+
+/// Lines of context around each change in synthesized diffs.
+pub(crate) const CONTEXT: usize = 3;
+
+/// Combined line count above which a `Write` is recorded as a whole-file
+/// replacement rather than a line diff, bounding the work spent on rewrites
+/// of very large files.
+pub(crate) const MAX_DIFF_LINES: usize = 50_000;
+
+/// A file-mutating tool use, resolved to an artifact change.
+#[derive(Debug)]
+pub(crate) struct FileChange {
+    pub path: String,
+    pub change: ArtifactChange,
+    /// Content the `raw` diff applies to, when it was known.
+    pub base: Option<String>,
+}
+
+/// Last known content of each file touched in a conversation.
+#[derive(Debug, Default)]
+pub(crate) struct FileTracker {
+    known: HashMap<String, String>,
+}
+
+impl FileTracker {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the current content of `path` is known.
+    pub(crate) fn knows(&self, path: &str) -> bool {
+        self.known.contains_key(path)
+    }
+
+    /// Remember `content` for `path` unless something newer is already known.
+    pub(crate) fn seed(&mut self, path: &str, content: String) {
+        self.known.entry(path.to_string()).or_insert(content);
+    }
+
+    /// Remember a file's content from a `Read` result, when it covers the
+    /// whole file. Returns the file's path, with the previously known
+    /// content if the read shows it has since changed outside the tracked
+    /// tool calls.
+    pub(crate) fn observe_read(&mut self, result: &Value) -> Option<(String, Option<String>)> {
+        let file = result.get("file")?;
+        let path = file.get("filePath").and_then(Value::as_str)?;
+        let content = file.get("content").and_then(Value::as_str)?;
+        let start = file.get("startLine").and_then(Value::as_u64).unwrap_or(1);
+        let num = file.get("numLines").and_then(Value::as_u64);
+        let total = file.get("totalLines").and_then(Value::as_u64);
+        if start > 1 || num < total {
+            return None;
+        }
+        let previous = self
+            .known
+            .insert(path.to_string(), content.to_string())
+            .filter(|previous| previous != content);
+        Some((path.to_string(), previous))
+    }
+
+    /// The last known content of `path`.
+    pub(crate) fn content(&self, path: &str) -> Option<&str> {
+        self.known.get(path).map(String::as_str)
+    }
+
+    /// The artifact change for a file-mutating tool use. Returns `None` for
+    /// tools that do not modify files.
+    pub(crate) fn change_for(
+        &mut self,
+        name: &str,
+        input: &Value,
+        result: Option<&Value>,
+    ) -> Option<FileChange> {
+        match name {
+            "Write" => {
+                let path = str_field(input, "file_path")?;
+                // A null `originalFile` means the file did not exist.
+                let created = result
+                    .and_then(|r| r.get("originalFile"))
+                    .is_some_and(Value::is_null);
+                let prior = if created {
+                    Some(String::new())
+                } else {
+                    self.prior(&path, result)
+                };
+                let Some(content) = str_field(input, "content") else {
+                    self.known.remove(&path);
+                    return Some(FileChange {
+                        path,
+                        change: ArtifactChange {
+                            raw: None,
+                            structural: None,
+                        },
+                        base: None,
+                    });
+                };
+                let change = match &prior {
+                    Some(prior) => ArtifactChange {
+                        raw: Some(whole_file_diff(prior, &content).to_string()),
+                        structural: None,
+                    },
+                    // The replaced content is unknown, so there is nothing
+                    // to diff against.
+                    None => ArtifactChange {
+                        raw: None,
+                        structural: Some(StructuralChange {
+                            change_type: "file.write".to_string(),
+                            extra: HashMap::from([(
+                                "lines".to_string(),
+                                json!(content.lines().count()),
+                            )]),
+                        }),
+                    },
+                };
+                self.known.insert(path.clone(), content);
+                Some(FileChange {
+                    path,
+                    change,
+                    base: prior,
+                })
+            }
+            "Edit" => {
+                let path = str_field(input, "file_path")?;
+                let (change, base) = self.apply_edits(&path, std::slice::from_ref(input), result);
+                Some(FileChange { path, change, base })
+            }
+            "MultiEdit" => {
+                let path = str_field(input, "file_path")?;
+                let edits = input
+                    .get("edits")
+                    .and_then(Value::as_array)
+                    .cloned()
+                    .unwrap_or_default();
+                let (change, base) = self.apply_edits(&path, &edits, result);
+                Some(FileChange { path, change, base })
+            }
+            "NotebookEdit" => {
+                let path = str_field(input, "notebook_path")?;
+                let mut extra = HashMap::new();
+                for key in ["cell_id", "cell_type", "edit_mode"] {
+                    if let Some(v) = input.get(key).filter(|v| !v.is_null()) {
+                        extra.insert(key.to_string(), v.clone());
+                    }
+                }
+                if let Some(source) = input.get("new_source").and_then(Value::as_str) {
+                    extra.insert("lines".to_string(), json!(source.lines().count()));
+                }
+                // Cell edits are structural; the notebook's content is not
+                // tracked, so forget any remembered text.
+                self.known.remove(&path);
+                let structural = StructuralChange {
+                    change_type: "notebook.edit".to_string(),
+                    extra,
+                };
+                Some(FileChange {
+                    path,
+                    change: ArtifactChange {
+                        raw: None,
+                        structural: Some(structural),
+                    },
+                    base: None,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Prior content of `path`: the result's `originalFile` (or
+    /// `originalFileContents`) when present, else what was last seen.
+    fn prior(&self, path: &str, result: Option<&Value>) -> Option<String> {
+        result
+            .and_then(|r| {
+                r.get("originalFile")
+                    .or_else(|| r.get("originalFileContents"))
+                    .and_then(Value::as_str)
+            })
+            .map(str::to_string)
+            .or_else(|| self.known.get(path).cloned())
+    }
+
+    /// Change for a sequence of `{old_string, new_string, replace_all}` edits
+    /// applied in order, and the content its diff applies to when known.
+    fn apply_edits(
+        &mut self,
+        path: &str,
+        edits: &[Value],
+        result: Option<&Value>,
+    ) -> (ArtifactChange, Option<String>) {
+        if let Some(prior) = self.prior(path, result)
+            && let Some(updated) = replace_all_edits(&prior, edits)
+        {
+            let diff = UnifiedDiff::between(&prior, &updated, CONTEXT);
+            self.known.insert(path.to_string(), updated);
+            return (ArtifactChange::raw(diff.to_string()), Some(prior));
+        }
+
+        // The prior content is unknown or stale from here on.
+        self.known.remove(path);
+        if let Some(diff) = result.and_then(structured_patch) {
+            return (ArtifactChange::raw(diff), None);
+        }
+        // Without the file, the edits can't be placed; keep their strings.
+        let edits: Vec<Value> = edits
+            .iter()
+            .filter_map(|e| {
+                let mut edit = serde_json::Map::new();
+                for key in ["old_string", "new_string"] {
+                    edit.insert(key.to_string(), json!(str_field(e, key)?));
+                }
+                if e.get("replace_all").and_then(Value::as_bool) == Some(true) {
+                    edit.insert("replace_all".to_string(), json!(true));
+                }
+                Some(Value::Object(edit))
+            })
+            .collect();
+        let change = ArtifactChange {
+            raw: None,
+            structural: Some(StructuralChange {
+                change_type: "file.edit".to_string(),
+                extra: HashMap::from([("edits".to_string(), json!(edits))]),
+            }),
+        };
+        (change, None)
+    }
+}
+
+/// Diff a file's content before and after a rewrite, as a single
+/// replacement hunk past [`MAX_DIFF_LINES`].
+pub(crate) fn whole_file_diff(before: &str, after: &str) -> UnifiedDiff {
+    if before.lines().count() + after.lines().count() > MAX_DIFF_LINES {
+        UnifiedDiff::replacing(before, after)
+    } else {
+        UnifiedDiff::between(before, after, CONTEXT)
+    }
+}
+
+/// Apply edits to `content` the way the Edit tool does. `None` when an
+/// `old_string` is not found, i.e. `content` is not what Claude edited.
+fn replace_all_edits(content: &str, edits: &[Value]) -> Option<String> {
+    let mut content = content.to_string();
+    for edit in edits {
+        let old = str_field(edit, "old_string")?;
+        let new = str_field(edit, "new_string")?;
+        if old.is_empty() || !content.contains(&old) {
+            return None;
+        }
+        let all = edit
+            .get("replace_all")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        content = if all {
+            content.replace(&old, &new)
+        } else {
+            content.replacen(&old, &new, 1)
+        };
+    }
+    Some(content)
+}
+
+/// Convert a `structuredPatch` array (`[{oldStart, oldLines, newStart,
+/// newLines, lines}]`) into a unified diff. `None` if absent or malformed.
+fn structured_patch(result: &Value) -> Option<String> {
+    let hunks = result.get("structuredPatch")?.as_array()?;
+    if hunks.is_empty() {
+        return None;
+    }
+    let mut text = String::new();
+    for hunk in hunks {
+        let num = |key: &str| hunk.get(key).and_then(Value::as_u64);
+        text.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            num("oldStart")?,
+            num("oldLines")?,
+            num("newStart")?,
+            num("newLines")?
+        ));
+        for line in hunk.get("lines")?.as_array()? {
+            text.push_str(line.as_str()?);
+            text.push('\n');
+        }
+    }
+    // Only keep it if it is a well-formed diff.
+    UnifiedDiff::parse(&text).ok().map(|d| d.to_string())
+}
+
+fn str_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(path: &str, old: &str, new: &str) -> Value {
+        json!({"file_path": path, "old_string": old, "new_string": new})
+    }
+
+    #[test]
+    fn test_write_new_file() {
+        let mut tracker = FileTracker::new();
+        let input = json!({"file_path": "/p/a.txt", "content": "one\ntwo\n"});
+        let result = json!({"type": "create", "originalFile": null});
+        let FileChange { path, change, base } =
+            tracker.change_for("Write", &input, Some(&result)).unwrap();
+        assert_eq!(base.as_deref(), Some(""));
+        assert_eq!(path, "/p/a.txt");
+        assert_eq!(change.raw.as_deref(), Some("@@ -0,0 +1,2 @@\n+one\n+two\n"));
+    }
+
+    #[test]
+    fn test_write_diffs_against_last_known() {
+        let mut tracker = FileTracker::new();
+        let first = json!({"file_path": "/p/a.txt", "content": "one\ntwo\n"});
+        tracker.change_for("Write", &first, None);
+        let second = json!({"file_path": "/p/a.txt", "content": "one\nTWO\n"});
+        let change = tracker.change_for("Write", &second, None).unwrap().change;
+        assert_eq!(
+            change.raw.as_deref(),
+            Some("@@ -1,2 +1,2 @@\n one\n-two\n+TWO\n")
+        );
+    }
+
+    #[test]
+    fn test_write_large_file_is_replaced_whole() {
+        let mut tracker = FileTracker::new();
+        let old: String = (0..MAX_DIFF_LINES).map(|i| format!("{}\n", i)).collect();
+        let new = format!("{}tail\n", old);
+        tracker.seed("big.txt", old.clone());
+        let input = json!({"file_path": "big.txt", "content": new});
+        let raw = tracker
+            .change_for("Write", &input, None)
+            .unwrap()
+            .change
+            .raw;
+        let diff = UnifiedDiff::parse(&raw.unwrap()).unwrap();
+        assert_eq!(diff.hunks.len(), 1);
+        assert_eq!(diff.stats().removed, MAX_DIFF_LINES);
+        assert_eq!(diff.apply(&old).unwrap(), new);
+    }
+
+    #[test]
+    fn test_edit_with_original_file() {
+        let mut tracker = FileTracker::new();
+        let result = json!({"originalFile": "a\nb\nc\nd\ne\nf\ng\nh\n"});
+        let change = tracker
+            .change_for("Edit", &edit("/p/f", "g", "G"), Some(&result))
+            .unwrap()
+            .change;
+        assert_eq!(
+            change.raw.as_deref(),
+            Some("@@ -4,5 +4,5 @@\n d\n e\n f\n-g\n+G\n h\n")
+        );
+
+        // The edited content is remembered for the next edit.
+        let change = tracker
+            .change_for("Edit", &edit("/p/f", "b", "B"), None)
+            .unwrap()
+            .change;
+        assert_eq!(
+            change.raw.as_deref(),
+            Some("@@ -1,5 +1,5 @@\n a\n-b\n+B\n c\n d\n e\n")
+        );
+    }
+
+    #[test]
+    fn test_edit_uses_read_content() {
+        let mut tracker = FileTracker::new();
+        tracker.observe_read(&json!({
+            "type": "text",
+            "file": {"filePath": "/p/f", "content": "x\ny\n", "startLine": 1, "numLines": 2, "totalLines": 2}
+        }));
+        let change = tracker
+            .change_for("Edit", &edit("/p/f", "y", "z"), None)
+            .unwrap()
+            .change;
+        assert_eq!(change.raw.as_deref(), Some("@@ -1,2 +1,2 @@\n x\n-y\n+z\n"));
+    }
+
+    #[test]
+    fn test_read_reports_drift() {
+        let mut tracker = FileTracker::new();
+        let read = |content: &str| {
+            json!({
+                "file": {"filePath": "/p/f", "content": content, "startLine": 1, "numLines": 1, "totalLines": 1}
+            })
+        };
+        assert_eq!(
+            tracker.observe_read(&read("x\n")),
+            Some(("/p/f".into(), None))
+        );
+        assert_eq!(
+            tracker.observe_read(&read("x\n")),
+            Some(("/p/f".into(), None))
+        );
+        assert_eq!(
+            tracker.observe_read(&read("y\n")),
+            Some(("/p/f".into(), Some("x\n".into())))
+        );
+        assert_eq!(tracker.content("/p/f"), Some("y\n"));
+    }
+
+    #[test]
+    fn test_partial_read_is_ignored() {
+        let mut tracker = FileTracker::new();
+        tracker.observe_read(&json!({
+            "file": {"filePath": "/p/f", "content": "x\n", "startLine": 10, "numLines": 1, "totalLines": 40}
+        }));
+        assert!(tracker.known.is_empty());
+    }
+
+    #[test]
+    fn test_edit_replace_all() {
+        let mut tracker = FileTracker::new();
+        tracker.known.insert("/p/f".into(), "a\nb\na\n".into());
+        let mut input = edit("/p/f", "a", "c");
+        input["replace_all"] = json!(true);
+        tracker.change_for("Edit", &input, None);
+        assert_eq!(tracker.known["/p/f"], "c\nb\nc\n");
+    }
+
+    #[test]
+    fn test_edit_structured_patch_fallback() {
+        let mut tracker = FileTracker::new();
+        let result = json!({
+            "structuredPatch": [
+                {"oldStart": 12, "oldLines": 3, "newStart": 12, "newLines": 3,
+                 "lines": [" fn a() {", "-    old()", "+    new()", " }"]}
+            ]
+        });
+        let change = tracker
+            .change_for("Edit", &edit("/p/f", "old()", "new()"), Some(&result))
+            .unwrap()
+            .change;
+        assert_eq!(
+            change.raw.as_deref(),
+            Some("@@ -12,3 +12,3 @@\n fn a() {\n-    old()\n+    new()\n }\n")
+        );
+    }
+
+    #[test]
+    fn test_edit_without_content_is_structural() {
+        let mut tracker = FileTracker::new();
+        let input = json!({
+            "file_path": "/p/f",
+            "edits": [
+                {"old_string": "let x = 1;", "new_string": "let x = 2;"},
+                {"old_string": "a", "new_string": "b", "replace_all": true}
+            ]
+        });
+        let change = tracker
+            .change_for("MultiEdit", &input, None)
+            .unwrap()
+            .change;
+        assert!(change.raw.is_none());
+        let structural = change.structural.unwrap();
+        assert_eq!(structural.change_type, "file.edit");
+        assert_eq!(
+            structural.extra["edits"],
+            json!([
+                {"old_string": "let x = 1;", "new_string": "let x = 2;"},
+                {"old_string": "a", "new_string": "b", "replace_all": true}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_write_over_unknown_content_is_structural() {
+        let mut tracker = FileTracker::new();
+        let input = json!({"file_path": "/p/a.txt", "content": "one\ntwo\n"});
+        let FileChange { change, base, .. } = tracker.change_for("Write", &input, None).unwrap();
+        assert_eq!(base, None);
+        assert!(change.raw.is_none());
+        let structural = change.structural.unwrap();
+        assert_eq!(structural.change_type, "file.write");
+        assert_eq!(structural.extra["lines"], 2);
+
+        // The written content is known from here on.
+        let input = json!({"file_path": "/p/a.txt", "content": "one\nTWO\n"});
+        let change = tracker.change_for("Write", &input, None).unwrap().change;
+        assert_eq!(
+            change.raw.as_deref(),
+            Some("@@ -1,2 +1,2 @@\n one\n-two\n+TWO\n")
+        );
+    }
+
+    #[test]
+    fn test_stale_content_is_dropped() {
+        let mut tracker = FileTracker::new();
+        tracker.known.insert("/p/f".into(), "unrelated\n".into());
+        tracker.change_for("Edit", &edit("/p/f", "missing", "b"), None);
+        assert!(!tracker.known.contains_key("/p/f"));
+    }
+
+    #[test]
+    fn test_multi_edit_composed_in_order() {
+        let mut tracker = FileTracker::new();
+        tracker
+            .known
+            .insert("/p/f".into(), "one\ntwo\nthree\n".into());
+        let input = json!({
+            "file_path": "/p/f",
+            "edits": [
+                {"old_string": "one", "new_string": "uno"},
+                {"old_string": "uno\ntwo", "new_string": "uno\ndos"}
+            ]
+        });
+        let change = tracker
+            .change_for("MultiEdit", &input, None)
+            .unwrap()
+            .change;
+        assert_eq!(
+            change.raw.as_deref(),
+            Some("@@ -1,3 +1,3 @@\n-one\n-two\n+uno\n+dos\n three\n")
+        );
+        assert_eq!(tracker.known["/p/f"], "uno\ndos\nthree\n");
+    }
+
+    #[test]
+    fn test_multi_edit_original_file_contents() {
+        let mut tracker = FileTracker::new();
+        let input = json!({
+            "file_path": "/p/f",
+            "edits": [{"old_string": "b", "new_string": "B"}]
+        });
+        let result = json!({"originalFileContents": "a\nb\n"});
+        let change = tracker
+            .change_for("MultiEdit", &input, Some(&result))
+            .unwrap()
+            .change;
+        assert_eq!(change.raw.as_deref(), Some("@@ -1,2 +1,2 @@\n a\n-b\n+B\n"));
+    }
+
+    #[test]
+    fn test_notebook_edit_is_structural() {
+        let mut tracker = FileTracker::new();
+        let input = json!({
+            "notebook_path": "/p/n.ipynb",
+            "cell_id": "c1",
+            "edit_mode": "replace",
+            "new_source": "print(1)\nprint(2)"
+        });
+        let FileChange { path, change, .. } =
+            tracker.change_for("NotebookEdit", &input, None).unwrap();
+        assert_eq!(path, "/p/n.ipynb");
+        assert!(change.raw.is_none());
+        let structural = change.structural.unwrap();
+        assert_eq!(structural.change_type, "notebook.edit");
+        assert_eq!(structural.extra["cell_id"], "c1");
+        assert_eq!(structural.extra["lines"], 2);
+    }
+
+    #[test]
+    fn test_non_mutating_tool() {
+        let mut tracker = FileTracker::new();
+        assert!(
+            tracker
+                .change_for("Read", &json!({"file_path": "/p/f"}), None)
+                .is_none()
+        );
+    }
+}
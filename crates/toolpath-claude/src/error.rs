@@ -11,6 +11,9 @@ pub enum ConvoError {
     #[error("JSON parsing error: {0}")]
     Json(#[from] serde_json::Error),
 
+    #[error("TOML parsing error: {0}")]
+    Toml(#[from] toml::de::Error),
+
     #[error("Home directory not found")]
     NoHomeDirectory,
 
@@ -26,9 +29,18 @@ pub enum ConvoError {
     #[error("Invalid conversation format in file: {0}")]
     InvalidFormat(PathBuf),
 
+    #[error("Malformed line in {}: {diagnostic}", path.display())]
+    MalformedLine {
+        path: PathBuf,
+        diagnostic: Box<crate::reader::LineDiagnostic>,
+    },
+
     #[error("Path conversion error: {0}")]
     PathConversion(String),
 
+    #[error("Invalid search query: {0}")]
+    InvalidQuery(String),
+
     #[error("Timestamp parsing error: {0}")]
     TimestampParse(String),
 
@@ -2,7 +2,8 @@ use crate::error::Result;
 use crate::paths::PathResolver;
 use crate::reader::ConversationReader;
 use crate::types::{Conversation, ConversationMetadata, HistoryEntry};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 #[derive(Debug, Clone)]
 pub struct ConvoIO {
@@ -58,7 +59,7 @@ impl ConvoIO {
             }
         }
 
-        metadata.sort_by(|a, b| b.last_activity.cmp(&a.last_activity));
+        metadata.sort_by_key(|m| std::cmp::Reverse(m.last_activity));
         Ok(metadata)
     }
 
@@ -98,6 +99,26 @@ impl Default for ConvoIO {
     }
 }
 
+/// Write `bytes` to `path` through a temporary file in the same directory,
+/// so readers never see a partial file. The temporary name is unique per
+/// process and call, so concurrent writers don't clobber each other's.
+pub(crate) fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(
+        ".tmp-{}-{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let tmp = PathBuf::from(tmp);
+    let written = std::fs::write(&tmp, bytes).and_then(|()| std::fs::rename(&tmp, path));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    written
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -123,6 +144,20 @@ mod tests {
         (temp, io)
     }
 
+    #[test]
+    fn test_write_atomic() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("out.json");
+        write_atomic(&path, b"one").unwrap();
+        write_atomic(&path, b"two").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"two");
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 1);
+
+        let missing = temp.path().join("missing/out.json");
+        assert!(write_atomic(&missing, b"x").is_err());
+        assert!(!temp.path().join("missing").exists());
+    }
+
     #[test]
     fn test_default() {
         let _io = ConvoIO::default();
@@ -2,28 +2,52 @@
 
 #[cfg(feature = "watcher")]
 pub mod async_watcher;
+pub mod attachments;
+pub mod capture;
 pub(crate) mod chain;
+#[cfg(feature = "watcher")]
+pub(crate) mod checkpoint;
 pub mod derive;
+pub(crate) mod digest;
+pub(crate) mod edits;
 pub mod error;
 pub mod io;
 pub mod paths;
+pub(crate) mod plans;
+#[cfg(feature = "watcher")]
+pub mod project_watcher;
 pub mod provider;
 pub mod query;
 pub mod reader;
+pub mod search;
+pub(crate) mod shell;
+pub mod subagents;
 pub mod types;
+pub mod usage;
+pub(crate) mod vcs;
 #[cfg(feature = "watcher")]
 pub mod watcher;
 
 #[cfg(feature = "watcher")]
-pub use async_watcher::{AsyncConversationWatcher, WatcherConfig, WatcherHandle};
+pub use async_watcher::{
+    AsyncConversationWatcher, WatchEvent, WatchPosition, WatcherConfig, WatcherHandle,
+};
 pub use error::{ConvoError, Result};
 pub use io::ConvoIO;
-pub use paths::PathResolver;
+pub use paths::{PathResolver, ProjectDir, Resolution};
+#[cfg(feature = "watcher")]
+pub use project_watcher::{
+    AsyncProjectWatcher, ProjectEvent, ProjectWatcher, ProjectWatcherHandle, SessionState,
+};
+pub use provider::ToolCategories;
 pub use query::{ConversationQuery, HistoryQuery};
-pub use reader::ConversationReader;
+pub use reader::{ConversationReader, EntryIter, LineDiagnostic, LogCheck, ParseMode};
+pub use search::{IndexUpdate, SearchHit, SearchIndex, SearchQuery};
+pub use subagents::Subagent;
 pub use types::{
-    CacheCreation, ContentPart, Conversation, ConversationEntry, ConversationMetadata,
-    HistoryEntry, Message, MessageContent, MessageRole, ToolResultContent, ToolResultRef,
+    CacheCreation, CompactMetadata, ContentPart, Conversation, ConversationEntry,
+    ConversationMetadata, FileBackup, FileSnapshot, HistoryEntry, MediaKind, MediaRef, MediaSource,
+    Message, MessageContent, MessageRole, PastedContent, ToolResultContent, ToolResultRef,
     ToolUseRef, Usage,
 };
 #[cfg(feature = "watcher")]
@@ -62,6 +86,7 @@ pub use watcher::ConversationWatcher;
 pub struct ClaudeConvo {
     io: ConvoIO,
     chain_cache: std::cell::RefCell<std::collections::HashMap<String, chain::ChainIndex>>,
+    tool_categories: provider::ToolCategories,
 }
 
 impl Clone for ClaudeConvo {
@@ -69,6 +94,7 @@ impl Clone for ClaudeConvo {
         Self {
             io: self.io.clone(),
             chain_cache: std::cell::RefCell::new(self.chain_cache.borrow().clone()),
+            tool_categories: self.tool_categories.clone(),
         }
     }
 }
@@ -85,6 +111,7 @@ impl ClaudeConvo {
         Self {
             io: ConvoIO::new(),
             chain_cache: std::cell::RefCell::new(std::collections::HashMap::new()),
+            tool_categories: provider::ToolCategories::default(),
         }
     }
 
@@ -107,9 +134,23 @@ impl ClaudeConvo {
         Self {
             io: ConvoIO::with_resolver(resolver),
             chain_cache: std::cell::RefCell::new(std::collections::HashMap::new()),
+            tool_categories: provider::ToolCategories::default(),
         }
     }
 
+    /// Categorize tools the built-in map doesn't know, such as MCP tools,
+    /// in conversations loaded through
+    /// [`ConversationProvider`](toolpath_convo::ConversationProvider).
+    pub fn with_tool_categories(mut self, categories: provider::ToolCategories) -> Self {
+        self.tool_categories = categories;
+        self
+    }
+
+    /// The tool categories set by [`Self::with_tool_categories`].
+    pub fn tool_categories(&self) -> &provider::ToolCategories {
+        &self.tool_categories
+    }
+
     /// Returns a reference to the underlying ConvoIO.
     pub fn io(&self) -> &ConvoIO {
         &self.io
@@ -155,6 +196,9 @@ impl ClaudeConvo {
                 }
                 merged.add_entry(entry.clone());
             }
+            for snapshot in convo.file_snapshots {
+                merged.merge_file_snapshot(snapshot);
+            }
         }
 
         merged.session_ids = chain;
@@ -240,7 +284,7 @@ impl ClaudeConvo {
             }
         }
 
-        metadata.sort_by(|a, b| b.last_activity.cmp(&a.last_activity));
+        metadata.sort_by_key(|m| std::cmp::Reverse(m.last_activity));
         Ok(metadata)
     }
 
@@ -316,7 +360,7 @@ impl ClaudeConvo {
             }
         }
 
-        conversations.sort_by(|a, b| b.last_activity.cmp(&a.last_activity));
+        conversations.sort_by_key(|c| std::cmp::Reverse(c.last_activity));
         Ok(conversations)
     }
 
@@ -333,6 +377,62 @@ impl ClaudeConvo {
         }
     }
 
+    /// Loads the subagent transcripts of `conversation`, a session of
+    /// `project_path`: its sidechain entries, then any `agent-*.jsonl` files
+    /// recorded for its sessions. Each is matched to the `Task` tool use
+    /// that spawned it; see [`subagents`]. Transcripts that can't be read
+    /// are skipped with a warning.
+    pub fn read_subagents(
+        &self,
+        project_path: &str,
+        conversation: &Conversation,
+    ) -> Result<Vec<Subagent>> {
+        let session_ids = if conversation.session_ids.is_empty() {
+            vec![conversation.session_id.clone()]
+        } else {
+            conversation.session_ids.clone()
+        };
+
+        let mut transcripts = Vec::new();
+        let mut seen = std::collections::HashSet::new();
+        for session_id in &session_ids {
+            for file in self.resolver().agent_files(project_path, session_id)? {
+                if !seen.insert(file.clone()) {
+                    continue;
+                }
+                // Project-level agent files may belong to any session: check
+                // the first recorded session ID before reading the rest.
+                let agent = agent_session_id(&file).and_then(|session_id| {
+                    match session_id.filter(|s| session_ids.contains(s)) {
+                        Some(_) => ConversationReader::read_conversation(&file).map(Some),
+                        None => Ok(None),
+                    }
+                });
+                match agent {
+                    Ok(Some(agent)) => transcripts.push(agent.entries),
+                    Ok(None) => {}
+                    Err(e) => {
+                        eprintln!(
+                            "Warning: Failed to read subagent transcript {}: {}",
+                            file.display(),
+                            e
+                        );
+                    }
+                }
+            }
+        }
+
+        let mut found = subagents::sidechains(conversation);
+        let claimed: std::collections::HashSet<String> =
+            found.iter().map(|s| s.tool_use_id.clone()).collect();
+        found.extend(
+            subagents::match_transcripts(conversation, transcripts)
+                .into_iter()
+                .filter(|s| !claimed.contains(&s.tool_use_id)),
+        );
+        Ok(found)
+    }
+
     /// Resolves the full session chain containing `session_id`, returned
     /// in chronological order (oldest segment first).
     ///
@@ -399,6 +499,16 @@ impl ClaudeConvo {
     }
 }
 
+/// The session ID of the first entry in `file` that records one.
+fn agent_session_id(file: &std::path::Path) -> Result<Option<String>> {
+    for entry in ConversationReader::entries(file, ParseMode::Tolerant)? {
+        if let Some(session_id) = entry?.session_id {
+            return Ok(Some(session_id));
+        }
+    }
+    Ok(None)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,11 +1,54 @@
 use crate::error::{ConvoError, Result};
+use std::collections::HashMap;
 use std::env;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Lines read from the start of a session log looking for its `cwd`.
+const CWD_LINES: usize = 10;
+
+/// Resolved project directories, with the directory's mtime when each was
+/// resolved.
+type ResolvedDirs = HashMap<PathBuf, (Option<SystemTime>, ProjectDir)>;
 
 #[derive(Debug, Clone)]
 pub struct PathResolver {
     home_dir: Option<PathBuf>,
     claude_dir: Option<PathBuf>,
+    extra_claude_dirs: Vec<PathBuf>,
+    /// Shared between clones.
+    resolved: Arc<Mutex<ResolvedDirs>>,
+}
+
+/// A directory under `projects/`, and the project path it holds sessions for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectDir {
+    /// The Claude config directory it is in.
+    pub claude_dir: PathBuf,
+    /// The directory itself.
+    pub dir: PathBuf,
+    /// The directory's name: the project path with every character other
+    /// than an ASCII letter or digit replaced by `-`.
+    pub name: String,
+    /// The project path.
+    pub path: String,
+    /// How `path` was recovered from `name`.
+    pub resolution: Resolution,
+}
+
+/// How a [`ProjectDir::path`] was recovered. Sanitizing is lossy — `/`,
+/// `.`, `_` and `-` all become `-` — so the name alone can't be reversed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    /// From the `cwd` recorded in one of the project's sessions.
+    Cwd,
+    /// The only existing path on this machine with that name.
+    Filesystem,
+    /// Several existing paths have that name; `path` is the first of them.
+    Ambiguous { candidates: Vec<String> },
+    /// No session or existing path matched; every `-` is read as `/`.
+    Unsanitized,
 }
 
 impl Default for PathResolver {
@@ -15,11 +58,17 @@ impl Default for PathResolver {
 }
 
 impl PathResolver {
+    /// A resolver for `~/.claude`, or for the directory named by the
+    /// `CLAUDE_CONFIG_DIR` environment variable when it is set.
     pub fn new() -> Self {
         let home_dir = dirs::home_dir();
         Self {
             home_dir,
-            claude_dir: None,
+            claude_dir: env::var_os("CLAUDE_CONFIG_DIR")
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from),
+            extra_claude_dirs: Vec::new(),
+            resolved: Arc::default(),
         }
     }
 
@@ -33,6 +82,15 @@ impl PathResolver {
         self
     }
 
+    /// Also look for projects and sessions in these Claude config
+    /// directories, after the primary one. Files are still written to, and
+    /// `history.jsonl` read from, the primary directory only.
+    pub fn with_claude_dirs<P: Into<PathBuf>>(mut self, dirs: impl IntoIterator<Item = P>) -> Self {
+        self.extra_claude_dirs
+            .extend(dirs.into_iter().map(Into::into));
+        self
+    }
+
     pub fn home_dir(&self) -> Result<&Path> {
         self.home_dir.as_deref().ok_or(ConvoError::NoHomeDirectory)
     }
@@ -46,70 +104,312 @@ impl PathResolver {
         Ok(home.join(".claude"))
     }
 
+    /// The primary Claude config directory, then any added with
+    /// [`with_claude_dirs`](Self::with_claude_dirs).
+    pub fn claude_dirs(&self) -> Result<Vec<PathBuf>> {
+        let mut dirs = vec![self.claude_dir()?];
+        for dir in &self.extra_claude_dirs {
+            if !dirs.contains(dir) {
+                dirs.push(dir.clone());
+            }
+        }
+        Ok(dirs)
+    }
+
     pub fn projects_dir(&self) -> Result<PathBuf> {
         Ok(self.claude_dir()?.join("projects"))
     }
 
+    /// Directory holding a session's file backups, referenced by
+    /// `file-history-snapshot` entries.
+    pub fn file_history_dir(&self, session_id: &str) -> Result<PathBuf> {
+        let dirs = self.claude_dirs()?;
+        let candidates: Vec<PathBuf> = dirs
+            .iter()
+            .map(|dir| dir.join("file-history").join(session_id))
+            .collect();
+        Ok(first_existing(candidates))
+    }
+
     pub fn history_file(&self) -> Result<PathBuf> {
         Ok(self.claude_dir()?.join("history.jsonl"))
     }
 
+    /// The project's directory: the first that exists across the config
+    /// directories, else where it would be in the primary one.
     pub fn project_dir(&self, project_path: &str) -> Result<PathBuf> {
+        Ok(first_existing(self.project_dir_candidates(project_path)?))
+    }
+
+    fn project_dir_candidates(&self, project_path: &str) -> Result<Vec<PathBuf>> {
         let sanitized = sanitize_project_path(project_path);
-        Ok(self.projects_dir()?.join(sanitized))
+        Ok(self
+            .claude_dirs()?
+            .into_iter()
+            .map(|dir| dir.join("projects").join(&sanitized))
+            .collect())
     }
 
-    pub fn conversation_file(&self, project_path: &str, session_id: &str) -> Result<PathBuf> {
+    /// The project's directories that exist, in config directory order.
+    pub(crate) fn existing_project_dirs(&self, project_path: &str) -> Result<Vec<PathBuf>> {
         Ok(self
-            .project_dir(project_path)?
-            .join(format!("{}.jsonl", session_id)))
+            .project_dir_candidates(project_path)?
+            .into_iter()
+            .filter(|dir| dir.is_dir())
+            .collect())
+    }
+
+    pub fn conversation_file(&self, project_path: &str, session_id: &str) -> Result<PathBuf> {
+        let file = format!("{}.jsonl", session_id);
+        let candidates: Vec<PathBuf> = self
+            .project_dir_candidates(project_path)?
+            .into_iter()
+            .map(|dir| dir.join(&file))
+            .collect();
+        Ok(first_existing(candidates))
     }
 
+    /// Every project's path, recovered as [`project_dirs`](Self::project_dirs)
+    /// does, sorted and without duplicates.
     pub fn list_project_dirs(&self) -> Result<Vec<String>> {
-        let projects_dir = self.projects_dir()?;
-        if !projects_dir.exists() {
-            return Ok(Vec::new());
-        }
+        let mut projects: Vec<String> = self.project_dirs()?.into_iter().map(|p| p.path).collect();
+        projects.sort();
+        projects.dedup();
+        Ok(projects)
+    }
 
+    /// Every directory under `projects/` in each config directory, with the
+    /// project path it belongs to.
+    ///
+    /// The path is taken from the `cwd` of the first entries of the
+    /// project's sessions. Failing that — no sessions, or none recording a
+    /// matching `cwd` — it is looked for on the filesystem, by walking down
+    /// from `/` through the directories whose sanitized names match. Results
+    /// are cached for the life of the resolver (and its clones); those not
+    /// found from a `cwd` are looked up again once the directory changes.
+    pub fn project_dirs(&self) -> Result<Vec<ProjectDir>> {
         let mut projects = Vec::new();
-        for entry in std::fs::read_dir(&projects_dir)? {
-            let entry = entry?;
-            if entry.file_type()?.is_dir()
-                && let Some(name) = entry.file_name().to_str()
-            {
-                projects.push(unsanitize_project_path(name));
+        for claude_dir in self.claude_dirs()? {
+            let projects_dir = claude_dir.join("projects");
+            if !projects_dir.is_dir() {
+                continue;
+            }
+            let mut dirs = Vec::new();
+            for entry in std::fs::read_dir(&projects_dir)? {
+                let entry = entry?;
+                if entry.file_type()?.is_dir()
+                    && let Some(name) = entry.file_name().to_str()
+                {
+                    dirs.push((name.to_string(), entry.path()));
+                }
+            }
+            dirs.sort();
+            for (name, dir) in dirs {
+                projects.push(self.resolve_project_dir(&claude_dir, name, dir)?);
             }
         }
         Ok(projects)
     }
 
-    pub fn list_conversations(&self, project_path: &str) -> Result<Vec<String>> {
-        let project_dir = self.project_dir(project_path)?;
-        if !project_dir.exists() {
-            return Ok(Vec::new());
+    fn resolve_project_dir(
+        &self,
+        claude_dir: &Path,
+        name: String,
+        dir: PathBuf,
+    ) -> Result<ProjectDir> {
+        let modified = std::fs::metadata(&dir)?.modified().ok();
+        if let Ok(resolved) = self.resolved.lock()
+            && let Some((at, project)) = resolved.get(&dir)
+            && (project.resolution == Resolution::Cwd || *at == modified)
+        {
+            return Ok(project.clone());
         }
 
+        let (path, resolution) = match session_cwd(&dir, &name)? {
+            Some(cwd) => (cwd, Resolution::Cwd),
+            None => {
+                let mut candidates = filesystem_candidates(&name);
+                match candidates.len() {
+                    0 => (unsanitize_project_path(&name), Resolution::Unsanitized),
+                    1 => (candidates.remove(0), Resolution::Filesystem),
+                    _ => (candidates[0].clone(), Resolution::Ambiguous { candidates }),
+                }
+            }
+        };
+        let project = ProjectDir {
+            claude_dir: claude_dir.to_path_buf(),
+            dir: dir.clone(),
+            name,
+            path,
+            resolution,
+        };
+        if let Ok(mut resolved) = self.resolved.lock() {
+            resolved.insert(dir, (modified, project.clone()));
+        }
+        Ok(project)
+    }
+
+    /// Session IDs of the project, across config directories.
+    pub fn list_conversations(&self, project_path: &str) -> Result<Vec<String>> {
         let mut sessions = Vec::new();
-        for entry in std::fs::read_dir(&project_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.extension().and_then(|s| s.to_str()) == Some("jsonl")
-                && let Some(stem) = path.file_stem().and_then(|s| s.to_str())
-            {
-                sessions.push(stem.to_string());
+        for dir in self.existing_project_dirs(project_path)? {
+            for stem in session_stems(&dir)? {
+                if !sessions.contains(&stem) {
+                    sessions.push(stem);
+                }
             }
         }
         Ok(sessions)
     }
 
+    /// Subagent transcript files that may belong to `session_id`: those
+    /// under `<session-id>/subagents/`, then every `agent-*.jsonl` in the
+    /// project directory (which callers filter by their `sessionId`).
+    pub fn agent_files(&self, project_path: &str, session_id: &str) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        for project_dir in self.existing_project_dirs(project_path)? {
+            for dir in [project_dir.join(session_id).join("subagents"), project_dir] {
+                if !dir.is_dir() {
+                    continue;
+                }
+                let mut found: Vec<PathBuf> = std::fs::read_dir(&dir)?
+                    .filter_map(|e| e.ok().map(|e| e.path()))
+                    .filter(|p| {
+                        p.extension().and_then(|s| s.to_str()) == Some("jsonl")
+                            && p.file_stem()
+                                .and_then(|s| s.to_str())
+                                .is_some_and(crate::subagents::is_agent_stem)
+                    })
+                    .collect();
+                found.sort();
+                files.extend(found);
+            }
+        }
+        Ok(files)
+    }
+
+    /// Whether two project paths name the same project directory: equal
+    /// once sanitized as Claude Code names project directories, so a
+    /// trailing `/` or a differently spelled separator doesn't matter.
+    pub fn same_project(a: &str, b: &str) -> bool {
+        sanitize_project_path(a) == sanitize_project_path(b)
+    }
+
     pub fn exists(&self) -> bool {
         self.claude_dir().map(|p| p.exists()).unwrap_or(false)
     }
 }
 
+/// The first of `paths` that exists, else the first.
+fn first_existing(mut paths: Vec<PathBuf>) -> PathBuf {
+    match paths.iter().position(|p| p.exists()) {
+        Some(i) => paths.swap_remove(i),
+        None => paths.swap_remove(0),
+    }
+}
+
+/// The `cwd` of the first entry, in any of the session logs in `dir`, whose
+/// `cwd` sanitizes to `name`. Sessions started in a subdirectory record that
+/// instead, so a `cwd` that doesn't match is passed over.
+fn session_cwd(dir: &Path, name: &str) -> Result<Option<String>> {
+    use std::io::BufRead;
+
+    let mut sessions = session_stems(dir)?;
+    sessions.sort();
+    for session in sessions {
+        let Ok(file) = std::fs::File::open(dir.join(format!("{}.jsonl", session))) else {
+            continue;
+        };
+        for line in std::io::BufReader::new(file).lines().take(CWD_LINES) {
+            let Ok(line) = line else {
+                break;
+            };
+            let cwd = serde_json::from_str::<serde_json::Value>(&line)
+                .ok()
+                .and_then(|v| v.get("cwd")?.as_str().map(str::to_string));
+            if let Some(cwd) = cwd
+                && sanitize_project_path(&cwd) == name
+            {
+                return Ok(Some(cwd));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Existing absolute paths whose sanitized form is `name`, sorted.
+fn filesystem_candidates(name: &str) -> Vec<String> {
+    let Some(rest) = name.strip_prefix('-') else {
+        return Vec::new();
+    };
+    let mut found = Vec::new();
+    walk_candidates(Path::new("/"), rest, &mut found);
+    found.sort();
+    found
+}
+
+/// Descend from `base` through the directories whose sanitized names make
+/// up `rest`, `-`-separated, collecting each path that uses all of it.
+/// Only directories whose names match are read, and symlinks are not
+/// followed, so the walk stays on the paths `rest` spells out.
+fn walk_candidates(base: &Path, rest: &str, found: &mut Vec<String>) {
+    let Ok(entries) = std::fs::read_dir(base) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let Some(file_name) = entry.file_name().to_str().map(sanitize_project_path) else {
+            continue;
+        };
+        let matches = rest == file_name;
+        let tail = rest
+            .strip_prefix(file_name.as_str())
+            .and_then(|r| r.strip_prefix('-'));
+        if !matches && tail.is_none() || !entry.file_type().is_ok_and(|t| t.is_dir()) {
+            continue;
+        }
+        let path = entry.path();
+        if matches {
+            if let Some(path) = path.to_str() {
+                found.push(path.to_string());
+            }
+        } else if let Some(tail) = tail {
+            walk_candidates(&path, tail, found);
+        }
+    }
+}
+
+/// Session log file stems in a project directory (subagent transcripts
+/// excluded). Empty if the directory doesn't exist.
+pub(crate) fn session_stems(dir: &Path) -> Result<Vec<String>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut sessions = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().and_then(|s| s.to_str()) == Some("jsonl")
+            && let Some(stem) = path.file_stem().and_then(|s| s.to_str())
+            && !crate::subagents::is_agent_stem(stem)
+        {
+            sessions.push(stem.to_string());
+        }
+    }
+    Ok(sessions)
+}
+
 fn sanitize_project_path(path: &str) -> String {
-    // Claude Code converts both '/' and '_' to '-' when creating project directories
-    path.replace(['/', '_'], "-")
+    // A trailing separator doesn't change the directory
+    let path = match path.trim_end_matches(['/', '\\']) {
+        "" => path,
+        trimmed => trimmed,
+    };
+    // Claude Code replaces every character but ASCII letters and digits
+    // with '-' when naming project directories
+    path.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
 }
 
 fn unsanitize_project_path(sanitized: &str) -> String {
@@ -161,6 +461,151 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_project_path_sanitization_lossy() {
+        assert_eq!(
+            sanitize_project_path("/Users/alex/my-app.v2_old"),
+            "-Users-alex-my-app-v2-old"
+        );
+        assert_eq!(sanitize_project_path("C:\\src\\app"), "C--src-app");
+        assert_eq!(
+            sanitize_project_path("/Users/alex/project/"),
+            "-Users-alex-project"
+        );
+        assert_eq!(sanitize_project_path("/"), "-");
+        assert!(PathResolver::same_project("/p/app/", "/p/app"));
+        assert!(!PathResolver::same_project("/p/app", "/p/apps"));
+    }
+
+    fn session(dir: &Path, id: &str, cwd: &str) {
+        fs::create_dir_all(dir).unwrap();
+        fs::write(
+            dir.join(format!("{}.jsonl", id)),
+            format!(
+                "{{\"type\":\"summary\"}}\n{{\"type\":\"user\",\"uuid\":\"u1\",\"cwd\":\"{}\"}}\n",
+                cwd
+            ),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_project_dirs_from_cwd() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path().join("projects/-work-my-app-v2");
+        // A session started in a subdirectory doesn't name the project.
+        session(&dir, "a", "/work/my-app.v2/src");
+        session(&dir, "b", "/work/my-app.v2");
+
+        let resolver = PathResolver::new().with_claude_dir(temp.path());
+        let projects = resolver.project_dirs().unwrap();
+        assert_eq!(
+            projects,
+            vec![ProjectDir {
+                claude_dir: temp.path().to_path_buf(),
+                dir: dir.clone(),
+                name: "-work-my-app-v2".to_string(),
+                path: "/work/my-app.v2".to_string(),
+                resolution: Resolution::Cwd,
+            }]
+        );
+        assert_eq!(resolver.list_project_dirs().unwrap(), ["/work/my-app.v2"]);
+        assert_eq!(resolver.project_dir("/work/my-app.v2").unwrap(), dir);
+
+        // Cached: found again without reading the sessions.
+        fs::remove_file(dir.join("b.jsonl")).unwrap();
+        let clone = resolver.clone();
+        assert_eq!(clone.project_dirs().unwrap()[0].path, "/work/my-app.v2");
+    }
+
+    #[test]
+    fn test_project_dirs_from_filesystem() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().canonicalize().unwrap();
+        let project = root.join("code/my-app");
+        fs::create_dir_all(&project).unwrap();
+        let name = sanitize_project_path(project.to_str().unwrap());
+        let claude = root.join("claude");
+        fs::create_dir_all(claude.join("projects").join(&name)).unwrap();
+
+        let resolver = PathResolver::new().with_claude_dir(&claude);
+        let found = &resolver.project_dirs().unwrap()[0];
+        assert_eq!(found.path, project.to_str().unwrap());
+        assert_eq!(found.resolution, Resolution::Filesystem);
+
+        // Another existing path with the same name makes it ambiguous.
+        let other = root.join("code/my/app");
+        fs::create_dir_all(&other).unwrap();
+        let resolver = PathResolver::new().with_claude_dir(&claude);
+        let found = &resolver.project_dirs().unwrap()[0];
+        let mut candidates = vec![
+            project.to_str().unwrap().to_string(),
+            other.to_str().unwrap().to_string(),
+        ];
+        candidates.sort();
+        assert_eq!(found.path, candidates[0]);
+        assert_eq!(found.resolution, Resolution::Ambiguous { candidates });
+
+        // Symlinked directories are not followed.
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&project, root.join("code/linked")).unwrap();
+            std::os::unix::fs::symlink(root.join("code"), root.join("alias")).unwrap();
+            let mut found = Vec::new();
+            walk_candidates(&root, "code-linked", &mut found);
+            walk_candidates(&root, "alias-my-app", &mut found);
+            assert!(found.is_empty());
+        }
+
+        // Nothing on disk: read every '-' as '/'.
+        fs::create_dir_all(claude.join("projects/-nowhere-at-all")).unwrap();
+        let resolver = PathResolver::new().with_claude_dir(&claude);
+        let found = resolver
+            .project_dirs()
+            .unwrap()
+            .into_iter()
+            .find(|p| p.name == "-nowhere-at-all")
+            .unwrap();
+        assert_eq!(found.path, "/nowhere/at/all");
+        assert_eq!(found.resolution, Resolution::Unsanitized);
+    }
+
+    #[test]
+    fn test_multiple_claude_dirs() {
+        let temp = TempDir::new().unwrap();
+        let primary = temp.path().join("primary");
+        let work = temp.path().join("work");
+        session(&primary.join("projects/-app"), "s1", "/app");
+        session(&work.join("projects/-app"), "s2", "/app");
+        session(&work.join("projects/-lib"), "s3", "/lib");
+
+        let resolver = PathResolver::new()
+            .with_claude_dir(&primary)
+            .with_claude_dirs([&work, &primary]);
+        assert_eq!(
+            resolver.claude_dirs().unwrap(),
+            [primary.clone(), work.clone()]
+        );
+        assert_eq!(resolver.project_dirs().unwrap().len(), 3);
+        assert_eq!(resolver.list_project_dirs().unwrap(), ["/app", "/lib"]);
+
+        let mut sessions = resolver.list_conversations("/app").unwrap();
+        sessions.sort();
+        assert_eq!(sessions, ["s1", "s2"]);
+        assert_eq!(
+            resolver.conversation_file("/app", "s2").unwrap(),
+            work.join("projects/-app/s2.jsonl")
+        );
+        assert_eq!(
+            resolver.project_dir("/lib").unwrap(),
+            work.join("projects/-lib")
+        );
+        assert_eq!(
+            resolver.conversation_file("/new", "s9").unwrap(),
+            primary.join("projects/-new/s9.jsonl")
+        );
+    }
+
     #[test]
     fn test_conversation_file_path() {
         let temp = TempDir::new().unwrap();
@@ -221,6 +666,7 @@ mod tests {
         fs::write(project_dir.join("session-1.jsonl"), "{}").unwrap();
         fs::write(project_dir.join("session-2.jsonl"), "{}").unwrap();
         fs::write(project_dir.join("not-jsonl.txt"), "{}").unwrap();
+        fs::write(project_dir.join("agent-a1b2.jsonl"), "{}").unwrap();
 
         let resolver = PathResolver::new().with_claude_dir(temp.path());
         let sessions = resolver.list_conversations("/test/project").unwrap();
@@ -229,6 +675,27 @@ mod tests {
         assert!(sessions.contains(&"session-2".to_string()));
     }
 
+    #[test]
+    fn test_agent_files() {
+        let temp = TempDir::new().unwrap();
+        let project_dir = temp.path().join("projects/-test-project");
+        let nested = project_dir.join("session-1/subagents");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(nested.join("agent-c3d4.jsonl"), "{}").unwrap();
+        fs::write(project_dir.join("agent-a1b2.jsonl"), "{}").unwrap();
+        fs::write(project_dir.join("session-1.jsonl"), "{}").unwrap();
+
+        let resolver = PathResolver::new().with_claude_dir(temp.path());
+        let files = resolver.agent_files("/test/project", "session-1").unwrap();
+        assert_eq!(
+            files,
+            vec![
+                nested.join("agent-c3d4.jsonl"),
+                project_dir.join("agent-a1b2.jsonl")
+            ]
+        );
+    }
+
     #[test]
     fn test_list_conversations_empty_project() {
         let temp = TempDir::new().unwrap();
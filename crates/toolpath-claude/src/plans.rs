@@ -0,0 +1,352 @@
+//! Plan-mode and todo-list tool uses as plan artifacts.
+//!
+//! `ExitPlanMode` carries the plan Claude proposes before leaving plan mode;
+//! its result says whether the user accepted it. `TodoWrite` replaces the
+//! session's todo list wholesale. [`PlanTracker`] remembers the last plan and
+//! list so each tool use becomes a change against them: a `raw` diff of the
+//! text, and a structural change saying what happened.
+
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use toolpath::v1::patch::UnifiedDiff;
+use toolpath::v1::{ArtifactChange, StructuralChange};
+
+use crate::capture::{self, BlobDir, Capture};
+use crate::edits::CONTEXT;
+
+/// How the user answered a proposed plan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PlanOutcome {
+    /// No result (yet): the plan is only proposed.
+    Pending,
+    Accepted,
+    Rejected,
+}
+
+/// The last plan and todo list seen in a conversation.
+#[derive(Debug, Default)]
+pub(crate) struct PlanTracker {
+    plan: Option<String>,
+    todos: Vec<Todo>,
+}
+
+#[derive(Debug, Clone)]
+struct Todo {
+    /// The item's `id`, or its content when it has none.
+    key: String,
+    content: String,
+    status: String,
+}
+
+impl PlanTracker {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// The change an `ExitPlanMode` tool use makes to the plan artifact.
+    /// `feedback` is the result text, recorded as `capture` says when the
+    /// plan is rejected.
+    pub(crate) fn plan_change(
+        &mut self,
+        input: &Value,
+        outcome: PlanOutcome,
+        feedback: Option<&str>,
+        capture: Capture,
+        blobs: Option<&BlobDir>,
+    ) -> Option<ArtifactChange> {
+        let plan = input.get("plan").and_then(Value::as_str)?;
+        let previous = self.plan.replace(plan.to_string());
+        let raw = (previous.as_deref() != Some(plan))
+            .then(|| UnifiedDiff::between(previous.as_deref().unwrap_or(""), plan, CONTEXT))
+            .map(|diff| diff.to_string());
+
+        let change_type = match outcome {
+            PlanOutcome::Pending => "plan.propose",
+            PlanOutcome::Accepted => "plan.accept",
+            PlanOutcome::Rejected => "plan.reject",
+        };
+        let mut extra = HashMap::new();
+        extra.insert("lines".to_string(), json!(plan.lines().count()));
+        if outcome == PlanOutcome::Rejected
+            && let Some(feedback) = feedback.filter(|f| !f.trim().is_empty())
+        {
+            capture::record(&mut extra, "feedback", feedback, capture, blobs);
+        }
+        Some(ArtifactChange {
+            raw,
+            structural: Some(StructuralChange {
+                change_type: change_type.to_string(),
+                extra,
+            }),
+        })
+    }
+
+    /// The change a `TodoWrite` tool use makes to the todo artifact: one
+    /// operation per item added, removed, or moved to a new status. `None`
+    /// when the list is unchanged.
+    pub(crate) fn todos_change(&mut self, input: &Value) -> Option<ArtifactChange> {
+        let todos: Vec<Todo> = input
+            .get("todos")?
+            .as_array()?
+            .iter()
+            .filter_map(Todo::from_value)
+            .collect();
+
+        let mut operations = Vec::new();
+        for todo in &todos {
+            match self.todos.iter().find(|t| t.key == todo.key) {
+                None => operations.push(json!({
+                    "op": "add",
+                    "item": todo.content,
+                    "status": todo.status,
+                })),
+                Some(old) if old.status != todo.status && todo.status == "completed" => {
+                    operations.push(json!({"op": "complete", "item": todo.content}))
+                }
+                Some(old) if old.status != todo.status => operations.push(json!({
+                    "op": "status",
+                    "item": todo.content,
+                    "from": old.status,
+                    "to": todo.status,
+                })),
+                Some(_) => {}
+            }
+        }
+        for old in &self.todos {
+            if !todos.iter().any(|t| t.key == old.key) {
+                operations.push(json!({"op": "remove", "item": old.content}));
+            }
+        }
+
+        let before = checklist(&self.todos);
+        let after = checklist(&todos);
+        self.todos = todos;
+        if operations.is_empty() && before == after {
+            return None;
+        }
+
+        let completed = self
+            .todos
+            .iter()
+            .filter(|t| t.status == "completed")
+            .count();
+        let mut extra = HashMap::new();
+        extra.insert("operations".to_string(), json!(operations));
+        extra.insert("total".to_string(), json!(self.todos.len()));
+        extra.insert("completed".to_string(), json!(completed));
+        Some(ArtifactChange {
+            raw: Some(UnifiedDiff::between(&before, &after, CONTEXT).to_string()),
+            structural: Some(StructuralChange {
+                change_type: "todos.update".to_string(),
+                extra,
+            }),
+        })
+    }
+}
+
+impl Todo {
+    fn from_value(value: &Value) -> Option<Self> {
+        let content = value.get("content").and_then(Value::as_str)?.to_string();
+        let status = value
+            .get("status")
+            .and_then(Value::as_str)
+            .unwrap_or("pending")
+            .to_string();
+        let key = value
+            .get("id")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| content.clone());
+        Some(Self {
+            key,
+            content,
+            status,
+        })
+    }
+}
+
+/// A todo list as a Markdown checklist, the text its `raw` diffs apply to.
+fn checklist(todos: &[Todo]) -> String {
+    todos
+        .iter()
+        .map(|t| {
+            let mark = match t.status.as_str() {
+                "completed" => "x",
+                "in_progress" => "~",
+                _ => " ",
+            };
+            format!("- [{}] {}\n", mark, t.content)
+        })
+        .collect()
+}
+
+/// Append `later`'s todo operations to `earlier`'s, for several `TodoWrite`
+/// calls in one turn. Other structural fields are taken from `later`.
+pub(crate) fn append_operations(earlier: Option<&StructuralChange>, later: &mut StructuralChange) {
+    let Some(Value::Array(before)) = earlier.and_then(|s| s.extra.get("operations")) else {
+        return;
+    };
+    if let Some(Value::Array(ops)) = later.extra.get_mut("operations") {
+        let mut all = before.clone();
+        all.append(ops);
+        *ops = all;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn todos(items: &[(&str, &str)]) -> Value {
+        json!({
+            "todos": items
+                .iter()
+                .map(|(content, status)| json!({"content": content, "status": status, "activeForm": content}))
+                .collect::<Vec<_>>()
+        })
+    }
+
+    fn operations(change: &ArtifactChange) -> Vec<Value> {
+        change.structural.as_ref().unwrap().extra["operations"]
+            .as_array()
+            .unwrap()
+            .clone()
+    }
+
+    #[test]
+    fn test_plan_outcomes() {
+        let mut tracker = PlanTracker::new();
+        let input = json!({"plan": "1. Parse\n2. Test\n"});
+
+        let change = tracker
+            .plan_change(
+                &input,
+                PlanOutcome::Rejected,
+                Some("Write tests first"),
+                Capture::Full,
+                None,
+            )
+            .unwrap();
+        let structural = change.structural.unwrap();
+        assert_eq!(structural.change_type, "plan.reject");
+        assert_eq!(structural.extra["feedback"], "Write tests first");
+        assert_eq!(
+            change.raw.as_deref(),
+            Some("@@ -0,0 +1,2 @@\n+1. Parse\n+2. Test\n")
+        );
+
+        let revised = json!({"plan": "1. Test\n2. Parse\n"});
+        let change = tracker
+            .plan_change(&revised, PlanOutcome::Accepted, None, Capture::Full, None)
+            .unwrap();
+        assert_eq!(change.structural.unwrap().change_type, "plan.accept");
+        let diff = UnifiedDiff::parse(change.raw.as_deref().unwrap()).unwrap();
+        assert_eq!(
+            diff.apply("1. Parse\n2. Test\n").unwrap(),
+            "1. Test\n2. Parse\n"
+        );
+    }
+
+    #[test]
+    fn test_plan_feedback_capture() {
+        let mut tracker = PlanTracker::new();
+        let feedback = "No, split the parser out first";
+        let change = tracker
+            .plan_change(
+                &json!({"plan": "1. Parse\n"}),
+                PlanOutcome::Rejected,
+                Some(feedback),
+                Capture::Digest,
+                None,
+            )
+            .unwrap();
+        let extra = change.structural.unwrap().extra;
+        assert!(!extra.contains_key("feedback"));
+        assert_eq!(
+            extra["feedback_sha256"],
+            crate::digest::sha256(feedback.as_bytes())
+        );
+    }
+
+    #[test]
+    fn test_plan_pending_is_proposal() {
+        let mut tracker = PlanTracker::new();
+        let change = tracker
+            .plan_change(
+                &json!({"plan": "Do it"}),
+                PlanOutcome::Pending,
+                None,
+                Capture::Full,
+                None,
+            )
+            .unwrap();
+        assert_eq!(change.structural.unwrap().change_type, "plan.propose");
+        assert!(
+            tracker
+                .plan_change(&json!({}), PlanOutcome::Pending, None, Capture::Full, None)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_todo_operations() {
+        let mut tracker = PlanTracker::new();
+        let first = tracker
+            .todos_change(&todos(&[("Parse", "in_progress"), ("Test", "pending")]))
+            .unwrap();
+        assert_eq!(
+            operations(&first),
+            vec![
+                json!({"op": "add", "item": "Parse", "status": "in_progress"}),
+                json!({"op": "add", "item": "Test", "status": "pending"}),
+            ]
+        );
+        assert_eq!(
+            first.raw.as_deref(),
+            Some("@@ -0,0 +1,2 @@\n+- [~] Parse\n+- [ ] Test\n")
+        );
+
+        let second = tracker
+            .todos_change(&todos(&[("Parse", "completed"), ("Test", "in_progress")]))
+            .unwrap();
+        assert_eq!(
+            operations(&second),
+            vec![
+                json!({"op": "complete", "item": "Parse"}),
+                json!({"op": "status", "item": "Test", "from": "pending", "to": "in_progress"}),
+            ]
+        );
+        let structural = second.structural.unwrap();
+        assert_eq!(structural.extra["completed"], 1);
+        assert_eq!(structural.extra["total"], 2);
+
+        let third = tracker
+            .todos_change(&todos(&[("Test", "in_progress")]))
+            .unwrap();
+        assert_eq!(
+            operations(&third),
+            vec![json!({"op": "remove", "item": "Parse"})]
+        );
+    }
+
+    #[test]
+    fn test_unchanged_todos() {
+        let mut tracker = PlanTracker::new();
+        let list = todos(&[("Parse", "pending")]);
+        assert!(tracker.todos_change(&list).is_some());
+        assert!(tracker.todos_change(&list).is_none());
+    }
+
+    #[test]
+    fn test_append_operations() {
+        let mut tracker = PlanTracker::new();
+        let first = tracker.todos_change(&todos(&[("A", "pending")])).unwrap();
+        let mut second = tracker
+            .todos_change(&todos(&[("A", "completed")]))
+            .unwrap()
+            .structural
+            .unwrap();
+        append_operations(first.structural.as_ref(), &mut second);
+        assert_eq!(second.extra["operations"].as_array().unwrap().len(), 2);
+    }
+}
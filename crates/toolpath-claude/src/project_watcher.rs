@@ -0,0 +1,796 @@
+//! Project-wide watching: every session in a project, or in all projects.
+//!
+//! [`ConversationWatcher`](crate::ConversationWatcher) and
+//! [`AsyncConversationWatcher`](crate::AsyncConversationWatcher) follow one
+//! session. [`ProjectWatcher`] tails every session log under a project
+//! directory (or under [`PathResolver::projects_dir`](crate::PathResolver::projects_dir)),
+//! notices new sessions as their files appear, follows rotations to
+//! successor files, and reports sessions that have gone quiet.
+//! [`AsyncProjectWatcher`] runs one in a background task, woken by
+//! filesystem events with a periodic fallback poll.
+
+use crate::ClaudeConvo;
+use crate::async_watcher::WatcherConfig;
+use crate::chain::{self, ChainIndex};
+use crate::checkpoint;
+use crate::error::Result;
+use crate::paths;
+use crate::reader::ConversationReader;
+use crate::types::ConversationEntry;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+use toolpath_convo::{SessionCursor, WatcherCheckpoint};
+
+/// Something that happened in a watched project.
+#[derive(Debug, Clone)]
+pub enum ProjectEvent {
+    /// A session log appeared (or was already there on the first poll).
+    SessionStarted { project: String, session_id: String },
+    /// A session continued in a new file. Entries after this are reported
+    /// under `to`.
+    SessionRotated {
+        project: String,
+        from: String,
+        to: String,
+    },
+    /// New entries in a session, bridge entries excluded.
+    Entries {
+        project: String,
+        session_id: String,
+        entries: Vec<ConversationEntry>,
+    },
+    /// A session's log has not been written to for the idle timeout.
+    /// Reported once; new entries make the session active again.
+    SessionIdle { project: String, session_id: String },
+}
+
+impl ProjectEvent {
+    /// The project the event belongs to.
+    pub fn project(&self) -> &str {
+        match self {
+            ProjectEvent::SessionStarted { project, .. }
+            | ProjectEvent::SessionRotated { project, .. }
+            | ProjectEvent::Entries { project, .. }
+            | ProjectEvent::SessionIdle { project, .. } => project,
+        }
+    }
+
+    /// The session the event belongs to; for a rotation, the new one.
+    pub fn session_id(&self) -> &str {
+        match self {
+            ProjectEvent::SessionStarted { session_id, .. }
+            | ProjectEvent::Entries { session_id, .. }
+            | ProjectEvent::SessionIdle { session_id, .. } => session_id,
+            ProjectEvent::SessionRotated { to, .. } => to,
+        }
+    }
+}
+
+/// What a [`ProjectWatcher`] knows about one session.
+#[derive(Debug, Clone)]
+pub struct SessionState {
+    pub project: String,
+    /// The session ID of the file currently being read.
+    pub session_id: String,
+    pub file_path: PathBuf,
+    /// Byte offset read up to in `file_path`.
+    pub offset: u64,
+    /// Entries reported for this session, across rotations.
+    pub entry_count: usize,
+    /// When the session's log was last written to.
+    pub last_activity: SystemTime,
+    pub idle: bool,
+    /// Earlier files of this session, oldest first.
+    pub rotated_from: Vec<String>,
+    /// The last entry read from `file_path`.
+    pub last_uuid: Option<String>,
+}
+
+/// Watches every session in one project, or in all projects.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use toolpath_claude::{ClaudeConvo, ProjectEvent, ProjectWatcher};
+///
+/// let mut watcher = ProjectWatcher::all(ClaudeConvo::new());
+/// watcher.skip_existing().unwrap();
+///
+/// loop {
+///     std::thread::sleep(std::time::Duration::from_secs(1));
+///     for event in watcher.poll().unwrap() {
+///         if let ProjectEvent::SessionStarted { project, session_id } = event {
+///             println!("{project}: new session {session_id}");
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ProjectWatcher {
+    manager: ClaudeConvo,
+    /// The project watched, or `None` for all of them.
+    project: Option<String>,
+    idle_after: Duration,
+    /// Tracked sessions by project and current session ID.
+    sessions: BTreeMap<(String, String), SessionState>,
+    /// Files already handled — tracked, or rotated away from — by project.
+    known: HashMap<String, HashSet<String>>,
+    chains: HashMap<String, ChainIndex>,
+    seen: usize,
+}
+
+impl ProjectWatcher {
+    /// Idle timeout used unless [`Self::with_idle_after`] sets another.
+    pub const DEFAULT_IDLE_AFTER: Duration = Duration::from_secs(5 * 60);
+
+    /// Watch the sessions of `project` (a project path, as elsewhere).
+    pub fn new(manager: ClaudeConvo, project: String) -> Self {
+        Self::with_scope(manager, Some(project))
+    }
+
+    /// Watch the sessions of every project, including projects created
+    /// while watching.
+    pub fn all(manager: ClaudeConvo) -> Self {
+        Self::with_scope(manager, None)
+    }
+
+    fn with_scope(manager: ClaudeConvo, project: Option<String>) -> Self {
+        Self {
+            manager,
+            project,
+            idle_after: Self::DEFAULT_IDLE_AFTER,
+            sessions: BTreeMap::new(),
+            known: HashMap::new(),
+            chains: HashMap::new(),
+            seen: 0,
+        }
+    }
+
+    /// Report a session idle once its log has not been written to for
+    /// `idle_after`.
+    pub fn with_idle_after(mut self, idle_after: Duration) -> Self {
+        self.idle_after = idle_after;
+        self
+    }
+
+    /// Returns the project being watched, or `None` when watching all.
+    pub fn project(&self) -> Option<&str> {
+        self.project.as_deref()
+    }
+
+    /// The tracked sessions, by project then session ID.
+    pub fn sessions(&self) -> impl Iterator<Item = &SessionState> {
+        self.sessions.values()
+    }
+
+    /// The state of a tracked session, by its current session ID.
+    pub fn session(&self, project: &str, session_id: &str) -> Option<&SessionState> {
+        self.sessions
+            .get(&(project.to_string(), session_id.to_string()))
+    }
+
+    /// Returns the number of entries that have been reported.
+    pub fn seen_count(&self) -> usize {
+        self.seen
+    }
+
+    /// Track the sessions that exist now without reporting them or their
+    /// entries; the next poll reports only what happens after. Returns the
+    /// number of sessions tracked.
+    pub fn skip_existing(&mut self) -> Result<usize> {
+        self.scan(true)?;
+        Ok(self.sessions.len())
+    }
+
+    /// Poll for new sessions, rotations, entries and idle sessions.
+    ///
+    /// On the first call, every existing session is reported as started,
+    /// with all its entries. Within a project, a session's remaining
+    /// entries are reported before its rotation to a successor file.
+    pub fn poll(&mut self) -> Result<Vec<ProjectEvent>> {
+        self.scan(false)
+    }
+
+    /// Every tracked session's position, to save and
+    /// [`restore`](Self::restore) from.
+    pub fn checkpoint(&self) -> Result<WatcherCheckpoint> {
+        let mut sessions = Vec::new();
+        for state in self.sessions.values() {
+            let file = if state.file_path.exists() {
+                Some(checkpoint::identify(&state.file_path, state.offset)?)
+            } else {
+                None
+            };
+            sessions.push(SessionCursor {
+                project: state.project.clone(),
+                session_id: state.session_id.clone(),
+                chain: state.rotated_from.clone(),
+                offset: state.offset,
+                last_uuid: state.last_uuid.clone(),
+                file,
+            });
+        }
+        Ok(WatcherCheckpoint { sessions })
+    }
+
+    /// Resume the sessions in `checkpoint` that are in scope and still
+    /// exist; what was read before it isn't reported again. Sessions
+    /// created since are reported as started on the next poll.
+    pub fn restore(&mut self, checkpoint: &WatcherCheckpoint) -> Result<()> {
+        for cursor in &checkpoint.sessions {
+            if self.project.as_ref().is_some_and(|p| *p != cursor.project) {
+                continue;
+            }
+            let file_path = self
+                .manager
+                .resolver()
+                .conversation_file(&cursor.project, &cursor.session_id)?;
+            let Ok(metadata) = std::fs::metadata(&file_path) else {
+                continue;
+            };
+            let offset = checkpoint::resume_offset(&file_path, cursor)?;
+
+            let known = self.known.entry(cursor.project.clone()).or_default();
+            known.insert(cursor.session_id.clone());
+            known.extend(cursor.chain.iter().cloned());
+            self.sessions.insert(
+                (cursor.project.clone(), cursor.session_id.clone()),
+                SessionState {
+                    project: cursor.project.clone(),
+                    session_id: cursor.session_id.clone(),
+                    file_path,
+                    offset,
+                    entry_count: 0,
+                    last_activity: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                    idle: false,
+                    rotated_from: cursor.chain.clone(),
+                    last_uuid: cursor.last_uuid.clone(),
+                },
+            );
+        }
+        Ok(())
+    }
+
+    fn scan(&mut self, skip: bool) -> Result<Vec<ProjectEvent>> {
+        let projects = match &self.project {
+            Some(project) => vec![project.clone()],
+            None => {
+                let mut projects = self.manager.resolver().list_project_dirs()?;
+                projects.sort();
+                projects
+            }
+        };
+
+        let now = SystemTime::now();
+        let mut events = Vec::new();
+        for project in projects {
+            let dirs = self.manager.resolver().existing_project_dirs(&project)?;
+            let mut active = HashSet::new();
+
+            // Tail the sessions already tracked first, so a session's last
+            // entries come before its rotation.
+            let tracked: Vec<(String, String)> = self
+                .sessions
+                .range((project.clone(), String::new())..)
+                .take_while(|((p, _), _)| *p == project)
+                .map(|(key, _)| key.clone())
+                .collect();
+            for key in tracked {
+                if self.read(&key, skip, &mut events)? {
+                    active.insert(key.1);
+                }
+            }
+
+            for (dir, stem) in self.discover(&project, &dirs)? {
+                let key = (project.clone(), stem.clone());
+                let predecessor = self
+                    .chains
+                    .get(&project)
+                    .and_then(|chain| chain.predecessor_of(&stem))
+                    .map(str::to_string);
+                let previous =
+                    predecessor.and_then(|from| self.sessions.remove(&(project.clone(), from)));
+
+                let state = SessionState {
+                    project: project.clone(),
+                    session_id: stem.clone(),
+                    file_path: dir.join(format!("{}.jsonl", stem)),
+                    offset: 0,
+                    entry_count: 0,
+                    last_activity: SystemTime::UNIX_EPOCH,
+                    idle: false,
+                    rotated_from: Vec::new(),
+                    last_uuid: None,
+                };
+                let state = match previous {
+                    Some(previous) => {
+                        if !skip {
+                            events.push(ProjectEvent::SessionRotated {
+                                project: project.clone(),
+                                from: previous.session_id.clone(),
+                                to: stem.clone(),
+                            });
+                        }
+                        let mut rotated_from = previous.rotated_from;
+                        rotated_from.push(previous.session_id);
+                        SessionState {
+                            entry_count: previous.entry_count,
+                            rotated_from,
+                            ..state
+                        }
+                    }
+                    None => {
+                        if !skip {
+                            events.push(ProjectEvent::SessionStarted {
+                                project: project.clone(),
+                                session_id: stem.clone(),
+                            });
+                        }
+                        state
+                    }
+                };
+                self.sessions.insert(key.clone(), state);
+                if self.read(&key, skip, &mut events)? {
+                    active.insert(stem);
+                }
+            }
+
+            if skip {
+                continue;
+            }
+            for ((p, session_id), state) in self.sessions.iter_mut() {
+                if *p != project || state.idle || active.contains(session_id) {
+                    continue;
+                }
+                if now
+                    .duration_since(state.last_activity)
+                    .is_ok_and(|quiet| quiet >= self.idle_after)
+                {
+                    state.idle = true;
+                    events.push(ProjectEvent::SessionIdle {
+                        project: project.clone(),
+                        session_id: session_id.clone(),
+                    });
+                }
+            }
+        }
+        Ok(events)
+    }
+
+    /// New session files in the project's `dirs`, each with its directory
+    /// and after the file it continues.
+    fn discover(&mut self, project: &str, dirs: &[PathBuf]) -> Result<Vec<(PathBuf, String)>> {
+        let chain = self
+            .chains
+            .entry(project.to_string())
+            .or_insert_with(ChainIndex::new);
+        let known = self.known.entry(project.to_string()).or_default();
+
+        let mut stems = Vec::new();
+        for dir in dirs {
+            chain.refresh_dir(dir)?;
+            for stem in paths::session_stems(dir)? {
+                if known.insert(stem.clone()) {
+                    stems.push((dir.clone(), stem));
+                }
+            }
+        }
+        let mut new: Vec<(usize, String, PathBuf)> = stems
+            .into_iter()
+            .map(|(dir, stem)| {
+                let mut depth = 0;
+                let mut current = stem.as_str();
+                while let Some(previous) = chain.predecessor_of(current) {
+                    depth += 1;
+                    if depth > known.len() {
+                        break; // cycle
+                    }
+                    current = previous;
+                }
+                (depth, stem, dir)
+            })
+            .collect();
+        new.sort();
+        Ok(new.into_iter().map(|(_, stem, dir)| (dir, stem)).collect())
+    }
+
+    /// Read a tracked session's new entries. Returns whether there were any.
+    fn read(
+        &mut self,
+        key: &(String, String),
+        skip: bool,
+        events: &mut Vec<ProjectEvent>,
+    ) -> Result<bool> {
+        let Some(state) = self.sessions.get_mut(key) else {
+            return Ok(false);
+        };
+        let metadata = match std::fs::metadata(&state.file_path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                // Deleted: stop tracking it.
+                self.sessions.remove(key);
+                return Ok(false);
+            }
+            Err(e) => return Err(e.into()),
+        };
+        state.last_activity = metadata.modified().unwrap_or(state.last_activity);
+        if skip {
+            state.offset = metadata.len();
+            return Ok(false);
+        }
+
+        let (entries, offset) =
+            ConversationReader::read_from_offset(&state.file_path, state.offset)?;
+        state.offset = offset;
+        if let Some(last) = entries.last() {
+            state.last_uuid = Some(last.uuid.clone());
+        }
+        let entries: Vec<ConversationEntry> = entries
+            .into_iter()
+            .filter(|e| !chain::is_bridge_entry(e, &state.session_id))
+            .collect();
+        if entries.is_empty() {
+            return Ok(false);
+        }
+
+        state.idle = false;
+        state.entry_count += entries.len();
+        self.seen += entries.len();
+        events.push(ProjectEvent::Entries {
+            project: state.project.clone(),
+            session_id: state.session_id.clone(),
+            entries,
+        });
+        Ok(true)
+    }
+}
+
+/// Runs a [`ProjectWatcher`] in a background task.
+///
+/// The task polls when a session log under the projects directory changes,
+/// and every [`WatcherConfig::poll_interval`] as a safety net.
+#[derive(Debug)]
+pub struct AsyncProjectWatcher {
+    watcher: ProjectWatcher,
+    config: WatcherConfig,
+}
+
+impl AsyncProjectWatcher {
+    /// Create an async watcher running `watcher` (uses default
+    /// configuration if `config` is None).
+    pub fn new(watcher: ProjectWatcher, config: Option<WatcherConfig>) -> Self {
+        Self {
+            watcher,
+            config: config.unwrap_or_default(),
+        }
+    }
+
+    /// Start watching, sending events to `tx`. Returns a handle that stops
+    /// the watcher.
+    pub async fn start(self, tx: mpsc::Sender<ProjectEvent>) -> Result<ProjectWatcherHandle> {
+        let (stop_tx, mut stop_rx) = mpsc::channel::<()>(1);
+        let (event_tx, mut event_rx) = mpsc::channel::<()>(16);
+        let Self {
+            mut watcher,
+            config,
+        } = self;
+
+        // Watch the whole projects directory of each config directory: a
+        // watched project's directory may not exist until its first session
+        // starts.
+        let roots: Vec<PathBuf> = watcher
+            .manager
+            .resolver()
+            .claude_dirs()?
+            .into_iter()
+            .map(|dir| dir.join("projects"))
+            .collect();
+        let fs_watcher =
+            notify::recommended_watcher(move |res: std::result::Result<Event, notify::Error>| {
+                if let Ok(event) = res
+                    && (event.kind.is_modify() || event.kind.is_create())
+                    && event
+                        .paths
+                        .iter()
+                        .any(|p| p.extension().is_some_and(|ext| ext == "jsonl"))
+                {
+                    let _ = event_tx.try_send(());
+                }
+            })
+            .and_then(|mut w| {
+                // The primary directory must be watchable; others only if present.
+                for (i, root) in roots.iter().enumerate() {
+                    if i == 0 || root.is_dir() {
+                        w.watch(root, RecursiveMode::Recursive)?;
+                    }
+                }
+                Ok(w)
+            });
+        let mut fs_watcher: Option<RecommendedWatcher> = match fs_watcher {
+            Ok(w) => Some(w),
+            Err(e) => {
+                eprintln!(
+                    "Warning: Failed to create file watcher: {}. Using poll-only mode.",
+                    e
+                );
+                None
+            }
+        };
+
+        let task = tokio::spawn(async move {
+            let mut poll_timer = tokio::time::interval(config.poll_interval);
+            let mut last_event = std::time::Instant::now();
+
+            loop {
+                tokio::select! {
+                    biased;
+
+                    _ = stop_rx.recv() => break,
+
+                    Some(()) = event_rx.recv() => {
+                        let now = std::time::Instant::now();
+                        if now.duration_since(last_event) < config.debounce {
+                            continue;
+                        }
+                        last_event = now;
+                    }
+
+                    _ = poll_timer.tick() => {}
+                }
+
+                let Ok(events) = watcher.poll() else {
+                    continue;
+                };
+                for event in events {
+                    if tx.send(event).await.is_err() {
+                        drop(fs_watcher.take());
+                        return watcher; // Receiver dropped
+                    }
+                }
+            }
+
+            drop(fs_watcher.take());
+            watcher
+        });
+
+        Ok(ProjectWatcherHandle { stop_tx, task })
+    }
+}
+
+/// Handle to control a running [`AsyncProjectWatcher`].
+pub struct ProjectWatcherHandle {
+    stop_tx: mpsc::Sender<()>,
+    task: tokio::task::JoinHandle<ProjectWatcher>,
+}
+
+impl ProjectWatcherHandle {
+    /// Stop the watcher and wait for it to finish. Returns the
+    /// [`ProjectWatcher`] with its session state, to poll or restart later;
+    /// `None` if the task panicked.
+    pub async fn stop(self) -> Option<ProjectWatcher> {
+        let _ = self.stop_tx.send(()).await;
+        self.task.await.ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PathResolver;
+    use std::fs;
+    use std::io::Write;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    fn entry(uuid: &str, session: &str) -> String {
+        format!(
+            r#"{{"type":"user","uuid":"{uuid}","timestamp":"2024-01-01T00:00:00Z","sessionId":"{session}","message":{{"role":"user","content":"Hello"}}}}"#
+        )
+    }
+
+    fn write_session(claude_dir: &Path, project_dir: &str, session: &str, lines: &[String]) {
+        let dir = claude_dir.join("projects").join(project_dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join(format!("{}.jsonl", session)),
+            lines.iter().map(|l| format!("{}\n", l)).collect::<String>(),
+        )
+        .unwrap();
+    }
+
+    fn append(claude_dir: &Path, project_dir: &str, session: &str, line: &str) {
+        let path = claude_dir
+            .join("projects")
+            .join(project_dir)
+            .join(format!("{}.jsonl", session));
+        let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+        writeln!(file, "{}", line).unwrap();
+    }
+
+    fn manager(claude_dir: &Path) -> ClaudeConvo {
+        ClaudeConvo::with_resolver(PathResolver::new().with_claude_dir(claude_dir))
+    }
+
+    fn describe(events: &[ProjectEvent]) -> Vec<String> {
+        events
+            .iter()
+            .map(|event| match event {
+                ProjectEvent::SessionStarted { session_id, .. } => format!("start {session_id}"),
+                ProjectEvent::SessionRotated { from, to, .. } => format!("rotate {from} {to}"),
+                ProjectEvent::Entries {
+                    session_id,
+                    entries,
+                    ..
+                } => format!("entries {session_id} {}", entries.len()),
+                ProjectEvent::SessionIdle { session_id, .. } => format!("idle {session_id}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_project_watcher_discovers_sessions() {
+        let temp = TempDir::new().unwrap();
+        let claude = temp.path().join(".claude");
+        write_session(&claude, "-test-project", "s1", &[entry("a", "s1")]);
+
+        let mut watcher = ProjectWatcher::new(manager(&claude), "/test/project".into());
+        assert_eq!(
+            describe(&watcher.poll().unwrap()),
+            ["start s1", "entries s1 1"]
+        );
+        assert!(watcher.poll().unwrap().is_empty());
+
+        append(&claude, "-test-project", "s1", &entry("b", "s1"));
+        write_session(&claude, "-test-project", "s2", &[entry("c", "s2")]);
+        assert_eq!(
+            describe(&watcher.poll().unwrap()),
+            ["entries s1 1", "start s2", "entries s2 1"]
+        );
+        assert_eq!(watcher.seen_count(), 3);
+        assert_eq!(
+            watcher.session("/test/project", "s1").unwrap().entry_count,
+            2
+        );
+    }
+
+    #[test]
+    fn test_project_watcher_reads_every_config_dir() {
+        let temp = TempDir::new().unwrap();
+        let claude = temp.path().join(".claude");
+        let work = temp.path().join("work");
+        write_session(&claude, "-test-project", "s1", &[entry("a", "s1")]);
+        write_session(&work, "-test-project", "s2", &[entry("b", "s2")]);
+
+        let manager = ClaudeConvo::with_resolver(
+            PathResolver::new()
+                .with_claude_dir(&claude)
+                .with_claude_dirs([&work]),
+        );
+        let mut watcher = ProjectWatcher::new(manager, "/test/project".into());
+        assert_eq!(
+            describe(&watcher.poll().unwrap()),
+            ["start s1", "entries s1 1", "start s2", "entries s2 1"]
+        );
+
+        append(&work, "-test-project", "s2", &entry("c", "s2"));
+        assert_eq!(describe(&watcher.poll().unwrap()), ["entries s2 1"]);
+    }
+
+    #[test]
+    fn test_project_watcher_follows_rotation() {
+        let temp = TempDir::new().unwrap();
+        let claude = temp.path().join(".claude");
+        write_session(&claude, "-test-project", "s1", &[entry("a", "s1")]);
+
+        let mut watcher = ProjectWatcher::new(manager(&claude), "/test/project".into());
+        watcher.poll().unwrap();
+
+        append(&claude, "-test-project", "s1", &entry("b", "s1"));
+        write_session(
+            &claude,
+            "-test-project",
+            "s2",
+            &[entry("bridge", "s1"), entry("c", "s2")],
+        );
+        assert_eq!(
+            describe(&watcher.poll().unwrap()),
+            ["entries s1 1", "rotate s1 s2", "entries s2 1"]
+        );
+
+        let sessions: Vec<&SessionState> = watcher.sessions().collect();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].session_id, "s2");
+        assert_eq!(sessions[0].rotated_from, ["s1"]);
+        assert_eq!(sessions[0].entry_count, 3);
+    }
+
+    #[test]
+    fn test_project_watcher_all_projects() {
+        let temp = TempDir::new().unwrap();
+        let claude = temp.path().join(".claude");
+        write_session(&claude, "-a", "s1", &[entry("a", "s1")]);
+
+        let mut watcher = ProjectWatcher::all(manager(&claude));
+        assert_eq!(watcher.skip_existing().unwrap(), 1);
+        assert!(watcher.poll().unwrap().is_empty());
+
+        write_session(&claude, "-b", "s2", &[entry("b", "s2")]);
+        let events = watcher.poll().unwrap();
+        assert_eq!(describe(&events), ["start s2", "entries s2 1"]);
+        assert_eq!(events[0].project(), "/b");
+    }
+
+    #[test]
+    fn test_project_watcher_idle() {
+        let temp = TempDir::new().unwrap();
+        let claude = temp.path().join(".claude");
+        write_session(&claude, "-test-project", "s1", &[entry("a", "s1")]);
+
+        let mut watcher = ProjectWatcher::new(manager(&claude), "/test/project".into())
+            .with_idle_after(Duration::ZERO);
+        watcher.poll().unwrap();
+        assert_eq!(describe(&watcher.poll().unwrap()), ["idle s1"]);
+        assert!(watcher.poll().unwrap().is_empty());
+        assert!(watcher.session("/test/project", "s1").unwrap().idle);
+
+        append(&claude, "-test-project", "s1", &entry("b", "s1"));
+        assert_eq!(describe(&watcher.poll().unwrap()), ["entries s1 1"]);
+        assert!(!watcher.session("/test/project", "s1").unwrap().idle);
+    }
+
+    #[tokio::test]
+    async fn test_async_project_watcher() {
+        let temp = TempDir::new().unwrap();
+        let claude = temp.path().join(".claude");
+        write_session(&claude, "-test-project", "s1", &[entry("a", "s1")]);
+
+        let watcher = ProjectWatcher::new(manager(&claude), "/test/project".into());
+        let config = WatcherConfig {
+            poll_interval: Duration::from_millis(20),
+            debounce: Duration::from_millis(10),
+        };
+        let (tx, mut rx) = mpsc::channel(16);
+        let handle = AsyncProjectWatcher::new(watcher, Some(config))
+            .start(tx)
+            .await
+            .unwrap();
+
+        let mut events = Vec::new();
+        while events.len() < 2 {
+            let event = tokio::time::timeout(Duration::from_secs(1), rx.recv())
+                .await
+                .expect("timeout")
+                .expect("channel closed");
+            events.push(event);
+        }
+        assert_eq!(describe(&events), ["start s1", "entries s1 1"]);
+
+        let watcher = handle.stop().await.unwrap();
+        assert_eq!(watcher.seen_count(), 1);
+    }
+
+    #[test]
+    fn test_project_watcher_restore() {
+        let temp = TempDir::new().unwrap();
+        let claude = temp.path().join(".claude");
+        write_session(&claude, "-test-project", "s1", &[entry("a", "s1")]);
+
+        let mut watcher = ProjectWatcher::new(manager(&claude), "/test/project".into());
+        watcher.poll().unwrap();
+        let checkpoint = watcher.checkpoint().unwrap();
+        drop(watcher);
+
+        append(&claude, "-test-project", "s1", &entry("b", "s1"));
+        write_session(&claude, "-test-project", "s2", &[entry("c", "s2")]);
+
+        let mut watcher = ProjectWatcher::new(manager(&claude), "/test/project".into());
+        watcher.restore(&checkpoint).unwrap();
+        assert_eq!(
+            describe(&watcher.poll().unwrap()),
+            ["entries s1 1", "start s2", "entries s2 1"]
+        );
+    }
+}
@@ -5,15 +5,21 @@
 //! pairs them by `tool_use_id` so consumers get complete `Turn` values
 //! with `ToolInvocation.result` populated.
 
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 use crate::ClaudeConvo;
-use crate::types::{Conversation, ConversationEntry, Message, MessageContent, MessageRole};
+use crate::subagents::Subagent;
+use crate::types::{
+    CompactMetadata, Conversation, ConversationEntry, MediaKind, MediaRef, Message, MessageContent,
+    MessageRole,
+};
 #[cfg(any(feature = "watcher", test))]
 use toolpath_convo::WatcherEvent;
 use toolpath_convo::{
-    ConversationMeta, ConversationProvider, ConversationView, ConvoError, DelegatedWork,
-    EnvironmentSnapshot, Role, TokenUsage, ToolCategory, ToolInvocation, ToolResult, Turn,
+    Attachment, AttachmentKind, Compaction, ConversationMeta, ConversationProvider,
+    ConversationView, ConvoError, DelegatedWork, EnvironmentSnapshot, Role, TokenUsage,
+    ToolCategory, ToolInvocation, ToolOrigin, ToolResult, Turn,
 };
 
 // ── Conversion helpers ───────────────────────────────────────────────
@@ -42,9 +48,70 @@ fn tool_category(name: &str) -> Option<ToolCategory> {
     }
 }
 
+/// Split an MCP tool name, `mcp__<server>__<tool>`, into server and tool.
+pub fn parse_mcp_tool(name: &str) -> Option<(&str, &str)> {
+    let (server, tool) = name.strip_prefix("mcp__")?.split_once("__")?;
+    if server.is_empty() || tool.is_empty() {
+        return None;
+    }
+    Some((server, tool))
+}
+
+/// Where a Claude Code tool comes from, by its name.
+pub fn tool_origin(name: &str) -> ToolOrigin {
+    match parse_mcp_tool(name) {
+        Some((server, tool)) => ToolOrigin::Mcp {
+            server: server.to_string(),
+            tool: tool.to_string(),
+        },
+        None => ToolOrigin::Builtin,
+    }
+}
+
+/// Categories for tools the built-in map doesn't know, typically MCP
+/// tools. Parsed from TOML:
+///
+/// ```toml
+/// [servers]
+/// postgres = "file_read"
+///
+/// [tools]
+/// mcp__jira__create_issue = "network"
+/// ```
+///
+/// A tool is looked up by its full name in `tools`, then by MCP server in
+/// `servers`, then in the built-in map.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolCategories {
+    /// By MCP server name, for every tool of that server.
+    #[serde(default)]
+    pub servers: HashMap<String, ToolCategory>,
+    /// By full tool name.
+    #[serde(default)]
+    pub tools: HashMap<String, ToolCategory>,
+}
+
+impl ToolCategories {
+    /// Parse a mapping from TOML.
+    pub fn from_toml(s: &str) -> crate::Result<Self> {
+        Ok(toml::from_str(s)?)
+    }
+
+    /// The category of tool `name`.
+    pub fn category(&self, name: &str) -> Option<ToolCategory> {
+        if let Some(category) = self.tools.get(name) {
+            return Some(*category);
+        }
+        match parse_mcp_tool(name) {
+            Some((server, _)) => self.servers.get(server).copied(),
+            None => tool_category(name),
+        }
+    }
+}
+
 /// Convert a single entry to a Turn without cross-entry assembly.
 /// Tool results within the same message are still matched.
-fn message_to_turn(entry: &ConversationEntry, msg: &Message) -> Turn {
+fn message_to_turn(entry: &ConversationEntry, msg: &Message, tools: &ToolCategories) -> Turn {
     let text = msg.text();
 
     let thinking = msg.thinking().map(|parts| parts.join("\n"));
@@ -54,13 +121,13 @@ fn message_to_turn(entry: &ConversationEntry, msg: &Message) -> Turn {
         .into_iter()
         .map(|tu| {
             let result = find_tool_result_in_parts(msg, tu.id);
-            let category = tool_category(tu.name);
             ToolInvocation {
                 id: tu.id.to_string(),
                 name: tu.name.to_string(),
                 input: tu.input.clone(),
                 result,
-                category,
+                category: tools.category(tu.name),
+                origin: Some(tool_origin(tu.name)),
             }
         })
         .collect();
@@ -84,6 +151,8 @@ fn message_to_turn(entry: &ConversationEntry, msg: &Message) -> Turn {
 
     let delegations = extract_delegations(&tool_uses);
 
+    let attachments = msg.media().iter().map(to_attachment).collect();
+
     let extra = if entry.extra.is_empty() {
         HashMap::new()
     } else {
@@ -95,10 +164,18 @@ fn message_to_turn(entry: &ConversationEntry, msg: &Message) -> Turn {
         map
     };
 
+    // A compaction summary is logged as a user message, but it is Claude
+    // Code speaking, not the user.
+    let (role, compaction) = if entry.is_compact_summary() {
+        (Role::System, Some(Compaction::default()))
+    } else {
+        (claude_role_to_role(&msg.role), None)
+    };
+
     Turn {
         id: entry.uuid.clone(),
         parent_id: entry.parent_uuid.clone(),
-        role: claude_role_to_role(&msg.role),
+        role,
         timestamp: entry.timestamp.clone(),
         text,
         thinking,
@@ -108,10 +185,27 @@ fn message_to_turn(entry: &ConversationEntry, msg: &Message) -> Turn {
         token_usage,
         environment,
         delegations,
+        attachments,
+        compaction,
         extra,
     }
 }
 
+fn to_attachment(media: &MediaRef<'_>) -> Attachment {
+    let bytes = media.source.bytes();
+    Attachment {
+        kind: match media.kind {
+            MediaKind::Image => AttachmentKind::Image,
+            MediaKind::Document => AttachmentKind::Document,
+        },
+        media_type: media.source.media_type.clone(),
+        size: bytes.as_ref().map(|b| b.len() as u64),
+        sha256: bytes.as_deref().map(crate::digest::sha256),
+        url: media.source.url.clone(),
+        title: media.title.map(str::to_string),
+    }
+}
+
 /// Extract delegation info from Task tool invocations.
 fn extract_delegations(tool_uses: &[ToolInvocation]) -> Vec<DelegatedWork> {
     tool_uses
@@ -187,22 +281,28 @@ fn merge_tool_results(turns: &mut [Turn], msg: &Message) -> bool {
     merged
 }
 
-fn entry_to_turn(entry: &ConversationEntry) -> Option<Turn> {
+fn entry_to_turn(entry: &ConversationEntry, tools: &ToolCategories) -> Option<Turn> {
     entry
         .message
         .as_ref()
-        .map(|msg| message_to_turn(entry, msg))
+        .map(|msg| message_to_turn(entry, msg, tools))
 }
 
 /// Convert a full conversation to a view with cross-entry tool result assembly.
 ///
 /// Tool-result-only user entries are absorbed into the preceding assistant
 /// turn's `ToolInvocation.result` fields rather than emitted as separate turns.
-fn conversation_to_view(convo: &Conversation) -> ConversationView {
+fn conversation_to_view(convo: &Conversation, tools: &ToolCategories) -> ConversationView {
     let mut turns: Vec<Turn> = Vec::new();
+    // The last compaction boundary, and the turns since the last summary
+    let mut boundary: Option<CompactMetadata> = None;
+    let mut since_compaction = 0;
 
     for entry in &convo.entries {
         let Some(msg) = &entry.message else {
+            if let Some(metadata) = entry.compact_metadata() {
+                boundary = Some(metadata);
+            }
             continue;
         };
 
@@ -212,7 +312,18 @@ fn conversation_to_view(convo: &Conversation) -> ConversationView {
             continue;
         }
 
-        turns.push(message_to_turn(entry, msg));
+        let mut turn = message_to_turn(entry, msg, tools);
+        match &mut turn.compaction {
+            Some(compaction) => {
+                let metadata = boundary.take().unwrap_or_default();
+                compaction.trigger = metadata.trigger;
+                compaction.pre_tokens = metadata.pre_tokens;
+                compaction.replaced_turns = Some(since_compaction);
+                since_compaction = 0;
+            }
+            None => since_compaction += 1,
+        }
+        turns.push(turn);
     }
 
     // Re-derive delegation results now that tool results are merged
@@ -244,6 +355,34 @@ fn conversation_to_view(convo: &Conversation) -> ConversationView {
     }
 }
 
+/// `convo` minus the sidechain entries that belong to a matched subagent,
+/// which are reported under the delegation instead.
+fn without_subagents(convo: &Conversation, subagents: &[Subagent]) -> Conversation {
+    let delegated: std::collections::HashSet<&str> = subagents
+        .iter()
+        .flat_map(|s| s.conversation.entries.iter().map(|e| e.uuid.as_str()))
+        .collect();
+    let mut main = convo.clone();
+    main.entries
+        .retain(|e| !delegated.contains(e.uuid.as_str()));
+    main
+}
+
+/// Fill each delegation's `turns` from the matching subagent transcript.
+/// Subagent turns are assembled the same way as the parent's.
+fn attach_subagents(view: &mut ConversationView, subagents: &[Subagent], tools: &ToolCategories) {
+    for turn in &mut view.turns {
+        for delegation in &mut turn.delegations {
+            if let Some(sub) = subagents
+                .iter()
+                .find(|s| s.tool_use_id == delegation.agent_id)
+            {
+                delegation.turns = conversation_to_view(&sub.conversation, tools).turns;
+            }
+        }
+    }
+}
+
 /// Sum token usage across all turns.
 fn sum_usage(turns: &[Turn]) -> Option<TokenUsage> {
     let mut total = TokenUsage::default();
@@ -291,7 +430,7 @@ fn extract_files_changed(turns: &[Turn]) -> Vec<String> {
 
 #[cfg(any(feature = "watcher", test))]
 fn entry_to_watcher_event(entry: &ConversationEntry) -> WatcherEvent {
-    match entry_to_turn(entry) {
+    match entry_to_turn(entry, &ToolCategories::default()) {
         Some(turn) => WatcherEvent::Turn(Box::new(turn)),
         None => {
             let mut data = serde_json::json!({
@@ -325,7 +464,12 @@ impl ConversationProvider for ClaudeConvo {
         let convo = self
             .read_conversation(project, conversation_id)
             .map_err(|e| ConvoError::Provider(e.to_string()))?;
-        let mut view = conversation_to_view(&convo);
+        let subagents = self
+            .read_subagents(project, &convo)
+            .map_err(|e| ConvoError::Provider(e.to_string()))?;
+        let tools = self.tool_categories();
+        let mut view = conversation_to_view(&without_subagents(&convo, &subagents), tools);
+        attach_subagents(&mut view, &subagents, tools);
         view.session_ids = convo.session_ids.clone();
         Ok(view)
     }
@@ -391,54 +535,152 @@ impl toolpath_convo::ConversationWatcher for crate::watcher::ConversationWatcher
             });
         }
 
-        for entry in &entries {
-            let Some(msg) = &entry.message else {
-                events.push(entry_to_watcher_event(entry));
-                continue;
-            };
+        push_entry_events(&mut events, &entries);
+        Ok(events)
+    }
 
-            if is_tool_result_only(entry) {
-                // Find matching turns in previously emitted events and in
-                // our assembled state, merge results, emit TurnUpdated.
-                // Walk events in reverse to find the turn to update.
-                let mut updated_turn: Option<Turn> = None;
-
-                // Search backwards through events emitted this poll cycle
-                for event in events.iter_mut().rev() {
-                    if let WatcherEvent::Turn(turn) | WatcherEvent::TurnUpdated(turn) = event
-                        && turn.tool_uses.iter().any(|tu| {
-                            tu.result.is_none()
-                                && msg.tool_results().iter().any(|tr| tr.tool_use_id == tu.id)
-                        })
-                    {
-                        // Merge results into this turn
-                        let mut updated = (**turn).clone();
-                        merge_tool_results(std::slice::from_mut(&mut updated), msg);
-                        updated_turn = Some(updated.clone());
-                        // Also update the existing event in-place so later
-                        // result entries can find the right state
-                        **turn = updated;
-                        break;
-                    }
-                }
+    fn seen_count(&self) -> usize {
+        crate::watcher::ConversationWatcher::seen_count(self)
+    }
+}
+
+#[cfg(feature = "watcher")]
+impl toolpath_convo::ResumableWatcher for crate::watcher::ConversationWatcher {
+    fn checkpoint(&self) -> toolpath_convo::Result<toolpath_convo::WatcherCheckpoint> {
+        crate::watcher::ConversationWatcher::checkpoint(self)
+            .map_err(|e| ConvoError::Provider(e.to_string()))
+    }
 
-                if let Some(turn) = updated_turn {
-                    events.push(WatcherEvent::TurnUpdated(Box::new(turn)));
+    fn restore(
+        &mut self,
+        checkpoint: &toolpath_convo::WatcherCheckpoint,
+    ) -> toolpath_convo::Result<()> {
+        crate::watcher::ConversationWatcher::restore(self, checkpoint)
+            .map_err(|e| ConvoError::Provider(e.to_string()))
+    }
+}
+
+/// Turn events for `entries`, appended to `events`. A tool-result-only
+/// entry updates the turn it answers, if that turn is in `events`.
+#[cfg(feature = "watcher")]
+fn push_entry_events(events: &mut Vec<WatcherEvent>, entries: &[ConversationEntry]) {
+    for entry in entries {
+        let Some(msg) = &entry.message else {
+            events.push(entry_to_watcher_event(entry));
+            continue;
+        };
+
+        if is_tool_result_only(entry) {
+            // Find matching turns in previously emitted events and in
+            // our assembled state, merge results, emit TurnUpdated.
+            // Walk events in reverse to find the turn to update.
+            let mut updated_turn: Option<Turn> = None;
+
+            // Search backwards through events emitted this poll cycle
+            for event in events.iter_mut().rev() {
+                if let WatcherEvent::Turn(turn) | WatcherEvent::TurnUpdated(turn) = event
+                    && turn.tool_uses.iter().any(|tu| {
+                        tu.result.is_none()
+                            && msg.tool_results().iter().any(|tr| tr.tool_use_id == tu.id)
+                    })
+                {
+                    // Merge results into this turn
+                    let mut updated = (**turn).clone();
+                    merge_tool_results(std::slice::from_mut(&mut updated), msg);
+                    updated_turn = Some(updated.clone());
+                    // Also update the existing event in-place so later
+                    // result entries can find the right state
+                    **turn = updated;
+                    break;
                 }
-                // If no matching turn found, the tool-result-only entry
-                // is silently dropped (the matching turn was emitted in a
-                // prior poll cycle and can't be updated from here).
-                continue;
             }
 
-            events.push(entry_to_watcher_event(entry));
+            if let Some(turn) = updated_turn {
+                events.push(WatcherEvent::TurnUpdated(Box::new(turn)));
+            }
+            // If no matching turn found, the tool-result-only entry
+            // is silently dropped (the matching turn was emitted in a
+            // prior poll cycle and can't be updated from here).
+            continue;
         }
 
+        events.push(entry_to_watcher_event(entry));
+    }
+}
+
+#[cfg(feature = "watcher")]
+impl toolpath_convo::ConversationWatcher for crate::project_watcher::ProjectWatcher {
+    /// Session lifecycle events become `Progress` events of kind
+    /// `session_started`, `session_rotated` and `session_idle`. Each batch
+    /// of turns is preceded by a `session_entries` progress event naming
+    /// the session the turns belong to.
+    fn poll(&mut self) -> toolpath_convo::Result<Vec<WatcherEvent>> {
+        use crate::project_watcher::ProjectEvent;
+
+        let project_events = crate::project_watcher::ProjectWatcher::poll(self)
+            .map_err(|e| ConvoError::Provider(e.to_string()))?;
+
+        let mut events = Vec::new();
+        for event in project_events {
+            match event {
+                ProjectEvent::SessionStarted {
+                    project,
+                    session_id,
+                } => events.push(WatcherEvent::Progress {
+                    kind: "session_started".into(),
+                    data: serde_json::json!({"project": project, "session_id": session_id}),
+                }),
+                ProjectEvent::SessionRotated { project, from, to } => {
+                    events.push(WatcherEvent::Progress {
+                        kind: "session_rotated".into(),
+                        data: serde_json::json!({"project": project, "from": from, "to": to}),
+                    })
+                }
+                ProjectEvent::Entries {
+                    project,
+                    session_id,
+                    entries,
+                } => {
+                    events.push(WatcherEvent::Progress {
+                        kind: "session_entries".into(),
+                        data: serde_json::json!({
+                            "project": project,
+                            "session_id": session_id,
+                            "count": entries.len(),
+                        }),
+                    });
+                    push_entry_events(&mut events, &entries);
+                }
+                ProjectEvent::SessionIdle {
+                    project,
+                    session_id,
+                } => events.push(WatcherEvent::Progress {
+                    kind: "session_idle".into(),
+                    data: serde_json::json!({"project": project, "session_id": session_id}),
+                }),
+            }
+        }
         Ok(events)
     }
 
     fn seen_count(&self) -> usize {
-        crate::watcher::ConversationWatcher::seen_count(self)
+        crate::project_watcher::ProjectWatcher::seen_count(self)
+    }
+}
+
+#[cfg(feature = "watcher")]
+impl toolpath_convo::ResumableWatcher for crate::project_watcher::ProjectWatcher {
+    fn checkpoint(&self) -> toolpath_convo::Result<toolpath_convo::WatcherCheckpoint> {
+        crate::project_watcher::ProjectWatcher::checkpoint(self)
+            .map_err(|e| ConvoError::Provider(e.to_string()))
+    }
+
+    fn restore(
+        &mut self,
+        checkpoint: &toolpath_convo::WatcherCheckpoint,
+    ) -> toolpath_convo::Result<()> {
+        crate::project_watcher::ProjectWatcher::restore(self, checkpoint)
+            .map_err(|e| ConvoError::Provider(e.to_string()))
     }
 }
 
@@ -450,7 +692,12 @@ impl toolpath_convo::ConversationWatcher for crate::watcher::ConversationWatcher
 /// entries are merged into the preceding assistant turn rather than emitted
 /// as separate turns.
 pub fn to_view(convo: &Conversation) -> ConversationView {
-    conversation_to_view(convo)
+    conversation_to_view(convo, &ToolCategories::default())
+}
+
+/// [`to_view`], categorizing tools with `tools`.
+pub fn to_view_with(convo: &Conversation, tools: &ToolCategories) -> ConversationView {
+    conversation_to_view(convo, tools)
 }
 
 /// Convert a single Claude [`ConversationEntry`] into a [`Turn`], if it
@@ -459,7 +706,7 @@ pub fn to_view(convo: &Conversation) -> ConversationView {
 /// Note: this does *not* perform cross-entry assembly. For assembled
 /// results, use [`to_view`] instead.
 pub fn to_turn(entry: &ConversationEntry) -> Option<Turn> {
-    entry_to_turn(entry)
+    entry_to_turn(entry, &ToolCategories::default())
 }
 
 // ── Tests ────────────────────────────────────────────────────────────
@@ -713,6 +960,66 @@ mod tests {
         assert_eq!(turn.role, Role::User);
     }
 
+    #[test]
+    fn test_to_turn_with_attachments() {
+        let entry: ConversationEntry = serde_json::from_str(
+            r#"{"uuid":"u1","type":"user","timestamp":"2024-01-01T00:00:00Z","message":{"role":"user","content":[{"type":"text","text":"Like this"},{"type":"image","source":{"type":"base64","media_type":"image/png","data":"Zm9v"}},{"type":"document","title":"Spec","source":{"type":"url","url":"https://example.com/spec.pdf"}}]}}"#,
+        )
+        .unwrap();
+        let turn = to_turn(&entry).unwrap();
+        assert_eq!(turn.text, "Like this");
+        assert_eq!(turn.attachments.len(), 2);
+
+        let image = &turn.attachments[0];
+        assert_eq!(image.kind, AttachmentKind::Image);
+        assert_eq!(image.media_type.as_deref(), Some("image/png"));
+        assert_eq!(image.size, Some(3));
+        assert_eq!(image.sha256, Some(crate::digest::sha256(b"foo")));
+
+        let document = &turn.attachments[1];
+        assert_eq!(document.kind, AttachmentKind::Document);
+        assert_eq!(document.title.as_deref(), Some("Spec"));
+        assert_eq!(
+            document.url.as_deref(),
+            Some("https://example.com/spec.pdf")
+        );
+        assert!(document.sha256.is_none());
+    }
+
+    #[test]
+    fn test_compaction_summary_turn() {
+        let entries = [
+            r#"{"uuid":"u1","type":"user","timestamp":"2024-01-01T00:00:00Z","message":{"role":"user","content":"Use tabs"}}"#,
+            r#"{"uuid":"u2","type":"assistant","parentUuid":"u1","timestamp":"2024-01-01T00:00:01Z","message":{"role":"assistant","content":"OK"}}"#,
+            r#"{"uuid":"u3","type":"system","subtype":"compact_boundary","parentUuid":null,"logicalParentUuid":"u2","timestamp":"2024-01-01T00:00:02Z","compactMetadata":{"trigger":"manual","preTokens":9000}}"#,
+            r#"{"uuid":"u4","type":"user","parentUuid":"u3","isCompactSummary":true,"timestamp":"2024-01-01T00:00:03Z","message":{"role":"user","content":"Summary: use tabs."}}"#,
+            r#"{"uuid":"u5","type":"user","parentUuid":"u4","timestamp":"2024-01-01T00:00:04Z","message":{"role":"user","content":"Next"}}"#,
+        ];
+        let mut convo = Conversation::new("s".into());
+        for line in entries {
+            convo.add_entry(serde_json::from_str(line).unwrap());
+        }
+
+        let view = to_view(&convo);
+        assert_eq!(view.turns.len(), 4);
+        let summary = &view.turns[2];
+        assert_eq!(summary.role, Role::System);
+        assert!(summary.is_compaction());
+        assert_eq!(
+            summary.compaction,
+            Some(Compaction {
+                trigger: Some("manual".into()),
+                pre_tokens: Some(9000),
+                replaced_turns: Some(2),
+            })
+        );
+        assert!(!view.turns[3].is_compaction());
+
+        // Converted on its own, the replaced turns are unknown
+        let turn = to_turn(&convo.entries[3]).unwrap();
+        assert_eq!(turn.compaction, Some(Compaction::default()));
+    }
+
     #[test]
     fn test_to_turn_without_message() {
         let entry: ConversationEntry = serde_json::from_str(
@@ -904,6 +1211,7 @@ mod tests {
                     input: serde_json::json!({}),
                     result: None,
                     category: Some(ToolCategory::FileRead),
+                    origin: None,
                 },
                 ToolInvocation {
                     id: "tool-b".into(),
@@ -911,6 +1219,7 @@ mod tests {
                     input: serde_json::json!({}),
                     result: None,
                     category: Some(ToolCategory::FileWrite),
+                    origin: None,
                 },
             ],
             model: None,
@@ -918,6 +1227,8 @@ mod tests {
             token_usage: None,
             environment: None,
             delegations: vec![],
+            attachments: vec![],
+            compaction: None,
             extra: Default::default(),
         }];
 
@@ -992,6 +1303,92 @@ mod tests {
         assert_eq!(tool_category("UnknownTool"), None);
     }
 
+    #[test]
+    fn test_parse_mcp_tool() {
+        assert_eq!(
+            parse_mcp_tool("mcp__postgres__query"),
+            Some(("postgres", "query"))
+        );
+        assert_eq!(
+            parse_mcp_tool("mcp__ticket_db__get__all"),
+            Some(("ticket_db", "get__all"))
+        );
+        assert_eq!(parse_mcp_tool("mcp__postgres"), None);
+        assert_eq!(parse_mcp_tool("Read"), None);
+        assert_eq!(tool_origin("Read"), ToolOrigin::Builtin);
+        assert_eq!(
+            tool_origin("mcp__jira__get_issue"),
+            ToolOrigin::Mcp {
+                server: "jira".into(),
+                tool: "get_issue".into()
+            }
+        );
+    }
+
+    #[test]
+    fn test_tool_categories() {
+        let tools = ToolCategories::from_toml(
+            r#"
+[servers]
+postgres = "file_read"
+
+[tools]
+mcp__postgres__write = "file_write"
+Bash = "network"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            tools.category("mcp__postgres__query"),
+            Some(ToolCategory::FileRead)
+        );
+        assert_eq!(
+            tools.category("mcp__postgres__write"),
+            Some(ToolCategory::FileWrite)
+        );
+        assert_eq!(tools.category("mcp__jira__get_issue"), None);
+        assert_eq!(tools.category("Bash"), Some(ToolCategory::Network));
+        assert_eq!(tools.category("Read"), Some(ToolCategory::FileRead));
+        assert!(ToolCategories::from_toml("[servers]\nx = \"bogus\"").is_err());
+    }
+
+    #[test]
+    fn test_load_conversation_mcp_tools() {
+        let temp = TempDir::new().unwrap();
+        let claude_dir = temp.path().join(".claude");
+        let project_dir = claude_dir.join("projects/-test-project");
+        fs::create_dir_all(&project_dir).unwrap();
+        fs::write(
+            project_dir.join("s.jsonl"),
+            r#"{"uuid":"u1","type":"assistant","timestamp":"2024-01-01T00:00:00Z","message":{"role":"assistant","content":[{"type":"tool_use","id":"t1","name":"mcp__postgres__query","input":{"sql":"select 1"}}]}}"#,
+        )
+        .unwrap();
+        let tools = ToolCategories {
+            servers: HashMap::from([("postgres".to_string(), ToolCategory::FileRead)]),
+            ..Default::default()
+        };
+        let provider = ClaudeConvo::with_resolver(PathResolver::new().with_claude_dir(&claude_dir))
+            .with_tool_categories(tools);
+
+        let view =
+            ConversationProvider::load_conversation(&provider, "/test/project", "s").unwrap();
+        let tool_use = &view.turns[0].tool_uses[0];
+        assert_eq!(tool_use.category, Some(ToolCategory::FileRead));
+        assert_eq!(
+            tool_use.origin,
+            Some(ToolOrigin::Mcp {
+                server: "postgres".into(),
+                tool: "query".into()
+            })
+        );
+        assert_eq!(
+            to_view(&provider.read_conversation("/test/project", "s").unwrap()).turns[0].tool_uses
+                [0]
+            .category,
+            None
+        );
+    }
+
     #[test]
     fn test_turn_has_tool_category() {
         let (_temp, provider) = setup_provider();
@@ -1140,6 +1537,74 @@ mod tests {
 
     // ── Provider-specific extras (Turn.extra["claude"]) ─────────────
 
+    #[test]
+    fn test_delegation_turns_from_sidechain() {
+        let temp = TempDir::new().unwrap();
+        let claude_dir = temp.path().join(".claude");
+        let project_dir = claude_dir.join("projects/-test-project");
+        fs::create_dir_all(&project_dir).unwrap();
+
+        let entries = [
+            r#"{"uuid":"u1","type":"assistant","timestamp":"2024-01-01T00:00:01Z","message":{"role":"assistant","content":[{"type":"tool_use","id":"task-1","name":"Task","input":{"prompt":"Find the bug"}}]}}"#,
+            r#"{"uuid":"s1","parentUuid":null,"isSidechain":true,"type":"user","timestamp":"2024-01-01T00:00:02Z","message":{"role":"user","content":"Find the bug"}}"#,
+            r#"{"uuid":"s2","parentUuid":"s1","isSidechain":true,"type":"assistant","timestamp":"2024-01-01T00:00:03Z","message":{"role":"assistant","content":"In auth.rs"}}"#,
+            r#"{"uuid":"u2","parentUuid":"u1","type":"user","timestamp":"2024-01-01T00:00:04Z","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"task-1","content":"In auth.rs"}]}}"#,
+        ];
+        fs::write(project_dir.join("s1.jsonl"), entries.join("\n")).unwrap();
+
+        let resolver = PathResolver::new().with_claude_dir(&claude_dir);
+        let provider = ClaudeConvo::with_resolver(resolver);
+        let view =
+            ConversationProvider::load_conversation(&provider, "/test/project", "s1").unwrap();
+
+        assert_eq!(view.turns.len(), 1);
+        let delegation = &view.turns[0].delegations[0];
+        assert_eq!(delegation.turns.len(), 2);
+        assert_eq!(delegation.turns[1].text, "In auth.rs");
+    }
+
+    #[test]
+    fn test_delegation_turns_from_agent_file() {
+        let temp = TempDir::new().unwrap();
+        let claude_dir = temp.path().join(".claude");
+        let project_dir = claude_dir.join("projects/-test-project");
+        fs::create_dir_all(&project_dir).unwrap();
+
+        let entries = [
+            r#"{"uuid":"u1","type":"assistant","sessionId":"s1","timestamp":"2024-01-01T00:00:01Z","message":{"role":"assistant","content":[{"type":"tool_use","id":"task-1","name":"Task","input":{"prompt":"Find the bug"}}]}}"#,
+            r#"{"uuid":"u2","parentUuid":"u1","type":"user","sessionId":"s1","timestamp":"2024-01-01T00:00:04Z","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"task-1","content":"In auth.rs"}]},"toolUseResult":{"agentId":"a1b2"}}"#,
+        ];
+        fs::write(project_dir.join("s1.jsonl"), entries.join("\n")).unwrap();
+        let agent = [
+            r#"{"uuid":"a1","isSidechain":true,"agentId":"a1b2","sessionId":"s1","type":"user","timestamp":"2024-01-01T00:00:02Z","message":{"role":"user","content":"Find the bug"}}"#,
+            r#"{"uuid":"a2","parentUuid":"a1","isSidechain":true,"agentId":"a1b2","sessionId":"s1","type":"assistant","timestamp":"2024-01-01T00:00:03Z","message":{"role":"assistant","content":"In auth.rs"}}"#,
+        ];
+        fs::write(project_dir.join("agent-a1b2.jsonl"), agent.join("\n")).unwrap();
+        // Another session's transcript, and one that can't be read, are
+        // passed over.
+        fs::write(
+            project_dir.join("agent-c3d4.jsonl"),
+            agent[0].replace("\"s1\"", "\"s9\"").replace("a1b2", "c3d4"),
+        )
+        .unwrap();
+        fs::create_dir(project_dir.join("agent-e5f6.jsonl")).unwrap();
+
+        let resolver = PathResolver::new().with_claude_dir(&claude_dir);
+        let provider = ClaudeConvo::with_resolver(resolver);
+        assert_eq!(
+            ConversationProvider::list_conversations(&provider, "/test/project").unwrap(),
+            vec!["s1"]
+        );
+        let convo = provider.read_conversation("/test/project", "s1").unwrap();
+        let subagents = provider.read_subagents("/test/project", &convo).unwrap();
+        assert_eq!(subagents.len(), 1);
+        assert_eq!(subagents[0].conversation.entries.len(), 2);
+        let view =
+            ConversationProvider::load_conversation(&provider, "/test/project", "s1").unwrap();
+
+        assert_eq!(view.turns[0].delegations[0].turns.len(), 2);
+    }
+
     #[test]
     fn test_turn_extra_populated_from_entry() {
         let entry: ConversationEntry = serde_json::from_str(
@@ -1317,6 +1782,32 @@ mod tests {
         assert!(metas[0].successor.is_none());
     }
 
+    #[cfg(feature = "watcher")]
+    #[test]
+    fn test_project_watcher_trait() {
+        let (_temp, manager) = setup_provider();
+        let mut watcher =
+            crate::project_watcher::ProjectWatcher::new(manager, "/test/project".to_string());
+
+        let events = toolpath_convo::ConversationWatcher::poll(&mut watcher).unwrap();
+        assert!(matches!(
+            &events[0],
+            WatcherEvent::Progress { kind, data }
+                if kind == "session_started" && data["session_id"] == "session-1"
+        ));
+        assert!(matches!(
+            &events[1],
+            WatcherEvent::Progress { kind, data }
+                if kind == "session_entries" && data["count"] == 7
+        ));
+        // Tool results are merged into the turns that asked for them
+        assert!(events.iter().any(|e| e.is_update()));
+        assert_eq!(toolpath_convo::ConversationWatcher::seen_count(&watcher), 7);
+
+        let events = toolpath_convo::ConversationWatcher::poll(&mut watcher).unwrap();
+        assert!(events.is_empty());
+    }
+
     #[cfg(feature = "watcher")]
     #[test]
     fn test_watcher_emits_rotation_progress() {
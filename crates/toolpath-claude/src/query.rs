@@ -1,3 +1,4 @@
+use crate::paths::PathResolver;
 use crate::types::{ContentPart, Conversation, ConversationEntry, HistoryEntry, MessageRole};
 use chrono::{DateTime, Utc};
 
@@ -127,10 +128,16 @@ impl<'a> HistoryQuery<'a> {
         Self { history }
     }
 
+    /// Entries recorded in `project`, compared as
+    /// [`PathResolver::same_project`] does.
     pub fn by_project(&self, project: &str) -> Vec<&'a HistoryEntry> {
         self.history
             .iter()
-            .filter(|e| e.project.as_deref() == Some(project))
+            .filter(|e| {
+                e.project
+                    .as_deref()
+                    .is_some_and(|p| PathResolver::same_project(p, project))
+            })
             .collect()
     }
 
@@ -432,6 +439,7 @@ mod tests {
 
         let results = query.by_project("/project/a");
         assert_eq!(results.len(), 2);
+        assert_eq!(query.by_project("/project/a/").len(), 2);
     }
 
     #[test]
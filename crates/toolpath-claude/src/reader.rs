@@ -1,8 +1,12 @@
 use crate::error::{ConvoError, Result};
 use crate::types::{Conversation, ConversationEntry, HistoryEntry};
+use serde::Serialize;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Seek, SeekFrom};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// Longest raw excerpt kept on a [`LineDiagnostic`], in characters.
+const EXCERPT_LIMIT: usize = 200;
 
 pub struct ConversationReader;
 
@@ -13,9 +17,6 @@ impl ConversationReader {
             return Err(ConvoError::ConversationNotFound(path.display().to_string()));
         }
 
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
-
         let session_id = path
             .file_stem()
             .and_then(|s| s.to_str())
@@ -23,48 +24,73 @@ impl ConversationReader {
             .to_string();
 
         let mut conversation = Conversation::new(session_id);
+        let mut entries = Self::entries(path, ParseMode::Tolerant)?;
+        for entry in entries.by_ref() {
+            let entry = entry?;
+            if entry.entry_type == "file-history-snapshot" {
+                let session_id = conversation.session_id.clone();
+                conversation.add_file_snapshot(&session_id, &entry);
+            } else if !entry.uuid.is_empty() {
+                // Only add entries with valid UUIDs (skip metadata entries)
+                conversation.add_entry(entry);
+            }
+        }
 
-        for (line_num, line) in reader.lines().enumerate() {
-            let line = line?;
-            if line.trim().is_empty() {
+        for diagnostic in entries.diagnostics() {
+            // Known metadata types we can safely ignore
+            if diagnostic.entry_type.as_deref() == Some("file-history-snapshot") {
                 continue;
             }
-
-            // Try to parse as a conversation entry
-            match serde_json::from_str::<ConversationEntry>(&line) {
-                Ok(entry) => {
-                    // Only add entries with valid UUIDs (skip metadata entries)
-                    if !entry.uuid.is_empty() {
-                        conversation.add_entry(entry);
-                    }
-                }
-                Err(_) => {
-                    // Try to parse as a generic JSON value to check the type
-                    if let Ok(value) = serde_json::from_str::<serde_json::Value>(&line)
-                        && let Some(entry_type) = value.get("type").and_then(|t| t.as_str())
-                    {
-                        // Known metadata types we can safely ignore
-                        if entry_type == "file-history-snapshot" {
-                            // Silently skip file snapshots
-                            continue;
-                        }
-                    }
-
-                    // Only warn about truly unexpected parse failures
-                    if line_num < 5 || std::env::var("CLAUDE_CLI_DEBUG").is_ok() {
-                        eprintln!(
-                            "Warning: Failed to parse line {} in {:?}: entry type not recognized",
-                            line_num + 1,
-                            path.file_name().unwrap_or_default()
-                        );
-                    }
-                }
+            // Only warn about truly unexpected parse failures
+            if diagnostic.line <= 5 || std::env::var("CLAUDE_CLI_DEBUG").is_ok() {
+                eprintln!(
+                    "Warning: Failed to parse line {} in {:?}: entry type not recognized",
+                    diagnostic.line,
+                    path.file_name().unwrap_or_default()
+                );
             }
         }
 
         Ok(conversation)
     }
 
+    /// Stream the entries of a session log without loading it whole.
+    ///
+    /// Every line that parses as a [`ConversationEntry`] is yielded,
+    /// metadata entries included. Lines that don't are skipped with a
+    /// [`LineDiagnostic`] in [`ParseMode::Tolerant`], and end the stream
+    /// with [`ConvoError::MalformedLine`] in [`ParseMode::Strict`].
+    pub fn entries<P: AsRef<Path>>(path: P, mode: ParseMode) -> Result<EntryIter> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Err(ConvoError::ConversationNotFound(path.display().to_string()));
+        }
+        Ok(EntryIter {
+            path: path.to_path_buf(),
+            reader: BufReader::new(File::open(path)?),
+            mode,
+            line: 0,
+            offset: 0,
+            diagnostics: Vec::new(),
+            done: false,
+        })
+    }
+
+    /// Read a whole session log, reporting the lines that don't parse.
+    pub fn check<P: AsRef<Path>>(path: P) -> Result<LogCheck> {
+        let mut entries = Self::entries(path, ParseMode::Tolerant)?;
+        let mut count = 0;
+        for entry in entries.by_ref() {
+            entry?;
+            count += 1;
+        }
+        Ok(LogCheck {
+            entries: count,
+            lines: entries.line,
+            diagnostics: std::mem::take(&mut entries.diagnostics),
+        })
+    }
+
     pub fn read_conversation_metadata<P: AsRef<Path>>(
         path: P,
     ) -> Result<crate::types::ConversationMetadata> {
@@ -247,6 +273,149 @@ impl ConversationReader {
     }
 }
 
+/// How [`ConversationReader::entries`] treats lines that don't parse.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Skip them, recording a [`LineDiagnostic`] for each.
+    #[default]
+    Tolerant,
+    /// Fail at the first one.
+    Strict,
+}
+
+/// A session log line that didn't parse as a conversation entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LineDiagnostic {
+    /// Line number, from 1.
+    pub line: usize,
+    /// Byte offset of the start of the line.
+    pub offset: u64,
+    /// The parser's error.
+    pub error: String,
+    /// The start of the raw line.
+    pub excerpt: String,
+    /// The line's `type`, if it is a JSON object with one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry_type: Option<String>,
+    /// The last line of the file, without a trailing newline: most likely
+    /// still being written.
+    pub partial: bool,
+}
+
+impl std::fmt::Display for LineDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {} (byte {}): {}",
+            self.line, self.offset, self.error
+        )?;
+        if self.partial {
+            write!(f, " (partially written)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Streaming iterator over a session log's entries; see
+/// [`ConversationReader::entries`].
+pub struct EntryIter {
+    path: PathBuf,
+    reader: BufReader<File>,
+    mode: ParseMode,
+    line: usize,
+    offset: u64,
+    diagnostics: Vec<LineDiagnostic>,
+    done: bool,
+}
+
+impl EntryIter {
+    /// Diagnostics for the lines skipped so far.
+    pub fn diagnostics(&self) -> &[LineDiagnostic] {
+        &self.diagnostics
+    }
+
+    /// Byte offset read up to.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    fn diagnose(&self, raw: &[u8], offset: u64, error: String) -> LineDiagnostic {
+        let text = String::from_utf8_lossy(raw);
+        let text = text.trim_end_matches(['\n', '\r']);
+        LineDiagnostic {
+            line: self.line,
+            offset,
+            error,
+            excerpt: crate::derive::truncate(text, EXCERPT_LIMIT),
+            entry_type: serde_json::from_str::<serde_json::Value>(text)
+                .ok()
+                .and_then(|v| v.get("type")?.as_str().map(str::to_string)),
+            partial: !raw.ends_with(b"\n"),
+        }
+    }
+}
+
+impl Iterator for EntryIter {
+    type Item = Result<ConversationEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut raw = Vec::new();
+        while !self.done {
+            raw.clear();
+            let read = match self.reader.read_until(b'\n', &mut raw) {
+                Ok(read) => read,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e.into()));
+                }
+            };
+            if read == 0 {
+                self.done = true;
+                break;
+            }
+            let start = self.offset;
+            self.offset += read as u64;
+            self.line += 1;
+            if raw.trim_ascii().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_slice::<ConversationEntry>(&raw) {
+                Ok(entry) => return Some(Ok(entry)),
+                Err(e) => {
+                    let diagnostic = self.diagnose(&raw, start, e.to_string());
+                    if self.mode == ParseMode::Strict {
+                        self.done = true;
+                        return Some(Err(ConvoError::MalformedLine {
+                            path: self.path.clone(),
+                            diagnostic: Box::new(diagnostic),
+                        }));
+                    }
+                    self.diagnostics.push(diagnostic);
+                }
+            }
+        }
+        None
+    }
+}
+
+/// What [`ConversationReader::check`] found in a session log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LogCheck {
+    /// Lines that parsed as entries.
+    pub entries: usize,
+    /// Lines read, blank ones included.
+    pub lines: usize,
+    pub diagnostics: Vec<LineDiagnostic>,
+}
+
+impl LogCheck {
+    /// Whether every non-blank line parsed.
+    pub fn is_clean(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -451,6 +620,28 @@ mod tests {
         assert_eq!(convo.entries.len(), 1);
     }
 
+    #[test]
+    fn test_read_conversation_keeps_file_snapshots() {
+        let mut temp = NamedTempFile::new().unwrap();
+        writeln!(
+            temp,
+            r#"{{"type":"file-history-snapshot","messageId":"u1","snapshot":{{"messageId":"u1","trackedFileBackups":{{"/p/a.rs":{{"backupFileName":"abc@v1","version":1,"backupTime":"2024-01-01T00:00:00Z"}}}},"timestamp":"2024-01-01T00:00:00Z"}},"isSnapshotUpdate":false}}"#
+        )
+        .unwrap();
+        writeln!(
+            temp,
+            r#"{{"type":"user","uuid":"u1","timestamp":"2024-01-01T00:00:00Z","message":{{"role":"user","content":"Hi"}}}}"#
+        ).unwrap();
+        temp.flush().unwrap();
+
+        let convo = ConversationReader::read_conversation(temp.path()).unwrap();
+        assert_eq!(convo.entries.len(), 1);
+        assert_eq!(convo.file_snapshots.len(), 1);
+        let (snapshot, backup) = convo.file_backup("u1", "/p/a.rs").unwrap();
+        assert_eq!(snapshot.session_id, convo.session_id);
+        assert_eq!(backup.backup_file_name.as_deref(), Some("abc@v1"));
+    }
+
     #[test]
     fn test_read_conversation_metadata_empty_file() {
         let mut temp = NamedTempFile::new().unwrap();
@@ -531,4 +722,69 @@ mod tests {
         let convo = ConversationReader::read_conversation(temp.path()).unwrap();
         assert_eq!(convo.entries.len(), 1);
     }
+
+    const GOOD: &str = r#"{"type":"user","uuid":"u1","timestamp":"2024-01-01T00:00:00Z","message":{"role":"user","content":"Hi"}}"#;
+    const DRIFTED: &str =
+        r#"{"type":"assistant","uuid":"u2","message":{"role":"robot","content":"Hi"}}"#;
+
+    fn malformed_log() -> NamedTempFile {
+        let mut temp = NamedTempFile::new().unwrap();
+        write!(
+            temp,
+            "{}\n\n{}\nnot json\n{}\n{{\"type\":\"user\",\"uu",
+            GOOD, DRIFTED, GOOD
+        )
+        .unwrap();
+        temp.flush().unwrap();
+        temp
+    }
+
+    #[test]
+    fn test_entries_tolerant_collects_diagnostics() {
+        let temp = malformed_log();
+        let mut entries = ConversationReader::entries(temp.path(), ParseMode::Tolerant).unwrap();
+        let parsed: Vec<ConversationEntry> = entries.by_ref().map(|e| e.unwrap()).collect();
+        assert_eq!(parsed.len(), 2);
+
+        let diagnostics = entries.diagnostics();
+        assert_eq!(
+            diagnostics.iter().map(|d| d.line).collect::<Vec<_>>(),
+            [3, 4, 6]
+        );
+        assert_eq!(diagnostics[0].offset, (GOOD.len() + 2) as u64);
+        assert_eq!(diagnostics[0].entry_type.as_deref(), Some("assistant"));
+        assert!(diagnostics[0].error.contains("robot"));
+        assert_eq!(diagnostics[1].excerpt, "not json");
+        assert_eq!(diagnostics[1].entry_type, None);
+        assert!(!diagnostics[1].partial);
+        assert!(diagnostics[2].partial);
+    }
+
+    #[test]
+    fn test_entries_strict_fails_fast() {
+        let temp = malformed_log();
+        let results: Vec<Result<ConversationEntry>> =
+            ConversationReader::entries(temp.path(), ParseMode::Strict)
+                .unwrap()
+                .collect();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        match &results[1] {
+            Err(ConvoError::MalformedLine { diagnostic, .. }) => assert_eq!(diagnostic.line, 3),
+            other => panic!("expected a malformed line, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let temp = malformed_log();
+        let check = ConversationReader::check(temp.path()).unwrap();
+        assert_eq!(check.entries, 2);
+        assert_eq!(check.lines, 6);
+        assert!(!check.is_clean());
+
+        // read_conversation still skips what it can't parse
+        let convo = ConversationReader::read_conversation(temp.path()).unwrap();
+        assert_eq!(convo.entries.len(), 2);
+    }
 }
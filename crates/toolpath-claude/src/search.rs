@@ -0,0 +1,943 @@
+//! Full-text search over every session log.
+//!
+//! [`SearchIndex`] is an inverted index from words to turns, kept on disk as
+//! JSON. [`SearchIndex::update`] brings it up to date with the session logs:
+//! files that haven't changed are skipped, files that grew are read from
+//! where the last update stopped, and files that were rewritten are indexed
+//! again from the start. [`SearchIndex::search`] runs a [`SearchQuery`]
+//! against it without reading any logs.
+//!
+//! ```rust,no_run
+//! use toolpath_claude::ClaudeConvo;
+//! use toolpath_claude::search::{SearchIndex, SearchQuery};
+//!
+//! let manager = ClaudeConvo::new();
+//! let location = SearchIndex::default_location(manager.resolver())?;
+//! let mut index = SearchIndex::load(&location)?;
+//! index.update(manager.resolver())?;
+//! index.save(&location)?;
+//!
+//! let query = SearchQuery {
+//!     tool: Some("Edit".into()),
+//!     ..SearchQuery::new("\"rate limiter\"")
+//! };
+//! for hit in index.search(&query)? {
+//!     println!("{} {} {}", hit.session_id, hit.step_id, hit.snippet);
+//! }
+//! # Ok::<(), toolpath_claude::ConvoError>(())
+//! ```
+
+use crate::digest::sha256;
+use crate::error::{ConvoError, Result};
+use crate::paths::{PathResolver, session_stems};
+use crate::types::{ConversationEntry, MessageRole};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Format version of the index file. An index with another version is
+/// discarded and rebuilt.
+const VERSION: u32 = 1;
+
+/// Bytes before a file's offset covered by [`FileState::tail`].
+const TAIL: u64 = 1024;
+
+/// Characters of context on each side of a match in [`SearchHit::snippet`].
+const SNIPPET_CONTEXT: usize = 60;
+
+/// Tool input fields that name a file.
+const FILE_FIELDS: &[&str] = &["file_path", "notebook_path", "path"];
+
+/// An inverted index over the turns of every Claude session log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchIndex {
+    version: u32,
+    next_file: u32,
+    files: BTreeMap<String, FileState>,
+    docs: Vec<SearchDoc>,
+    postings: BTreeMap<String, Vec<u32>>,
+}
+
+/// How far a session log has been indexed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FileState {
+    id: u32,
+    project: String,
+    session_id: String,
+    modified_ms: i64,
+    len: u64,
+    /// Byte offset just past the last complete line indexed.
+    offset: u64,
+    /// Digest of the bytes just before `offset`, to notice rewrites.
+    tail: String,
+}
+
+/// One indexed turn.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SearchDoc {
+    file: u32,
+    project: String,
+    session_id: String,
+    uuid: String,
+    timestamp: String,
+    role: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    files: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    text: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    inputs: String,
+}
+
+/// What a call to [`SearchIndex::update`] did.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct IndexUpdate {
+    /// Session logs found.
+    pub files: usize,
+    /// Logs read, in part or in full.
+    pub updated: usize,
+    /// Logs indexed again from the start because they were rewritten.
+    pub rebuilt: usize,
+    /// Logs that no longer exist.
+    pub removed: usize,
+    /// Turns added to the index.
+    pub turns: usize,
+}
+
+/// A search over a [`SearchIndex`].
+///
+/// Every word of `text` must appear in a turn; `"quoted phrases"` must
+/// appear with their words in order. The other fields narrow the turns
+/// searched; `None` matches anything.
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    /// Words and quoted phrases, matched case-insensitively.
+    pub text: String,
+    /// A regular expression the turn's text, tool inputs or file paths must
+    /// match.
+    pub regex: Option<String>,
+    /// `user`, `assistant` or `system` (context compaction summaries).
+    pub role: Option<String>,
+    /// Name of a tool the turn used, compared case-insensitively.
+    pub tool: Option<String>,
+    /// Part of a path the turn's tools read or wrote.
+    pub file: Option<String>,
+    /// Part of the model name, e.g. `opus`.
+    pub model: Option<String>,
+    /// Part of the project path.
+    pub project: Option<String>,
+    /// Only turns at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Only turns before this time.
+    pub until: Option<DateTime<Utc>>,
+    /// At most this many hits, newest first.
+    pub limit: Option<usize>,
+}
+
+impl SearchQuery {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// A turn matching a [`SearchQuery`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchHit {
+    pub project: String,
+    pub session_id: String,
+    /// UUID of the turn's log entry.
+    pub uuid: String,
+    /// ID of the step derived from the turn.
+    pub step_id: String,
+    pub timestamp: String,
+    pub role: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+    /// The text around the first match.
+    pub snippet: String,
+}
+
+impl Default for SearchIndex {
+    fn default() -> Self {
+        Self {
+            version: VERSION,
+            next_file: 0,
+            files: BTreeMap::new(),
+            docs: Vec::new(),
+            postings: BTreeMap::new(),
+        }
+    }
+}
+
+impl SearchIndex {
+    /// Where the index is kept by default: `~/.cache/toolpath/claude-search.json`.
+    pub fn default_location(resolver: &PathResolver) -> Result<PathBuf> {
+        Ok(resolver
+            .home_dir()?
+            .join(".cache")
+            .join("toolpath")
+            .join("claude-search.json"))
+    }
+
+    /// Read an index from `path`. Empty — to be rebuilt by the next
+    /// [`update`](Self::update) — if the file doesn't exist, can't be
+    /// parsed, or was written by an incompatible version.
+    pub fn load(path: &Path) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Header {
+            version: u32,
+        }
+        let bytes = match std::fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.into()),
+        };
+        match serde_json::from_slice::<Header>(&bytes) {
+            Ok(header) if header.version == VERSION => {
+                Ok(serde_json::from_slice(&bytes).unwrap_or_default())
+            }
+            _ => Ok(Self::default()),
+        }
+    }
+
+    /// Write the index to `path`, replacing it atomically.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        crate::io::write_atomic(path, &serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+
+    /// Number of turns indexed.
+    pub fn len(&self) -> usize {
+        self.docs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.docs.is_empty()
+    }
+
+    /// Bring the index up to date with the session logs of every project
+    /// under `resolver`.
+    pub fn update(&mut self, resolver: &PathResolver) -> Result<IndexUpdate> {
+        let mut stats = IndexUpdate::default();
+        let mut seen = HashSet::new();
+        let mut dropped = HashSet::new();
+
+        for project in resolver.project_dirs()? {
+            let dir = &project.dir;
+            let mut sessions = session_stems(dir)?;
+            sessions.sort();
+            for session_id in sessions {
+                let path = dir.join(format!("{}.jsonl", session_id));
+                let key = path.to_string_lossy().into_owned();
+                let metadata = match std::fs::metadata(&path) {
+                    Ok(metadata) => metadata,
+                    // Deleted since the directory was listed.
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                    Err(e) => return Err(e.into()),
+                };
+                stats.files += 1;
+                seen.insert(key.clone());
+
+                let len = metadata.len();
+                let modified_ms = metadata
+                    .modified()
+                    .ok()
+                    .map(|t| DateTime::<Utc>::from(t).timestamp_millis())
+                    .unwrap_or_default();
+
+                let resume = match self.files.get(&key) {
+                    Some(state) if state.len == len && state.modified_ms == modified_ms => {
+                        continue;
+                    }
+                    Some(state)
+                        if state.offset <= len && tail(&path, state.offset)? == state.tail =>
+                    {
+                        Some(state.offset)
+                    }
+                    Some(state) => {
+                        dropped.insert(state.id);
+                        stats.rebuilt += 1;
+                        None
+                    }
+                    None => None,
+                };
+
+                let state = match resume {
+                    Some(_) => self.files.remove(&key).expect("state checked above"),
+                    None => {
+                        let id = self.next_file;
+                        self.next_file += 1;
+                        FileState {
+                            id,
+                            project: project.path.clone(),
+                            session_id: session_id.clone(),
+                            modified_ms: 0,
+                            len: 0,
+                            offset: 0,
+                            tail: String::new(),
+                        }
+                    }
+                };
+                let state = self.index_file(&path, state, &mut stats)?;
+                self.files.insert(
+                    key,
+                    FileState {
+                        modified_ms,
+                        len,
+                        ..state
+                    },
+                );
+                stats.updated += 1;
+            }
+        }
+
+        self.files.retain(|key, state| {
+            if seen.contains(key) {
+                true
+            } else {
+                dropped.insert(state.id);
+                stats.removed += 1;
+                false
+            }
+        });
+        if !dropped.is_empty() {
+            self.docs.retain(|doc| !dropped.contains(&doc.file));
+            self.reindex();
+        }
+        Ok(stats)
+    }
+
+    /// Index the complete lines of `path` after `state.offset`.
+    fn index_file(
+        &mut self,
+        path: &Path,
+        mut state: FileState,
+        stats: &mut IndexUpdate,
+    ) -> Result<FileState> {
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(state.offset))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        let Some(end) = bytes.iter().rposition(|&b| b == b'\n') else {
+            return Ok(state);
+        };
+
+        for line in bytes[..end].split(|&b| b == b'\n') {
+            let Ok(entry) = serde_json::from_slice::<ConversationEntry>(line) else {
+                continue;
+            };
+            if let Some(doc) = to_doc(&entry, &state) {
+                let id = self.docs.len() as u32;
+                for term in doc_terms(&doc) {
+                    self.postings.entry(term).or_default().push(id);
+                }
+                self.docs.push(doc);
+                stats.turns += 1;
+            }
+        }
+
+        state.offset += end as u64 + 1;
+        state.tail = tail(path, state.offset)?;
+        Ok(state)
+    }
+
+    /// Rebuild the postings after turns were removed.
+    fn reindex(&mut self) {
+        self.postings.clear();
+        for (id, doc) in self.docs.iter().enumerate() {
+            for term in doc_terms(doc) {
+                self.postings.entry(term).or_default().push(id as u32);
+            }
+        }
+    }
+
+    /// The turns matching `query`, newest first.
+    pub fn search(&self, query: &SearchQuery) -> Result<Vec<SearchHit>> {
+        let regex = query
+            .regex
+            .as_deref()
+            .map(regex::Regex::new)
+            .transpose()
+            .map_err(|e| ConvoError::InvalidQuery(e.to_string()))?;
+        // Phrase tokens are among `words`, so phrases narrow the candidates
+        // through the postings too; only their order is checked below.
+        let (words, phrases) = parse_text(&query.text);
+
+        let candidates: Vec<u32> = match self.lookup(&words) {
+            Some(ids) => ids,
+            None => (0..self.docs.len() as u32).collect(),
+        };
+
+        let mut hits = Vec::new();
+        for id in candidates {
+            let doc = &self.docs[id as usize];
+            if !filters_match(doc, query) {
+                continue;
+            }
+            if !phrases.is_empty() {
+                let haystack = format!(" {} ", tokenize(&searchable(doc)).join(" "));
+                if !phrases
+                    .iter()
+                    .all(|p| haystack.contains(&format!(" {} ", p.join(" "))))
+                {
+                    continue;
+                }
+            }
+            let found = match &regex {
+                Some(re) => {
+                    let Some(found) = [&doc.text, &doc.inputs]
+                        .into_iter()
+                        .chain(&doc.files)
+                        .find_map(|field| re.find(field).map(|m| (field, m.start(), m.end())))
+                    else {
+                        continue;
+                    };
+                    Some(found)
+                }
+                None => None,
+            };
+            let snippet = match found {
+                Some((field, start, end)) => snippet(field, start, end),
+                None => first_match(doc, &words, &phrases),
+            };
+            hits.push(SearchHit {
+                project: doc.project.clone(),
+                session_id: doc.session_id.clone(),
+                uuid: doc.uuid.clone(),
+                step_id: crate::derive::step_id(&doc.uuid),
+                timestamp: doc.timestamp.clone(),
+                role: doc.role.clone(),
+                model: doc.model.clone(),
+                tools: doc.tools.clone(),
+                files: doc.files.clone(),
+                snippet,
+            });
+        }
+
+        hits.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        if let Some(limit) = query.limit {
+            hits.truncate(limit);
+        }
+        Ok(hits)
+    }
+
+    /// Turns containing every word, in index order; `None` if there are no
+    /// words to look up.
+    fn lookup(&self, words: &[String]) -> Option<Vec<u32>> {
+        let mut lists: Vec<&[u32]> = Vec::new();
+        for word in words {
+            match self.postings.get(word) {
+                Some(ids) => lists.push(ids),
+                None => return Some(Vec::new()),
+            }
+        }
+        lists.sort_by_key(|ids| ids.len());
+        let (first, rest) = lists.split_first()?;
+        Some(
+            first
+                .iter()
+                .copied()
+                .filter(|id| rest.iter().all(|ids| ids.binary_search(id).is_ok()))
+                .collect(),
+        )
+    }
+}
+
+/// The searchable parts of an entry, or `None` if it has no text and used
+/// no tools.
+fn to_doc(entry: &ConversationEntry, state: &FileState) -> Option<SearchDoc> {
+    let message = entry.message.as_ref()?;
+    let text = message.text();
+    let uses = message.tool_uses();
+    if text.trim().is_empty() && uses.is_empty() {
+        return None;
+    }
+
+    let role = if entry.is_compact_summary() {
+        "system"
+    } else {
+        match message.role {
+            MessageRole::User => "user",
+            MessageRole::Assistant => "assistant",
+            MessageRole::System => "system",
+        }
+    };
+    let mut tools = Vec::new();
+    let mut files = Vec::new();
+    let mut inputs = Vec::new();
+    for tool in &uses {
+        if !tools.iter().any(|t| t == tool.name) {
+            tools.push(tool.name.to_string());
+        }
+        for field in FILE_FIELDS {
+            if let Some(file) = tool.input.get(field).and_then(Value::as_str)
+                && !files.iter().any(|f| f == file)
+            {
+                files.push(file.to_string());
+            }
+        }
+        collect_strings(tool.input, &mut inputs);
+    }
+
+    Some(SearchDoc {
+        file: state.id,
+        project: state.project.clone(),
+        session_id: entry
+            .session_id
+            .clone()
+            .unwrap_or_else(|| state.session_id.clone()),
+        uuid: entry.uuid.clone(),
+        timestamp: entry.timestamp.clone(),
+        role: role.to_string(),
+        model: message.model.clone(),
+        tools,
+        files,
+        text,
+        inputs: inputs.join("\n"),
+    })
+}
+
+fn collect_strings(value: &Value, out: &mut Vec<String>) {
+    match value {
+        Value::String(s) => out.push(s.clone()),
+        Value::Array(items) => items.iter().for_each(|v| collect_strings(v, out)),
+        Value::Object(map) => map.values().for_each(|v| collect_strings(v, out)),
+        _ => {}
+    }
+}
+
+fn searchable(doc: &SearchDoc) -> String {
+    let mut s = format!("{}\n{}", doc.text, doc.inputs);
+    for file in &doc.files {
+        s.push('\n');
+        s.push_str(file);
+    }
+    s
+}
+
+/// The distinct words of a turn.
+fn doc_terms(doc: &SearchDoc) -> BTreeSet<String> {
+    tokenize(&searchable(doc)).into_iter().collect()
+}
+
+/// Lowercased runs of letters and digits.
+fn tokenize(s: &str) -> Vec<String> {
+    s.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Split query text into all its words, including those inside quotes, and
+/// its quoted phrases of more than one word.
+fn parse_text(text: &str) -> (Vec<String>, Vec<Vec<String>>) {
+    let mut words = Vec::new();
+    let mut phrases = Vec::new();
+    for (i, part) in text.split('"').enumerate() {
+        let tokens = tokenize(part);
+        if i % 2 == 1 && tokens.len() > 1 {
+            phrases.push(tokens.clone());
+        }
+        words.extend(tokens);
+    }
+    words.sort();
+    words.dedup();
+    (words, phrases)
+}
+
+fn filters_match(doc: &SearchDoc, query: &SearchQuery) -> bool {
+    let contains =
+        |haystack: &str, needle: &str| haystack.to_lowercase().contains(&needle.to_lowercase());
+    if let Some(role) = &query.role
+        && !doc.role.eq_ignore_ascii_case(role)
+    {
+        return false;
+    }
+    if let Some(tool) = &query.tool
+        && !doc.tools.iter().any(|t| t.eq_ignore_ascii_case(tool))
+    {
+        return false;
+    }
+    if let Some(file) = &query.file
+        && !doc.files.iter().any(|f| f.contains(file.as_str()))
+    {
+        return false;
+    }
+    if let Some(model) = &query.model
+        && !doc.model.as_deref().is_some_and(|m| contains(m, model))
+    {
+        return false;
+    }
+    if let Some(project) = &query.project
+        && !contains(&doc.project, project)
+    {
+        return false;
+    }
+    if query.since.is_some() || query.until.is_some() {
+        let Ok(at) = DateTime::parse_from_rfc3339(&doc.timestamp) else {
+            return false;
+        };
+        let at = at.with_timezone(&Utc);
+        if query.since.is_some_and(|since| at < since)
+            || query.until.is_some_and(|until| at >= until)
+        {
+            return false;
+        }
+    }
+    true
+}
+
+/// A snippet around the first phrase or word found in the turn's text,
+/// else its tool inputs, else the start of its text.
+fn first_match(doc: &SearchDoc, words: &[String], phrases: &[Vec<String>]) -> String {
+    let needles: Vec<String> = phrases
+        .iter()
+        .map(|p| p.join(" "))
+        .chain(words.iter().cloned())
+        .collect();
+    for field in [&doc.text, &doc.inputs] {
+        let lower = field.to_ascii_lowercase();
+        if let Some((start, len)) = needles
+            .iter()
+            .find_map(|n| lower.find(n.as_str()).map(|i| (i, n.len())))
+        {
+            return snippet(field, start, start + len);
+        }
+    }
+    let field = if doc.text.is_empty() {
+        &doc.inputs
+    } else {
+        &doc.text
+    };
+    snippet(field, 0, 0)
+}
+
+/// `s[start..end]` with up to [`SNIPPET_CONTEXT`] characters either side,
+/// on one line.
+fn snippet(s: &str, start: usize, end: usize) -> String {
+    let before: String = s[..start]
+        .chars()
+        .rev()
+        .take(SNIPPET_CONTEXT)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    let after: String = s[end..].chars().take(SNIPPET_CONTEXT).collect();
+    let mut out = String::new();
+    if before.len() < start {
+        out.push('…');
+    }
+    out.push_str(&before);
+    out.push_str(&s[start..end]);
+    out.push_str(&after);
+    if end + after.len() < s.len() {
+        out.push('…');
+    }
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Digest of up to [`TAIL`] bytes before `offset`.
+fn tail(path: &Path, offset: u64) -> Result<String> {
+    let mut file = File::open(path)?;
+    let start = offset.saturating_sub(TAIL);
+    file.seek(SeekFrom::Start(start))?;
+    let mut window = Vec::new();
+    file.take(offset - start).read_to_end(&mut window)?;
+    Ok(sha256(&window))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn user(uuid: &str, ts: &str, text: &str) -> String {
+        format!(
+            r#"{{"type":"user","uuid":"{uuid}","timestamp":"{ts}","sessionId":"s1","cwd":"/work/my-app","message":{{"role":"user","content":"{text}"}}}}"#
+        )
+    }
+
+    fn edit(uuid: &str, ts: &str, file: &str, new: &str) -> String {
+        format!(
+            r#"{{"type":"assistant","uuid":"{uuid}","timestamp":"{ts}","sessionId":"s1","message":{{"role":"assistant","model":"claude-opus-4","content":[{{"type":"text","text":"Updating the limiter."}},{{"type":"tool_use","id":"t-{uuid}","name":"Edit","input":{{"file_path":"{file}","old_string":"a","new_string":"{new}"}}}}]}}}}"#
+        )
+    }
+
+    fn setup(lines: &[String]) -> (TempDir, PathResolver, PathBuf) {
+        let temp = TempDir::new().unwrap();
+        let claude = temp.path().join(".claude");
+        let project = claude.join("projects/-work-my-app");
+        fs::create_dir_all(&project).unwrap();
+        let log = project.join("s1.jsonl");
+        fs::write(&log, lines.join("\n") + "\n").unwrap();
+        let resolver = PathResolver::new()
+            .with_home(temp.path())
+            .with_claude_dir(claude);
+        (temp, resolver, log)
+    }
+
+    fn sample() -> Vec<String> {
+        vec![
+            user(
+                "u1-aaaaaaaa",
+                "2026-08-30T10:00:00Z",
+                "Add a rate limiter to the API",
+            ),
+            edit(
+                "a1-bbbbbbbb",
+                "2026-09-02T10:00:00Z",
+                "src/limiter.rs",
+                "fn rate_limiter()",
+            ),
+            user(
+                "u2-cccccccc",
+                "2026-09-03T10:00:00Z",
+                "limiter looks rate ok",
+            ),
+        ]
+    }
+
+    fn uuids(hits: &[SearchHit]) -> Vec<&str> {
+        hits.iter().map(|h| h.uuid.as_str()).collect()
+    }
+
+    #[test]
+    fn test_words_and_phrases() {
+        let (_temp, resolver, _) = setup(&sample());
+        let mut index = SearchIndex::default();
+        let stats = index.update(&resolver).unwrap();
+        assert_eq!(stats.files, 1);
+        assert_eq!(stats.turns, 3);
+
+        let hits = index.search(&SearchQuery::new("rate limiter")).unwrap();
+        assert_eq!(uuids(&hits), ["u2-cccccccc", "a1-bbbbbbbb", "u1-aaaaaaaa"]);
+
+        let hits = index.search(&SearchQuery::new("\"rate limiter\"")).unwrap();
+        assert_eq!(uuids(&hits), ["a1-bbbbbbbb", "u1-aaaaaaaa"]);
+        let hit = &hits[1];
+        assert_eq!(hit.project, "/work/my-app");
+        assert_eq!(hit.session_id, "s1");
+        assert_eq!(hit.step_id, "step-u1-aaaaa");
+        assert_eq!(hit.snippet, "Add a rate limiter to the API");
+
+        assert!(
+            index
+                .search(&SearchQuery::new("missing"))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_filters() {
+        let (_temp, resolver, _) = setup(&sample());
+        let mut index = SearchIndex::default();
+        index.update(&resolver).unwrap();
+        let search = |query: SearchQuery| uuids(&index.search(&query).unwrap()).join(",");
+
+        let since = DateTime::parse_from_rfc3339("2026-09-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            search(SearchQuery {
+                tool: Some("edit".into()),
+                since: Some(since),
+                ..SearchQuery::new("limiter")
+            }),
+            "a1-bbbbbbbb"
+        );
+        assert_eq!(
+            search(SearchQuery {
+                until: Some(since),
+                ..SearchQuery::new("limiter")
+            }),
+            "u1-aaaaaaaa"
+        );
+        assert_eq!(
+            search(SearchQuery {
+                role: Some("user".into()),
+                limit: Some(1),
+                ..SearchQuery::new("limiter")
+            }),
+            "u2-cccccccc"
+        );
+        assert_eq!(
+            search(SearchQuery {
+                file: Some("limiter.rs".into()),
+                model: Some("opus".into()),
+                ..Default::default()
+            }),
+            "a1-bbbbbbbb"
+        );
+        assert_eq!(
+            search(SearchQuery {
+                project: Some("elsewhere".into()),
+                ..Default::default()
+            }),
+            ""
+        );
+    }
+
+    #[test]
+    fn test_regex() {
+        let (_temp, resolver, _) = setup(&sample());
+        let mut index = SearchIndex::default();
+        index.update(&resolver).unwrap();
+
+        let hits = index
+            .search(&SearchQuery {
+                regex: Some(r"fn rate_\w+".into()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(uuids(&hits), ["a1-bbbbbbbb"]);
+        assert_eq!(hits[0].files, ["src/limiter.rs"]);
+        assert!(hits[0].snippet.contains("fn rate_limiter()"));
+
+        let err = index
+            .search(&SearchQuery {
+                regex: Some("(".into()),
+                ..Default::default()
+            })
+            .unwrap_err();
+        assert!(matches!(err, ConvoError::InvalidQuery(_)));
+    }
+
+    #[test]
+    fn test_incremental_update() {
+        let mut lines = sample();
+        let (temp, resolver, log) = setup(&lines[..2]);
+        let location = SearchIndex::default_location(&resolver).unwrap();
+        assert!(location.starts_with(temp.path()));
+
+        let mut index = SearchIndex::load(&location).unwrap();
+        assert!(index.is_empty());
+        index.update(&resolver).unwrap();
+        index.save(&location).unwrap();
+
+        // Unchanged: nothing read.
+        let mut index = SearchIndex::load(&location).unwrap();
+        assert_eq!(index.len(), 2);
+        let stats = index.update(&resolver).unwrap();
+        assert_eq!((stats.updated, stats.turns), (0, 0));
+
+        // Appended, with a partial line still being written.
+        let partial = &lines[2][..20];
+        fs::write(&log, format!("{}\n{}\n{}", lines[0], lines[1], partial)).unwrap();
+        let stats = index.update(&resolver).unwrap();
+        assert_eq!((stats.updated, stats.turns, stats.rebuilt), (1, 0, 0));
+        fs::write(&log, lines.join("\n") + "\n").unwrap();
+        let stats = index.update(&resolver).unwrap();
+        assert_eq!((stats.updated, stats.turns, stats.rebuilt), (1, 1, 0));
+        assert_eq!(index.len(), 3);
+
+        // Rewritten: indexed again from the start.
+        lines.remove(0);
+        fs::write(&log, lines.join("\n") + "\n").unwrap();
+        let stats = index.update(&resolver).unwrap();
+        assert_eq!((stats.rebuilt, stats.turns), (1, 2));
+        assert_eq!(index.len(), 2);
+        let hits = index.search(&SearchQuery::new("api")).unwrap();
+        assert!(hits.is_empty());
+        assert_eq!(index.search(&SearchQuery::new("ok")).unwrap().len(), 1);
+
+        // Removed.
+        fs::remove_file(&log).unwrap();
+        let stats = index.update(&resolver).unwrap();
+        assert_eq!(stats.removed, 1);
+        assert!(index.is_empty());
+        assert!(
+            index
+                .search(&SearchQuery::new("limiter"))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_load_rebuilds_unreadable_index() {
+        let (_temp, resolver, _log) = setup(&sample()[..2]);
+        let location = SearchIndex::default_location(&resolver).unwrap();
+        let mut index = SearchIndex::load(&location).unwrap();
+        index.update(&resolver).unwrap();
+        index.save(&location).unwrap();
+        let saved = fs::read(&location).unwrap();
+
+        // Cut short, as by an interrupted write from an older version.
+        fs::write(&location, &saved[..saved.len() / 2]).unwrap();
+        let mut index = SearchIndex::load(&location).unwrap();
+        assert!(index.is_empty());
+        assert_eq!(index.update(&resolver).unwrap().turns, 2);
+
+        // The right header over a body that doesn't parse.
+        fs::write(&location, format!(r#"{{"version":{},"docs":7}}"#, VERSION)).unwrap();
+        assert!(SearchIndex::load(&location).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_text() {
+        let (words, phrases) = parse_text(r#"Edit "rate  limiter" api_key"#);
+        assert_eq!(words, ["api", "edit", "key", "limiter", "rate"]);
+        assert_eq!(phrases, [vec!["rate".to_string(), "limiter".to_string()]]);
+
+        let (words, phrases) = parse_text(r#""rate limiter""#);
+        assert_eq!(words, ["limiter", "rate"]);
+        assert_eq!(phrases.len(), 1);
+    }
+
+    #[test]
+    fn test_phrase_only_query_uses_postings() {
+        let (_temp, resolver, _) = setup(&sample());
+        let mut index = SearchIndex::default();
+        index.update(&resolver).unwrap();
+
+        let (words, _) = parse_text(r#""the missing api""#);
+        assert_eq!(index.lookup(&words), Some(Vec::new()));
+        let (words, _) = parse_text(r#""to the api""#);
+        assert_eq!(index.lookup(&words).unwrap().len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_update_skips_vanished_log() {
+        let (_temp, resolver, log) = setup(&sample());
+        std::os::unix::fs::symlink(
+            log.with_file_name("gone.jsonl"),
+            log.with_file_name("s2.jsonl"),
+        )
+        .unwrap();
+
+        let mut index = SearchIndex::default();
+        let stats = index.update(&resolver).unwrap();
+        assert_eq!((stats.files, stats.turns), (1, 3));
+    }
+
+    #[test]
+    fn test_snippet() {
+        let long = format!("{}needle{}", "x ".repeat(50), " y".repeat(50));
+        let s = snippet(&long, 100, 106);
+        assert!(s.starts_with('…') && s.ends_with('…'));
+        assert!(s.contains("needle"));
+        assert_eq!(snippet("short needle", 6, 12), "short needle");
+    }
+}
@@ -0,0 +1,376 @@
+//! `Bash` tool uses as `shell.exec` structural changes.
+//!
+//! Commands are parsed only as far as the heuristics need: splitting a
+//! command line into segments on `&&`, `||`, `;`, `|` and newlines, and
+//! looking at each segment's program and subcommand. Anything more exotic
+//! (subshells, quoting across separators) simply isn't recognised.
+
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use toolpath::v1::StructuralChange;
+
+use crate::capture::{self, BlobDir, Capture};
+
+/// The `shell.exec` change for a `Bash` tool use: the command and its
+/// description from the input, and the exit status and output from the
+/// result, with stdout and stderr recorded as `output` says.
+pub(crate) fn exec_change(
+    input: &Value,
+    result: Option<&Value>,
+    content: Option<&str>,
+    is_error: bool,
+    cwd: Option<&str>,
+    output: Capture,
+    blobs: Option<&BlobDir>,
+) -> StructuralChange {
+    let mut extra = HashMap::new();
+    if let Some(command) = input.get("command").and_then(Value::as_str) {
+        extra.insert("command".to_string(), json!(command));
+    }
+    if let Some(description) = input.get("description").and_then(Value::as_str) {
+        extra.insert("description".to_string(), json!(description));
+    }
+    if let Some(cwd) = cwd {
+        extra.insert("cwd".to_string(), json!(cwd));
+    }
+
+    let (stdout, stderr) = match result.filter(|r| r.is_object()) {
+        Some(r) => (
+            r.get("stdout").and_then(Value::as_str),
+            r.get("stderr").and_then(Value::as_str),
+        ),
+        // No structured result: the text returned to the model is all there is.
+        None => (content, None),
+    };
+    let interrupted = result
+        .and_then(|r| r.get("interrupted"))
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    let exit_code = if is_error {
+        content.and_then(exit_code)
+    } else if interrupted {
+        None
+    } else {
+        Some(0)
+    };
+
+    if let Some(code) = exit_code {
+        extra.insert("exit_code".to_string(), json!(code));
+    }
+    extra.insert("is_error".to_string(), json!(is_error));
+    if interrupted {
+        extra.insert("interrupted".to_string(), json!(true));
+    }
+    for (key, text) in [("stdout", stdout), ("stderr", stderr)] {
+        if let Some(text) = text.filter(|s| !s.is_empty()) {
+            capture::record(&mut extra, key, text, output, blobs);
+        }
+    }
+
+    StructuralChange {
+        change_type: "shell.exec".to_string(),
+        extra,
+    }
+}
+
+/// The exit code in a failed command's result text (`Exit code 2`).
+fn exit_code(text: &str) -> Option<i64> {
+    text.lines().find_map(|line| {
+        let rest = line.trim().trim_start_matches("Error: ");
+        rest.strip_prefix("Exit code ")?
+            .split_whitespace()
+            .next()?
+            .parse()
+            .ok()
+    })
+}
+
+/// The formatter or linter a command runs, as the binary name used for a
+/// `tool:<binary>` actor. Every segment (other than `cd`) must run a
+/// recognised tool; the first one names the actor.
+pub(crate) fn tool_binary(command: &str) -> Option<&'static str> {
+    let mut tools = segments(command)
+        .into_iter()
+        .filter(|words| words.first() != Some(&"cd"))
+        .map(|words| recognised_tool(&words));
+    let first = tools.next()??;
+    tools.all(|t| t.is_some()).then_some(first)
+}
+
+fn recognised_tool(words: &[&str]) -> Option<&'static str> {
+    let words = strip_runner(words);
+    let program = words.first()?.rsplit('/').next()?;
+    let sub = words.get(1).copied();
+    let tool = match (program, sub) {
+        ("cargo", Some("fmt")) => "rustfmt",
+        ("cargo", Some("clippy")) => "clippy",
+        ("go", Some("fmt")) => "gofmt",
+        ("go", Some("vet")) => "go-vet",
+        ("terraform", Some("fmt")) => "terraform-fmt",
+        ("mix", Some("format")) => "mix-format",
+        ("rustfmt", _) => "rustfmt",
+        ("gofmt", _) => "gofmt",
+        ("goimports", _) => "goimports",
+        ("golangci-lint", _) => "golangci-lint",
+        ("prettier", _) => "prettier",
+        ("eslint", _) => "eslint",
+        ("biome", _) => "biome",
+        ("dprint", _) => "dprint",
+        ("black", _) => "black",
+        ("isort", _) => "isort",
+        ("ruff", _) => "ruff",
+        ("autopep8", _) => "autopep8",
+        ("yapf", _) => "yapf",
+        ("flake8", _) => "flake8",
+        ("pylint", _) => "pylint",
+        ("clang-format", _) => "clang-format",
+        ("clang-tidy", _) => "clang-tidy",
+        ("shfmt", _) => "shfmt",
+        ("shellcheck", _) => "shellcheck",
+        ("rubocop", _) => "rubocop",
+        ("stylua", _) => "stylua",
+        ("swiftformat", _) => "swiftformat",
+        ("swiftlint", _) => "swiftlint",
+        ("ktlint", _) => "ktlint",
+        _ => return None,
+    };
+    Some(tool)
+}
+
+/// Drop package-runner prefixes: `npx prettier`, `uv run ruff`,
+/// `python -m black`, `pnpm exec eslint` and the like.
+fn strip_runner<'a, 'w>(words: &'a [&'w str]) -> &'a [&'w str] {
+    let skip = match words {
+        ["npx" | "bunx" | "pnpx", ..] => 1,
+        ["pnpm" | "yarn" | "bun" | "npm", "exec" | "run" | "dlx", ..] => 2,
+        ["uv" | "poetry" | "pipenv", "run", ..] => 2,
+        ["uvx", ..] => 1,
+        [python, "-m", ..] if python.starts_with("python") => 2,
+        _ => 0,
+    };
+    let rest = &words[skip..];
+    // Runner flags such as `npx --yes prettier`
+    let flags = rest.iter().take_while(|w| w.starts_with('-')).count();
+    if skip > 0 { &rest[flags..] } else { rest }
+}
+
+/// Whether a command only queries git (`status`, `diff`, `log`, `show`),
+/// and so cannot have changed the working tree.
+pub(crate) fn is_git_query(command: &str) -> bool {
+    let segments = segments(command);
+    !segments.is_empty()
+        && segments.iter().all(|words| match git_subcommand(words) {
+            Some(sub) => matches!(sub, "status" | "diff" | "log" | "show"),
+            None => words.first() == Some(&"cd"),
+        })
+}
+
+/// Paths `git status` reports as changed, if `command` ran it. Handles the
+/// short (`-s` / `--porcelain`) and long formats; renames report the new
+/// path.
+pub(crate) fn git_status_paths(command: &str, output: &str) -> Option<Vec<String>> {
+    if !segments(command)
+        .iter()
+        .any(|words| git_subcommand(words) == Some("status"))
+    {
+        return None;
+    }
+
+    const LONG: [&str; 5] = [
+        "modified:",
+        "new file:",
+        "deleted:",
+        "renamed:",
+        "typechange:",
+    ];
+    let mut paths = Vec::new();
+    for line in output.lines() {
+        let trimmed = line.trim();
+        let path = if let Some(rest) = LONG.iter().find_map(|p| trimmed.strip_prefix(p)) {
+            rest.trim()
+        } else if line.len() > 3
+            && line.is_char_boundary(3)
+            && line.as_bytes()[2] == b' '
+            && line[..2].chars().all(|c| " MADRCU?T".contains(c))
+            && line[..2] != *"  "
+        {
+            line[3..].trim()
+        } else {
+            continue;
+        };
+        let path = path.rsplit(" -> ").next().unwrap_or(path);
+        paths.push(path.trim_matches('"').to_string());
+    }
+    Some(paths)
+}
+
+/// The subcommand of a `git` invocation, skipping global options such as
+/// `-C <dir>` and `--no-pager`.
+pub(crate) fn git_subcommand<'w>(words: &[&'w str]) -> Option<&'w str> {
+    if words.first() != Some(&"git") {
+        return None;
+    }
+    let mut rest = words[1..].iter();
+    while let Some(word) = rest.next() {
+        match *word {
+            "-C" | "-c" | "--git-dir" | "--work-tree" => {
+                rest.next();
+            }
+            w if w.starts_with('-') => {}
+            w => return Some(w),
+        }
+    }
+    None
+}
+
+/// A command line's segments, each split into words. Leading environment
+/// assignments (`RUST_LOG=debug cargo ...`) are dropped.
+pub(crate) fn segments(command: &str) -> Vec<Vec<&str>> {
+    command
+        .split(['\n', ';', '|', '&'])
+        .map(|segment| {
+            segment
+                .split_whitespace()
+                .skip_while(|w| is_assignment(w))
+                .collect::<Vec<_>>()
+        })
+        // The tail of a `2>&1` or `&>` redirect is not a segment of its own.
+        .filter(|words| !words.is_empty() && !is_redirect_tail(words))
+        .collect()
+}
+
+fn is_redirect_tail(words: &[&str]) -> bool {
+    words[0].starts_with('>') || words[0].chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exec_change_success() {
+        let change = exec_change(
+            &json!({"command": "cargo test", "description": "Run tests"}),
+            Some(&json!({"stdout": "ok", "stderr": "", "interrupted": false})),
+            Some("ok"),
+            false,
+            Some("/repo"),
+            Capture::Full,
+            None,
+        );
+        assert_eq!(change.change_type, "shell.exec");
+        assert_eq!(change.extra["command"], "cargo test");
+        assert_eq!(change.extra["description"], "Run tests");
+        assert_eq!(change.extra["cwd"], "/repo");
+        assert_eq!(change.extra["exit_code"], 0);
+        assert_eq!(change.extra["is_error"], false);
+        assert_eq!(change.extra["stdout"], "ok");
+        assert!(!change.extra.contains_key("stderr"));
+    }
+
+    #[test]
+    fn test_exec_change_failure() {
+        let change = exec_change(
+            &json!({"command": "cargo build"}),
+            Some(&json!("Error: Exit code 101\nerror[E0308]")),
+            Some("Exit code 101\nerror[E0308]: mismatched types"),
+            true,
+            None,
+            Capture::Full,
+            None,
+        );
+        assert_eq!(change.extra["exit_code"], 101);
+        assert_eq!(change.extra["is_error"], true);
+        assert!(change.extra["stdout"].as_str().unwrap().contains("E0308"));
+    }
+
+    #[test]
+    fn test_exec_change_truncates_output() {
+        let long = "x".repeat(2010);
+        let change = exec_change(
+            &json!({"command": "yes"}),
+            Some(&json!({"stdout": long, "stderr": "warning"})),
+            None,
+            false,
+            None,
+            Capture::Truncate(2000),
+            None,
+        );
+        assert_eq!(
+            change.extra["stdout"].as_str().unwrap().chars().count(),
+            2000
+        );
+        assert_eq!(
+            change.extra["stdout_sha256"],
+            crate::digest::sha256(long.as_bytes())
+        );
+        assert_eq!(change.extra["stderr"], "warning");
+        assert!(!change.extra.contains_key("stderr_sha256"));
+    }
+
+    #[test]
+    fn test_exec_change_interrupted_has_no_exit_code() {
+        let change = exec_change(
+            &json!({"command": "sleep 100"}),
+            Some(&json!({"stdout": "", "interrupted": true})),
+            None,
+            false,
+            None,
+            Capture::Full,
+            None,
+        );
+        assert!(!change.extra.contains_key("exit_code"));
+        assert_eq!(change.extra["interrupted"], true);
+    }
+
+    #[test]
+    fn test_tool_binary() {
+        assert_eq!(tool_binary("cargo fmt --all"), Some("rustfmt"));
+        assert_eq!(
+            tool_binary("cd web && npx prettier --write ."),
+            Some("prettier")
+        );
+        assert_eq!(tool_binary("python3 -m black src"), Some("black"));
+        assert_eq!(tool_binary("uv run ruff check --fix"), Some("ruff"));
+        assert_eq!(tool_binary("cargo fmt && cargo clippy"), Some("rustfmt"));
+        assert_eq!(tool_binary("cargo clippy --fix 2>&1 | tail"), None);
+        assert_eq!(tool_binary("cargo clippy 2>&1"), Some("clippy"));
+        assert_eq!(tool_binary("cargo fmt && cargo test"), None);
+        assert_eq!(tool_binary("sed -i 's/a/b/' f.rs"), None);
+        assert_eq!(tool_binary("cd src"), None);
+    }
+
+    #[test]
+    fn test_is_git_query() {
+        assert!(is_git_query("git status"));
+        assert!(is_git_query("cd repo && git --no-pager diff --stat"));
+        assert!(!is_git_query("git status && cargo fmt"));
+        assert!(!is_git_query("git checkout -- src"));
+        assert!(!is_git_query("ls"));
+    }
+
+    #[test]
+    fn test_git_status_paths_short() {
+        let out = " M src/lib.rs\n?? notes.txt\nR  old.rs -> new.rs\n";
+        assert_eq!(
+            git_status_paths("git status --short", out).unwrap(),
+            vec!["src/lib.rs", "notes.txt", "new.rs"]
+        );
+    }
+
+    #[test]
+    fn test_git_status_paths_long() {
+        let out = "On branch main\nChanges not staged for commit:\n\tmodified:   src/lib.rs\n\tdeleted:    old.rs\n";
+        assert_eq!(
+            git_status_paths("git -C repo status", out).unwrap(),
+            vec!["src/lib.rs", "old.rs"]
+        );
+        assert!(git_status_paths("git diff", out).is_none());
+    }
+}
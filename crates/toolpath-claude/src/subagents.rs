@@ -0,0 +1,247 @@
+//! Subagent (`Task`) transcripts.
+//!
+//! Claude Code records a subagent's work in one of two places: as sidechain
+//! entries (`isSidechain: true`) in the delegating session's own log, or as a
+//! separate `agent-<id>.jsonl` file — beside the session logs, or under
+//! `<session-id>/subagents/`. Either way, a transcript is matched back to the
+//! `Task` tool use that spawned it by the `agentId` recorded in the task's
+//! result, or failing that by the prompt the subagent was started with.
+
+use crate::types::{Conversation, ConversationEntry, MessageRole};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// A subagent's transcript, matched to the `Task` tool use that spawned it.
+#[derive(Debug, Clone)]
+pub struct Subagent {
+    /// ID of the spawning `Task` tool use in the parent conversation.
+    pub tool_use_id: String,
+    /// Claude's agent ID, when the transcript records one.
+    pub agent_id: Option<String>,
+    /// The subagent's entries as a conversation of its own. `session_id` is
+    /// the agent ID (or the tool use ID), and entries are no longer marked
+    /// as sidechain.
+    pub conversation: Conversation,
+}
+
+/// Whether a log file stem names a subagent transcript rather than a session.
+pub(crate) fn is_agent_stem(stem: &str) -> bool {
+    stem.starts_with("agent-")
+}
+
+/// Subagent transcripts recorded as sidechain entries in `conversation`.
+///
+/// Sidechain entries are grouped into trees by `parentUuid`. Trees that
+/// match no `Task` tool use are left out.
+pub fn sidechains(conversation: &Conversation) -> Vec<Subagent> {
+    let sidechain: HashMap<&str, &ConversationEntry> = conversation
+        .entries
+        .iter()
+        .filter(|e| e.is_sidechain)
+        .map(|e| (e.uuid.as_str(), e))
+        .collect();
+
+    let mut roots: Vec<&str> = Vec::new();
+    let mut groups: HashMap<&str, Vec<ConversationEntry>> = HashMap::new();
+    for entry in conversation.entries.iter().filter(|e| e.is_sidechain) {
+        let root = root_of(entry, &sidechain);
+        if !groups.contains_key(root) {
+            roots.push(root);
+        }
+        groups.entry(root).or_default().push(entry.clone());
+    }
+
+    let transcripts = roots
+        .into_iter()
+        .filter_map(|root| groups.remove(root))
+        .collect();
+    match_transcripts(conversation, transcripts)
+}
+
+/// Match transcripts — each a subagent's entries, in order — to the `Task`
+/// tool uses in `conversation`. Each task takes at most one transcript;
+/// unmatched transcripts are dropped.
+pub fn match_transcripts(
+    conversation: &Conversation,
+    transcripts: Vec<Vec<ConversationEntry>>,
+) -> Vec<Subagent> {
+    let mut tasks = tasks(conversation);
+    let mut subagents = Vec::new();
+
+    for entries in transcripts {
+        let agent_id = entries
+            .iter()
+            .find_map(|e| e.extra.get("agentId").and_then(Value::as_str))
+            .map(str::to_string);
+        let prompt = entries
+            .iter()
+            .find(|e| {
+                e.message
+                    .as_ref()
+                    .is_some_and(|m| m.role == MessageRole::User)
+            })
+            .map(|e| e.text());
+
+        let by_agent = agent_id
+            .as_deref()
+            .and_then(|id| tasks.iter().position(|t| t.agent_id == Some(id)));
+        let by_prompt = || {
+            let prompt = prompt.as_deref()?.trim();
+            tasks.iter().position(|t| t.prompt.trim() == prompt)
+        };
+        let Some(index) = by_agent.or_else(by_prompt) else {
+            continue;
+        };
+        let task = tasks.remove(index);
+
+        let mut sub = Conversation::new(
+            agent_id
+                .clone()
+                .unwrap_or_else(|| task.tool_use_id.to_string()),
+        );
+        sub.project_path = conversation.project_path.clone();
+        for mut entry in entries {
+            entry.is_sidechain = false;
+            sub.add_entry(entry);
+        }
+        subagents.push(Subagent {
+            tool_use_id: task.tool_use_id.to_string(),
+            agent_id,
+            conversation: sub,
+        });
+    }
+    subagents
+}
+
+/// A `Task` tool use in the parent conversation.
+struct Task<'a> {
+    tool_use_id: &'a str,
+    prompt: &'a str,
+    agent_id: Option<&'a str>,
+}
+
+fn tasks(conversation: &Conversation) -> Vec<Task<'_>> {
+    let agent_ids: HashMap<&str, &str> = conversation
+        .entries
+        .iter()
+        .filter_map(|e| {
+            let agent_id = e.tool_use_result.as_ref()?.get("agentId")?.as_str()?;
+            let message = e.message.as_ref()?;
+            Some(
+                message
+                    .tool_results()
+                    .into_iter()
+                    .map(move |r| (r.tool_use_id, agent_id)),
+            )
+        })
+        .flatten()
+        .collect();
+
+    conversation
+        .entries
+        .iter()
+        .filter(|e| !e.is_sidechain)
+        .flat_map(|e| e.tool_uses())
+        .filter(|tu| tu.name == "Task")
+        .map(|tu| Task {
+            tool_use_id: tu.id,
+            prompt: tu.input.get("prompt").and_then(Value::as_str).unwrap_or(""),
+            agent_id: agent_ids.get(tu.id).copied(),
+        })
+        .collect()
+}
+
+/// The first entry of `entry`'s sidechain tree.
+fn root_of<'a>(
+    entry: &'a ConversationEntry,
+    sidechain: &HashMap<&str, &'a ConversationEntry>,
+) -> &'a str {
+    let mut current = entry;
+    // Bounded by the number of entries, in case of a malformed cycle.
+    for _ in 0..sidechain.len() {
+        match current
+            .parent_uuid
+            .as_deref()
+            .and_then(|p| sidechain.get(p))
+        {
+            Some(parent) => current = parent,
+            None => break,
+        }
+    }
+    &current.uuid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(line: &str) -> ConversationEntry {
+        serde_json::from_str(line).unwrap()
+    }
+
+    fn conversation(lines: &[&str]) -> Conversation {
+        let mut convo = Conversation::new("main-session".to_string());
+        for line in lines {
+            convo.add_entry(entry(line));
+        }
+        convo
+    }
+
+    const TASK: &str = r#"{"uuid":"u1","type":"assistant","timestamp":"2024-01-01T00:00:00Z","message":{"role":"assistant","content":[{"type":"tool_use","id":"task-1","name":"Task","input":{"prompt":"Find the bug","description":"Bug hunt"}}]}}"#;
+
+    #[test]
+    fn test_sidechains_grouped_and_matched_by_prompt() {
+        let convo = conversation(&[
+            TASK,
+            r#"{"uuid":"s1","parentUuid":null,"isSidechain":true,"type":"user","timestamp":"2024-01-01T00:00:01Z","message":{"role":"user","content":"Find the bug"}}"#,
+            r#"{"uuid":"s2","parentUuid":"s1","isSidechain":true,"type":"assistant","timestamp":"2024-01-01T00:00:02Z","message":{"role":"assistant","content":"Found it"}}"#,
+            r#"{"uuid":"s3","parentUuid":null,"isSidechain":true,"type":"user","timestamp":"2024-01-01T00:00:03Z","message":{"role":"user","content":"Unrelated"}}"#,
+        ]);
+
+        let subs = sidechains(&convo);
+
+        assert_eq!(subs.len(), 1);
+        assert_eq!(subs[0].tool_use_id, "task-1");
+        assert_eq!(subs[0].conversation.session_id, "task-1");
+        assert_eq!(subs[0].conversation.entries.len(), 2);
+        assert!(subs[0].conversation.entries.iter().all(|e| !e.is_sidechain));
+    }
+
+    #[test]
+    fn test_match_transcripts_by_agent_id() {
+        let convo = conversation(&[
+            TASK,
+            r#"{"uuid":"u2","type":"user","timestamp":"2024-01-01T00:00:05Z","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"task-1","content":"done"}]},"toolUseResult":{"agentId":"a1b2"}}"#,
+        ]);
+        let transcript = vec![entry(
+            r#"{"uuid":"s1","isSidechain":true,"agentId":"a1b2","type":"user","timestamp":"2024-01-01T00:00:01Z","message":{"role":"user","content":"Prompt was rewritten"}}"#,
+        )];
+
+        let subs = match_transcripts(&convo, vec![transcript]);
+
+        assert_eq!(subs.len(), 1);
+        assert_eq!(subs[0].tool_use_id, "task-1");
+        assert_eq!(subs[0].agent_id.as_deref(), Some("a1b2"));
+        assert_eq!(subs[0].conversation.session_id, "a1b2");
+    }
+
+    #[test]
+    fn test_each_task_matched_once() {
+        let convo = conversation(&[TASK]);
+        let transcript = || {
+            vec![entry(
+                r#"{"uuid":"s1","isSidechain":true,"type":"user","timestamp":"2024-01-01T00:00:01Z","message":{"role":"user","content":"Find the bug"}}"#,
+            )]
+        };
+
+        let subs = match_transcripts(&convo, vec![transcript(), transcript()]);
+
+        assert_eq!(subs.len(), 1);
+    }
+
+    #[test]
+    fn test_is_agent_stem() {
+        assert!(is_agent_stem("agent-a1b2"));
+        assert!(!is_agent_stem("5f0c-session"));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::{attachments, digest};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -111,11 +112,80 @@ pub enum ContentPart {
         #[serde(default)]
         is_error: bool,
     },
+    Image {
+        source: MediaSource,
+    },
+    Document {
+        source: MediaSource,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
     /// Catch-all for unknown content types
     #[serde(other)]
     Unknown,
 }
 
+/// The data behind an image or document part: inline (`base64` or `text`)
+/// or by reference (`url`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaSource {
+    #[serde(rename = "type")]
+    pub source_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl MediaSource {
+    /// The decoded bytes of an inline source. `None` for a `url` source or
+    /// data that isn't valid base64.
+    pub fn bytes(&self) -> Option<Vec<u8>> {
+        let data = self.data.as_deref()?;
+        match self.source_type.as_str() {
+            "base64" => attachments::decode_base64(data),
+            "text" => Some(data.as_bytes().to_vec()),
+            _ => None,
+        }
+    }
+
+    /// Size in bytes of the decoded data.
+    pub fn size(&self) -> Option<u64> {
+        self.bytes().map(|b| b.len() as u64)
+    }
+
+    /// SHA-256 of the decoded data, as `sha256:<hex>`.
+    pub fn sha256(&self) -> Option<String> {
+        self.bytes().map(|b| digest::sha256(&b))
+    }
+}
+
+/// Whether a [`MediaRef`] is an image or a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Image,
+    Document,
+}
+
+impl MediaKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MediaKind::Image => "image",
+            MediaKind::Document => "document",
+        }
+    }
+}
+
+/// A reference to an image or document within a content part.
+#[derive(Debug)]
+pub struct MediaRef<'a> {
+    pub kind: MediaKind,
+    pub source: &'a MediaSource,
+    pub title: Option<&'a str>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum ToolResultContent {
@@ -237,6 +307,30 @@ impl Message {
             .collect()
     }
 
+    /// Image and document parts, if any.
+    pub fn media(&self) -> Vec<MediaRef<'_>> {
+        let parts = match &self.content {
+            Some(MessageContent::Parts(parts)) => parts,
+            _ => return Vec::new(),
+        };
+        parts
+            .iter()
+            .filter_map(|p| match p {
+                ContentPart::Image { source } => Some(MediaRef {
+                    kind: MediaKind::Image,
+                    source,
+                    title: None,
+                }),
+                ContentPart::Document { source, title } => Some(MediaRef {
+                    kind: MediaKind::Document,
+                    source,
+                    title: title.as_deref(),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
     /// Whether this message has the given role.
     pub fn is_role(&self, role: MessageRole) -> bool {
         self.role == role
@@ -254,6 +348,33 @@ impl Message {
 }
 
 impl ConversationEntry {
+    /// Whether this is the `compact_boundary` system entry Claude Code
+    /// writes when it compacts the context. It has no message; its
+    /// `logicalParentUuid` links to the last entry before compaction.
+    pub fn is_compact_boundary(&self) -> bool {
+        self.entry_type == "system"
+            && self.extra.get("subtype").and_then(Value::as_str) == Some("compact_boundary")
+    }
+
+    /// The metadata of a `compact_boundary` entry.
+    pub fn compact_metadata(&self) -> Option<CompactMetadata> {
+        if !self.is_compact_boundary() {
+            return None;
+        }
+        Some(
+            self.extra
+                .get("compactMetadata")
+                .and_then(|v| serde_json::from_value(v.clone()).ok())
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Whether this is the user entry carrying the summary the conversation
+    /// continues from after compaction.
+    pub fn is_compact_summary(&self) -> bool {
+        self.extra.get("isCompactSummary").and_then(Value::as_bool) == Some(true)
+    }
+
     /// Role of the message, if present.
     pub fn role(&self) -> Option<&MessageRole> {
         self.message.as_ref().map(|m| &m.role)
@@ -279,6 +400,11 @@ impl ConversationEntry {
             .unwrap_or_default()
     }
 
+    /// Image and document parts from the message, if any.
+    pub fn media(&self) -> Vec<MediaRef<'_>> {
+        self.message.as_ref().map(|m| m.media()).unwrap_or_default()
+    }
+
     /// Stop reason, if present.
     pub fn stop_reason(&self) -> Option<&str> {
         self.message.as_ref().and_then(|m| m.stop_reason.as_deref())
@@ -316,6 +442,16 @@ impl ContentPart {
                     format!("[{}: {}]", prefix, text)
                 }
             }
+            ContentPart::Image { source } => match &source.media_type {
+                Some(media_type) => format!("[image: {}]", media_type),
+                None => "[image]".to_string(),
+            },
+            ContentPart::Document { source, title } => {
+                match title.as_deref().or(source.media_type.as_deref()) {
+                    Some(label) => format!("[document: {}]", label),
+                    None => "[document]".to_string(),
+                }
+            }
             ContentPart::Unknown => "[unknown]".to_string(),
         }
     }
@@ -368,12 +504,24 @@ pub struct CacheCreation {
     pub ephemeral_1h_input_tokens: Option<u32>,
 }
 
+/// What a `compact_boundary` entry records about the compacted context.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactMetadata {
+    /// `"auto"`, or `"manual"` for `/compact`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trigger: Option<String>,
+    /// Context size in tokens before compaction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_tokens: Option<u64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HistoryEntry {
     pub display: String,
 
     #[serde(rename = "pastedContents", default)]
-    pub pasted_contents: HashMap<String, Value>,
+    pub pasted_contents: HashMap<String, PastedContent>,
 
     pub timestamp: i64,
 
@@ -384,6 +532,52 @@ pub struct HistoryEntry {
     pub session_id: Option<String>,
 }
 
+impl HistoryEntry {
+    /// Images pasted into the prompt, by paste key, in key order.
+    pub fn images(&self) -> Vec<(&str, MediaSource)> {
+        let mut images: Vec<(&str, MediaSource)> = self
+            .pasted_contents
+            .iter()
+            .filter_map(|(key, paste)| Some((key.as_str(), paste.image()?)))
+            .collect();
+        images.sort_by(|a, b| a.0.cmp(b.0));
+        images
+    }
+}
+
+/// Text or an image pasted into a prompt, as recorded in `history.jsonl`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PastedContent {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    /// `"text"` or `"image"`.
+    #[serde(rename = "type", default)]
+    pub content_type: String,
+    /// The pasted text, or base64 image data.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl PastedContent {
+    /// The pasted image as a `base64` [`MediaSource`], if this is one.
+    pub fn image(&self) -> Option<MediaSource> {
+        if self.content_type != "image" {
+            return None;
+        }
+        Some(MediaSource {
+            source_type: "base64".to_string(),
+            media_type: self.media_type.clone(),
+            data: self.content.clone(),
+            url: None,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Conversation {
     pub session_id: String,
@@ -395,6 +589,38 @@ pub struct Conversation {
     /// Empty for single-segment conversations.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub session_ids: Vec<String>,
+    /// `file-history-snapshot` entries, in file order. See
+    /// [`Conversation::file_content_at`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub file_snapshots: Vec<FileSnapshot>,
+}
+
+/// A file Claude Code backed up before editing it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileBackup {
+    /// Backup file under `file-history/<session-id>/`; `None` when the file
+    /// did not exist yet.
+    #[serde(default)]
+    pub backup_file_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup_time: Option<String>,
+}
+
+/// The backups recorded for one user message by `file-history-snapshot`
+/// entries, keyed by file path.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileSnapshot {
+    /// UUID of the user message the snapshot belongs to.
+    pub message_id: String,
+    /// Session whose `file-history` directory holds the backups.
+    pub session_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+    pub files: HashMap<String, FileBackup>,
 }
 
 impl Conversation {
@@ -406,6 +632,152 @@ impl Conversation {
             started_at: None,
             last_activity: None,
             session_ids: Vec::new(),
+            file_snapshots: Vec::new(),
+        }
+    }
+
+    /// Record a `file-history-snapshot` entry read from `session_id`'s log.
+    ///
+    /// Snapshot updates for the same message add newly tracked files; a
+    /// file's first backup for a message is kept, since that is its content
+    /// before the turn touched it.
+    pub fn add_file_snapshot(&mut self, session_id: &str, entry: &ConversationEntry) {
+        let Some(snapshot) = &entry.snapshot else {
+            return;
+        };
+        let Some(message_id) = snapshot
+            .get("messageId")
+            .and_then(Value::as_str)
+            .or(entry.message_id.as_deref())
+        else {
+            return;
+        };
+        let files: HashMap<String, FileBackup> = snapshot
+            .get("trackedFileBackups")
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default();
+
+        self.merge_file_snapshot(FileSnapshot {
+            message_id: message_id.to_string(),
+            session_id: session_id.to_string(),
+            timestamp: snapshot
+                .get("timestamp")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            files,
+        });
+    }
+
+    /// Add a parsed snapshot, folding it into an existing snapshot for the
+    /// same message (keeping each file's first backup).
+    pub fn merge_file_snapshot(&mut self, snapshot: FileSnapshot) {
+        match self
+            .file_snapshots
+            .iter_mut()
+            .find(|s| s.message_id == snapshot.message_id)
+        {
+            Some(existing) => {
+                for (path, backup) in snapshot.files {
+                    existing.files.entry(path).or_insert(backup);
+                }
+            }
+            None => self.file_snapshots.push(snapshot),
+        }
+    }
+
+    /// Each entry's index in `entries`, by UUID, for [`file_backup_in`] and
+    /// [`file_content_in`].
+    ///
+    /// [`file_backup_in`]: Conversation::file_backup_in
+    /// [`file_content_in`]: Conversation::file_content_in
+    pub fn entry_positions(&self) -> HashMap<String, usize> {
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (e.uuid.clone(), i))
+            .collect()
+    }
+
+    /// The backup of `file_path` in effect at `message_uuid`: from the latest
+    /// snapshot whose message is at or before it in the conversation.
+    ///
+    /// Indexes every entry on each call; use [`file_backup_in`] for repeated
+    /// lookups.
+    ///
+    /// [`file_backup_in`]: Conversation::file_backup_in
+    pub fn file_backup(
+        &self,
+        message_uuid: &str,
+        file_path: &str,
+    ) -> Option<(&FileSnapshot, &FileBackup)> {
+        self.file_backup_in(&self.entry_positions(), message_uuid, file_path)
+    }
+
+    /// [`file_backup`](Conversation::file_backup), ordering messages by
+    /// `positions` (as from [`entry_positions`](Conversation::entry_positions)).
+    /// A message missing from `positions` is treated as not yet seen.
+    pub fn file_backup_in(
+        &self,
+        positions: &HashMap<String, usize>,
+        message_uuid: &str,
+        file_path: &str,
+    ) -> Option<(&FileSnapshot, &FileBackup)> {
+        let at = *positions.get(message_uuid)?;
+        self.file_snapshots
+            .iter()
+            .filter_map(|s| Some((*positions.get(&s.message_id).filter(|&&i| i <= at)?, s)))
+            .filter_map(|(i, s)| s.files.get(file_path).map(|b| (i, s, b)))
+            .max_by_key(|(i, _, _)| *i)
+            .map(|(_, s, b)| (s, b))
+    }
+
+    /// Content of `file_path` as backed up at `message_uuid`, read from the
+    /// Claude data dir. Returns an empty string for a file that did not
+    /// exist yet, and `None` when no snapshot covers the file or its backup
+    /// has been cleaned up.
+    pub fn file_content_at(
+        &self,
+        message_uuid: &str,
+        file_path: &str,
+        resolver: &crate::paths::PathResolver,
+    ) -> crate::error::Result<Option<String>> {
+        self.file_content_in(&self.entry_positions(), message_uuid, file_path, resolver)
+    }
+
+    /// [`file_content_at`](Conversation::file_content_at), ordering messages
+    /// by `positions`. A backup name that is not a plain relative path inside
+    /// the file-history directory is an error.
+    pub fn file_content_in(
+        &self,
+        positions: &HashMap<String, usize>,
+        message_uuid: &str,
+        file_path: &str,
+        resolver: &crate::paths::PathResolver,
+    ) -> crate::error::Result<Option<String>> {
+        let Some((snapshot, backup)) = self.file_backup_in(positions, message_uuid, file_path)
+        else {
+            return Ok(None);
+        };
+        let Some(name) = &backup.backup_file_name else {
+            return Ok(Some(String::new()));
+        };
+        let relative = std::path::Path::new(name);
+        if !relative
+            .components()
+            .all(|c| matches!(c, std::path::Component::Normal(_)))
+        {
+            return Err(crate::error::ConvoError::PathConversion(format!(
+                "backup file name {:?} is outside the file-history directory",
+                name
+            )));
+        }
+        let path = resolver
+            .file_history_dir(&snapshot.session_id)?
+            .join(relative);
+        match std::fs::read_to_string(&path) {
+            Ok(content) => Ok(Some(content)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
         }
     }
 
@@ -508,18 +880,22 @@ impl Conversation {
         })
     }
 
-    /// Full text of the first user message, untruncated.
+    /// Full text of the first user message, untruncated. Compaction
+    /// summaries don't count.
     pub fn first_user_text(&self) -> Option<String> {
-        self.entries.iter().find_map(|e| {
-            e.message.as_ref().and_then(|msg| {
-                if msg.is_user() {
-                    let text = msg.text();
-                    if text.is_empty() { None } else { Some(text) }
-                } else {
-                    None
-                }
+        self.entries
+            .iter()
+            .filter(|e| !e.is_compact_summary())
+            .find_map(|e| {
+                e.message.as_ref().and_then(|msg| {
+                    if msg.is_user() {
+                        let text = msg.text();
+                        if text.is_empty() { None } else { Some(text) }
+                    } else {
+                        None
+                    }
+                })
             })
-        })
     }
 }
 
@@ -555,6 +931,163 @@ mod tests {
         convo
     }
 
+    fn snapshot_entry(message_id: &str, files: Value) -> ConversationEntry {
+        serde_json::from_value(serde_json::json!({
+            "type": "file-history-snapshot",
+            "messageId": message_id,
+            "snapshot": {
+                "messageId": message_id,
+                "trackedFileBackups": files,
+                "timestamp": "2024-01-01T00:00:00Z"
+            },
+            "isSnapshotUpdate": false
+        }))
+        .unwrap()
+    }
+
+    fn user_entry(uuid: &str) -> ConversationEntry {
+        serde_json::from_value(serde_json::json!({
+            "type": "user",
+            "uuid": uuid,
+            "timestamp": "2024-01-01T00:00:00Z",
+            "message": {"role": "user", "content": "hi"}
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_file_snapshot_updates_keep_first_backup() {
+        let mut convo = Conversation::new("s1".into());
+        convo.add_file_snapshot(
+            "s1",
+            &snapshot_entry(
+                "m1",
+                serde_json::json!({"/p/a": {"backupFileName": "a@v1", "version": 1}}),
+            ),
+        );
+        convo.add_file_snapshot(
+            "s1",
+            &snapshot_entry(
+                "m1",
+                serde_json::json!({
+                    "/p/a": {"backupFileName": "a@v2", "version": 2},
+                    "/p/b": {"backupFileName": null, "version": 1}
+                }),
+            ),
+        );
+        assert_eq!(convo.file_snapshots.len(), 1);
+        let files = &convo.file_snapshots[0].files;
+        assert_eq!(files["/p/a"].backup_file_name.as_deref(), Some("a@v1"));
+        assert_eq!(files["/p/b"].backup_file_name, None);
+    }
+
+    #[test]
+    fn test_file_backup_uses_latest_snapshot_before_point() {
+        let mut convo = Conversation::new("s1".into());
+        convo.add_entry(user_entry("m1"));
+        convo.add_entry(user_entry("m2"));
+        convo.add_entry(user_entry("m3"));
+        convo.add_file_snapshot(
+            "s1",
+            &snapshot_entry(
+                "m1",
+                serde_json::json!({"/p/a": {"backupFileName": "a@v1"}}),
+            ),
+        );
+        convo.add_file_snapshot(
+            "s1",
+            &snapshot_entry(
+                "m3",
+                serde_json::json!({"/p/a": {"backupFileName": "a@v3"}}),
+            ),
+        );
+
+        let name = |uuid| {
+            convo
+                .file_backup(uuid, "/p/a")
+                .and_then(|(_, b)| b.backup_file_name.clone())
+        };
+        assert_eq!(name("m1").as_deref(), Some("a@v1"));
+        assert_eq!(name("m2").as_deref(), Some("a@v1"));
+        assert_eq!(name("m3").as_deref(), Some("a@v3"));
+        assert!(convo.file_backup("unknown", "/p/a").is_none());
+        assert!(convo.file_backup("m3", "/p/other").is_none());
+    }
+
+    #[test]
+    fn test_file_backup_in_positions() {
+        let mut convo = Conversation::new("s1".into());
+        convo.add_entry(user_entry("m1"));
+        convo.add_entry(user_entry("m2"));
+        convo.add_file_snapshot(
+            "s1",
+            &snapshot_entry(
+                "m2",
+                serde_json::json!({"/p/a": {"backupFileName": "a@v2"}}),
+            ),
+        );
+
+        let mut positions = convo.entry_positions();
+        assert!(convo.file_backup_in(&positions, "m2", "/p/a").is_some());
+        assert!(convo.file_backup_in(&positions, "m1", "/p/a").is_none());
+
+        // Only m1 seen so far: m2's snapshot is not yet in effect.
+        positions.remove("m2");
+        assert!(convo.file_backup_in(&positions, "m2", "/p/a").is_none());
+    }
+
+    #[test]
+    fn test_file_content_at_reads_backup() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let resolver = crate::PathResolver::new().with_claude_dir(temp.path());
+        let dir = temp.path().join("file-history").join("s1");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a@v1"), "before\n").unwrap();
+
+        let mut convo = Conversation::new("s1".into());
+        convo.add_entry(user_entry("m1"));
+        convo.add_file_snapshot(
+            "s1",
+            &snapshot_entry(
+                "m1",
+                serde_json::json!({
+                    "/p/a": {"backupFileName": "a@v1"},
+                    "/p/new": {"backupFileName": null},
+                    "/p/gone": {"backupFileName": "gone@v1"}
+                }),
+            ),
+        );
+
+        let at = |path| convo.file_content_at("m1", path, &resolver).unwrap();
+        assert_eq!(at("/p/a").as_deref(), Some("before\n"));
+        assert_eq!(at("/p/new").as_deref(), Some(""));
+        assert_eq!(at("/p/gone"), None);
+        assert_eq!(at("/p/untracked"), None);
+    }
+
+    #[test]
+    fn test_file_content_at_rejects_escaping_names() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let resolver = crate::PathResolver::new().with_claude_dir(temp.path());
+        std::fs::write(temp.path().join("secret"), "x").unwrap();
+
+        let mut convo = Conversation::new("s1".into());
+        convo.add_entry(user_entry("m1"));
+        convo.add_file_snapshot(
+            "s1",
+            &snapshot_entry(
+                "m1",
+                serde_json::json!({
+                    "/p/up": {"backupFileName": "../../secret"},
+                    "/p/abs": {"backupFileName": "/etc/passwd"}
+                }),
+            ),
+        );
+
+        assert!(convo.file_content_at("m1", "/p/up", &resolver).is_err());
+        assert!(convo.file_content_at("m1", "/p/abs", &resolver).is_err());
+    }
+
     #[test]
     fn test_entries_since_middle() {
         let convo = create_test_conversation();
@@ -773,6 +1306,78 @@ mod tests {
         assert_eq!(part.summary(), "[unknown]");
     }
 
+    #[test]
+    fn test_content_part_image_and_document() {
+        let parts: Vec<ContentPart> = serde_json::from_str(
+            r#"[{"type":"image","source":{"type":"base64","media_type":"image/png","data":"Zm9v"}},{"type":"document","title":"Notes","source":{"type":"text","media_type":"text/plain","data":"hi"}}]"#,
+        )
+        .unwrap();
+        assert_eq!(parts[0].summary(), "[image: image/png]");
+        assert_eq!(parts[1].summary(), "[document: Notes]");
+
+        let message = Message {
+            role: MessageRole::User,
+            content: Some(MessageContent::Parts(parts)),
+            model: None,
+            id: None,
+            message_type: None,
+            stop_reason: None,
+            stop_sequence: None,
+            usage: None,
+        };
+        let media = message.media();
+        assert_eq!(media.len(), 2);
+        assert_eq!(media[0].kind, MediaKind::Image);
+        assert_eq!(media[0].source.bytes().unwrap(), b"foo");
+        assert_eq!(media[0].source.size(), Some(3));
+        assert_eq!(media[1].kind, MediaKind::Document);
+        assert_eq!(media[1].title, Some("Notes"));
+        assert_eq!(media[1].source.sha256(), Some(crate::digest::sha256(b"hi")));
+    }
+
+    #[test]
+    fn test_compaction_entries() {
+        let boundary: ConversationEntry = serde_json::from_str(
+            r#"{"uuid":"b","type":"system","subtype":"compact_boundary","timestamp":"2024-01-01T00:00:00Z","compactMetadata":{"trigger":"auto","preTokens":1000}}"#,
+        )
+        .unwrap();
+        assert!(boundary.is_compact_boundary());
+        assert_eq!(
+            boundary.compact_metadata(),
+            Some(CompactMetadata {
+                trigger: Some("auto".into()),
+                pre_tokens: Some(1000),
+            })
+        );
+
+        let summary: ConversationEntry = serde_json::from_str(
+            r#"{"uuid":"s","type":"user","isCompactSummary":true,"timestamp":"2024-01-01T00:00:01Z","message":{"role":"user","content":"Summary"}}"#,
+        )
+        .unwrap();
+        assert!(summary.is_compact_summary());
+        assert!(!summary.is_compact_boundary());
+        assert!(summary.compact_metadata().is_none());
+
+        let mut convo = Conversation::new("c".into());
+        convo.add_entry(boundary);
+        convo.add_entry(summary);
+        assert_eq!(convo.first_user_text(), None);
+    }
+
+    #[test]
+    fn test_history_entry_pasted_images() {
+        let entry: HistoryEntry = serde_json::from_str(
+            r#"{"display":"[Pasted text #1] [Image #2]","timestamp":1,"pastedContents":{"1":{"id":1,"type":"text","content":"abc"},"2":{"id":2,"type":"image","content":"Zm9v","mediaType":"image/png"}}}"#,
+        )
+        .unwrap();
+        assert_eq!(entry.pasted_contents["1"].content.as_deref(), Some("abc"));
+        let images = entry.images();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].0, "2");
+        assert_eq!(images[0].1.media_type.as_deref(), Some("image/png"));
+        assert_eq!(images[0].1.bytes().unwrap(), b"foo");
+    }
+
     // ── ToolResultContent::text ────────────────────────────────────────
 
     #[test]
@@ -0,0 +1,214 @@
+//! Token usage and estimated cost on Claude-derived documents.
+//!
+//! Each assistant step records the usage Claude Code logged for its API
+//! response as a [`StepUsage`] extension (`claude.usage`); the path records
+//! the sum as [`UsageTotals`] (`claude.usage_totals`). Costs are only
+//! estimated when a user-supplied [`PriceTable`] is given — prices change,
+//! and this crate does not ship any.
+//!
+//! A response streamed as several log entries repeats its usage on each one;
+//! it is counted once, on the step of its first entry.
+
+use crate::error::Result;
+use crate::types::ConversationEntry;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use toolpath::v1::Step;
+use toolpath::v1::ext::Extension;
+
+/// Token usage of the API response behind a step.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StepUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Input tokens read from the prompt cache.
+    pub cache_read_tokens: u64,
+    /// Input tokens written to the prompt cache.
+    pub cache_write_tokens: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Estimated cost in USD, when a price table covers the model.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+}
+
+impl Extension for StepUsage {
+    const KEY: &'static str = "claude.usage";
+}
+
+impl StepUsage {
+    /// The usage logged on `entry`, if it has any.
+    pub fn from_entry(entry: &ConversationEntry, prices: Option<&PriceTable>) -> Option<Self> {
+        let message = entry.message.as_ref()?;
+        let usage = message.usage.as_ref()?;
+        let tokens = |n: Option<u32>| u64::from(n.unwrap_or(0));
+        let mut step = Self {
+            input_tokens: tokens(usage.input_tokens),
+            output_tokens: tokens(usage.output_tokens),
+            cache_read_tokens: tokens(usage.cache_read_input_tokens),
+            cache_write_tokens: tokens(usage.cache_creation_input_tokens),
+            model: message.model.clone(),
+            stop_reason: message.stop_reason.clone(),
+            request_id: entry.request_id.clone(),
+            cost_usd: None,
+        };
+        step.cost_usd = prices
+            .zip(step.model.as_deref())
+            .and_then(|(prices, model)| prices.price(model))
+            .map(|price| price.cost(&step));
+        Some(step)
+    }
+}
+
+/// Token usage summed over a path's steps, dead ends included.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageTotals {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_write_tokens: u64,
+    /// Estimated cost in USD of the steps that have one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+}
+
+impl Extension for UsageTotals {
+    const KEY: &'static str = "claude.usage_totals";
+}
+
+impl UsageTotals {
+    /// Sum the [`StepUsage`] recorded on `steps`. `None` if no step has any.
+    pub fn of(steps: &[Step]) -> Option<Self> {
+        let mut totals: Option<Self> = None;
+        for usage in steps
+            .iter()
+            .filter_map(|s| s.meta.as_ref()?.get_ext::<StepUsage>().ok().flatten())
+        {
+            let totals = totals.get_or_insert_with(Default::default);
+            totals.input_tokens += usage.input_tokens;
+            totals.output_tokens += usage.output_tokens;
+            totals.cache_read_tokens += usage.cache_read_tokens;
+            totals.cache_write_tokens += usage.cache_write_tokens;
+            if let Some(cost) = usage.cost_usd {
+                *totals.cost_usd.get_or_insert(0.0) += cost;
+            }
+        }
+        totals
+    }
+}
+
+/// Per-model prices, in USD per million tokens.
+///
+/// ```toml
+/// [models."claude-sonnet-4"]
+/// input = 3.0
+/// output = 15.0
+/// cache_read = 0.3
+/// cache_write = 3.75
+/// ```
+///
+/// A model is priced by the longest key that is a prefix of its name, so
+/// `claude-sonnet-4` covers `claude-sonnet-4-20250514`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PriceTable {
+    #[serde(default)]
+    pub models: HashMap<String, ModelPrice>,
+}
+
+/// Prices for one model, in USD per million tokens. Cache prices default
+/// to zero.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+    #[serde(default)]
+    pub cache_read: f64,
+    #[serde(default)]
+    pub cache_write: f64,
+}
+
+impl PriceTable {
+    /// Parse a price table from TOML.
+    pub fn from_toml(s: &str) -> Result<Self> {
+        Ok(toml::from_str(s)?)
+    }
+
+    /// The prices for `model`.
+    pub fn price(&self, model: &str) -> Option<&ModelPrice> {
+        self.models
+            .iter()
+            .filter(|(key, _)| model.starts_with(key.as_str()))
+            .max_by_key(|(key, _)| key.len())
+            .map(|(_, price)| price)
+    }
+}
+
+impl ModelPrice {
+    /// Estimated cost of `usage` in USD.
+    pub fn cost(&self, usage: &StepUsage) -> f64 {
+        let per_token = |price: f64, tokens: u64| price * tokens as f64 / 1_000_000.0;
+        per_token(self.input, usage.input_tokens)
+            + per_token(self.output, usage.output_tokens)
+            + per_token(self.cache_read, usage.cache_read_tokens)
+            + per_token(self.cache_write, usage.cache_write_tokens)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRICES: &str = r#"
+[models."claude-sonnet-4"]
+input = 3.0
+output = 15.0
+cache_read = 0.3
+
+[models."claude"]
+input = 1.0
+output = 1.0
+"#;
+
+    fn entry(line: &str) -> ConversationEntry {
+        serde_json::from_str(line).unwrap()
+    }
+
+    const ASSISTANT: &str = r#"{"uuid":"u1","type":"assistant","timestamp":"2024-01-01T00:00:00Z","requestId":"req_1","message":{"id":"msg_1","role":"assistant","model":"claude-sonnet-4-20250514","content":"Done","stop_reason":"end_turn","usage":{"input_tokens":1000,"output_tokens":2000,"cache_read_input_tokens":10000,"cache_creation_input_tokens":500}}}"#;
+
+    #[test]
+    fn test_step_usage_from_entry() {
+        let usage = StepUsage::from_entry(&entry(ASSISTANT), None).unwrap();
+        assert_eq!(usage.input_tokens, 1000);
+        assert_eq!(usage.output_tokens, 2000);
+        assert_eq!(usage.cache_read_tokens, 10000);
+        assert_eq!(usage.cache_write_tokens, 500);
+        assert_eq!(usage.stop_reason.as_deref(), Some("end_turn"));
+        assert_eq!(usage.request_id.as_deref(), Some("req_1"));
+        assert_eq!(usage.cost_usd, None);
+    }
+
+    #[test]
+    fn test_step_usage_cost() {
+        let prices = PriceTable::from_toml(PRICES).unwrap();
+        let usage = StepUsage::from_entry(&entry(ASSISTANT), Some(&prices)).unwrap();
+        // 1000 * 3 + 2000 * 15 + 10000 * 0.3 + 500 * 0 per million
+        assert!((usage.cost_usd.unwrap() - 0.036).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_price_longest_prefix() {
+        let prices = PriceTable::from_toml(PRICES).unwrap();
+        assert_eq!(prices.price("claude-sonnet-4-5").unwrap().input, 3.0);
+        assert_eq!(prices.price("claude-haiku").unwrap().input, 1.0);
+        assert!(prices.price("gpt-4").is_none());
+    }
+
+    #[test]
+    fn test_invalid_price_table() {
+        assert!(PriceTable::from_toml("[models.x]\ninput = \"free\"").is_err());
+    }
+}
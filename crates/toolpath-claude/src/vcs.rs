@@ -0,0 +1,87 @@
+//! Detect git commits made through `Bash` tool calls.
+//!
+//! Heuristic, per the correlation RFC: a commit is only reported when the
+//! command ran `git commit` and the output names the new commit the way git
+//! does (`[main 1a2b3c4] message`). Missing a commit is preferable to
+//! stamping a wrong one.
+
+/// The abbreviated hash of the last commit `command` created, as reported
+/// in its `output`.
+pub(crate) fn commit_hash(command: &str, output: &str) -> Option<String> {
+    if !runs_git_commit(command) {
+        return None;
+    }
+    output.lines().rev().find_map(summary_hash)
+}
+
+/// Whether any segment of a shell command line runs `git commit`.
+fn runs_git_commit(command: &str) -> bool {
+    crate::shell::segments(command)
+        .iter()
+        .any(|words| crate::shell::git_subcommand(words) == Some("commit"))
+}
+
+/// The hash in a `git commit` summary line: `[<branch> <hash>] <subject>`,
+/// with an optional `(root-commit)` between.
+fn summary_hash(line: &str) -> Option<String> {
+    let inner = line.trim_start().strip_prefix('[')?;
+    let (inner, _) = inner.split_once(']')?;
+    let words: Vec<&str> = inner.split_whitespace().collect();
+    let (hash, branch) = words.split_last()?;
+    if branch.is_empty() || !is_short_hash(hash) {
+        return None;
+    }
+    Some(hash.to_string())
+}
+
+fn is_short_hash(s: &str) -> bool {
+    (7..=40).contains(&s.len()) && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commit_hash_from_summary() {
+        let output = "[main 1a2b3c4] Fix the parser\n 1 file changed, 2 insertions(+)";
+        assert_eq!(
+            commit_hash("git add -A && git commit -m 'Fix the parser'", output).as_deref(),
+            Some("1a2b3c4")
+        );
+    }
+
+    #[test]
+    fn test_commit_hash_root_commit_and_git_flags() {
+        let output = "[main (root-commit) deadbeef] Initial commit";
+        assert_eq!(
+            commit_hash("git -C repo commit -m init", output).as_deref(),
+            Some("deadbeef")
+        );
+    }
+
+    #[test]
+    fn test_commit_hash_takes_last() {
+        let output = "[feature 1111111] One\n[feature 2222222] Two";
+        assert_eq!(
+            commit_hash("git commit -m One; git commit -m Two", output).as_deref(),
+            Some("2222222")
+        );
+    }
+
+    #[test]
+    fn test_commit_hash_requires_git_commit() {
+        let output = "[main 1a2b3c4] Fix the parser";
+        assert_eq!(commit_hash("git log -1 --oneline", output), None);
+        assert_eq!(commit_hash("echo commit", output), None);
+        assert_eq!(commit_hash("git log --grep commit", output), None);
+        assert_eq!(commit_hash("git show commit", output), None);
+    }
+
+    #[test]
+    fn test_commit_hash_rejects_other_brackets() {
+        assert_eq!(commit_hash("git commit", "[WARN] nothing to commit"), None);
+        assert_eq!(commit_hash("git commit", "[abc1234]"), None);
+        assert_eq!(commit_hash("git commit", "[main xyz1234] msg"), None);
+    }
+}
@@ -5,9 +5,12 @@
 
 use crate::ClaudeConvo;
 use crate::chain;
+use crate::checkpoint;
 use crate::error::Result;
+use crate::reader::ConversationReader;
 use crate::types::{Conversation, ConversationEntry, MessageRole};
 use std::collections::HashSet;
+use toolpath_convo::{SessionCursor, WatcherCheckpoint};
 
 /// Watches a conversation for new entries.
 ///
@@ -56,6 +59,10 @@ pub struct ConversationWatcher {
     pending_rotations: Vec<(String, String)>,
     /// Cached chain index for incremental successor lookup.
     chain_index: chain::ChainIndex,
+    /// Sessions rotated away from, oldest first.
+    chain: Vec<String>,
+    /// The last entry read from the current file, in file order.
+    last_uuid: Option<String>,
 }
 
 impl ConversationWatcher {
@@ -70,6 +77,8 @@ impl ConversationWatcher {
             successor_checked: false,
             pending_rotations: Vec::new(),
             chain_index: chain::ChainIndex::new(),
+            chain: Vec::new(),
+            last_uuid: None,
         }
     }
 
@@ -150,6 +159,7 @@ impl ConversationWatcher {
         self.seen_uuids.clear();
         self.successor_checked = false;
         self.pending_rotations.clear();
+        self.last_uuid = None;
     }
 
     /// Pre-marks entries as seen without returning them.
@@ -178,6 +188,67 @@ impl ConversationWatcher {
         std::mem::take(&mut self.pending_rotations)
     }
 
+    /// Where the watcher is: the current session, the sessions rotated away
+    /// from, and the byte offset just past the last entry read.
+    pub fn checkpoint(&self) -> Result<WatcherCheckpoint> {
+        let path = self
+            .manager
+            .resolver()
+            .conversation_file(&self.project, &self.session_id)?;
+        let offset = match &self.last_uuid {
+            Some(uuid) if path.exists() => checkpoint::offset_after(&path, uuid)?.unwrap_or(0),
+            _ => 0,
+        };
+        let file = if path.exists() {
+            Some(checkpoint::identify(&path, offset)?)
+        } else {
+            None
+        };
+        Ok(WatcherCheckpoint {
+            sessions: vec![SessionCursor {
+                project: self.project.clone(),
+                session_id: self.session_id.clone(),
+                chain: self.chain.clone(),
+                offset,
+                last_uuid: self.last_uuid.clone(),
+                file,
+            }],
+        })
+    }
+
+    /// Resume from a checkpoint taken by a watcher of this session (or of
+    /// an earlier segment of its chain): entries read before it are marked
+    /// seen. A checkpoint without this session leaves the watcher as is.
+    pub fn restore(&mut self, checkpoint: &WatcherCheckpoint) -> Result<()> {
+        let Some(cursor) = checkpoint.session(&self.project, &self.session_id) else {
+            return Ok(());
+        };
+        let path = self
+            .manager
+            .resolver()
+            .conversation_file(&self.project, &cursor.session_id)?;
+        self.session_id = cursor.session_id.clone();
+        self.chain = cursor.chain.clone();
+        self.successor_checked = false;
+        if !path.exists() {
+            return Ok(());
+        }
+
+        let offset = checkpoint::resume_offset(&path, cursor)?;
+        let (unread, _) = ConversationReader::read_from_offset(&path, offset)?;
+        let unread: HashSet<&str> = unread.iter().map(|e| e.uuid.as_str()).collect();
+        let convo = self.manager.read_segment(&self.project, &self.session_id)?;
+        for entry in convo
+            .entries
+            .iter()
+            .filter(|e| !unread.contains(e.uuid.as_str()))
+        {
+            self.seen_uuids.insert(entry.uuid.clone());
+            self.last_uuid = Some(entry.uuid.clone());
+        }
+        Ok(())
+    }
+
     /// Check for and follow a session rotation. Returns `true` if a
     /// successor was found and the watcher switched to it.
     fn follow_rotation(&mut self) -> Result<bool> {
@@ -192,8 +263,11 @@ impl ConversationWatcher {
         if let Some(successor) = self.chain_index.successor_of(&self.session_id) {
             let successor = successor.to_string();
             let old_id = self.session_id.clone();
-            self.pending_rotations.push((old_id, successor.clone()));
+            self.pending_rotations
+                .push((old_id.clone(), successor.clone()));
+            self.chain.push(old_id);
             self.session_id = successor;
+            self.last_uuid = None;
             self.successor_checked = false;
             return Ok(true);
         }
@@ -205,6 +279,7 @@ impl ConversationWatcher {
         let mut new_entries = Vec::new();
 
         for entry in &convo.entries {
+            self.last_uuid = Some(entry.uuid.clone());
             if self.seen_uuids.contains(&entry.uuid) {
                 continue;
             }
@@ -580,4 +655,62 @@ mod tests {
         assert_eq!(convo2.entries.len(), 1);
         assert!(new_entries2.is_empty());
     }
+
+    #[test]
+    fn test_watcher_checkpoint_restore() {
+        let temp = TempDir::new().unwrap();
+        let claude_dir = temp.path().join(".claude");
+        let project_dir = claude_dir.join("projects/-test-project");
+        fs::create_dir_all(&project_dir).unwrap();
+        let line = |uuid: &str, session: &str| {
+            format!(
+                r#"{{"uuid":"{uuid}","type":"user","timestamp":"2024-01-01T00:00:00Z","sessionId":"{session}","message":{{"role":"user","content":"Hi"}}}}"#
+            ) + "\n"
+        };
+        let manager =
+            || ClaudeConvo::with_resolver(PathResolver::new().with_claude_dir(&claude_dir));
+
+        fs::write(
+            project_dir.join("session-a.jsonl"),
+            line("a1", "session-a") + &line("a2", "session-a"),
+        )
+        .unwrap();
+        let mut watcher = ConversationWatcher::new(
+            manager(),
+            "/test/project".to_string(),
+            "session-a".to_string(),
+        );
+        assert_eq!(watcher.poll().unwrap().len(), 2);
+
+        fs::write(
+            project_dir.join("session-b.jsonl"),
+            line("b0", "session-a") + &line("b1", "session-b"),
+        )
+        .unwrap();
+        assert_eq!(watcher.poll().unwrap().len(), 1);
+
+        let state = temp.path().join("watcher.json");
+        watcher.checkpoint().unwrap().save(&state).unwrap();
+        drop(watcher);
+
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .open(project_dir.join("session-b.jsonl"))
+            .unwrap();
+        std::io::Write::write_all(&mut file, line("b2", "session-b").as_bytes()).unwrap();
+
+        // A restarted watcher of the original session resumes on session-b
+        let mut watcher = ConversationWatcher::new(
+            manager(),
+            "/test/project".to_string(),
+            "session-a".to_string(),
+        );
+        let checkpoint = WatcherCheckpoint::load(&state).unwrap().unwrap();
+        assert_eq!(checkpoint.sessions[0].chain, ["session-a"]);
+        watcher.restore(&checkpoint).unwrap();
+        assert_eq!(watcher.session_id(), "session-b");
+        let entries = watcher.poll().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].uuid, "b2");
+    }
 }
@@ -63,6 +63,51 @@ pub enum DeriveSource {
         /// Process all sessions in the project
         #[arg(long)]
         all: bool,
+
+        /// Emit a Graph per session, with each subagent as a nested path.
+        /// With --all, emit one Graph of the project, linking resumed sessions
+        #[arg(long, conflicts_with = "follow")]
+        graph: bool,
+
+        /// With --all --graph, add sessions known only from history.jsonl
+        #[arg(long, requires_all = ["all", "graph"])]
+        history: bool,
+
+        /// Keep watching the session, streaming new steps as JSON lines
+        #[arg(long, conflicts_with = "all")]
+        follow: bool,
+
+        /// With --follow, append steps to this file instead of stdout
+        #[arg(short, long, requires = "follow")]
+        output: Option<PathBuf>,
+
+        /// With --follow, seconds between polls of the session log
+        #[arg(long, default_value = "1", value_parser = parse_interval, requires = "follow")]
+        interval: std::time::Duration,
+
+        /// TOML price table for estimating each step's cost
+        #[arg(long)]
+        prices: Option<PathBuf>,
+
+        /// Write the bytes of attached images and documents to this
+        /// directory, named by SHA-256
+        #[arg(long)]
+        attachments: Option<PathBuf>,
+
+        /// TOML mapping of MCP servers and tools to tool categories
+        #[arg(long)]
+        tool_categories: Option<PathBuf>,
+
+        /// How much of a kind of text to record, as FIELD=POLICY: FIELD is
+        /// text, thinking, tool_output, summary or all; POLICY is full,
+        /// truncate:<n>, digest or omit. Repeatable
+        #[arg(long, value_name = "FIELD=POLICY", value_parser = parse_capture)]
+        capture: Vec<(String, toolpath_claude::capture::Capture)>,
+
+        /// Write the full text of shortened or digest-only fields to this
+        /// directory as <sha256>.txt
+        #[arg(long)]
+        blobs: Option<PathBuf>,
     },
 }
 
@@ -86,7 +131,34 @@ pub fn run(source: DeriveSource, pretty: bool) -> Result<()> {
             project,
             session,
             all,
-        } => run_claude(project, session, all, pretty),
+            graph,
+            history,
+            follow,
+            output,
+            interval,
+            prices,
+            attachments,
+            tool_categories,
+            capture,
+            blobs,
+        } => {
+            let options = ClaudeOptions {
+                prices: load_prices(prices)?,
+                attachments,
+                tool_categories: load_tool_categories(tool_categories)?,
+                capture: capture_policy(capture)?,
+                blobs,
+            };
+            if follow {
+                return run_claude_follow(project, session, output, interval, options);
+            }
+            let output = match (graph, all) {
+                (false, _) => ClaudeOutput::Paths,
+                (true, false) => ClaudeOutput::SessionGraphs,
+                (true, true) => ClaudeOutput::ProjectGraph { history },
+            };
+            run_claude(project, session, all, output, options, pretty)
+        }
     }
 }
 
@@ -123,7 +195,8 @@ fn run_git(
             base,
         };
 
-        let doc = toolpath_git::derive(&repo, &branches, &config)?;
+        let mut doc = toolpath_git::derive(&repo, &branches, &config)?;
+        doc.stamp_version();
 
         let json = if pretty {
             doc.to_json_pretty()?
@@ -180,7 +253,8 @@ fn run_github(
         };
 
         let path = toolpath_github::derive_pull_request(&owner, &repo_name, pr_number, &config)?;
-        let doc = toolpath::v1::Document::Path(path);
+        let mut doc = toolpath::v1::Document::Path(path);
+        doc.stamp_version();
 
         let json = if pretty {
             doc.to_json_pretty()?
@@ -193,9 +267,99 @@ fn run_github(
     }
 }
 
-fn run_claude(project: String, session: Option<String>, all: bool, pretty: bool) -> Result<()> {
+/// What `path derive claude` emits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ClaudeOutput {
+    /// A Path per session.
+    Paths,
+    /// A Graph per session, with its subagents.
+    SessionGraphs,
+    /// One Graph of all sessions, optionally with history-only sessions.
+    ProjectGraph { history: bool },
+}
+
+/// Settings for `derive claude` beyond what to read and emit.
+#[derive(Default)]
+struct ClaudeOptions {
+    prices: Option<toolpath_claude::usage::PriceTable>,
+    /// Where to extract attachment bytes.
+    attachments: Option<PathBuf>,
+    tool_categories: toolpath_claude::ToolCategories,
+    capture: toolpath_claude::capture::CapturePolicy,
+    /// Where to write the full text of fields `capture` shortens.
+    blobs: Option<PathBuf>,
+}
+
+fn run_claude(
+    project: String,
+    session: Option<String>,
+    all: bool,
+    output: ClaudeOutput,
+    options: ClaudeOptions,
+    pretty: bool,
+) -> Result<()> {
     let manager = toolpath_claude::ClaudeConvo::new();
-    run_claude_with_manager(&manager, project, session, all, pretty)
+    run_claude_with_manager(&manager, project, session, all, output, options, pretty)
+}
+
+/// Parse an `--interval` value: a positive, finite number of seconds.
+fn parse_interval(s: &str) -> Result<std::time::Duration, String> {
+    let secs: f64 = s
+        .parse()
+        .map_err(|_| format!("expected a number of seconds, got {:?}", s))?;
+    if secs <= 0.0 {
+        return Err(format!("interval must be positive, got {}", s));
+    }
+    std::time::Duration::try_from_secs_f64(secs)
+        .map_err(|e| format!("invalid interval {}: {}", s, e))
+}
+
+/// Parse a `--capture` value: `<field>=<policy>`.
+fn parse_capture(s: &str) -> Result<(String, toolpath_claude::capture::Capture), String> {
+    let (field, policy) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected FIELD=POLICY, got {:?}", s))?;
+    Ok((field.to_string(), policy.parse()?))
+}
+
+/// The default capture policy with each `--capture` applied in turn.
+fn capture_policy(
+    captures: Vec<(String, toolpath_claude::capture::Capture)>,
+) -> Result<toolpath_claude::capture::CapturePolicy> {
+    let mut policy = toolpath_claude::capture::CapturePolicy::default();
+    for (field, capture) in captures {
+        if field == "all" {
+            for field in ["text", "thinking", "tool_output", "summary"] {
+                policy.set(field, capture).map_err(anyhow::Error::msg)?;
+            }
+        } else {
+            policy.set(&field, capture).map_err(anyhow::Error::msg)?;
+        }
+    }
+    Ok(policy)
+}
+
+/// Read a `--tool-categories` mapping.
+fn load_tool_categories(file: Option<PathBuf>) -> Result<toolpath_claude::ToolCategories> {
+    let Some(file) = file else {
+        return Ok(Default::default());
+    };
+    let content =
+        std::fs::read_to_string(&file).with_context(|| format!("Failed to read {:?}", file))?;
+    toolpath_claude::ToolCategories::from_toml(&content)
+        .with_context(|| format!("Invalid tool categories {:?}", file))
+}
+
+/// Read a `--prices` table.
+fn load_prices(file: Option<PathBuf>) -> Result<Option<toolpath_claude::usage::PriceTable>> {
+    let Some(file) = file else {
+        return Ok(None);
+    };
+    let content =
+        std::fs::read_to_string(&file).with_context(|| format!("Failed to read {:?}", file))?;
+    let table = toolpath_claude::usage::PriceTable::from_toml(&content)
+        .with_context(|| format!("Invalid price table {:?}", file))?;
+    Ok(Some(table))
 }
 
 fn run_claude_with_manager(
@@ -203,34 +367,106 @@ fn run_claude_with_manager(
     project: String,
     session: Option<String>,
     all: bool,
+    output: ClaudeOutput,
+    options: ClaudeOptions,
     pretty: bool,
 ) -> Result<()> {
     let config = toolpath_claude::derive::DeriveConfig {
         project_path: Some(project.clone()),
-        include_thinking: false,
+        file_history: Some(manager.resolver().clone()),
+        repository: project_repository(&project),
+        prices: options.prices,
+        tool_categories: options.tool_categories,
+        capture: options.capture,
+        blobs: options
+            .blobs
+            .as_ref()
+            .map(toolpath_claude::capture::BlobDir::new),
+        ..Default::default()
     };
 
-    let docs: Vec<toolpath::v1::Path> = if let Some(session_id) = session {
-        let convo = manager
-            .read_conversation(&project, &session_id)
-            .map_err(|e| anyhow::anyhow!("{}", e))?;
-        vec![toolpath_claude::derive::derive_path(&convo, &config)]
+    let convos = if let Some(session_id) = session {
+        vec![
+            manager
+                .read_conversation(&project, &session_id)
+                .map_err(|e| anyhow::anyhow!("{}", e))?,
+        ]
     } else if all {
-        let convos = manager
+        manager
             .read_all_conversations(&project)
-            .map_err(|e| anyhow::anyhow!("{}", e))?;
-        toolpath_claude::derive::derive_project(&convos, &config)
+            .map_err(|e| anyhow::anyhow!("{}", e))?
     } else {
         // Default: most recent conversation
-        let convo = manager
-            .most_recent_conversation(&project)
-            .map_err(|e| anyhow::anyhow!("{}", e))?
-            .ok_or_else(|| anyhow::anyhow!("No conversations found for project: {}", project))?;
-        vec![toolpath_claude::derive::derive_path(&convo, &config)]
+        vec![
+            manager
+                .most_recent_conversation(&project)
+                .map_err(|e| anyhow::anyhow!("{}", e))?
+                .ok_or_else(|| {
+                    anyhow::anyhow!("No conversations found for project: {}", project)
+                })?,
+        ]
+    };
+
+    if let Some(dir) = &options.attachments {
+        for convo in &convos {
+            toolpath_claude::attachments::extract(convo, dir)
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+        }
+    }
+
+    let docs: Vec<toolpath::v1::Document> = match output {
+        ClaudeOutput::Paths => toolpath_claude::derive::derive_project(&convos, &config)
+            .into_iter()
+            .map(toolpath::v1::Document::Path)
+            .collect(),
+        ClaudeOutput::SessionGraphs => convos
+            .iter()
+            .map(|convo| {
+                let subagents = manager
+                    .read_subagents(&project, convo)
+                    .map_err(|e| anyhow::anyhow!("{}", e))?;
+                Ok(toolpath::v1::Document::Graph(
+                    toolpath_claude::derive::derive_graph(convo, &subagents, &config),
+                ))
+            })
+            .collect::<Result<_>>()?,
+        ClaudeOutput::ProjectGraph { history } => {
+            let history = if history {
+                manager
+                    .read_history()
+                    .map_err(|e| anyhow::anyhow!("{}", e))?
+                    .into_iter()
+                    .filter(|h| {
+                        h.project.as_deref().is_some_and(|p| {
+                            toolpath_claude::PathResolver::same_project(p, &project)
+                        })
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            if let Some(dir) = &options.attachments {
+                let images = history
+                    .iter()
+                    .flat_map(|h| h.images().into_iter().map(|(_, image)| image));
+                toolpath_claude::attachments::write_all(images, dir)
+                    .map_err(|e| anyhow::anyhow!("{}", e))?;
+            }
+            vec![toolpath::v1::Document::Graph(
+                toolpath_claude::derive::derive_project_graph(&convos, &history, &config),
+            )]
+        }
     };
 
-    for path in &docs {
-        let doc = toolpath::v1::Document::Path(path.clone());
+    if let Some(blobs) = &config.blobs
+        && let Some(e) = blobs.take_error()
+    {
+        return Err(anyhow::Error::new(e)
+            .context(format!("Failed to write text blobs to {:?}", blobs.dir())));
+    }
+
+    for mut doc in docs {
+        doc.stamp_version();
         let json = if pretty {
             doc.to_json_pretty()?
         } else {
@@ -242,6 +478,220 @@ fn run_claude_with_manager(
     Ok(())
 }
 
+fn run_claude_follow(
+    project: String,
+    session: Option<String>,
+    output: Option<PathBuf>,
+    interval: std::time::Duration,
+    options: ClaudeOptions,
+) -> Result<()> {
+    #[cfg(target_os = "emscripten")]
+    {
+        let _ = (project, session, output, interval, options);
+        anyhow::bail!("'path derive claude --follow' requires a native environment");
+    }
+
+    #[cfg(not(target_os = "emscripten"))]
+    {
+        let manager = toolpath_claude::ClaudeConvo::new();
+        let (stop_tx, stop) = std::sync::mpsc::channel();
+        ctrlc::set_handler(move || {
+            let _ = stop_tx.send(());
+        })
+        .context("Failed to install the Ctrl-C handler")?;
+        let mut follow = Follow {
+            interval,
+            max_hold: MAX_HOLD,
+            written: std::collections::HashSet::new(),
+            stop,
+        };
+        match output {
+            Some(file) => {
+                follow.written = written_step_ids(&file)?;
+                let mut out = std::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&file)
+                    .with_context(|| format!("Failed to open {:?}", file))?;
+                follow_claude(&manager, project, session, &mut out, options, follow)
+            }
+            None => follow_claude(
+                &manager,
+                project,
+                session,
+                &mut std::io::stdout(),
+                options,
+                follow,
+            ),
+        }
+    }
+}
+
+/// How long `--follow` holds entries back waiting for tool results before
+/// deriving them anyway. Tool calls themselves time out well before this.
+#[cfg(not(target_os = "emscripten"))]
+const MAX_HOLD: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+
+/// How [`follow_claude`] polls, and when it stops.
+#[cfg(not(target_os = "emscripten"))]
+struct Follow {
+    interval: std::time::Duration,
+    /// Derive entries held back for tool results after this long.
+    max_hold: std::time::Duration,
+    /// Step IDs already in the output, from an earlier run; not written again.
+    written: std::collections::HashSet<String>,
+    /// Signalled on Ctrl-C: poll once more, flush held-back entries and stop.
+    stop: std::sync::mpsc::Receiver<()>,
+}
+
+/// IDs of the steps already written to a `--follow` output file, if it exists.
+#[cfg(not(target_os = "emscripten"))]
+fn written_step_ids(file: &std::path::Path) -> Result<std::collections::HashSet<String>> {
+    let content = match std::fs::read_to_string(file) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Default::default()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", file)),
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter_map(|step| step["step"]["id"].as_str().map(str::to_string))
+        .collect())
+}
+
+/// Tail a Claude session, writing each new step as a line of JSON, until
+/// `follow.stop` is signalled (after which held-back entries are flushed).
+#[cfg(not(target_os = "emscripten"))]
+fn follow_claude(
+    manager: &toolpath_claude::ClaudeConvo,
+    project: String,
+    session: Option<String>,
+    out: &mut dyn std::io::Write,
+    options: ClaudeOptions,
+    mut follow: Follow,
+) -> Result<()> {
+    let session_id = match session {
+        Some(id) => id,
+        None => manager
+            .list_conversation_metadata(&project)
+            .map_err(|e| anyhow::anyhow!("{}", e))?
+            .into_iter()
+            .next()
+            .map(|m| m.session_id)
+            .ok_or_else(|| anyhow::anyhow!("No conversations found for project: {}", project))?,
+    };
+    let config = toolpath_claude::derive::DeriveConfig {
+        project_path: Some(project.clone()),
+        file_history: Some(manager.resolver().clone()),
+        repository: project_repository(&project),
+        prices: options.prices,
+        tool_categories: options.tool_categories,
+        capture: options.capture,
+        blobs: options
+            .blobs
+            .as_ref()
+            .map(toolpath_claude::capture::BlobDir::new),
+        ..Default::default()
+    };
+
+    let mut watcher =
+        toolpath_claude::ConversationWatcher::new(manager.clone(), project, session_id.clone());
+    let mut deriver = toolpath_claude::derive::IncrementalDeriver::new(session_id, config);
+    eprintln!("Following {} (Ctrl-C to stop)", deriver.path_id());
+
+    let mut stopping = false;
+    let mut held_since: Option<std::time::Instant> = None;
+    loop {
+        let (convo, entries) = watcher
+            .poll_with_full()
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        for (from, to) in watcher.take_pending_rotations() {
+            eprintln!("Session rotated: {} -> {}", from, to);
+            deriver.rotate(&from, &to);
+        }
+        if let Some(dir) = &options.attachments {
+            let sources = entries
+                .iter()
+                .filter_map(|e| e.message.as_ref())
+                .flat_map(|m| m.media().into_iter().map(|media| media.source.clone()));
+            toolpath_claude::attachments::write_all(sources, dir)
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+        }
+        deriver.merge_snapshots(convo.file_snapshots);
+        let mut steps = deriver.push(entries);
+
+        if !steps.is_empty() || !deriver.is_waiting() {
+            held_since = None;
+        }
+        if stopping {
+            steps.extend(deriver.flush());
+        } else if deriver.is_waiting()
+            && held_since
+                .get_or_insert_with(std::time::Instant::now)
+                .elapsed()
+                >= follow.max_hold
+        {
+            eprintln!(
+                "No tool results after {:?}; deriving anyway",
+                follow.max_hold
+            );
+            steps.extend(deriver.flush());
+            held_since = None;
+        }
+        if let Some(blobs) = &deriver.config().blobs
+            && let Some(e) = blobs.take_error()
+        {
+            return Err(anyhow::Error::new(e)
+                .context(format!("Failed to write text blobs to {:?}", blobs.dir())));
+        }
+        for step in &steps {
+            if follow.written.insert(step.step.id.clone()) {
+                writeln!(out, "{}", serde_json::to_string(step)?)?;
+            }
+        }
+        out.flush()?;
+
+        if stopping {
+            return Ok(());
+        }
+        match follow.stop.recv_timeout(follow.interval) {
+            Ok(()) => stopping = true,
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                std::thread::sleep(follow.interval)
+            }
+        }
+    }
+}
+
+/// The git repository containing `project`, for resolving commits made
+/// during a Claude session to full revisions.
+fn project_repository(project: &str) -> Option<Box<dyn toolpath_claude::derive::RevisionResolver>> {
+    #[cfg(target_os = "emscripten")]
+    {
+        let _ = project;
+        None
+    }
+
+    #[cfg(not(target_os = "emscripten"))]
+    {
+        let repo = git2::Repository::discover(project).ok()?;
+        Some(Box::new(GitRevisions(repo)))
+    }
+}
+
+#[cfg(not(target_os = "emscripten"))]
+struct GitRevisions(git2::Repository);
+
+#[cfg(not(target_os = "emscripten"))]
+impl toolpath_claude::derive::RevisionResolver for GitRevisions {
+    fn resolve(&self, short: &str) -> Option<String> {
+        let commit = self.0.revparse_single(short).ok()?.peel_to_commit().ok()?;
+        let id = commit.id().to_string();
+        id.starts_with(short).then_some(id)
+    }
+}
+
 #[cfg(all(test, not(target_os = "emscripten")))]
 mod tests {
     use super::*;
@@ -364,11 +814,97 @@ mod tests {
             "/test/project".to_string(),
             Some("session-abc".to_string()),
             false,
+            ClaudeOutput::Paths,
+            ClaudeOptions::default(),
             false,
         );
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_run_claude_extracts_attachments() {
+        let (temp, manager) = setup_claude_manager();
+        let entry = r#"{"type":"user","uuid":"uuid-img","timestamp":"2024-01-01T00:00:00Z","message":{"role":"user","content":[{"type":"image","source":{"type":"base64","media_type":"image/png","data":"Zm9v"}}]}}"#;
+        std::fs::write(
+            temp.path()
+                .join(".claude/projects/-test-project/session-img.jsonl"),
+            format!("{}\n", entry),
+        )
+        .unwrap();
+
+        let dir = temp.path().join("attachments");
+        run_claude_with_manager(
+            &manager,
+            "/test/project".to_string(),
+            Some("session-img".to_string()),
+            false,
+            ClaudeOutput::Paths,
+            ClaudeOptions {
+                attachments: Some(dir.clone()),
+                ..Default::default()
+            },
+            false,
+        )
+        .unwrap();
+        let files: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(files.len(), 1);
+        let file = files[0].as_ref().unwrap().path();
+        assert_eq!(file.extension().unwrap(), "png");
+        assert_eq!(std::fs::read(file).unwrap(), b"foo");
+    }
+
+    #[test]
+    fn test_run_claude_writes_blobs() {
+        let (temp, manager) = setup_claude_manager();
+        let dir = temp.path().join("blobs");
+        run_claude_with_manager(
+            &manager,
+            "/test/project".to_string(),
+            Some("session-abc".to_string()),
+            false,
+            ClaudeOutput::Paths,
+            ClaudeOptions {
+                capture: capture_policy(vec![parse_capture("text=digest").unwrap()]).unwrap(),
+                blobs: Some(dir.clone()),
+                ..Default::default()
+            },
+            false,
+        )
+        .unwrap();
+        let mut texts: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|f| std::fs::read_to_string(f.unwrap().path()).unwrap())
+            .collect();
+        texts.sort();
+        assert_eq!(texts, ["Hello", "Hi there"]);
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!(
+            parse_interval("0.5"),
+            Ok(std::time::Duration::from_millis(500))
+        );
+        for bad in ["-1", "0", "NaN", "inf", "soon"] {
+            assert!(parse_interval(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_capture_policy() {
+        use toolpath_claude::capture::Capture;
+        let policy = capture_policy(vec![
+            parse_capture("all=full").unwrap(),
+            parse_capture("thinking=truncate:100").unwrap(),
+        ])
+        .unwrap();
+        assert_eq!(policy.text, Capture::Full);
+        assert_eq!(policy.thinking, Capture::Truncate(100));
+        assert!(parse_capture("text").is_err());
+        assert!(parse_capture("text=some").is_err());
+        assert!(capture_policy(vec![parse_capture("output=full").unwrap()]).is_err());
+    }
+
     #[test]
     fn test_run_claude_session_pretty() {
         let (_temp, manager) = setup_claude_manager();
@@ -377,6 +913,8 @@ mod tests {
             "/test/project".to_string(),
             Some("session-abc".to_string()),
             false,
+            ClaudeOutput::Paths,
+            ClaudeOptions::default(),
             true,
         );
         assert!(result.is_ok());
@@ -385,19 +923,284 @@ mod tests {
     #[test]
     fn test_run_claude_most_recent() {
         let (_temp, manager) = setup_claude_manager();
-        let result =
-            run_claude_with_manager(&manager, "/test/project".to_string(), None, false, false);
+        let result = run_claude_with_manager(
+            &manager,
+            "/test/project".to_string(),
+            None,
+            false,
+            ClaudeOutput::Paths,
+            ClaudeOptions::default(),
+            false,
+        );
         assert!(result.is_ok());
     }
 
     #[test]
     fn test_run_claude_all() {
         let (_temp, manager) = setup_claude_manager();
-        let result =
-            run_claude_with_manager(&manager, "/test/project".to_string(), None, true, false);
+        let result = run_claude_with_manager(
+            &manager,
+            "/test/project".to_string(),
+            None,
+            true,
+            ClaudeOutput::Paths,
+            ClaudeOptions::default(),
+            false,
+        );
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_run_claude_graph() {
+        let (_temp, manager) = setup_claude_manager();
+        let result = run_claude_with_manager(
+            &manager,
+            "/test/project".to_string(),
+            Some("session-abc".to_string()),
+            false,
+            ClaudeOutput::SessionGraphs,
+            ClaudeOptions::default(),
+            false,
+        );
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_run_claude_project_graph() {
+        let (_temp, manager) = setup_claude_manager();
+        let result = run_claude_with_manager(
+            &manager,
+            "/test/project".to_string(),
+            None,
+            true,
+            ClaudeOutput::ProjectGraph { history: true },
+            ClaudeOptions::default(),
+            false,
+        );
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_load_prices() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let file = temp.path().join("prices.toml");
+        std::fs::write(&file, "[models.claude]\ninput = 3.0\noutput = 15.0\n").unwrap();
+
+        let table = load_prices(Some(file.clone())).unwrap().unwrap();
+        assert_eq!(table.price("claude-sonnet-4").unwrap().output, 15.0);
+        assert!(load_prices(None).unwrap().is_none());
+
+        std::fs::write(&file, "[models.claude]\ninput = \"free\"\n").unwrap();
+        assert!(load_prices(Some(file)).is_err());
+    }
+
+    #[test]
+    fn test_load_tool_categories() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let file = temp.path().join("tools.toml");
+        std::fs::write(&file, "[servers]\npostgres = \"file_read\"\n").unwrap();
+
+        let tools = load_tool_categories(Some(file.clone())).unwrap();
+        assert!(tools.category("mcp__postgres__query").is_some());
+        assert!(tools.category("mcp__jira__get_issue").is_none());
+        assert!(load_tool_categories(None).unwrap().servers.is_empty());
+
+        std::fs::write(&file, "[servers]\npostgres = \"database\"\n").unwrap();
+        assert!(load_tool_categories(Some(file)).is_err());
+    }
+
+    /// Follow settings that stop after a second poll.
+    fn stopped() -> Follow {
+        let (stop_tx, stop) = std::sync::mpsc::channel();
+        stop_tx.send(()).unwrap();
+        Follow {
+            interval: std::time::Duration::ZERO,
+            max_hold: MAX_HOLD,
+            written: std::collections::HashSet::new(),
+            stop,
+        }
+    }
+
+    #[test]
+    fn test_follow_claude_streams_steps() {
+        let (_temp, manager) = setup_claude_manager();
+        let mut out = Vec::new();
+        follow_claude(
+            &manager,
+            "/test/project".to_string(),
+            Some("session-abc".to_string()),
+            &mut out,
+            ClaudeOptions::default(),
+            stopped(),
+        )
+        .unwrap();
+
+        let lines: Vec<&str> = std::str::from_utf8(&out).unwrap().lines().collect();
+        assert_eq!(lines.len(), 2);
+        let second: toolpath::v1::Step = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(second.step.id, "step-uuid-2");
+        assert_eq!(second.step.parents, vec!["step-uuid-1"]);
+    }
+
+    #[test]
+    fn test_follow_claude_extracts_attachments() {
+        let (temp, manager) = setup_claude_manager();
+        let entry = r#"{"type":"user","uuid":"uuid-img","timestamp":"2024-01-01T00:00:00Z","message":{"role":"user","content":[{"type":"image","source":{"type":"base64","media_type":"image/png","data":"Zm9v"}}]}}"#;
+        std::fs::write(
+            temp.path()
+                .join(".claude/projects/-test-project/session-img.jsonl"),
+            format!("{}\n", entry),
+        )
+        .unwrap();
+
+        let dir = temp.path().join("attachments");
+        follow_claude(
+            &manager,
+            "/test/project".to_string(),
+            Some("session-img".to_string()),
+            &mut Vec::new(),
+            ClaudeOptions {
+                attachments: Some(dir.clone()),
+                ..Default::default()
+            },
+            stopped(),
+        )
+        .unwrap();
+        let files: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(files.len(), 1);
+        assert_eq!(
+            std::fs::read(files[0].as_ref().unwrap().path()).unwrap(),
+            b"foo"
+        );
+    }
+
+    #[test]
+    fn test_follow_claude_categorizes_tools() {
+        let (temp, manager) = setup_claude_manager();
+        let call = r#"{"type":"assistant","uuid":"uuid-mcp","timestamp":"2024-01-01T00:00:00Z","message":{"role":"assistant","content":[{"type":"tool_use","id":"t1","name":"mcp__postgres__query","input":{"sql":"select 1"}}]}}"#;
+        let result = r#"{"type":"user","uuid":"uuid-result","timestamp":"2024-01-01T00:00:01Z","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"t1","content":"ok"}]}}"#;
+        std::fs::write(
+            temp.path()
+                .join(".claude/projects/-test-project/session-mcp.jsonl"),
+            format!("{}\n{}\n", call, result),
+        )
+        .unwrap();
+
+        let mut out = Vec::new();
+        follow_claude(
+            &manager,
+            "/test/project".to_string(),
+            Some("session-mcp".to_string()),
+            &mut out,
+            ClaudeOptions {
+                tool_categories: toolpath_claude::ToolCategories::from_toml(
+                    "[servers]\npostgres = \"file_read\"\n",
+                )
+                .unwrap(),
+                ..Default::default()
+            },
+            stopped(),
+        )
+        .unwrap();
+
+        let steps: Vec<toolpath::v1::Step> = std::str::from_utf8(&out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let call = steps
+            .iter()
+            .find_map(|s| s.change.get("mcp://postgres/t1"))
+            .and_then(|c| c.structural.as_ref())
+            .unwrap();
+        assert_eq!(call.extra["category"], "file_read");
+    }
+
+    #[test]
+    fn test_follow_claude_writes_blobs() {
+        let (temp, manager) = setup_claude_manager();
+        let dir = temp.path().join("blobs");
+        let mut out = Vec::new();
+        follow_claude(
+            &manager,
+            "/test/project".to_string(),
+            Some("session-abc".to_string()),
+            &mut out,
+            ClaudeOptions {
+                capture: capture_policy(vec![parse_capture("text=digest").unwrap()]).unwrap(),
+                blobs: Some(dir.clone()),
+                ..Default::default()
+            },
+            stopped(),
+        )
+        .unwrap();
+
+        let first: toolpath::v1::Step =
+            serde_json::from_str(std::str::from_utf8(&out).unwrap().lines().next().unwrap())
+                .unwrap();
+        assert!(!serde_json::to_string(&first).unwrap().contains("Hello"));
+        let mut texts: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|f| std::fs::read_to_string(f.unwrap().path()).unwrap())
+            .collect();
+        texts.sort();
+        assert_eq!(texts, ["Hello", "Hi there"]);
+    }
+
+    #[test]
+    fn test_follow_claude_skips_written_steps() {
+        let (temp, manager) = setup_claude_manager();
+        let file = temp.path().join("steps.jsonl");
+        let mut out = Vec::new();
+        follow_claude(
+            &manager,
+            "/test/project".to_string(),
+            Some("session-abc".to_string()),
+            &mut out,
+            ClaudeOptions::default(),
+            stopped(),
+        )
+        .unwrap();
+        let first_line = std::str::from_utf8(&out).unwrap().lines().next().unwrap();
+        std::fs::write(&file, format!("{}\n", first_line)).unwrap();
+
+        // A restart appending to the same file writes only the new step.
+        let mut out = Vec::new();
+        follow_claude(
+            &manager,
+            "/test/project".to_string(),
+            Some("session-abc".to_string()),
+            &mut out,
+            ClaudeOptions::default(),
+            Follow {
+                written: written_step_ids(&file).unwrap(),
+                ..stopped()
+            },
+        )
+        .unwrap();
+        let lines: Vec<&str> = std::str::from_utf8(&out).unwrap().lines().collect();
+        assert_eq!(lines.len(), 1);
+        let step: toolpath::v1::Step = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(step.step.id, "step-uuid-2");
+        assert!(
+            written_step_ids(&temp.path().join("missing.jsonl"))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_git_revisions_resolve() {
+        let (dir, repo) = init_temp_repo();
+        let oid = create_commit(&repo, "initial commit", "file.txt", "hello", None);
+        let full = oid.to_string();
+
+        let resolver = project_repository(dir.path().to_str().unwrap()).unwrap();
+        assert_eq!(resolver.resolve(&full[..7]), Some(full.clone()));
+        assert_eq!(resolver.resolve("0000000"), None);
+        assert_eq!(resolver.resolve("HEAD"), None);
+    }
+
     #[test]
     fn test_run_claude_no_conversations() {
         let temp = tempfile::tempdir().unwrap();
@@ -408,8 +1211,15 @@ mod tests {
         let resolver = toolpath_claude::PathResolver::new().with_claude_dir(&claude_dir);
         let manager = toolpath_claude::ClaudeConvo::with_resolver(resolver);
 
-        let result =
-            run_claude_with_manager(&manager, "/empty/project".to_string(), None, false, false);
+        let result = run_claude_with_manager(
+            &manager,
+            "/empty/project".to_string(),
+            None,
+            false,
+            ClaudeOutput::Paths,
+            ClaudeOptions::default(),
+            false,
+        );
         assert!(result.is_err());
         assert!(
             result
@@ -27,6 +27,11 @@ pub enum ListSource {
         /// Project path — if omitted, lists all projects
         #[arg(short, long)]
         project: Option<String>,
+
+        /// Check session logs (of the project, or of all projects) for
+        /// lines that don't parse; fails if any are found
+        #[arg(long)]
+        check: bool,
     },
 }
 
@@ -34,7 +39,7 @@ pub fn run(source: ListSource, json: bool) -> Result<()> {
     match source {
         ListSource::Git { repo, remote } => run_git(repo, remote, json),
         ListSource::Github { repo } => run_github(repo, json),
-        ListSource::Claude { project } => run_claude(project, json),
+        ListSource::Claude { project, check } => run_claude(project, check, json),
     }
 }
 
@@ -159,9 +164,12 @@ fn run_github(repo: String, json: bool) -> Result<()> {
     }
 }
 
-fn run_claude(project: Option<String>, json: bool) -> Result<()> {
+fn run_claude(project: Option<String>, check: bool, json: bool) -> Result<()> {
     let manager = toolpath_claude::ClaudeConvo::new();
 
+    if check {
+        return check_claude_sessions(&manager, project.as_deref(), json);
+    }
     match project {
         None => list_claude_projects(&manager, json),
         Some(project_path) => list_claude_sessions(&manager, &project_path, json),
@@ -170,13 +178,32 @@ fn run_claude(project: Option<String>, json: bool) -> Result<()> {
 
 fn list_claude_projects(manager: &toolpath_claude::ClaudeConvo, json: bool) -> Result<()> {
     let projects = manager
-        .list_projects()
+        .resolver()
+        .project_dirs()
         .map_err(|e| anyhow::anyhow!("{}", e))?;
 
     if json {
         let items: Vec<serde_json::Value> = projects
             .iter()
-            .map(|p| serde_json::json!({ "path": p }))
+            .map(|p| {
+                let (resolution, candidates) = match &p.resolution {
+                    toolpath_claude::Resolution::Cwd => ("cwd", None),
+                    toolpath_claude::Resolution::Filesystem => ("filesystem", None),
+                    toolpath_claude::Resolution::Ambiguous { candidates } => {
+                        ("ambiguous", Some(candidates))
+                    }
+                    toolpath_claude::Resolution::Unsanitized => ("unsanitized", None),
+                };
+                let mut item = serde_json::json!({
+                    "path": p.path,
+                    "dir": p.dir,
+                    "resolution": resolution,
+                });
+                if let Some(candidates) = candidates {
+                    item["candidates"] = serde_json::json!(candidates);
+                }
+                item
+            })
             .collect();
         let output = serde_json::json!({
             "source": "claude",
@@ -190,7 +217,18 @@ fn list_claude_projects(manager: &toolpath_claude::ClaudeConvo, json: bool) -> R
             println!("  (none)");
         } else {
             for p in &projects {
-                println!("  {}", p);
+                match &p.resolution {
+                    toolpath_claude::Resolution::Ambiguous { candidates } => println!(
+                        "  {}  (ambiguous: {} paths match {})",
+                        p.path,
+                        candidates.len(),
+                        p.name
+                    ),
+                    toolpath_claude::Resolution::Unsanitized => {
+                        println!("  {}  (guessed from {})", p.path, p.name)
+                    }
+                    _ => println!("  {}", p.path),
+                }
             }
         }
     }
@@ -242,6 +280,87 @@ fn list_claude_sessions(
     Ok(())
 }
 
+fn check_claude_sessions(
+    manager: &toolpath_claude::ClaudeConvo,
+    project: Option<&str>,
+    json: bool,
+) -> Result<()> {
+    let projects = match project {
+        Some(project) => vec![project.to_string()],
+        None => manager
+            .list_projects()
+            .map_err(|e| anyhow::anyhow!("{}", e))?,
+    };
+
+    let mut checks = Vec::new();
+    for project in &projects {
+        let mut sessions = manager
+            .resolver()
+            .list_conversations(project)
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        sessions.sort();
+        for session in sessions {
+            let path = manager
+                .resolver()
+                .conversation_file(project, &session)
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            let check = toolpath_claude::ConversationReader::check(&path)
+                .map_err(|e| anyhow::anyhow!("{}", e))?;
+            checks.push((project.as_str(), session, check));
+        }
+    }
+    let failing = checks.iter().filter(|(_, _, c)| !c.is_clean()).count();
+
+    if json {
+        let items: Vec<serde_json::Value> = checks
+            .iter()
+            .map(|(project, session, check)| {
+                serde_json::json!({
+                    "project": project,
+                    "session_id": session,
+                    "entries": check.entries,
+                    "lines": check.lines,
+                    "diagnostics": check.diagnostics,
+                })
+            })
+            .collect();
+        let output = serde_json::json!({
+            "source": "claude",
+            "sessions": items,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        println!(
+            "Checked {} sessions: {} with malformed lines",
+            checks.len(),
+            failing
+        );
+        for (project, session, check) in checks.iter().filter(|(_, _, c)| !c.is_clean()) {
+            println!();
+            println!(
+                "  {} {}  {} of {} lines malformed",
+                project,
+                session,
+                check.diagnostics.len(),
+                check.lines
+            );
+            for diagnostic in &check.diagnostics {
+                println!("    {}", diagnostic);
+                println!("      {}", diagnostic.excerpt);
+            }
+        }
+    }
+
+    if failing > 0 {
+        anyhow::bail!(
+            "{} of {} sessions have malformed lines",
+            failing,
+            checks.len()
+        );
+    }
+    Ok(())
+}
+
 #[cfg(not(target_os = "emscripten"))]
 fn truncate(s: &str, max: usize) -> String {
     if s.chars().count() <= max {
@@ -416,4 +535,24 @@ mod tests {
         let result = list_claude_projects(&manager, false);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_check_claude_sessions_clean() {
+        let (_temp, manager) = setup_claude_manager();
+        assert!(check_claude_sessions(&manager, Some("/test/project"), false).is_ok());
+        assert!(check_claude_sessions(&manager, None, true).is_ok());
+    }
+
+    #[test]
+    fn test_check_claude_sessions_malformed() {
+        let (temp, manager) = setup_claude_manager();
+        std::fs::write(
+            temp.path()
+                .join(".claude/projects/-test-project/session-bad.jsonl"),
+            "{\"type\":\"user\",\"uuid\":\"u1\"}\n{\"type\":\"us",
+        )
+        .unwrap();
+        let err = check_claude_sessions(&manager, Some("/test/project"), false).unwrap_err();
+        assert!(err.to_string().contains("1 of 2 sessions"));
+    }
 }
@@ -26,7 +26,8 @@ pub fn run(inputs: Vec<String>, title: Option<String>, pretty: bool) -> Result<(
         extract_paths(doc, &mut all_paths);
     }
 
-    let doc = merge_into_graph(all_paths, title);
+    let mut doc = merge_into_graph(all_paths, title);
+    doc.stamp_version();
 
     let json = if pretty {
         doc.to_json_pretty()?
@@ -51,6 +52,7 @@ fn extract_paths(doc: Document, paths: &mut Vec<PathOrRef>) {
             // Wrap a bare step in a minimal path
             let step_id = s.step.id.clone();
             let path = toolpath::v1::Path {
+                version: None,
                 path: toolpath::v1::PathIdentity {
                     id: format!("path-{}", step_id),
                     base: None,
@@ -69,6 +71,7 @@ fn merge_into_graph(paths: Vec<PathOrRef>, title: Option<String>) -> Document {
     let graph_id = format!("graph-merged-{}", paths.len());
 
     Document::Graph(Graph {
+        version: None,
         graph: GraphIdentity { id: graph_id },
         paths,
         meta: title.map(|t| GraphMeta {
@@ -91,6 +94,7 @@ mod tests {
     fn make_path(id: &str, steps: Vec<Step>) -> Path {
         let head = steps.last().map(|s| s.step.id.clone()).unwrap_or_default();
         Path {
+            version: None,
             path: PathIdentity {
                 id: id.to_string(),
                 base: Some(Base::vcs("github:org/repo", "abc123")),
@@ -123,6 +127,7 @@ mod tests {
         let p1 = make_path("p1", vec![make_step("s1", "human:alex")]);
         let p2 = make_path("p2", vec![make_step("s2", "agent:claude")]);
         let graph = Graph {
+            version: None,
             graph: GraphIdentity {
                 id: "g1".to_string(),
             },
@@ -155,6 +160,7 @@ mod tests {
     fn test_extract_paths_from_graph_with_refs() {
         let p1 = make_path("p1", vec![make_step("s1", "human:alex")]);
         let graph = Graph {
+            version: None,
             graph: GraphIdentity {
                 id: "g1".to_string(),
             },
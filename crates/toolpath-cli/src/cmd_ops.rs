@@ -0,0 +1,272 @@
+use anyhow::{Context, Result, bail};
+use clap::Args;
+use std::path::PathBuf;
+use toolpath::v1::{Document, Path, PathOrRef, ops};
+
+#[derive(Args, Debug)]
+pub struct OpArgs {
+    /// Input file
+    #[arg(short, long)]
+    input: PathBuf,
+
+    /// Step to revert or pick
+    #[arg(short, long)]
+    step: String,
+
+    /// Actor recorded on the new step (e.g. "human:alex")
+    #[arg(long)]
+    actor: String,
+
+    /// ID for the new step (defaults to <step>-revert / <step>-pick)
+    #[arg(long)]
+    id: Option<String>,
+
+    /// Timestamp for the new step (defaults to now)
+    #[arg(long)]
+    timestamp: Option<String>,
+
+    /// Directory holding artifact content before the path's first step
+    #[arg(long)]
+    base_dir: Option<PathBuf>,
+
+    /// Path ID to operate on when the input is a Graph
+    #[arg(long)]
+    path: Option<String>,
+
+    /// Output file (writes to stdout if not provided)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Op {
+    Revert,
+    Pick,
+}
+
+pub fn run(op: Op, args: OpArgs, pretty: bool) -> Result<()> {
+    let content = std::fs::read_to_string(&args.input)
+        .with_context(|| format!("Failed to read {:?}", args.input))?;
+    let mut doc = Document::from_json(&content)
+        .with_context(|| format!("Failed to parse {:?}", args.input))?;
+
+    let path = select_path(&mut doc, args.path.as_deref())?;
+    let config = ops::OpConfig {
+        actor: args.actor,
+        timestamp: args
+            .timestamp
+            .unwrap_or_else(|| chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string()),
+        step_id: args.id,
+        base: match &args.base_dir {
+            Some(dir) => read_base(dir, path, &args.step)?,
+            None => Default::default(),
+        },
+    };
+
+    let result = match op {
+        Op::Revert => ops::revert_step(path, &args.step, &config),
+        Op::Pick => ops::cherry_pick(path, &args.step, &config),
+    };
+    let new_id = match result {
+        Ok(id) => id,
+        Err(ops::OpError::Conflicts(conflicts)) => {
+            for conflict in &conflicts {
+                eprintln!("Conflict: {}", conflict);
+            }
+            bail!(
+                "{} conflicting artifact(s); document unchanged",
+                conflicts.len()
+            );
+        }
+        Err(e) => bail!("{}", e),
+    };
+
+    doc.stamp_version();
+    let json = if pretty {
+        doc.to_json_pretty()?
+    } else {
+        doc.to_json()?
+    };
+    match &args.output {
+        Some(out) => {
+            std::fs::write(out, &json).with_context(|| format!("Failed to write {:?}", out))?;
+        }
+        None => println!("{}", json),
+    }
+    eprintln!("Created step {}", new_id);
+    Ok(())
+}
+
+/// The Path to operate on: the document itself, or an inline path of a
+/// Graph (selected by ID when there is more than one).
+fn select_path<'a>(doc: &'a mut Document, id: Option<&str>) -> Result<&'a mut Path> {
+    match doc {
+        Document::Path(p) => Ok(p),
+        Document::Graph(g) => {
+            let mut inline: Vec<&mut Path> = g
+                .paths
+                .iter_mut()
+                .filter_map(|p| match p {
+                    PathOrRef::Path(p) => Some(p.as_mut()),
+                    PathOrRef::Ref(_) => None,
+                })
+                .collect();
+            match id {
+                Some(id) => inline
+                    .into_iter()
+                    .find(|p| p.path.id == id)
+                    .with_context(|| format!("No inline path with ID {}", id)),
+                None if inline.len() == 1 => Ok(inline.remove(0)),
+                None => bail!(
+                    "Graph has {} inline paths; choose one with --path",
+                    inline.len()
+                ),
+            }
+        }
+        Document::Step(_) => bail!("Expected a Path or Graph document, got a Step"),
+    }
+}
+
+/// Base content for the target step's artifacts that exist under `dir`.
+/// Artifact keys are taken relative to the path's `file://` base (or with a
+/// `file://` scheme stripped); other URI schemes are not files and are
+/// skipped.
+fn read_base(
+    dir: &std::path::Path,
+    path: &Path,
+    step_id: &str,
+) -> Result<std::collections::HashMap<String, String>> {
+    let mut base = std::collections::HashMap::new();
+    let Some(step) = path.steps.iter().find(|s| s.step.id == step_id) else {
+        return Ok(base);
+    };
+    let root = path
+        .path
+        .base
+        .as_ref()
+        .and_then(|b| b.uri.strip_prefix("file://"));
+    for artifact in step.change.keys() {
+        let Some(relative) = base_relative(artifact, root)? else {
+            continue;
+        };
+        let file = dir.join(relative);
+        if file.is_file() {
+            let text = std::fs::read_to_string(&file)
+                .with_context(|| format!("Failed to read {:?}", file))?;
+            base.insert(artifact.clone(), text);
+        }
+    }
+    Ok(base)
+}
+
+/// Where `artifact` lives relative to `--base-dir`: its key with the project
+/// `root` removed. `None` for a non-file URI; an error for a key that would
+/// land outside the directory.
+fn base_relative(artifact: &str, root: Option<&str>) -> Result<Option<PathBuf>> {
+    let key = match artifact.strip_prefix("file://") {
+        Some(key) => key,
+        None if artifact.contains("://") => return Ok(None),
+        None => artifact,
+    };
+    let key = std::path::Path::new(key);
+    let relative = match root.and_then(|root| key.strip_prefix(root).ok()) {
+        Some(relative) => relative,
+        None => key,
+    };
+    let inside = relative.components().all(|c| {
+        matches!(
+            c,
+            std::path::Component::Normal(_) | std::path::Component::CurDir
+        )
+    });
+    if !inside {
+        bail!(
+            "Artifact {:?} is outside the path's base; can't read it from --base-dir",
+            artifact
+        );
+    }
+    Ok(Some(relative.to_path_buf()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use toolpath::v1::{Graph, Step};
+
+    fn sample_path() -> Path {
+        let mut path = Path::new("p1", None, "s2");
+        path.steps = vec![
+            Step::new("s1", "human:alex", "2026-01-29T10:00:00Z")
+                .with_raw_change("f.txt", "@@ -0,0 +1,2 @@\n+a\n+b\n"),
+            Step::new("s2", "agent:claude", "2026-01-29T10:01:00Z")
+                .with_parent("s1")
+                .with_raw_change("f.txt", "@@ -1,2 +1,2 @@\n a\n-b\n+B\n"),
+        ];
+        path
+    }
+
+    #[test]
+    fn test_select_path_graph_requires_id_when_ambiguous() {
+        let mut second = sample_path();
+        second.path.id = "p2".into();
+        let mut graph = Graph::new("g1");
+        graph.paths = vec![
+            PathOrRef::Path(Box::new(sample_path())),
+            PathOrRef::Path(Box::new(second)),
+        ];
+        let mut doc = Document::Graph(graph);
+        assert!(select_path(&mut doc, None).is_err());
+        assert_eq!(select_path(&mut doc, Some("p2")).unwrap().path.id, "p2");
+        assert!(select_path(&mut doc, Some("p3")).is_err());
+    }
+
+    #[test]
+    fn test_read_base_only_existing_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("f.txt"), "x\n").unwrap();
+        let base = read_base(dir.path(), &sample_path(), "s2").unwrap();
+        assert_eq!(base.get("f.txt").map(String::as_str), Some("x\n"));
+        assert!(
+            read_base(dir.path(), &sample_path(), "missing")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_read_base_strips_project_root() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/f.txt"), "x\n").unwrap();
+        let mut path = Path::new(
+            "p1",
+            Some(toolpath::v1::Base {
+                uri: "file:///work/app".into(),
+                ref_str: None,
+            }),
+            "s1",
+        );
+        path.steps = vec![
+            Step::new("s1", "agent:claude", "2026-01-29T10:00:00Z")
+                .with_raw_change("/work/app/src/f.txt", "@@ -1 +1 @@\n-x\n+y\n")
+                .with_raw_change("claude://s/plan", "@@ -0,0 +1 @@\n+p\n"),
+        ];
+        let base = read_base(dir.path(), &path, "s1").unwrap();
+        assert_eq!(base.len(), 1);
+        assert_eq!(base["/work/app/src/f.txt"], "x\n");
+    }
+
+    #[test]
+    fn test_base_relative_rejects_escapes() {
+        let root = Some("/work/app");
+        assert_eq!(
+            base_relative("file:///work/app/a.rs", root).unwrap(),
+            Some(PathBuf::from("a.rs"))
+        );
+        assert_eq!(base_relative("mcp://db/t1", root).unwrap(), None);
+        assert!(base_relative("/etc/passwd", root).is_err());
+        assert!(base_relative("../secret", root).is_err());
+        assert!(base_relative("/work/app/../other/a.rs", root).is_err());
+        assert!(base_relative("src/a.rs", None).is_ok());
+    }
+}
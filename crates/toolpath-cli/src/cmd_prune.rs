@@ -0,0 +1,312 @@
+use anyhow::{Context, Result, bail};
+use clap::Args;
+use std::path::PathBuf;
+use toolpath::v1::{Document, Graph, Path, PathOrRef, prune};
+
+#[derive(Args, Debug)]
+pub struct PruneArgs {
+    /// Input file
+    #[arg(short, long)]
+    input: PathBuf,
+
+    /// Only branches with no step newer than this age (30d, 12h, 2w) or timestamp
+    #[arg(long)]
+    older_than: Option<String>,
+
+    /// Only branches with fewer than this many steps
+    #[arg(long)]
+    smaller_than: Option<usize>,
+
+    /// Only branches where every actor has this kind (e.g. agent)
+    #[arg(long)]
+    actor_kind: Option<String>,
+
+    /// Move matching branches to this file instead of dropping them
+    #[arg(long)]
+    archive: Option<PathBuf>,
+
+    /// Output file (writes to stdout if not provided)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+pub fn run(args: PruneArgs, pretty: bool) -> Result<()> {
+    let content = std::fs::read_to_string(&args.input)
+        .with_context(|| format!("Failed to read {:?}", args.input))?;
+    let mut doc = Document::from_json(&content)
+        .with_context(|| format!("Failed to parse {:?}", args.input))?;
+
+    let config = prune::PruneConfig {
+        older_than: args
+            .older_than
+            .as_deref()
+            .map(|s| parse_cutoff(s, chrono::Utc::now()))
+            .transpose()?,
+        smaller_than: args.smaller_than,
+        actor_kind: args.actor_kind,
+    };
+
+    let (archive_id, paths): (String, Vec<&mut Path>) = match &mut doc {
+        Document::Path(p) => (format!("{}-archive", p.path.id), vec![p]),
+        Document::Graph(g) => (
+            format!("{}-archive", g.graph.id),
+            g.paths
+                .iter_mut()
+                .filter_map(|p| match p {
+                    PathOrRef::Path(p) => Some(p.as_mut()),
+                    PathOrRef::Ref(_) => None,
+                })
+                .collect(),
+        ),
+        Document::Step(_) => bail!("Expected a Path or Graph document, got a Step"),
+    };
+
+    let mut count = 0;
+    let mut archived = Vec::new();
+    for path in paths {
+        match &args.archive {
+            Some(file) => {
+                let url = file.to_string_lossy();
+                for p in prune::archive(path, &config, &url) {
+                    count += p.steps.len();
+                    archived.push(PathOrRef::Path(Box::new(p)));
+                }
+            }
+            None => count += prune::prune(path, &config).len(),
+        }
+    }
+
+    if let Some(file) = &args.archive
+        && !archived.is_empty()
+    {
+        let mut graph = load_archive(file, archive_id)?;
+        for path in archived {
+            let PathOrRef::Path(p) = &path else {
+                continue;
+            };
+            if graph.paths.iter().any(|existing| match existing {
+                PathOrRef::Path(e) => e.path.id == p.path.id,
+                PathOrRef::Ref(_) => false,
+            }) {
+                bail!("Archive {:?} already has a path {:?}", file, p.path.id);
+            }
+            graph.paths.push(path);
+        }
+        let mut archive = Document::Graph(graph);
+        archive.stamp_version();
+        let json = if pretty {
+            archive.to_json_pretty()?
+        } else {
+            archive.to_json()?
+        };
+        std::fs::write(file, json).with_context(|| format!("Failed to write {:?}", file))?;
+    }
+
+    doc.stamp_version();
+    let json = if pretty {
+        doc.to_json_pretty()?
+    } else {
+        doc.to_json()?
+    };
+    match &args.output {
+        Some(out) => {
+            std::fs::write(out, &json).with_context(|| format!("Failed to write {:?}", out))?;
+        }
+        None => println!("{}", json),
+    }
+
+    let verb = if args.archive.is_some() {
+        "Archived"
+    } else {
+        "Pruned"
+    };
+    eprintln!("{} {} dead-end step(s)", verb, count);
+    Ok(())
+}
+
+/// The Graph in an existing archive file, or a new one named `id`, so
+/// branches archived by earlier runs are kept.
+fn load_archive(file: &std::path::Path, id: String) -> Result<Graph> {
+    if !file.exists() {
+        return Ok(Graph::new(id));
+    }
+    let content =
+        std::fs::read_to_string(file).with_context(|| format!("Failed to read {:?}", file))?;
+    match Document::from_json(&content).with_context(|| format!("Failed to parse {:?}", file))? {
+        Document::Graph(graph) => Ok(graph),
+        _ => bail!("Archive {:?} is not a Graph document", file),
+    }
+}
+
+/// Turn `--older-than` into a timestamp cutoff. Accepts a relative age
+/// (`30d`, `12h`, `2w`, `45m`) or an absolute RFC 3339 timestamp.
+fn parse_cutoff(s: &str, now: chrono::DateTime<chrono::Utc>) -> Result<String> {
+    if let Ok(ts) = chrono::DateTime::parse_from_rfc3339(s) {
+        return Ok(ts
+            .with_timezone(&chrono::Utc)
+            .format("%Y-%m-%dT%H:%M:%SZ")
+            .to_string());
+    }
+    let split = s
+        .find(|c: char| !c.is_ascii_digit())
+        .filter(|&i| i > 0)
+        .with_context(|| format!("Invalid age {:?}: expected e.g. 30d or a timestamp", s))?;
+    let n: i64 = s[..split].parse()?;
+    let age = match &s[split..] {
+        "m" => chrono::Duration::minutes(n),
+        "h" => chrono::Duration::hours(n),
+        "d" => chrono::Duration::days(n),
+        "w" => chrono::Duration::weeks(n),
+        unit => bail!("Invalid age unit {:?}: use m, h, d or w", unit),
+    };
+    Ok((now - age).format("%Y-%m-%dT%H:%M:%SZ").to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_cutoff_relative() {
+        let now = chrono::Utc.with_ymd_and_hms(2026, 3, 31, 12, 0, 0).unwrap();
+        assert_eq!(parse_cutoff("30d", now).unwrap(), "2026-03-01T12:00:00Z");
+        assert_eq!(parse_cutoff("2w", now).unwrap(), "2026-03-17T12:00:00Z");
+        assert_eq!(parse_cutoff("6h", now).unwrap(), "2026-03-31T06:00:00Z");
+        assert_eq!(parse_cutoff("90m", now).unwrap(), "2026-03-31T10:30:00Z");
+    }
+
+    #[test]
+    fn test_parse_cutoff_absolute() {
+        let now = chrono::Utc::now();
+        assert_eq!(
+            parse_cutoff("2026-01-01T10:00:00+02:00", now).unwrap(),
+            "2026-01-01T08:00:00Z"
+        );
+    }
+
+    #[test]
+    fn test_parse_cutoff_invalid() {
+        let now = chrono::Utc::now();
+        assert!(parse_cutoff("30", now).is_err());
+        assert!(parse_cutoff("d", now).is_err());
+        assert!(parse_cutoff("3y", now).is_err());
+    }
+
+    #[test]
+    fn test_run_archives_into_graph() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("doc.json");
+        let archive = dir.path().join("dead-ends.json");
+        let output = dir.path().join("pruned.json");
+        std::fs::write(
+            &input,
+            r#"{"Path":{"path":{"id":"p1","head":"s2"},"steps":[
+                {"step":{"id":"s1","actor":"human:alex","timestamp":"2026-01-01T10:00:00Z"},"change":{}},
+                {"step":{"id":"s1a","actor":"agent:claude","timestamp":"2026-01-01T10:01:00Z","parents":["s1"]},"change":{}},
+                {"step":{"id":"s2","actor":"human:alex","timestamp":"2026-01-01T10:02:00Z","parents":["s1"]},"change":{}}
+            ]}}"#,
+        )
+        .unwrap();
+
+        let args = PruneArgs {
+            input,
+            older_than: Some("7d".into()),
+            smaller_than: None,
+            actor_kind: None,
+            archive: Some(archive.clone()),
+            output: Some(output.clone()),
+        };
+        run(args, false).unwrap();
+
+        let archived = std::fs::read_to_string(&archive).unwrap();
+        match Document::from_json(&archived).unwrap() {
+            Document::Graph(g) => {
+                assert_eq!(g.graph.id, "p1-archive");
+                assert_eq!(g.paths.len(), 1);
+            }
+            _ => panic!("Expected Graph"),
+        }
+        let pruned = std::fs::read_to_string(&output).unwrap();
+        assert!(pruned.contains("toolpath.archived"));
+    }
+
+    #[test]
+    fn test_run_appends_to_existing_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("dead-ends.json");
+        let prune_into_archive = |name: &str, dead: &str| {
+            let input = dir.path().join(format!("{}.json", name));
+            std::fs::write(
+                &input,
+                format!(
+                    r#"{{"Path":{{"path":{{"id":"{name}","head":"s2"}},"steps":[
+                        {{"step":{{"id":"s1","actor":"human:alex","timestamp":"2026-01-01T10:00:00Z"}},"change":{{}}}},
+                        {{"step":{{"id":"{dead}","actor":"agent:claude","timestamp":"2026-01-01T10:01:00Z","parents":["s1"]}},"change":{{}}}},
+                        {{"step":{{"id":"s2","actor":"human:alex","timestamp":"2026-01-01T10:02:00Z","parents":["s1"]}},"change":{{}}}}
+                    ]}}}}"#
+                ),
+            )
+            .unwrap();
+            run(
+                PruneArgs {
+                    input,
+                    older_than: None,
+                    smaller_than: None,
+                    actor_kind: None,
+                    archive: Some(archive.clone()),
+                    output: Some(dir.path().join(format!("{}-pruned.json", name))),
+                },
+                false,
+            )
+        };
+
+        prune_into_archive("p1", "s1a").unwrap();
+        prune_into_archive("p2", "s1b").unwrap();
+        let ids = || match Document::from_json(&std::fs::read_to_string(&archive).unwrap()) {
+            Ok(Document::Graph(g)) => {
+                assert_eq!(g.graph.id, "p1-archive");
+                g.paths
+                    .iter()
+                    .map(|p| match p {
+                        PathOrRef::Path(p) => p.path.id.clone(),
+                        PathOrRef::Ref(r) => r.ref_url.clone(),
+                    })
+                    .collect::<Vec<_>>()
+            }
+            _ => panic!("Expected Graph"),
+        };
+        assert_eq!(ids(), vec!["p1-s1a", "p2-s1b"]);
+
+        // Same archived path ID again: rejected, archive untouched.
+        assert!(prune_into_archive("p1", "s1a").is_err());
+        assert_eq!(ids(), vec!["p1-s1a", "p2-s1b"]);
+    }
+
+    #[test]
+    fn test_run_leaves_archive_alone_when_nothing_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("doc.json");
+        let archive = dir.path().join("dead-ends.json");
+        std::fs::write(
+            &input,
+            r#"{"Path":{"path":{"id":"p1","head":"s1"},"steps":[
+                {"step":{"id":"s1","actor":"human:alex","timestamp":"2026-01-01T10:00:00Z"},"change":{}}
+            ]}}"#,
+        )
+        .unwrap();
+        run(
+            PruneArgs {
+                input,
+                older_than: None,
+                smaller_than: None,
+                actor_kind: None,
+                archive: Some(archive.clone()),
+                output: Some(dir.path().join("pruned.json")),
+            },
+            false,
+        )
+        .unwrap();
+        assert!(!archive.exists());
+    }
+}
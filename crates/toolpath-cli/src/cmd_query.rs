@@ -171,6 +171,7 @@ mod tests {
             .with_parent("s2")
             .with_raw_change("src/main.rs", "@@");
         Document::Path(Path {
+            version: None,
             path: PathIdentity {
                 id: "p1".into(),
                 base: Some(Base::vcs("github:org/repo", "abc")),
@@ -209,6 +210,7 @@ mod tests {
         let s1 =
             Step::new("s1", "human:alex", "2026-01-01T00:00:00Z").with_raw_change("f.rs", "@@");
         let path = Path {
+            version: None,
             path: PathIdentity {
                 id: "p1".into(),
                 base: None,
@@ -218,6 +220,7 @@ mod tests {
             meta: None,
         };
         let graph = toolpath::v1::Graph {
+            version: None,
             graph: toolpath::v1::GraphIdentity { id: "g1".into() },
             paths: vec![toolpath::v1::PathOrRef::Path(Box::new(path))],
             meta: None,
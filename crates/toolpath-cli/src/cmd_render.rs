@@ -94,6 +94,7 @@ mod tests {
         let s1 =
             Step::new("s1", "human:alex", "2026-01-01T00:00:00Z").with_raw_change("f.rs", "@@");
         Document::Path(Path {
+            version: None,
             path: PathIdentity {
                 id: "p1".into(),
                 base: None,
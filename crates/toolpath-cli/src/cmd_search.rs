@@ -0,0 +1,232 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::Args;
+use std::path::PathBuf;
+use toolpath_claude::{ClaudeConvo, SearchIndex, SearchQuery};
+
+#[derive(Args, Debug)]
+pub struct SearchArgs {
+    /// Words that must all appear; quote a phrase to match it exactly
+    #[arg(default_value = "")]
+    query: String,
+
+    /// Regular expression over turn text, tool inputs and file paths
+    #[arg(long)]
+    regex: Option<String>,
+
+    /// Only turns with this role (user, assistant, system)
+    #[arg(long)]
+    role: Option<String>,
+
+    /// Only turns that used this tool (e.g. Edit)
+    #[arg(long)]
+    tool: Option<String>,
+
+    /// Only turns whose tools touched a path containing this
+    #[arg(long)]
+    file: Option<String>,
+
+    /// Only turns by a model whose name contains this
+    #[arg(long)]
+    model: Option<String>,
+
+    /// Only projects whose path contains this
+    #[arg(long)]
+    project: Option<String>,
+
+    /// Only turns on or after this date (YYYY-MM-DD) or timestamp
+    #[arg(long)]
+    since: Option<String>,
+
+    /// Only turns up to and including this date (YYYY-MM-DD), or before this timestamp
+    #[arg(long)]
+    until: Option<String>,
+
+    /// Maximum number of hits, newest first
+    #[arg(long, default_value_t = 20)]
+    limit: usize,
+
+    /// Index file [default: ~/.cache/toolpath/claude-search.json]
+    #[arg(long)]
+    index: Option<PathBuf>,
+
+    /// Output as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+pub fn run(args: SearchArgs) -> Result<()> {
+    run_with_manager(&ClaudeConvo::new(), args)
+}
+
+fn run_with_manager(manager: &ClaudeConvo, args: SearchArgs) -> Result<()> {
+    let query = SearchQuery {
+        text: args.query,
+        regex: args.regex,
+        role: args.role,
+        tool: args.tool,
+        file: args.file,
+        model: args.model,
+        project: args.project,
+        since: args
+            .since
+            .as_deref()
+            .map(|s| parse_date(s, false))
+            .transpose()?,
+        until: args
+            .until
+            .as_deref()
+            .map(|s| parse_date(s, true))
+            .transpose()?,
+        limit: Some(args.limit),
+    };
+
+    let location = match args.index {
+        Some(path) => path,
+        None => SearchIndex::default_location(manager.resolver())
+            .map_err(|e| anyhow::anyhow!("{}", e))?,
+    };
+    let mut index = SearchIndex::load(&location)
+        .with_context(|| format!("Failed to read search index {:?}", location))?;
+    index
+        .update(manager.resolver())
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    index
+        .save(&location)
+        .with_context(|| format!("Failed to write search index {:?}", location))?;
+
+    let hits = index.search(&query).map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    if args.json {
+        let output = serde_json::json!({
+            "source": "claude",
+            "hits": hits,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else if hits.is_empty() {
+        println!("No matches");
+    } else {
+        for hit in &hits {
+            println!(
+                "{}  {}  {} {} ({})",
+                hit.timestamp, hit.project, hit.session_id, hit.step_id, hit.role
+            );
+            if !hit.tools.is_empty() {
+                println!("  tools: {}", hit.tools.join(", "));
+            }
+            if !hit.files.is_empty() {
+                println!("  files: {}", hit.files.join(", "));
+            }
+            println!("  {}", hit.snippet);
+            println!();
+        }
+    }
+    Ok(())
+}
+
+/// A timestamp, or a date meaning the start of that day (UTC) — or, when
+/// `end`, the start of the next day, so the whole day is included.
+fn parse_date(s: &str, end: bool) -> Result<DateTime<Utc>> {
+    if let Ok(ts) = DateTime::parse_from_rfc3339(s) {
+        return Ok(ts.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .with_context(|| format!("Invalid date {:?}: expected YYYY-MM-DD or a timestamp", s))?;
+    let date = if end {
+        date.succ_opt().context("Date out of range")?
+    } else {
+        date
+    };
+    Ok(date
+        .and_hms_opt(0, 0, 0)
+        .expect("midnight is valid")
+        .and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: SearchArgs,
+    }
+
+    fn args(argv: &[&str]) -> SearchArgs {
+        Cli::parse_from(std::iter::once("search").chain(argv.iter().copied())).args
+    }
+
+    fn setup() -> (tempfile::TempDir, ClaudeConvo) {
+        let temp = tempfile::tempdir().unwrap();
+        let claude_dir = temp.path().join(".claude");
+        let project_dir = claude_dir.join("projects/-test-project");
+        std::fs::create_dir_all(&project_dir).unwrap();
+        let entry = r#"{"type":"assistant","uuid":"uuid-1","timestamp":"2026-09-02T00:00:00Z","cwd":"/test/project","message":{"role":"assistant","content":[{"type":"tool_use","id":"t1","name":"Edit","input":{"file_path":"src/limit.rs","new_string":"rate limiter"}}]}}"#;
+        std::fs::write(
+            project_dir.join("session-abc.jsonl"),
+            format!("{}\n", entry),
+        )
+        .unwrap();
+        let resolver = toolpath_claude::PathResolver::new()
+            .with_home(temp.path())
+            .with_claude_dir(&claude_dir);
+        (temp, ClaudeConvo::with_resolver(resolver))
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(
+            parse_date("2026-09-01", false).unwrap().to_rfc3339(),
+            "2026-09-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse_date("2026-09-01", true).unwrap().to_rfc3339(),
+            "2026-09-02T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse_date("2026-09-01T12:00:00+02:00", true)
+                .unwrap()
+                .to_rfc3339(),
+            "2026-09-01T10:00:00+00:00"
+        );
+        assert!(parse_date("September", false).is_err());
+    }
+
+    #[test]
+    fn test_search_builds_index() {
+        let (temp, manager) = setup();
+        run_with_manager(
+            &manager,
+            args(&["rate limiter", "--tool", "Edit", "--since", "2026-09-01"]),
+        )
+        .unwrap();
+        assert!(
+            temp.path()
+                .join(".cache/toolpath/claude-search.json")
+                .exists()
+        );
+
+        let index = temp.path().join("index.json");
+        run_with_manager(
+            &manager,
+            args(&[
+                "--json",
+                "--index",
+                index.to_str().unwrap(),
+                "--file",
+                "limit.rs",
+            ]),
+        )
+        .unwrap();
+        assert!(index.exists());
+    }
+
+    #[test]
+    fn test_search_invalid_regex() {
+        let (_temp, manager) = setup();
+        let err = run_with_manager(&manager, args(&["--regex", "("])).unwrap_err();
+        assert!(err.to_string().contains("Invalid search query"));
+    }
+}
@@ -160,7 +160,7 @@ pub enum TrackOp {
 // ============================================================================
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct TrackState {
+pub(crate) struct TrackState {
     version: u32,
     file: String,
     default_actor: String,
@@ -170,6 +170,11 @@ struct TrackState {
     created_at: String,
 }
 
+impl v1::ext::Extension for TrackState {
+    // Predates namespaced keys; kept bare so existing session files load.
+    const KEY: &'static str = "track";
+}
+
 // ============================================================================
 // Helpers
 // ============================================================================
@@ -201,13 +206,14 @@ fn load_session(path: &std::path::Path) -> Result<(v1::Path, TrackState)> {
     let v1::Document::Path(mut path_doc) = doc else {
         anyhow::bail!("session file is not a Path document: {}", path.display());
     };
-    let track_value = path_doc
+    let state: TrackState = path_doc
         .meta
         .as_mut()
-        .and_then(|m| m.extra.remove("track"))
+        .map(|m| m.take_ext::<TrackState>())
+        .transpose()
+        .with_context(|| format!("failed to parse meta.track: {}", path.display()))?
+        .flatten()
         .with_context(|| format!("session file missing meta.track: {}", path.display()))?;
-    let state: TrackState = serde_json::from_value(track_value)
-        .with_context(|| format!("failed to parse meta.track: {}", path.display()))?;
     // Clean up meta if now empty (track was the only content)
     if let Some(meta) = &path_doc.meta
         && meta_is_empty(meta)
@@ -222,11 +228,10 @@ fn load_session(path: &std::path::Path) -> Result<(v1::Path, TrackState)> {
 fn save_session(path: &std::path::Path, doc: &v1::Path, state: &TrackState) -> Result<()> {
     let mut doc = doc.clone();
     let meta = doc.meta.get_or_insert_with(v1::PathMeta::default);
-    meta.extra.insert(
-        "track".to_string(),
-        serde_json::to_value(state).context("failed to serialize track state")?,
-    );
-    let wrapped = v1::Document::Path(doc);
+    meta.set_ext(state.clone())
+        .context("failed to serialize track state")?;
+    let mut wrapped = v1::Document::Path(doc);
+    wrapped.stamp_version();
 
     let dir = path.parent().unwrap_or_else(|| std::path::Path::new("."));
     let tmp = tempfile::NamedTempFile::new_in(dir)
@@ -257,7 +262,8 @@ fn compute_diff(old: &str, new: &str) -> Option<String> {
     }
 }
 
-fn format_output(doc: v1::Document, pretty: bool) -> Result<String> {
+fn format_output(mut doc: v1::Document, pretty: bool) -> Result<String> {
+    doc.stamp_version();
     if pretty {
         doc.to_json_pretty()
     } else {
@@ -730,6 +736,20 @@ mod tests {
         assert!(d.contains("+world"));
     }
 
+    #[test]
+    fn test_compute_diff_parses_back() {
+        let old = "a\nb\nc";
+        let new = "a\nB\nc\nd\n";
+        let raw = compute_diff(old, new).unwrap();
+        let diff = v1::patch::UnifiedDiff::parse(&raw).unwrap();
+        assert_eq!(diff.to_string(), raw);
+        assert_eq!(diff.apply(old).unwrap(), new);
+        assert_eq!(
+            v1::patch::UnifiedDiff::between(old, new, 3).to_string(),
+            raw
+        );
+    }
+
     #[test]
     fn test_compute_diff_empty_strings() {
         assert!(compute_diff("", "").is_none());
@@ -0,0 +1,87 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use toolpath::v1::Document;
+use toolpath::v1::migrate::Migrator;
+
+pub fn run(input: PathBuf, output: Option<PathBuf>, pretty: bool) -> Result<()> {
+    let content =
+        std::fs::read_to_string(&input).with_context(|| format!("Failed to read {:?}", input))?;
+    let (json, from) = upgrade_content(&content, pretty)?;
+
+    match &output {
+        Some(path) => {
+            std::fs::write(path, &json).with_context(|| format!("Failed to write {:?}", path))?;
+        }
+        None => println!("{}", json),
+    }
+
+    if from != toolpath::v1::FORMAT_VERSION {
+        eprintln!(
+            "Upgraded from format {} to {}",
+            from,
+            toolpath::v1::FORMAT_VERSION
+        );
+    }
+    Ok(())
+}
+
+/// Migrate a document to the current format. Returns the serialized
+/// document and the version it was upgraded from.
+fn upgrade_content(content: &str, pretty: bool) -> Result<(String, String)> {
+    let value: serde_json::Value = serde_json::from_str(content).context("Failed to parse JSON")?;
+    let migrated = Migrator::new()
+        .migrate(value)
+        .context("Failed to upgrade document")?;
+    let doc: Document =
+        serde_json::from_value(migrated.value).context("Upgraded document is not valid")?;
+
+    let json = if pretty {
+        doc.to_json_pretty()?
+    } else {
+        doc.to_json()?
+    };
+    Ok((json, migrated.from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_upgrade_unversioned() {
+        let json = r#"{"Step":{"step":{"id":"s1","actor":"human:alex","timestamp":"2026-01-01T00:00:00Z"},"change":{}}}"#;
+        let (out, from) = upgrade_content(json, false).unwrap();
+        assert_eq!(from, "0");
+        let doc = Document::from_json(&out).unwrap();
+        assert_eq!(doc.version(), Some(toolpath::v1::FORMAT_VERSION));
+    }
+
+    #[test]
+    fn test_upgrade_current_is_noop() {
+        let json = r#"{"Path":{"version":"1.0.0","path":{"id":"p1","head":"s1"},"steps":[]}}"#;
+        let (out, from) = upgrade_content(json, false).unwrap();
+        assert_eq!(from, "1.0.0");
+        assert_eq!(out, json);
+    }
+
+    #[test]
+    fn test_upgrade_unknown_version_errors() {
+        let json = r#"{"Path":{"version":"7.0.0","path":{"id":"p1","head":"s1"},"steps":[]}}"#;
+        assert!(upgrade_content(json, false).is_err());
+    }
+
+    #[test]
+    fn test_run_writes_output_file() {
+        let mut input = tempfile::NamedTempFile::new().unwrap();
+        write!(input, r#"{{"Graph":{{"graph":{{"id":"g1"}},"paths":[]}}}}"#).unwrap();
+        input.flush().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("upgraded.json");
+
+        run(input.path().to_path_buf(), Some(out.clone()), true).unwrap();
+
+        let written = std::fs::read_to_string(&out).unwrap();
+        assert!(written.contains("\"version\": \"1.0.0\""));
+    }
+}
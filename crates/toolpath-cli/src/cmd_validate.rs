@@ -1,6 +1,7 @@
 use anyhow::{Context, Result};
 use std::path::PathBuf;
 use toolpath::v1::Document;
+use toolpath::v1::ext::{ExtensionRegistry, Severity};
 
 pub fn run(input: PathBuf) -> Result<()> {
     let content =
@@ -8,19 +9,45 @@ pub fn run(input: PathBuf) -> Result<()> {
     validate_content(&content)
 }
 
+/// Extensions written by the tools in this workspace.
+fn known_extensions() -> ExtensionRegistry {
+    let mut registry = ExtensionRegistry::new();
+    registry
+        .register::<crate::cmd_track::TrackState>()
+        .register::<toolpath::v1::prune::Archived>()
+        .register::<toolpath_claude::usage::StepUsage>()
+        .register::<toolpath_claude::usage::UsageTotals>()
+        .register::<toolpath_claude::derive::ProjectSummary>()
+        // toolpath-github stores PR labels under a bare `github` key
+        .allow_key("github");
+    registry
+}
+
 fn validate_content(content: &str) -> Result<()> {
-    match Document::from_json(content) {
-        Ok(doc) => {
-            let kind = match &doc {
-                Document::Graph(g) => format!("Graph (id: {})", g.graph.id),
-                Document::Path(p) => format!("Path (id: {}, {} steps)", p.path.id, p.steps.len()),
-                Document::Step(s) => format!("Step (id: {})", s.step.id),
-            };
-            println!("Valid: {}", kind);
-            Ok(())
+    let doc = Document::from_json(content).map_err(|e| anyhow::anyhow!("Invalid: {}", e))?;
+
+    let diags = known_extensions().validate(&doc);
+    let mut errors = 0;
+    for diag in &diags {
+        match diag.severity {
+            Severity::Error => {
+                errors += 1;
+                eprintln!("Error: {}", diag);
+            }
+            Severity::Warning => eprintln!("Warning: {}", diag),
         }
-        Err(e) => Err(anyhow::anyhow!("Invalid: {}", e)),
     }
+    if errors > 0 {
+        anyhow::bail!("Invalid: {} extension error(s)", errors);
+    }
+
+    let kind = match &doc {
+        Document::Graph(g) => format!("Graph (id: {})", g.graph.id),
+        Document::Path(p) => format!("Path (id: {}, {} steps)", p.path.id, p.steps.len()),
+        Document::Step(s) => format!("Step (id: {})", s.step.id),
+    };
+    println!("Valid: {}", kind);
+    Ok(())
 }
 
 #[cfg(test)]
@@ -56,6 +83,18 @@ mod tests {
         assert!(validate_content(r#"{"Unknown":{}}"#).is_err());
     }
 
+    #[test]
+    fn test_validate_known_extension_wrong_shape() {
+        let json = r#"{"Path":{"path":{"id":"p1","head":"s1"},"steps":[],"meta":{"track":{"version":"one"}}}}"#;
+        assert!(validate_content(json).is_err());
+    }
+
+    #[test]
+    fn test_validate_unknown_bare_key_is_warning_only() {
+        let json = r#"{"Step":{"step":{"id":"s1","actor":"human:alex","timestamp":"2026-01-01T00:00:00Z"},"change":{},"meta":{"scratch":1,"acme.review":{}}}}"#;
+        assert!(validate_content(json).is_ok());
+    }
+
     #[test]
     fn test_run_with_temp_file() {
         let mut f = tempfile::NamedTempFile::new().unwrap();
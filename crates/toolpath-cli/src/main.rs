@@ -2,9 +2,13 @@ mod cmd_derive;
 mod cmd_haiku;
 mod cmd_list;
 mod cmd_merge;
+mod cmd_ops;
+mod cmd_prune;
 mod cmd_query;
 mod cmd_render;
+mod cmd_search;
 mod cmd_track;
+mod cmd_upgrade;
 mod cmd_validate;
 
 use anyhow::Result;
@@ -70,6 +74,36 @@ enum Commands {
         #[arg(short, long)]
         input: PathBuf,
     },
+    /// Upgrade a document written in an older format version
+    Upgrade {
+        /// Input file
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Output file (writes to stdout if not provided)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Record a step on head that undoes an earlier step
+    Revert {
+        #[command(flatten)]
+        args: cmd_ops::OpArgs,
+    },
+    /// Copy a step from another branch (e.g. a dead end) onto head
+    Pick {
+        #[command(flatten)]
+        args: cmd_ops::OpArgs,
+    },
+    /// Drop or archive abandoned branches
+    Prune {
+        #[command(flatten)]
+        args: cmd_prune::PruneArgs,
+    },
+    /// Search Claude sessions across all projects
+    Search {
+        #[command(flatten)]
+        args: cmd_search::SearchArgs,
+    },
     /// Print a random Toolpath haiku
     Haiku,
 }
@@ -85,6 +119,11 @@ fn main() -> Result<()> {
         Commands::Merge { inputs, title } => cmd_merge::run(inputs, title, cli.pretty),
         Commands::Track { op } => cmd_track::run(op, cli.pretty),
         Commands::Validate { input } => cmd_validate::run(input),
+        Commands::Upgrade { input, output } => cmd_upgrade::run(input, output, cli.pretty),
+        Commands::Revert { args } => cmd_ops::run(cmd_ops::Op::Revert, args, cli.pretty),
+        Commands::Pick { args } => cmd_ops::run(cmd_ops::Op::Pick, args, cli.pretty),
+        Commands::Prune { args } => cmd_prune::run(args, cli.pretty),
+        Commands::Search { args } => cmd_search::run(args),
         Commands::Haiku => {
             cmd_haiku::run();
             Ok(())
@@ -313,3 +313,230 @@ fn merge_produces_graph() {
         .success()
         .stdout(predicate::str::contains("\"Graph\""));
 }
+
+// ── Format version ───────────────────────────────────────────────────
+
+#[test]
+fn written_documents_carry_version() {
+    let version = |stdout: &[u8]| {
+        let doc = toolpath::v1::Document::from_json(std::str::from_utf8(stdout).unwrap()).unwrap();
+        doc.version().map(str::to_string)
+    };
+    let current = Some(toolpath::v1::FORMAT_VERSION.to_string());
+
+    let (dir, branch) = git_fixture();
+    let derived = cmd()
+        .args(["derive", "git", "--branch", &branch, "--repo"])
+        .arg(dir.path())
+        .output()
+        .unwrap();
+    assert_eq!(version(&derived.stdout), current);
+
+    let merged = cmd()
+        .arg("merge")
+        .arg(examples_dir().join("path-01-pr.json"))
+        .output()
+        .unwrap();
+    assert_eq!(version(&merged.stdout), current);
+
+    let archive = dir.path().join("archive.json");
+    let pruned = cmd()
+        .args(["prune", "--input"])
+        .arg(examples_dir().join("path-01-pr.json"))
+        .arg("--archive")
+        .arg(&archive)
+        .output()
+        .unwrap();
+    assert_eq!(version(&pruned.stdout), current);
+    assert_eq!(version(&std::fs::read(&archive).unwrap()), current);
+
+    let input = dir.path().join("path.json");
+    std::fs::write(&input, &derived.stdout).unwrap();
+    let head = toolpath::v1::Document::from_json(std::str::from_utf8(&derived.stdout).unwrap())
+        .ok()
+        .and_then(|doc| match doc {
+            toolpath::v1::Document::Path(p) => Some(p.path.head),
+            _ => None,
+        })
+        .unwrap();
+    let base = tempfile::tempdir().unwrap();
+    std::fs::write(base.path().join("main.rs"), "fn main() {}").unwrap();
+    let reverted = cmd()
+        .args([
+            "revert",
+            "--actor",
+            "human:alex",
+            "--step",
+            &head,
+            "--input",
+        ])
+        .arg(&input)
+        .arg("--base-dir")
+        .arg(base.path())
+        .output()
+        .unwrap();
+    assert_eq!(version(&reverted.stdout), current);
+}
+
+// ── Revert / Pick ────────────────────────────────────────────────────
+
+#[test]
+fn revert_derived_git_step() {
+    let (dir, branch) = git_fixture();
+    let derived = cmd()
+        .arg("derive")
+        .arg("git")
+        .arg("--repo")
+        .arg(dir.path())
+        .arg("--branch")
+        .arg(&branch)
+        .output()
+        .unwrap();
+    assert!(derived.status.success());
+    let doc: serde_json::Value = serde_json::from_slice(&derived.stdout).unwrap();
+    let head = doc["Path"]["path"]["head"].as_str().unwrap().to_string();
+
+    let input = dir.path().join("path.json");
+    std::fs::write(&input, &derived.stdout).unwrap();
+
+    // The path starts after the initial commit, so main.rs needs base content.
+    let base = tempfile::tempdir().unwrap();
+    std::fs::write(base.path().join("main.rs"), "fn main() {}").unwrap();
+
+    cmd()
+        .arg("revert")
+        .arg("--input")
+        .arg(&input)
+        .arg("--step")
+        .arg(&head)
+        .arg("--actor")
+        .arg("human:alex")
+        .arg("--base-dir")
+        .arg(base.path())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"rel\":\"reverts\""))
+        .stdout(predicate::str::contains("+fn main() {}"))
+        .stderr(predicate::str::contains(format!("{}-revert", head)));
+}
+
+#[test]
+fn pick_active_step_fails() {
+    cmd()
+        .arg("pick")
+        .arg("--input")
+        .arg(examples_dir().join("path-01-pr.json"))
+        .arg("--step")
+        .arg("step-001")
+        .arg("--actor")
+        .arg("human:alex")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already on the head branch"));
+}
+
+// ── Prune ────────────────────────────────────────────────────────────
+
+#[test]
+fn prune_drops_dead_ends() {
+    cmd()
+        .arg("prune")
+        .arg("--input")
+        .arg(examples_dir().join("path-01-pr.json"))
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("step-002a").not())
+        .stderr(predicate::str::contains("Pruned"));
+}
+
+#[test]
+fn prune_archive_output_validates() {
+    let dir = tempfile::tempdir().unwrap();
+    let archive = dir.path().join("dead-ends.json");
+    let output = dir.path().join("pruned.json");
+
+    cmd()
+        .arg("prune")
+        .arg("--input")
+        .arg(examples_dir().join("path-01-pr.json"))
+        .arg("--older-than")
+        .arg("1d")
+        .arg("--archive")
+        .arg(&archive)
+        .arg("--output")
+        .arg(&output)
+        .assert()
+        .success();
+
+    for file in [&archive, &output] {
+        cmd()
+            .arg("validate")
+            .arg("--input")
+            .arg(file)
+            .assert()
+            .success();
+    }
+    let archived = std::fs::read_to_string(&archive).unwrap();
+    assert!(archived.contains("step-002a"));
+    assert!(archived.contains("toolpath:"));
+}
+
+#[test]
+fn derive_claude_history_requires_project_graph() {
+    cmd()
+        .args(["derive", "claude", "--project", "/tmp/x", "--history"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("--all"));
+}
+
+#[test]
+fn search_claude_sessions() {
+    let home = tempfile::tempdir().unwrap();
+    let project = home.path().join(".claude/projects/-work-app");
+    std::fs::create_dir_all(&project).unwrap();
+    let entry = r#"{"type":"assistant","uuid":"abcdef12-0000","timestamp":"2026-09-02T00:00:00Z","sessionId":"s1","cwd":"/work/app","message":{"role":"assistant","content":[{"type":"tool_use","id":"t1","name":"Edit","input":{"file_path":"src/limit.rs","new_string":"rate limiter"}}]}}"#;
+    std::fs::write(project.join("s1.jsonl"), format!("{}\n", entry)).unwrap();
+
+    cmd()
+        .env("HOME", home.path())
+        .env_remove("CLAUDE_CONFIG_DIR")
+        .args([
+            "search",
+            "rate limiter",
+            "--tool",
+            "Edit",
+            "--since",
+            "2026-09-01",
+        ])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("step-abcdef12"))
+        .stdout(predicates::str::contains("src/limit.rs"));
+
+    cmd()
+        .env("HOME", home.path())
+        .env_remove("CLAUDE_CONFIG_DIR")
+        .args(["search", "rate limiter", "--until", "2026-09-01"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("No matches"));
+}
+
+#[test]
+fn list_claude_projects_resolves_cwd() {
+    let home = tempfile::tempdir().unwrap();
+    let project = home.path().join(".claude/projects/-work-my-app-v2");
+    std::fs::create_dir_all(&project).unwrap();
+    let entry = r#"{"type":"user","uuid":"u1","timestamp":"2026-09-02T00:00:00Z","cwd":"/work/my-app.v2","message":{"role":"user","content":"hi"}}"#;
+    std::fs::write(project.join("s1.jsonl"), format!("{}\n", entry)).unwrap();
+
+    cmd()
+        .env("HOME", home.path())
+        .env_remove("CLAUDE_CONFIG_DIR")
+        .args(["list", "claude", "--json"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("\"path\": \"/work/my-app.v2\""))
+        .stdout(predicates::str::contains("\"resolution\": \"cwd\""));
+}
@@ -95,6 +95,38 @@ pub struct DelegatedWork {
     pub result: Option<String>,
 }
 
+/// Whether an [`Attachment`] is an image or a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttachmentKind {
+    Image,
+    Document,
+}
+
+/// An image or document attached to a turn.
+///
+/// Carries what identifies the content, not the content itself; providers
+/// expose the bytes through their own APIs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub kind: AttachmentKind,
+    /// MIME type (e.g. `"image/png"`, `"application/pdf"`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    /// Size in bytes, when the content is inline.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// SHA-256 of the content (`sha256:<hex>`), when the content is inline.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Where the content lives, when it is referenced rather than inline.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Document title, if given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
 /// Toolpath's classification of what a tool invocation does.
 ///
 /// This is toolpath's ontology, not a provider-specific label. Provider
@@ -118,6 +150,37 @@ pub enum ToolCategory {
     Delegation,
 }
 
+/// Marks a turn that stands in for earlier turns after the provider
+/// compacted the context: its text is the summary the model continued from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Compaction {
+    /// What triggered the compaction (e.g. `"auto"`, `"manual"`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trigger: Option<String>,
+    /// Context size in tokens just before compaction.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_tokens: Option<u64>,
+    /// How many turns the summary replaced. `None` when the turn was
+    /// converted without the turns before it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replaced_turns: Option<usize>,
+}
+
+/// Where a tool comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ToolOrigin {
+    /// Built into the agent.
+    Builtin,
+    /// Served by an MCP (Model Context Protocol) server.
+    Mcp {
+        /// Server name, as configured in the agent.
+        server: String,
+        /// Tool name on that server.
+        tool: String,
+    },
+}
+
 /// A tool invocation within a turn.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolInvocation {
@@ -133,6 +196,10 @@ pub struct ToolInvocation {
     /// crate; `None` for unrecognized tools.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub category: Option<ToolCategory>,
+    /// Where the tool comes from. Set by the provider crate when it can
+    /// tell; `None` otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<ToolOrigin>,
 }
 
 /// The result of a tool invocation.
@@ -185,6 +252,15 @@ pub struct Turn {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub delegations: Vec<DelegatedWork>,
 
+    /// Images and documents attached to this turn.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+
+    /// Set when this turn is a context-compaction summary. Such turns have
+    /// [`Role::System`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compaction: Option<Compaction>,
+
     /// Provider-specific data that doesn't fit the common schema.
     ///
     /// Providers namespace their data under a provider key (e.g.
@@ -229,6 +305,13 @@ pub struct ConversationView {
     pub session_ids: Vec<String>,
 }
 
+impl Turn {
+    /// Whether this turn is a context-compaction summary.
+    pub fn is_compaction(&self) -> bool {
+        self.compaction.is_some()
+    }
+}
+
 impl ConversationView {
     /// Title derived from the first user turn, truncated to `max_len` characters.
     pub fn title(&self, max_len: usize) -> Option<String> {
@@ -387,6 +470,113 @@ impl WatcherEvent {
     }
 }
 
+// ── Checkpoints ──────────────────────────────────────────────────────
+
+/// Fingerprint of a log file, telling whether a stored offset still refers
+/// to the same bytes after a restart.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileIdentity {
+    /// Inode number, on platforms that have one. A different inode means
+    /// the file was replaced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inode: Option<u64>,
+    /// Digest of the bytes just before the offset. A different digest (or a
+    /// file now shorter than the offset) means it was truncated or
+    /// rewritten.
+    pub digest: String,
+}
+
+/// Where a watcher is in one session, enough to resume it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionCursor {
+    pub project: String,
+    /// The session (file segment) being read.
+    pub session_id: String,
+    /// Earlier segments of the session's chain already read, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chain: Vec<String>,
+    /// Byte offset read up to in the session's file.
+    pub offset: u64,
+    /// ID of the last entry read, used to find the resume point again when
+    /// the file's identity changed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_uuid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<FileIdentity>,
+}
+
+/// A watcher's resumable state: one cursor per session it follows.
+///
+/// Saved as a small JSON file. [`save`](WatcherCheckpoint::save) writes a
+/// temporary file next to the target and renames it into place, so a crash
+/// leaves either the old checkpoint or the new one.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatcherCheckpoint {
+    #[serde(default)]
+    pub sessions: Vec<SessionCursor>,
+}
+
+impl WatcherCheckpoint {
+    /// Load a checkpoint; `None` if `path` doesn't exist.
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Option<Self>> {
+        match std::fs::read(path.as_ref()) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Write the checkpoint to `path` atomically, syncing it (and on unix
+    /// its directory) to disk. Concurrent saves each use their own
+    /// temporary file; the last rename wins.
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<()> {
+        use std::io::Write;
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(format!(
+            ".tmp-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let tmp = PathBuf::from(tmp);
+
+        let bytes = serde_json::to_vec_pretty(self)?;
+        let written = std::fs::File::create(&tmp)
+            .and_then(|mut file| {
+                file.write_all(&bytes)?;
+                file.sync_all()
+            })
+            .and_then(|()| std::fs::rename(&tmp, path));
+        if let Err(e) = written {
+            let _ = std::fs::remove_file(&tmp);
+            return Err(e.into());
+        }
+
+        // The rename is only durable once the directory entry is.
+        #[cfg(unix)]
+        {
+            let dir = match path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => std::path::Path::new("."),
+            };
+            std::fs::File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+
+    /// The cursor for a session, by the ID of any segment of its chain.
+    pub fn session(&self, project: &str, session_id: &str) -> Option<&SessionCursor> {
+        self.sessions.iter().find(|c| {
+            c.project == project
+                && (c.session_id == session_id || c.chain.iter().any(|s| s == session_id))
+        })
+    }
+}
+
 // ── Traits ───────────────────────────────────────────────────────────
 
 /// Trait for converting provider-specific conversation data into the
@@ -416,6 +606,20 @@ pub trait ConversationWatcher {
     fn seen_count(&self) -> usize;
 }
 
+/// A [`ConversationWatcher`] that can save where it is and resume there.
+///
+/// Restore a checkpoint before the first poll: what was read before it was
+/// taken is not reported again.
+pub trait ResumableWatcher: ConversationWatcher {
+    /// The watcher's current position.
+    fn checkpoint(&self) -> Result<WatcherCheckpoint>;
+
+    /// Resume from `checkpoint`. Cursors whose file was truncated or
+    /// rewritten resume after their last entry if it is still there, and
+    /// from the start of the file otherwise.
+    fn restore(&mut self, checkpoint: &WatcherCheckpoint) -> Result<()>;
+}
+
 // ── Tests ────────────────────────────────────────────────────────────
 
 #[cfg(test)]
@@ -441,6 +645,8 @@ mod tests {
                     token_usage: None,
                     environment: None,
                     delegations: vec![],
+                    attachments: vec![],
+                    compaction: None,
                     extra: HashMap::new(),
                 },
                 Turn {
@@ -459,6 +665,7 @@ mod tests {
                             is_error: false,
                         }),
                         category: Some(ToolCategory::FileRead),
+                        origin: None,
                     }],
                     model: Some("claude-opus-4-6".into()),
                     stop_reason: Some("end_turn".into()),
@@ -470,6 +677,8 @@ mod tests {
                     }),
                     environment: None,
                     delegations: vec![],
+                    attachments: vec![],
+                    compaction: None,
                     extra: HashMap::new(),
                 },
                 Turn {
@@ -485,6 +694,8 @@ mod tests {
                     token_usage: None,
                     environment: None,
                     delegations: vec![],
+                    attachments: vec![],
+                    compaction: None,
                     extra: HashMap::new(),
                 },
             ],
@@ -758,6 +969,7 @@ mod tests {
             input: serde_json::json!({"command": "ls"}),
             result: None,
             category: Some(ToolCategory::Shell),
+            origin: None,
         };
         let json = serde_json::to_string(&ti).unwrap();
         assert!(json.contains("\"shell\""));
@@ -773,17 +985,38 @@ mod tests {
             input: serde_json::json!({}),
             result: None,
             category: None,
+            origin: None,
         };
         let json = serde_json::to_string(&ti).unwrap();
         assert!(!json.contains("category"));
     }
 
+    #[test]
+    fn test_tool_origin_serde() {
+        let origin = ToolOrigin::Mcp {
+            server: "postgres".into(),
+            tool: "query".into(),
+        };
+        let json = serde_json::to_value(&origin).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"kind": "mcp", "server": "postgres", "tool": "query"})
+        );
+        let back: ToolOrigin = serde_json::from_value(json).unwrap();
+        assert_eq!(back, origin);
+        assert_eq!(
+            serde_json::to_value(ToolOrigin::Builtin).unwrap(),
+            serde_json::json!({"kind": "builtin"})
+        );
+    }
+
     #[test]
     fn test_tool_category_missing_defaults_none() {
         // Old-format JSON without category should deserialize as None
         let json = r#"{"id":"t1","name":"Read","input":{},"result":null}"#;
         let ti: ToolInvocation = serde_json::from_str(json).unwrap();
         assert!(ti.category.is_none());
+        assert!(ti.origin.is_none());
     }
 
     #[test]
@@ -827,9 +1060,19 @@ mod tests {
                 turns: vec![],
                 result: None,
             }],
+            compaction: None,
+            attachments: vec![Attachment {
+                kind: AttachmentKind::Image,
+                media_type: Some("image/png".into()),
+                size: Some(3),
+                sha256: Some("sha256:2c26".into()),
+                url: None,
+                title: None,
+            }],
             extra: HashMap::new(),
         };
         let json = serde_json::to_string(&turn).unwrap();
+        assert!(json.contains(r#""kind":"image""#));
         let back: Turn = serde_json::from_str(&json).unwrap();
         assert_eq!(
             back.environment.as_ref().unwrap().vcs_branch.as_deref(),
@@ -837,6 +1080,7 @@ mod tests {
         );
         assert_eq!(back.delegations.len(), 1);
         assert_eq!(back.delegations[0].agent_id, "sub-1");
+        assert_eq!(back.attachments[0].size, Some(3));
     }
 
     #[test]
@@ -846,6 +1090,8 @@ mod tests {
         let turn: Turn = serde_json::from_str(json).unwrap();
         assert!(turn.environment.is_none());
         assert!(turn.delegations.is_empty());
+        assert!(turn.attachments.is_empty());
+        assert!(!turn.is_compaction());
     }
 
     #[test]
@@ -901,4 +1147,45 @@ mod tests {
         let back: ConversationMeta = serde_json::from_str(&json).unwrap();
         assert_eq!(back.message_count, 5);
     }
+
+    #[test]
+    fn test_checkpoint_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("toolpath-convo-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("checkpoint.json");
+        assert!(WatcherCheckpoint::load(&path).unwrap().is_none());
+
+        let checkpoint = WatcherCheckpoint {
+            sessions: vec![SessionCursor {
+                project: "/p".into(),
+                session_id: "s2".into(),
+                chain: vec!["s1".into()],
+                offset: 42,
+                last_uuid: Some("u9".into()),
+                file: Some(FileIdentity {
+                    inode: Some(7),
+                    digest: "sha256:00".into(),
+                }),
+            }],
+        };
+        checkpoint.save(&path).unwrap();
+        let loaded = WatcherCheckpoint::load(&path).unwrap().unwrap();
+        assert_eq!(loaded, checkpoint);
+        assert_eq!(loaded.session("/p", "s1").unwrap().session_id, "s2");
+        assert!(loaded.session("/q", "s1").is_none());
+
+        // Only the checkpoint is left behind
+        let files: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(files.len(), 1);
+
+        // Saves racing from several threads don't trip over each other.
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| checkpoint.save(&path).unwrap());
+            }
+        });
+        assert_eq!(WatcherCheckpoint::load(&path).unwrap().unwrap(), checkpoint);
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -64,19 +64,25 @@ pub fn render_path(path: &Path, options: &RenderOptions) -> String {
     dot.push_str("  edge [color=\"#666666\"];\n");
     dot.push_str("  splines=ortho;\n\n");
 
-    // Add title
-    if let Some(meta) = &path.meta
-        && let Some(title) = &meta.title
-    {
+    // Find ancestors of head (active path)
+    let active_steps = query::ancestors(&path.steps, &path.path.head);
+
+    // Add title, with token usage when the steps record it
+    let title = path.meta.as_ref().and_then(|m| m.title.as_deref());
+    let label = [
+        title.map(str::to_string),
+        usage_summary(path, &active_steps),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+    if !label.is_empty() {
         dot.push_str("  labelloc=\"t\";\n");
-        dot.push_str(&format!("  label=\"{}\";\n", escape_dot(title)));
+        dot.push_str(&format!("  label=\"{}\";\n", escape_dot(&label.join("\n"))));
         dot.push_str("  fontsize=16;\n");
         dot.push_str("  fontname=\"Helvetica-Bold\";\n\n");
     }
 
-    // Find ancestors of head (active path)
-    let active_steps = query::ancestors(&path.steps, &path.path.head);
-
     // Add base node
     if let Some(base) = &path.path.base {
         let short_commit = safe_prefix(base.ref_str.as_deref().unwrap_or(""), 8);
@@ -251,16 +257,19 @@ pub fn render_graph(graph: &Graph, options: &RenderOptions) -> String {
                 .unwrap_or(&path.path.id);
 
             let cluster_color = path_colors[i % path_colors.len()];
+            let active_steps = query::ancestors(&path.steps, &path.path.head);
+            let cluster_label = match usage_summary(path, &active_steps) {
+                Some(usage) => format!("{}\n{}", path_name, usage),
+                None => path_name.to_string(),
+            };
 
             dot.push_str(&format!("  subgraph cluster_{} {{\n", i));
-            dot.push_str(&format!("    label=\"{}\";\n", escape_dot(path_name)));
+            dot.push_str(&format!("    label=\"{}\";\n", escape_dot(&cluster_label)));
             dot.push_str("    fontname=\"Helvetica-Bold\";\n");
             dot.push_str("    style=filled;\n");
             dot.push_str(&format!("    fillcolor=\"{}\";\n", cluster_color));
             dot.push_str("    margin=12;\n\n");
 
-            let active_steps = query::ancestors(&path.steps, &path.path.head);
-
             for step in &path.steps {
                 let label = format_step_label_html(step, options);
                 let color = actor_color(&step.step.actor);
@@ -408,6 +417,14 @@ fn format_step_label_html(step: &Step, options: &RenderOptions) -> String {
         ));
     }
 
+    // Token usage if recorded
+    if let Some(usage) = step.meta.as_ref().and_then(|m| m.extra.get(STEP_USAGE_KEY)) {
+        rows.push(format!(
+            "<font point-size=\"8\" color=\"#666666\">{}</font>",
+            escape_html(&Usage::from_value(usage).to_string())
+        ));
+    }
+
     // Timestamp if requested
     if options.show_timestamps {
         let ts = &step.step.timestamp;
@@ -443,6 +460,85 @@ fn format_step_label_html(step: &Step, options: &RenderOptions) -> String {
     format!("<{}>", rows.join("<br/>"))
 }
 
+/// Extension key of the token usage `toolpath-claude` records on each step.
+const STEP_USAGE_KEY: &str = "claude.usage";
+
+/// Extension key of the usage `toolpath-claude` sums over a path.
+const PATH_USAGE_KEY: &str = "claude.usage_totals";
+
+/// Tokens and estimated cost read from a usage extension.
+#[derive(Default)]
+struct Usage {
+    tokens: u64,
+    cost_usd: Option<f64>,
+}
+
+impl Usage {
+    fn from_value(value: &serde_json::Value) -> Self {
+        let tokens = [
+            "input_tokens",
+            "output_tokens",
+            "cache_read_tokens",
+            "cache_write_tokens",
+        ]
+        .iter()
+        .filter_map(|k| value.get(k)?.as_u64())
+        .sum();
+        Self {
+            tokens,
+            cost_usd: value.get("cost_usd").and_then(|c| c.as_f64()),
+        }
+    }
+
+    fn add(&mut self, other: &Usage) {
+        self.tokens += other.tokens;
+        if let Some(cost) = other.cost_usd {
+            *self.cost_usd.get_or_insert(0.0) += cost;
+        }
+    }
+}
+
+impl std::fmt::Display for Usage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.tokens {
+            n if n >= 1_000_000 => write!(f, "{:.1}M tokens", n as f64 / 1_000_000.0)?,
+            n if n >= 1_000 => write!(f, "{:.1}k tokens", n as f64 / 1_000.0)?,
+            n => write!(f, "{} tokens", n)?,
+        }
+        match self.cost_usd {
+            Some(cost) if cost >= 1.0 => write!(f, " \u{b7} ${:.2}", cost),
+            Some(cost) => write!(f, " \u{b7} ${:.4}", cost),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Summarize the token usage of a path and of its dead ends, if any step
+/// records usage.
+fn usage_summary(path: &Path, active_steps: &HashSet<String>) -> Option<String> {
+    let mut total: Option<Usage> = None;
+    let mut dead_ends = Usage::default();
+    for step in &path.steps {
+        let Some(value) = step.meta.as_ref().and_then(|m| m.extra.get(STEP_USAGE_KEY)) else {
+            continue;
+        };
+        let usage = Usage::from_value(value);
+        if !active_steps.contains(&step.step.id) {
+            dead_ends.add(&usage);
+        }
+        total.get_or_insert_with(Default::default).add(&usage);
+    }
+    if let Some(value) = path.meta.as_ref().and_then(|m| m.extra.get(PATH_USAGE_KEY)) {
+        total = Some(Usage::from_value(value));
+    }
+    let total = total?;
+    if dead_ends.tokens > 0 {
+        Some(format!("{} ({} on dead ends)", total, dead_ends))
+    } else {
+        Some(total.to_string())
+    }
+}
+
 /// Return a fill color for a given actor string.
 pub fn actor_color(actor: &str) -> &'static str {
     if actor.starts_with("human:") {
@@ -654,6 +750,7 @@ mod tests {
         let s1 = make_step("s1", "human:alex", &[]);
         let s2 = make_step("s2", "agent:claude", &["s1"]);
         let path = Path {
+            version: None,
             path: PathIdentity {
                 id: "p1".into(),
                 base: Some(Base::vcs("github:org/repo", "abc123")),
@@ -686,6 +783,7 @@ mod tests {
         let s2a = make_step("s2a", "agent:claude", &["s1"]); // dead end
         let s3 = make_step("s3", "human:alex", &["s2"]);
         let path = Path {
+            version: None,
             path: PathIdentity {
                 id: "p1".into(),
                 base: None,
@@ -708,6 +806,7 @@ mod tests {
     fn test_render_path_with_timestamps() {
         let s1 = make_step("s1", "human:alex", &[]);
         let path = Path {
+            version: None,
             path: PathIdentity {
                 id: "p1".into(),
                 base: None,
@@ -729,6 +828,7 @@ mod tests {
     fn test_render_path_with_files() {
         let s1 = make_step("s1", "human:alex", &[]);
         let path = Path {
+            version: None,
             path: PathIdentity {
                 id: "p1".into(),
                 base: None,
@@ -746,6 +846,72 @@ mod tests {
         assert!(dot.contains("main.rs"));
     }
 
+    fn with_usage(mut step: Step, tokens: u64, cost: f64) -> Step {
+        step.meta.get_or_insert_with(Default::default).extra.insert(
+            "claude.usage".into(),
+            serde_json::json!({
+                "input_tokens": tokens / 2,
+                "output_tokens": tokens / 2,
+                "cache_read_tokens": 0,
+                "cache_write_tokens": 0,
+                "cost_usd": cost,
+            }),
+        );
+        step
+    }
+
+    #[test]
+    fn test_render_step_with_usage() {
+        let step = with_usage(make_step("s1", "agent:claude", &[]), 1500, 0.0123);
+        let dot = render_step(&step, &RenderOptions::default());
+        assert!(dot.contains("1.5k tokens \u{b7} $0.0123"));
+
+        let dot = render_step(
+            &make_step("s1", "agent:claude", &[]),
+            &RenderOptions::default(),
+        );
+        assert!(!dot.contains("tokens"));
+    }
+
+    #[test]
+    fn test_render_path_usage_with_dead_ends() {
+        let s1 = with_usage(make_step("s1", "agent:claude", &[]), 1000, 0.5);
+        let s2 = with_usage(make_step("s2", "agent:claude", &["s1"]), 200, 0.25);
+        let s2a = with_usage(make_step("s2a", "agent:claude", &["s1"]), 300, 0.75);
+        let path = Path {
+            version: None,
+            path: PathIdentity {
+                id: "p1".into(),
+                base: None,
+                head: "s2".into(),
+            },
+            steps: vec![s1, s2, s2a],
+            meta: Some(PathMeta {
+                title: Some("Session".into()),
+                ..Default::default()
+            }),
+        };
+        let dot = render_path(&path, &RenderOptions::default());
+        assert!(dot.contains(
+            "label=\"Session\\n1.5k tokens \u{b7} $1.50 (300 tokens \u{b7} $0.7500 on dead ends)\""
+        ));
+
+        // Recorded path totals take precedence over the step sum.
+        let mut path = path;
+        path.meta.as_mut().unwrap().extra.insert(
+            "claude.usage_totals".into(),
+            serde_json::json!({"input_tokens": 2000, "output_tokens": 0}),
+        );
+        let graph = Graph {
+            version: None,
+            graph: GraphIdentity { id: "g1".into() },
+            paths: vec![PathOrRef::Path(Box::new(path))],
+            meta: None,
+        };
+        let dot = render_graph(&graph, &RenderOptions::default());
+        assert!(dot.contains("Session\\n2.0k tokens (300 tokens \u{b7} $0.7500 on dead ends)"));
+    }
+
     // ── render_graph ───────────────────────────────────────────────────
 
     #[test]
@@ -753,6 +919,7 @@ mod tests {
         let s1 = make_step("s1", "human:alex", &[]);
         let s2 = make_step("s2", "agent:claude", &["s1"]);
         let path1 = Path {
+            version: None,
             path: PathIdentity {
                 id: "p1".into(),
                 base: Some(Base::vcs("github:org/repo", "abc123")),
@@ -767,6 +934,7 @@ mod tests {
 
         let s3 = make_step("s3", "human:bob", &[]);
         let path2 = Path {
+            version: None,
             path: PathIdentity {
                 id: "p2".into(),
                 base: Some(Base::vcs("github:org/repo", "abc123")),
@@ -780,6 +948,7 @@ mod tests {
         };
 
         let graph = Graph {
+            version: None,
             graph: GraphIdentity { id: "g1".into() },
             paths: vec![
                 PathOrRef::Path(Box::new(path1)),
@@ -806,6 +975,7 @@ mod tests {
     #[test]
     fn test_render_graph_with_refs() {
         let graph = Graph {
+            version: None,
             graph: GraphIdentity { id: "g1".into() },
             paths: vec![PathOrRef::Ref(PathRef {
                 ref_url: "https://example.com/path.json".to_string(),
@@ -835,6 +1005,7 @@ mod tests {
     #[test]
     fn test_render_dispatches_path() {
         let path = Path {
+            version: None,
             path: PathIdentity {
                 id: "p1".into(),
                 base: None,
@@ -852,6 +1023,7 @@ mod tests {
     #[test]
     fn test_render_dispatches_graph() {
         let graph = Graph {
+            version: None,
             graph: GraphIdentity { id: "g1".into() },
             paths: vec![],
             meta: None,
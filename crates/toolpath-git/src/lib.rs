@@ -241,6 +241,7 @@ mod native {
         };
 
         Ok(Path {
+            version: None,
             path: PathIdentity {
                 id: format!("path-{}", spec.name.replace('/', "-")),
                 base: Some(Base {
@@ -316,6 +317,7 @@ mod native {
             .unwrap_or_else(|| format!("Branches: {}", branch_names.join(", ")));
 
         Ok(Graph {
+            version: None,
             graph: GraphIdentity { id: graph_id },
             paths,
             meta: Some(GraphMeta {
@@ -574,6 +576,7 @@ mod native {
         };
 
         Ok(Step {
+            version: None,
             step: StepIdentity {
                 id: step_id,
                 parents,
@@ -634,20 +637,21 @@ mod native {
                 '+' => "+",
                 '-' => "-",
                 ' ' => " ",
-                '>' => ">",
-                '<' => "<",
                 'F' => "",
                 'H' => "@",
                 'B' => "",
                 _ => "",
             };
+            // '=', '>' and '<' carry "\n\\ No newline at end of file\n"
+            // and need no prefix of their own.
+            let eofnl = matches!(line.origin(), '=' | '>' | '<');
 
             if line.origin() == 'H' {
                 if let Ok(content) = std::str::from_utf8(line.content()) {
                     current_diff.push_str("@@");
                     current_diff.push_str(content.trim_start_matches('@'));
                 }
-            } else if (!prefix.is_empty() || line.origin() == ' ')
+            } else if (!prefix.is_empty() || line.origin() == ' ' || eofnl)
                 && let Ok(content) = std::str::from_utf8(line.content())
             {
                 current_diff.push_str(prefix);
@@ -934,6 +938,21 @@ mod native {
             assert!(changes.contains_key("file.txt"));
         }
 
+        #[test]
+        fn test_generate_diff_missing_newline_parses() {
+            let (_dir, repo) = init_temp_repo();
+            let oid1 = create_commit(&repo, "first", "file.txt", "a\nb", None);
+            let commit1 = repo.find_commit(oid1).unwrap();
+            let oid2 = create_commit(&repo, "second", "file.txt", "a\nc", Some(&commit1));
+            let commit2 = repo.find_commit(oid2).unwrap();
+
+            let changes = generate_diff(&repo, &commit2).unwrap();
+            let raw = changes["file.txt"].raw.as_deref().unwrap();
+            let diff = toolpath::v1::patch::UnifiedDiff::parse(raw).unwrap();
+            assert_eq!(diff.to_string(), raw);
+            assert_eq!(diff.apply("a\nb").unwrap(), "a\nc");
+        }
+
         #[test]
         fn test_collect_commits_range() {
             let (_dir, repo) = init_temp_repo();
@@ -493,6 +493,7 @@ mod native {
         let meta = build_path_meta(pr, &actors)?;
 
         Ok(Path {
+            version: None,
             path: PathIdentity {
                 id: format!("pr-{}", pr_number),
                 base: Some(Base {
@@ -545,6 +546,7 @@ mod native {
         let intent = message.lines().next().unwrap_or("").to_string();
 
         let mut step = Step {
+            version: None,
             step: StepIdentity {
                 id: step_id,
                 parents: vec![],
@@ -611,6 +613,7 @@ mod native {
         )]);
 
         Ok(Step {
+            version: None,
             step: StepIdentity {
                 id: step_id,
                 parents: vec![],
@@ -649,6 +652,7 @@ mod native {
         )]);
 
         Ok(Step {
+            version: None,
             step: StepIdentity {
                 id: step_id,
                 parents: vec![],
@@ -694,6 +698,7 @@ mod native {
         )]);
 
         Ok(Step {
+            version: None,
             step: StepIdentity {
                 id: step_id,
                 parents: vec![],
@@ -750,6 +755,7 @@ mod native {
         )]);
 
         Ok(Step {
+            version: None,
             step: StepIdentity {
                 id: step_id,
                 parents: vec![],
@@ -0,0 +1,490 @@
+//! Typed extensions stored in the flattened `extra` maps of metadata.
+//!
+//! Every metadata container ([`StepMeta`], [`PathMeta`], [`GraphMeta`]) and
+//! [`StructuralChange`] accepts arbitrary additional properties. An
+//! [`Extension`] gives one of those properties a fixed key and a serde type,
+//! so producers and consumers agree on its shape without touching the core
+//! schema.
+
+use crate::types::{Document, GraphMeta, PathMeta, PathOrRef, StepMeta, StructuralChange};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+
+/// A typed value stored under a fixed key in a metadata `extra` map.
+///
+/// Keys should be namespaced (see [`is_namespaced`]) so that independent
+/// tools cannot collide, e.g. `"acme.review"` rather than `"review"`.
+///
+/// # Examples
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use toolpath::v1::StepMeta;
+/// use toolpath::v1::ext::Extension;
+///
+/// #[derive(Debug, PartialEq, Serialize, Deserialize)]
+/// struct Review {
+///     approved: bool,
+/// }
+///
+/// impl Extension for Review {
+///     const KEY: &'static str = "acme.review";
+/// }
+///
+/// let mut meta = StepMeta::default();
+/// meta.set_ext(Review { approved: true }).unwrap();
+/// assert!(meta.extra.contains_key("acme.review"));
+///
+/// let review: Option<Review> = meta.get_ext().unwrap();
+/// assert_eq!(review, Some(Review { approved: true }));
+/// ```
+pub trait Extension: Serialize + DeserializeOwned {
+    /// Key under which the extension is stored in `extra`.
+    const KEY: &'static str;
+}
+
+/// Whether `key` carries an owner prefix: at least one `.` separating
+/// non-empty segments (e.g. `"claude.usage"`, `"com.acme.review"`).
+pub fn is_namespaced(key: &str) -> bool {
+    key.contains('.') && key.split('.').all(|seg| !seg.is_empty())
+}
+
+fn get<T: Extension>(
+    extra: &HashMap<String, serde_json::Value>,
+) -> Result<Option<T>, serde_json::Error> {
+    extra.get(T::KEY).map(T::deserialize).transpose()
+}
+
+fn set<T: Extension>(
+    extra: &mut HashMap<String, serde_json::Value>,
+    value: T,
+) -> Result<(), serde_json::Error> {
+    extra.insert(T::KEY.to_string(), serde_json::to_value(value)?);
+    Ok(())
+}
+
+fn take<T: Extension>(
+    extra: &mut HashMap<String, serde_json::Value>,
+) -> Result<Option<T>, serde_json::Error> {
+    extra.remove(T::KEY).map(serde_json::from_value).transpose()
+}
+
+impl StepMeta {
+    /// Read a typed extension. Returns `Ok(None)` when the key is absent.
+    pub fn get_ext<T: Extension>(&self) -> Result<Option<T>, serde_json::Error> {
+        get(&self.extra)
+    }
+
+    /// Store a typed extension, replacing any previous value.
+    pub fn set_ext<T: Extension>(&mut self, value: T) -> Result<(), serde_json::Error> {
+        set(&mut self.extra, value)
+    }
+
+    /// Remove and return a typed extension.
+    pub fn take_ext<T: Extension>(&mut self) -> Result<Option<T>, serde_json::Error> {
+        take(&mut self.extra)
+    }
+}
+
+impl PathMeta {
+    /// Read a typed extension. Returns `Ok(None)` when the key is absent.
+    pub fn get_ext<T: Extension>(&self) -> Result<Option<T>, serde_json::Error> {
+        get(&self.extra)
+    }
+
+    /// Store a typed extension, replacing any previous value.
+    pub fn set_ext<T: Extension>(&mut self, value: T) -> Result<(), serde_json::Error> {
+        set(&mut self.extra, value)
+    }
+
+    /// Remove and return a typed extension.
+    pub fn take_ext<T: Extension>(&mut self) -> Result<Option<T>, serde_json::Error> {
+        take(&mut self.extra)
+    }
+}
+
+impl GraphMeta {
+    /// Read a typed extension. Returns `Ok(None)` when the key is absent.
+    pub fn get_ext<T: Extension>(&self) -> Result<Option<T>, serde_json::Error> {
+        get(&self.extra)
+    }
+
+    /// Store a typed extension, replacing any previous value.
+    pub fn set_ext<T: Extension>(&mut self, value: T) -> Result<(), serde_json::Error> {
+        set(&mut self.extra, value)
+    }
+
+    /// Remove and return a typed extension.
+    pub fn take_ext<T: Extension>(&mut self) -> Result<Option<T>, serde_json::Error> {
+        take(&mut self.extra)
+    }
+}
+
+impl StructuralChange {
+    /// Read a typed extension. Returns `Ok(None)` when the key is absent.
+    pub fn get_ext<T: Extension>(&self) -> Result<Option<T>, serde_json::Error> {
+        get(&self.extra)
+    }
+
+    /// Store a typed extension, replacing any previous value.
+    pub fn set_ext<T: Extension>(&mut self, value: T) -> Result<(), serde_json::Error> {
+        set(&mut self.extra, value)
+    }
+
+    /// Remove and return a typed extension.
+    pub fn take_ext<T: Extension>(&mut self) -> Result<Option<T>, serde_json::Error> {
+        take(&mut self.extra)
+    }
+}
+
+// ============================================================================
+// Registry
+// ============================================================================
+
+/// How serious an [`ExtensionDiagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// A registered extension whose value does not match its type.
+    Error,
+    /// An unregistered key without a namespace prefix.
+    Warning,
+}
+
+/// A problem found while validating a document's extensions.
+#[derive(Debug, Clone)]
+pub struct ExtensionDiagnostic {
+    pub severity: Severity,
+    /// Where the key was found, e.g. `"path p1 / step s2 / meta"`.
+    pub location: String,
+    pub key: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for ExtensionDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: `{}`: {}", self.location, self.key, self.message)
+    }
+}
+
+type Validator = fn(&serde_json::Value) -> Result<(), serde_json::Error>;
+
+fn validate_as<T: Extension>(value: &serde_json::Value) -> Result<(), serde_json::Error> {
+    T::deserialize(value).map(|_| ())
+}
+
+/// The set of extensions a consumer knows how to interpret.
+///
+/// Registered extensions are type-checked; keys registered with
+/// [`allow_key`](Self::allow_key) are accepted as-is. Any other key in a
+/// metadata `extra` map that is not namespaced produces a warning, since it
+/// is likely to collide with another tool's data.
+///
+/// # Examples
+///
+/// ```
+/// use serde::{Deserialize, Serialize};
+/// use toolpath::v1::{Document, Step, StepMeta};
+/// use toolpath::v1::ext::{Extension, ExtensionRegistry, Severity};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Review {
+///     approved: bool,
+/// }
+///
+/// impl Extension for Review {
+///     const KEY: &'static str = "acme.review";
+/// }
+///
+/// let mut step = Step::new("s1", "human:alex", "2026-01-29T10:00:00Z");
+/// let meta = step.meta.get_or_insert_with(StepMeta::default);
+/// meta.extra.insert("acme.review".into(), serde_json::json!({"approved": "yes"}));
+/// meta.extra.insert("scratch".into(), serde_json::json!(1));
+///
+/// let mut registry = ExtensionRegistry::new();
+/// registry.register::<Review>();
+///
+/// let diags = registry.validate(&Document::Step(step));
+/// assert_eq!(diags.len(), 2);
+/// assert!(diags.iter().any(|d| d.severity == Severity::Error && d.key == "acme.review"));
+/// assert!(diags.iter().any(|d| d.severity == Severity::Warning && d.key == "scratch"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionRegistry {
+    known: HashMap<String, Option<Validator>>,
+}
+
+impl ExtensionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a typed extension; its values will be type-checked.
+    pub fn register<T: Extension>(&mut self) -> &mut Self {
+        self.known
+            .insert(T::KEY.to_string(), Some(validate_as::<T> as Validator));
+        self
+    }
+
+    /// Accept a key without checking its shape (e.g. legacy un-namespaced
+    /// keys written by older producers).
+    pub fn allow_key(&mut self, key: impl Into<String>) -> &mut Self {
+        self.known.entry(key.into()).or_insert(None);
+        self
+    }
+
+    /// Whether `key` has been registered or allowed.
+    pub fn is_known(&self, key: &str) -> bool {
+        self.known.contains_key(key)
+    }
+
+    /// Check one `extra` map. Unknown, un-namespaced keys are only reported
+    /// when `warn_unknown` is set — structural changes carry their payload in
+    /// `extra`, so bare keys are expected there.
+    pub fn validate_extra(
+        &self,
+        location: &str,
+        extra: &HashMap<String, serde_json::Value>,
+        warn_unknown: bool,
+    ) -> Vec<ExtensionDiagnostic> {
+        let mut keys: Vec<&String> = extra.keys().collect();
+        keys.sort();
+
+        let mut diags = Vec::new();
+        for key in keys {
+            match self.known.get(key.as_str()) {
+                Some(Some(check)) => {
+                    if let Err(e) = check(&extra[key]) {
+                        diags.push(ExtensionDiagnostic {
+                            severity: Severity::Error,
+                            location: location.to_string(),
+                            key: key.clone(),
+                            message: format!("invalid value: {}", e),
+                        });
+                    }
+                }
+                Some(None) => {}
+                None => {
+                    if warn_unknown && !is_namespaced(key) {
+                        diags.push(ExtensionDiagnostic {
+                            severity: Severity::Warning,
+                            location: location.to_string(),
+                            key: key.clone(),
+                            message: "unknown key without a namespace prefix".to_string(),
+                        });
+                    }
+                }
+            }
+        }
+        diags
+    }
+
+    /// Check every metadata and structural `extra` map in a document.
+    pub fn validate(&self, doc: &Document) -> Vec<ExtensionDiagnostic> {
+        let mut diags = Vec::new();
+        match doc {
+            Document::Graph(g) => {
+                let loc = format!("graph {}", g.graph.id);
+                if let Some(meta) = &g.meta {
+                    diags.extend(self.validate_extra(
+                        &format!("{} / meta", loc),
+                        &meta.extra,
+                        true,
+                    ));
+                }
+                for p in &g.paths {
+                    if let PathOrRef::Path(path) = p {
+                        self.validate_path(&format!("{} / ", loc), path, &mut diags);
+                    }
+                }
+            }
+            Document::Path(p) => self.validate_path("", p, &mut diags),
+            Document::Step(s) => self.validate_step("", s, &mut diags),
+        }
+        diags
+    }
+
+    fn validate_path(
+        &self,
+        prefix: &str,
+        path: &crate::types::Path,
+        diags: &mut Vec<ExtensionDiagnostic>,
+    ) {
+        let loc = format!("{}path {}", prefix, path.path.id);
+        if let Some(meta) = &path.meta {
+            diags.extend(self.validate_extra(&format!("{} / meta", loc), &meta.extra, true));
+        }
+        for step in &path.steps {
+            self.validate_step(&format!("{} / ", loc), step, diags);
+        }
+    }
+
+    fn validate_step(
+        &self,
+        prefix: &str,
+        step: &crate::types::Step,
+        diags: &mut Vec<ExtensionDiagnostic>,
+    ) {
+        let loc = format!("{}step {}", prefix, step.step.id);
+        if let Some(meta) = &step.meta {
+            diags.extend(self.validate_extra(&format!("{} / meta", loc), &meta.extra, true));
+        }
+        let mut artifacts: Vec<&String> = step.change.keys().collect();
+        artifacts.sort();
+        for artifact in artifacts {
+            if let Some(structural) = &step.change[artifact].structural {
+                diags.extend(self.validate_extra(
+                    &format!("{} / change {}", loc, artifact),
+                    &structural.extra,
+                    false,
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Path, Step};
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Note {
+        text: String,
+    }
+
+    impl Extension for Note {
+        const KEY: &'static str = "test.note";
+    }
+
+    #[test]
+    fn test_is_namespaced() {
+        assert!(is_namespaced("acme.review"));
+        assert!(is_namespaced("com.acme.review"));
+        assert!(!is_namespaced("review"));
+        assert!(!is_namespaced(".review"));
+        assert!(!is_namespaced("acme."));
+        assert!(!is_namespaced("acme..review"));
+    }
+
+    #[test]
+    fn test_set_get_roundtrip() {
+        let mut meta = PathMeta::default();
+        meta.set_ext(Note { text: "hi".into() }).unwrap();
+        assert_eq!(meta.extra["test.note"]["text"], "hi");
+        let note: Note = meta.get_ext().unwrap().unwrap();
+        assert_eq!(note.text, "hi");
+    }
+
+    #[test]
+    fn test_get_absent() {
+        let meta = GraphMeta::default();
+        assert!(meta.get_ext::<Note>().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_get_wrong_shape_is_error() {
+        let mut meta = StepMeta::default();
+        meta.extra
+            .insert("test.note".into(), serde_json::json!({"text": 42}));
+        assert!(meta.get_ext::<Note>().is_err());
+    }
+
+    #[test]
+    fn test_take_removes() {
+        let mut meta = StepMeta::default();
+        meta.set_ext(Note { text: "x".into() }).unwrap();
+        let taken: Note = meta.take_ext().unwrap().unwrap();
+        assert_eq!(taken.text, "x");
+        assert!(meta.extra.is_empty());
+    }
+
+    #[test]
+    fn test_extension_survives_serialization() {
+        let mut step = Step::new("s1", "human:alex", "2026-01-29T10:00:00Z");
+        step.meta
+            .get_or_insert_with(StepMeta::default)
+            .set_ext(Note {
+                text: "keep".into(),
+            })
+            .unwrap();
+        let json = serde_json::to_string(&step).unwrap();
+        let parsed: Step = serde_json::from_str(&json).unwrap();
+        let note: Note = parsed.meta.unwrap().get_ext().unwrap().unwrap();
+        assert_eq!(note.text, "keep");
+    }
+
+    #[test]
+    fn test_structural_ext() {
+        let mut sc = StructuralChange {
+            change_type: "review".into(),
+            extra: HashMap::new(),
+        };
+        sc.set_ext(Note { text: "n".into() }).unwrap();
+        assert_eq!(sc.get_ext::<Note>().unwrap().unwrap().text, "n");
+    }
+
+    #[test]
+    fn test_registry_validates_path() {
+        let mut step = Step::new("s1", "human:alex", "2026-01-29T10:00:00Z");
+        step.meta
+            .get_or_insert_with(StepMeta::default)
+            .extra
+            .insert("test.note".into(), serde_json::json!("not an object"));
+        let mut path = Path::new("p1", None, "s1");
+        path.steps.push(step);
+        let mut meta = PathMeta::default();
+        meta.extra.insert("legacy".into(), serde_json::json!(true));
+        meta.extra
+            .insert("other.ns".into(), serde_json::json!(true));
+        path.meta = Some(meta);
+
+        let mut registry = ExtensionRegistry::new();
+        registry.register::<Note>();
+        let diags = registry.validate(&Document::Path(path));
+
+        assert_eq!(diags.len(), 2);
+        let err = diags
+            .iter()
+            .find(|d| d.severity == Severity::Error)
+            .unwrap();
+        assert_eq!(err.key, "test.note");
+        assert_eq!(err.location, "path p1 / step s1 / meta");
+        let warn = diags
+            .iter()
+            .find(|d| d.severity == Severity::Warning)
+            .unwrap();
+        assert_eq!(warn.key, "legacy");
+        assert_eq!(warn.location, "path p1 / meta");
+    }
+
+    #[test]
+    fn test_registry_allow_key_silences_warning() {
+        let mut meta = PathMeta::default();
+        meta.extra.insert("legacy".into(), serde_json::json!(1));
+        let mut registry = ExtensionRegistry::new();
+        registry.allow_key("legacy");
+        assert!(registry.is_known("legacy"));
+        assert!(registry.validate_extra("x", &meta.extra, true).is_empty());
+    }
+
+    #[test]
+    fn test_registry_structural_bare_keys_not_warned() {
+        let mut step = Step::new("s1", "agent:claude", "2026-01-29T10:00:00Z");
+        let mut extra = HashMap::new();
+        extra.insert("role".to_string(), serde_json::json!("user"));
+        step.change.insert(
+            "claude://x".into(),
+            crate::types::ArtifactChange {
+                raw: None,
+                structural: Some(StructuralChange {
+                    change_type: "conversation.append".into(),
+                    extra,
+                }),
+            },
+        );
+        let registry = ExtensionRegistry::new();
+        assert!(registry.validate(&Document::Step(step)).is_empty());
+    }
+}
@@ -1,5 +1,10 @@
 #![doc = include_str!("../README.md")]
 
+mod ext;
+mod migrate;
+mod ops;
+mod patch;
+mod prune;
 mod query;
 mod types;
 
@@ -48,6 +53,15 @@ pub mod v1 {
     //! - [`Signature`] — cryptographic signature
     //! - [`VcsSource`] — VCS revision reference
     //!
+    //! # Versioning
+    //!
+    //! Documents may declare a format `version` on their outermost object
+    //! ([`FORMAT_VERSION`] is the current one). The [`migrate`] module
+    //! upgrades older documents before typed parsing.
+    //!
+    //! Tool-specific data lives in the metadata `extra` maps; the [`ext`]
+    //! module gives it a typed, namespaced accessor layer.
+    //!
     //! # Example — build a Path with two Steps
     //!
     //! ```
@@ -63,6 +77,7 @@ pub mod v1 {
     //!     .with_intent("Refine fix");
     //!
     //! let path = Path {
+    //!     version: None,
     //!     path: PathIdentity {
     //!         id: "path-1".into(),
     //!         base: Some(Base::vcs("github:org/repo", "abc123")),
@@ -117,6 +132,74 @@ pub mod v1 {
             filter_by_time_range, step_index,
         };
     }
+
+    /// Typed, namespaced extensions for metadata `extra` maps.
+    ///
+    /// Implement [`Extension`](ext::Extension) for a serde type to read and
+    /// write it with `get_ext` / `set_ext` on [`StepMeta`], [`PathMeta`],
+    /// [`GraphMeta`] and [`StructuralChange`]. An
+    /// [`ExtensionRegistry`](ext::ExtensionRegistry) type-checks known
+    /// extensions in a document and flags un-namespaced unknown keys.
+    pub mod ext {
+        pub use crate::ext::{
+            Extension, ExtensionDiagnostic, ExtensionRegistry, Severity, is_namespaced,
+        };
+    }
+
+    /// Upgrade documents written in older format versions.
+    ///
+    /// Upgraders run on raw JSON before typed parsing, so archives whose
+    /// shape no longer matches the current types can still be loaded.
+    ///
+    /// ```
+    /// use toolpath::v1::{Document, migrate::Migrator};
+    ///
+    /// let old = r#"{"Step":{"step":{"id":"s1","actor":"human:alex","timestamp":"2026-01-29T10:00:00Z"},"change":{}}}"#;
+    /// let doc = Migrator::new().migrate_json(old).unwrap();
+    /// assert_eq!(doc.version(), Some(toolpath::v1::FORMAT_VERSION));
+    /// ```
+    pub mod migrate {
+        pub use crate::migrate::{
+            MigrateError, Migrated, Migrator, UNVERSIONED, Upgrader, migrate,
+        };
+    }
+
+    /// Parsed unified diffs: stats, apply, invert and compose.
+    ///
+    /// [`ArtifactChange::raw`] holds a unified diff string;
+    /// [`UnifiedDiff`](patch::UnifiedDiff) parses it and formats it back
+    /// byte for byte.
+    ///
+    /// ```
+    /// use toolpath::v1::patch::UnifiedDiff;
+    ///
+    /// let diff: UnifiedDiff = "@@ -1 +1 @@\n-old\n+new\n".parse().unwrap();
+    /// assert_eq!(diff.apply("old\n").unwrap(), "new\n");
+    /// ```
+    pub mod patch {
+        pub use crate::patch::{DiffStats, Hunk, Line, LineKind, PatchError, UnifiedDiff, compose};
+    }
+
+    /// Revert and cherry-pick steps on a [`Path`].
+    ///
+    /// Both record a new step on `head` whose diffs are checked against the
+    /// content reconstructed from the path's own history, and link back to
+    /// the original step with a [`Ref`].
+    pub mod ops {
+        pub use crate::ops::{Conflict, OpConfig, OpError, cherry_pick, content_at, revert_step};
+    }
+
+    /// Drop or archive abandoned branches of a [`Path`].
+    ///
+    /// [`prune`](prune::prune) removes matching dead branches outright;
+    /// [`archive`](prune::archive) moves them into separate Paths and leaves
+    /// a stub step linking to each.
+    pub mod prune {
+        pub use crate::prune::{
+            Archived, DeadBranch, PruneConfig, archive, dead_branches, matches, prune,
+        };
+    }
+    pub use crate::types::FORMAT_VERSION;
     pub use crate::types::{
         ActorDefinition, ArtifactChange, Base, Document, Graph, GraphIdentity, GraphMeta, Identity,
         Key, Path, PathIdentity, PathMeta, PathOrRef, PathRef, Ref, Signature, Step, StepIdentity,
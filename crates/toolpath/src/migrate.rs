@@ -0,0 +1,375 @@
+//! Upgrading documents written in older format versions.
+//!
+//! Migrations run on raw [`serde_json::Value`]s *before* typed parsing, so a
+//! document whose shape no longer matches the current types can still be
+//! loaded. Each [`Upgrader`] moves a document from one version to the next;
+//! a [`Migrator`] chains them until the document reaches
+//! [`FORMAT_VERSION`].
+
+use crate::types::{Document, FORMAT_VERSION};
+use serde_json::Value;
+
+/// Version assumed for documents that carry no `version` field — everything
+/// written before format versioning was introduced.
+pub const UNVERSIONED: &str = "0";
+
+/// Error raised while migrating a document.
+#[derive(Debug)]
+pub enum MigrateError {
+    /// The top-level value is not a `{"Graph"|"Path"|"Step": {…}}` object.
+    NotADocument,
+    /// The `version` field is present but not a string; `found` is its
+    /// JSON text.
+    InvalidVersion { found: String },
+    /// No registered upgrader starts at this version.
+    NoUpgrader { version: String },
+    /// An upgrader rejected the document.
+    Upgrade {
+        from: String,
+        to: String,
+        message: String,
+    },
+    /// The migrated value still fails typed parsing.
+    Parse(serde_json::Error),
+}
+
+impl std::fmt::Display for MigrateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrateError::NotADocument => {
+                write!(f, "expected a Graph, Path or Step document")
+            }
+            MigrateError::InvalidVersion { found } => {
+                write!(f, "format version must be a string, found {}", found)
+            }
+            MigrateError::NoUpgrader { version } => {
+                write!(f, "no upgrade path from format version {}", version)
+            }
+            MigrateError::Upgrade { from, to, message } => {
+                write!(f, "upgrade {} -> {} failed: {}", from, to, message)
+            }
+            MigrateError::Parse(e) => write!(f, "migrated document is invalid: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MigrateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MigrateError::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for MigrateError {
+    fn from(e: serde_json::Error) -> Self {
+        MigrateError::Parse(e)
+    }
+}
+
+/// One step in the upgrade chain: rewrites a document from `from` to `to`.
+///
+/// `apply` receives the whole document (`{"Path": {…}}` etc.) and may
+/// reshape it freely. The migrator updates the `version` field afterwards.
+#[derive(Debug, Clone, Copy)]
+pub struct Upgrader {
+    pub from: &'static str,
+    pub to: &'static str,
+    pub apply: fn(&mut Value) -> Result<(), String>,
+}
+
+/// Result of a successful migration.
+#[derive(Debug, Clone)]
+pub struct Migrated {
+    pub value: Value,
+    /// Version the input declared ([`UNVERSIONED`] when absent).
+    pub from: String,
+    /// Versions passed through, in order, ending at [`FORMAT_VERSION`].
+    pub applied: Vec<&'static str>,
+}
+
+/// An ordered chain of [`Upgrader`]s.
+///
+/// # Examples
+///
+/// ```
+/// use toolpath::v1::migrate::Migrator;
+///
+/// let old = serde_json::json!({
+///     "Step": {
+///         "step": { "id": "s1", "actor": "human:alex", "timestamp": "2026-01-29T10:00:00Z" },
+///         "change": {}
+///     }
+/// });
+///
+/// let migrated = Migrator::new().migrate(old).unwrap();
+/// assert_eq!(migrated.from, "0");
+/// assert_eq!(migrated.value["Step"]["version"], toolpath::v1::FORMAT_VERSION);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Migrator {
+    upgraders: Vec<Upgrader>,
+}
+
+impl Default for Migrator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Migrator {
+    /// A migrator with the built-in upgrade chain.
+    pub fn new() -> Self {
+        Self {
+            upgraders: builtin_upgraders(),
+        }
+    }
+
+    /// A migrator with no upgraders registered.
+    pub fn empty() -> Self {
+        Self {
+            upgraders: Vec::new(),
+        }
+    }
+
+    /// Register an upgrader. Later registrations for the same `from`
+    /// version replace earlier ones.
+    pub fn register(&mut self, upgrader: Upgrader) -> &mut Self {
+        self.upgraders.retain(|u| u.from != upgrader.from);
+        self.upgraders.push(upgrader);
+        self
+    }
+
+    /// Upgrade `value` to [`FORMAT_VERSION`].
+    pub fn migrate(&self, mut value: Value) -> Result<Migrated, MigrateError> {
+        let from = read_version(&value)?;
+        let mut current = from.clone();
+        let mut applied = Vec::new();
+
+        while current != FORMAT_VERSION {
+            let upgrader = self
+                .upgraders
+                .iter()
+                .find(|u| u.from == current)
+                .ok_or_else(|| MigrateError::NoUpgrader {
+                    version: current.clone(),
+                })?;
+            (upgrader.apply)(&mut value).map_err(|message| MigrateError::Upgrade {
+                from: upgrader.from.to_string(),
+                to: upgrader.to.to_string(),
+                message,
+            })?;
+            write_version(&mut value, upgrader.to)?;
+            applied.push(upgrader.to);
+            current = upgrader.to.to_string();
+
+            // A cycle in the chain would loop forever.
+            if applied.len() > self.upgraders.len() {
+                return Err(MigrateError::NoUpgrader { version: current });
+            }
+        }
+
+        Ok(Migrated {
+            value,
+            from,
+            applied,
+        })
+    }
+
+    /// Migrate a JSON string and parse the result as a [`Document`].
+    pub fn migrate_json(&self, json: &str) -> Result<Document, MigrateError> {
+        let value: Value = serde_json::from_str(json)?;
+        let migrated = self.migrate(value)?;
+        Ok(serde_json::from_value(migrated.value)?)
+    }
+}
+
+/// Upgrade a document with the built-in chain. Shorthand for
+/// `Migrator::new().migrate(value)`.
+pub fn migrate(value: Value) -> Result<Migrated, MigrateError> {
+    Migrator::new().migrate(value)
+}
+
+const KINDS: [&str; 3] = ["Graph", "Path", "Step"];
+
+fn document_body(value: &Value) -> Result<&serde_json::Map<String, Value>, MigrateError> {
+    let obj = value.as_object().ok_or(MigrateError::NotADocument)?;
+    KINDS
+        .iter()
+        .find_map(|k| obj.get(*k))
+        .and_then(Value::as_object)
+        .ok_or(MigrateError::NotADocument)
+}
+
+fn document_body_mut(
+    value: &mut Value,
+) -> Result<&mut serde_json::Map<String, Value>, MigrateError> {
+    let obj = value.as_object_mut().ok_or(MigrateError::NotADocument)?;
+    let kind = KINDS
+        .iter()
+        .find(|k| obj.contains_key(**k))
+        .ok_or(MigrateError::NotADocument)?;
+    obj.get_mut(*kind)
+        .and_then(Value::as_object_mut)
+        .ok_or(MigrateError::NotADocument)
+}
+
+fn read_version(value: &Value) -> Result<String, MigrateError> {
+    match document_body(value)?.get("version") {
+        None | Some(Value::Null) => Ok(UNVERSIONED.to_string()),
+        Some(Value::String(version)) => Ok(version.clone()),
+        Some(other) => Err(MigrateError::InvalidVersion {
+            found: other.to_string(),
+        }),
+    }
+}
+
+fn write_version(value: &mut Value, version: &str) -> Result<(), MigrateError> {
+    document_body_mut(value)?.insert("version".to_string(), Value::String(version.to_string()));
+    Ok(())
+}
+
+fn builtin_upgraders() -> Vec<Upgrader> {
+    vec![Upgrader {
+        from: UNVERSIONED,
+        to: "1.0.0",
+        // Unversioned documents already have the 1.0.0 shape.
+        apply: |_| Ok(()),
+    }]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn legacy_path() -> Value {
+        json!({
+            "Path": {
+                "path": { "id": "p1", "head": "s1" },
+                "steps": [
+                    { "step": { "id": "s1", "actor": "human:alex", "timestamp": "2026-01-29T10:00:00Z" }, "change": {} }
+                ]
+            }
+        })
+    }
+
+    #[test]
+    fn test_unversioned_is_stamped() {
+        let migrated = migrate(legacy_path()).unwrap();
+        assert_eq!(migrated.from, UNVERSIONED);
+        assert_eq!(migrated.applied, vec![FORMAT_VERSION]);
+        assert_eq!(migrated.value["Path"]["version"], FORMAT_VERSION);
+        // nested steps are left alone
+        assert!(migrated.value["Path"]["steps"][0].get("version").is_none());
+    }
+
+    #[test]
+    fn test_current_version_is_untouched() {
+        let mut value = legacy_path();
+        value["Path"]["version"] = json!(FORMAT_VERSION);
+        let migrated = migrate(value.clone()).unwrap();
+        assert!(migrated.applied.is_empty());
+        assert_eq!(migrated.value, value);
+    }
+
+    #[test]
+    fn test_unknown_version_errors() {
+        let mut value = legacy_path();
+        value["Path"]["version"] = json!("9.9.9");
+        match migrate(value) {
+            Err(MigrateError::NoUpgrader { version }) => assert_eq!(version, "9.9.9"),
+            other => panic!("expected NoUpgrader, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_non_string_version_errors() {
+        let mut value = legacy_path();
+        value["Path"]["version"] = json!(1);
+        match migrate(value) {
+            Err(MigrateError::InvalidVersion { found }) => assert_eq!(found, "1"),
+            other => panic!("expected InvalidVersion, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_not_a_document() {
+        assert!(matches!(
+            migrate(json!({"Other": {}})),
+            Err(MigrateError::NotADocument)
+        ));
+        assert!(matches!(
+            migrate(json!([1, 2])),
+            Err(MigrateError::NotADocument)
+        ));
+    }
+
+    #[test]
+    fn test_custom_chain_reshapes_before_parse() {
+        // Pretend "0" documents spelled the head field "tip".
+        let mut migrator = Migrator::empty();
+        migrator
+            .register(Upgrader {
+                from: UNVERSIONED,
+                to: "0.9.0",
+                apply: |v| {
+                    let path = v["Path"]["path"]
+                        .as_object_mut()
+                        .ok_or("missing path identity")?;
+                    let tip = path.remove("tip").ok_or("missing tip")?;
+                    path.insert("head".into(), tip);
+                    Ok(())
+                },
+            })
+            .register(Upgrader {
+                from: "0.9.0",
+                to: FORMAT_VERSION,
+                apply: |_| Ok(()),
+            });
+
+        let old = r#"{"Path":{"path":{"id":"p1","tip":"s1"},"steps":[]}}"#;
+        assert!(Document::from_json(old).is_err());
+
+        let doc = migrator.migrate_json(old).unwrap();
+        match doc {
+            Document::Path(p) => {
+                assert_eq!(p.path.head, "s1");
+                assert_eq!(p.version.as_deref(), Some(FORMAT_VERSION));
+            }
+            _ => panic!("Expected Path"),
+        }
+    }
+
+    #[test]
+    fn test_upgrader_failure_is_reported() {
+        let mut migrator = Migrator::empty();
+        migrator.register(Upgrader {
+            from: UNVERSIONED,
+            to: FORMAT_VERSION,
+            apply: |_| Err("boom".to_string()),
+        });
+        match migrator.migrate(legacy_path()) {
+            Err(MigrateError::Upgrade { message, .. }) => assert_eq!(message, "boom"),
+            other => panic!("expected Upgrade error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cycle_detected() {
+        let mut migrator = Migrator::empty();
+        migrator
+            .register(Upgrader {
+                from: UNVERSIONED,
+                to: "0.5",
+                apply: |_| Ok(()),
+            })
+            .register(Upgrader {
+                from: "0.5",
+                to: UNVERSIONED,
+                apply: |_| Ok(()),
+            });
+        assert!(migrator.migrate(legacy_path()).is_err());
+    }
+}
@@ -0,0 +1,577 @@
+//! Operations that record new provenance on a [`Path`]: reverting a step
+//! and cherry-picking an abandoned one.
+//!
+//! Both work on the `raw` diffs of the steps involved. To check that a diff
+//! still applies, the content of each artifact at `head` is reconstructed
+//! by replaying the diffs along the first-parent chain from the path's root,
+//! starting from caller-supplied base content (or empty, for artifacts
+//! created within the path).
+
+use crate::patch::{PatchError, UnifiedDiff};
+use crate::query;
+use crate::types::{ArtifactChange, Path, Ref, Step, StepMeta};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Settings for the step an operation creates.
+#[derive(Debug, Clone, Default)]
+pub struct OpConfig {
+    /// Actor recorded on the new step (e.g. `"human:alex"`).
+    pub actor: String,
+    /// Timestamp recorded on the new step.
+    pub timestamp: String,
+    /// ID for the new step, which must not already be in use; derived from
+    /// the target step (and suffixed until unique) when `None`.
+    pub step_id: Option<String>,
+    /// Content of artifacts before the path's first step, keyed by artifact.
+    /// Artifacts not listed are assumed to start empty.
+    pub base: HashMap<String, String>,
+}
+
+/// An artifact whose diff could not be carried onto `head`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub artifact: String,
+    /// Set when replaying this step's diff failed while reconstructing
+    /// content, rather than when applying the operation's own diff.
+    pub step: Option<String>,
+    pub error: PatchError,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.step {
+            Some(step) => write!(
+                f,
+                "{}: cannot reconstruct content (step {}: {})",
+                self.artifact, step, self.error
+            ),
+            None => write!(f, "{}: {}", self.artifact, self.error),
+        }
+    }
+}
+
+/// Error raised by [`revert_step`] and [`cherry_pick`]. The path is left
+/// unchanged.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpError {
+    /// No step with this ID exists in the path.
+    StepNotFound(String),
+    /// The step is already an ancestor of `head`; there is nothing to pick.
+    AlreadyOnHead(String),
+    /// The step is not an ancestor of `head`, so its diffs were never
+    /// applied there and there is nothing to revert.
+    NotOnHead(String),
+    /// The step carries no `raw` diffs to revert or pick.
+    NoRawChanges(String),
+    /// The requested ID for the new step is already in use.
+    StepIdTaken(String),
+    /// One or more artifacts did not apply cleanly.
+    Conflicts(Vec<Conflict>),
+}
+
+impl fmt::Display for OpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpError::StepNotFound(id) => write!(f, "step not found: {}", id),
+            OpError::AlreadyOnHead(id) => write!(f, "step {} is already on the head branch", id),
+            OpError::NotOnHead(id) => write!(f, "step {} is not on the head branch", id),
+            OpError::NoRawChanges(id) => write!(f, "step {} has no raw diffs", id),
+            OpError::StepIdTaken(id) => write!(f, "step ID {} is already in use", id),
+            OpError::Conflicts(conflicts) => {
+                write!(f, "{} conflicting artifact(s)", conflicts.len())?;
+                for c in conflicts {
+                    write!(f, "\n  {}", c)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for OpError {}
+
+/// Steps from the root to `step_id` following first parents, oldest first.
+fn first_parent_chain<'a>(path: &'a Path, step_id: &str) -> Vec<&'a Step> {
+    let index = query::step_index(&path.steps);
+    let mut chain = Vec::new();
+    let mut current = index.get(step_id).copied();
+    while let Some(step) = current {
+        // Guard against malformed cycles.
+        if chain.len() > path.steps.len() {
+            break;
+        }
+        chain.push(step);
+        current = step
+            .step
+            .parents
+            .first()
+            .and_then(|p| index.get(p.as_str()).copied());
+    }
+    chain.reverse();
+    chain
+}
+
+/// Content of `artifact` after `step_id`, replaying `raw` diffs along the
+/// first-parent chain on top of `base` (empty when `None`).
+///
+/// # Examples
+///
+/// ```
+/// use toolpath::v1::{Path, Step, ops};
+///
+/// let mut path = Path::new("p1", None, "s2");
+/// path.steps.push(Step::new("s1", "human:alex", "2026-01-29T10:00:00Z")
+///     .with_raw_change("notes.txt", "@@ -0,0 +1 @@\n+hello\n"));
+/// path.steps.push(Step::new("s2", "human:alex", "2026-01-29T10:01:00Z")
+///     .with_parent("s1")
+///     .with_raw_change("notes.txt", "@@ -1 +1,2 @@\n hello\n+world\n"));
+///
+/// let text = ops::content_at(&path, "s2", "notes.txt", None).unwrap();
+/// assert_eq!(text, "hello\nworld\n");
+/// ```
+pub fn content_at(
+    path: &Path,
+    step_id: &str,
+    artifact: &str,
+    base: Option<&str>,
+) -> Result<String, Conflict> {
+    let mut content = base.unwrap_or("").to_string();
+    for step in first_parent_chain(path, step_id) {
+        let Some(raw) = step.change.get(artifact).and_then(|c| c.raw.as_deref()) else {
+            continue;
+        };
+        let conflict = |error| Conflict {
+            artifact: artifact.to_string(),
+            step: Some(step.step.id.clone()),
+            error,
+        };
+        let diff = UnifiedDiff::parse(raw).map_err(conflict)?;
+        content = diff.apply(&content).map_err(conflict)?;
+    }
+    Ok(content)
+}
+
+/// Record a new step on `head` that undoes the `raw` diffs of `step_id`,
+/// which must be an ancestor of `head`.
+///
+/// The new step's diffs are the inversion of the target's, relocated to
+/// where the lines sit at `head`. It links back with a `reverts` ref and
+/// becomes the new head. Returns the new step's ID.
+///
+/// # Examples
+///
+/// ```
+/// use toolpath::v1::{Path, Step, ops};
+///
+/// let mut path = Path::new("p1", None, "s2");
+/// path.steps.push(Step::new("s1", "human:alex", "2026-01-29T10:00:00Z")
+///     .with_raw_change("f.txt", "@@ -0,0 +1,2 @@\n+a\n+b\n"));
+/// path.steps.push(Step::new("s2", "agent:claude", "2026-01-29T10:01:00Z")
+///     .with_parent("s1")
+///     .with_raw_change("f.txt", "@@ -1,2 +1,2 @@\n a\n-b\n+B\n"));
+///
+/// let config = ops::OpConfig {
+///     actor: "human:alex".into(),
+///     timestamp: "2026-01-29T10:02:00Z".into(),
+///     ..Default::default()
+/// };
+/// let id = ops::revert_step(&mut path, "s2", &config).unwrap();
+/// assert_eq!(path.path.head, id);
+/// assert_eq!(ops::content_at(&path, &id, "f.txt", None).unwrap(), "a\nb\n");
+/// ```
+pub fn revert_step(path: &mut Path, step_id: &str, config: &OpConfig) -> Result<String, OpError> {
+    let target = find_step(path, step_id)?;
+    if !query::ancestors(&path.steps, &path.path.head).contains(step_id) {
+        return Err(OpError::NotOnHead(step_id.to_string()));
+    }
+    let diffs = raw_diffs(target)?;
+    let intent = match target.meta.as_ref().and_then(|m| m.intent.as_deref()) {
+        Some(intent) => format!("Revert {}: {}", step_id, intent),
+        None => format!("Revert {}", step_id),
+    };
+
+    let new_id = unique_id(path, config.step_id.as_deref(), step_id, "revert")?;
+    let changes = carry(path, &config.base, diffs, |diff| diff.invert())?;
+    let rel = "reverts";
+    Ok(push_step(
+        path, new_id, changes, config, intent, rel, step_id,
+    ))
+}
+
+/// Copy the `raw` diffs of a step that is not on the head branch (typically
+/// a dead end) onto `head`.
+///
+/// The new step links back with a `cherry-picked-from` ref and becomes the
+/// new head. Returns the new step's ID.
+pub fn cherry_pick(path: &mut Path, step_id: &str, config: &OpConfig) -> Result<String, OpError> {
+    let target = find_step(path, step_id)?;
+    if query::ancestors(&path.steps, &path.path.head).contains(step_id) {
+        return Err(OpError::AlreadyOnHead(step_id.to_string()));
+    }
+    let diffs = raw_diffs(target)?;
+    let intent = match target.meta.as_ref().and_then(|m| m.intent.as_deref()) {
+        Some(intent) => intent.to_string(),
+        None => format!("Cherry-pick {}", step_id),
+    };
+
+    let new_id = unique_id(path, config.step_id.as_deref(), step_id, "pick")?;
+    let changes = carry(path, &config.base, diffs, |diff| diff.clone())?;
+    let rel = "cherry-picked-from";
+    Ok(push_step(
+        path, new_id, changes, config, intent, rel, step_id,
+    ))
+}
+
+fn find_step<'a>(path: &'a Path, step_id: &str) -> Result<&'a Step, OpError> {
+    path.steps
+        .iter()
+        .find(|s| s.step.id == step_id)
+        .ok_or_else(|| OpError::StepNotFound(step_id.to_string()))
+}
+
+/// An artifact and the result of parsing its `raw` diff.
+type ParsedDiff = (String, Result<UnifiedDiff, PatchError>);
+
+/// Parsed `raw` diffs of a step, sorted by artifact.
+fn raw_diffs(step: &Step) -> Result<Vec<ParsedDiff>, OpError> {
+    let mut diffs: Vec<_> = step
+        .change
+        .iter()
+        .filter_map(|(artifact, change)| {
+            change
+                .raw
+                .as_deref()
+                .map(|raw| (artifact.clone(), UnifiedDiff::parse(raw)))
+        })
+        .collect();
+    if diffs.is_empty() {
+        return Err(OpError::NoRawChanges(step.step.id.clone()));
+    }
+    diffs.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(diffs)
+}
+
+/// Apply `transform(diff)` to each artifact's content at `head`, returning
+/// the resulting changes or every conflict encountered.
+fn carry(
+    path: &Path,
+    base: &HashMap<String, String>,
+    diffs: Vec<ParsedDiff>,
+    transform: impl Fn(&UnifiedDiff) -> UnifiedDiff,
+) -> Result<HashMap<String, ArtifactChange>, OpError> {
+    let mut changes = HashMap::new();
+    let mut conflicts = Vec::new();
+
+    for (artifact, diff) in diffs {
+        let result = diff
+            .map_err(|error| Conflict {
+                artifact: artifact.clone(),
+                step: None,
+                error,
+            })
+            .and_then(|diff| {
+                let head = content_at(
+                    path,
+                    &path.path.head,
+                    &artifact,
+                    base.get(&artifact).map(String::as_str),
+                )?;
+                let patched = transform(&diff).apply(&head).map_err(|error| Conflict {
+                    artifact: artifact.clone(),
+                    step: None,
+                    error,
+                })?;
+                Ok((head, patched))
+            });
+        match result {
+            Ok((head, patched)) => {
+                let raw = UnifiedDiff::between(&head, &patched, 3);
+                if !raw.is_empty() {
+                    changes.insert(artifact, ArtifactChange::raw(raw.to_string()));
+                }
+            }
+            Err(conflict) => conflicts.push(conflict),
+        }
+    }
+
+    if conflicts.is_empty() {
+        Ok(changes)
+    } else {
+        Err(OpError::Conflicts(conflicts))
+    }
+}
+
+/// The requested ID if it is free, or `<target>-<suffix>` made unique with
+/// a numeric suffix.
+fn unique_id(
+    path: &Path,
+    requested: Option<&str>,
+    target: &str,
+    suffix: &str,
+) -> Result<String, OpError> {
+    let taken = |id: &str| path.steps.iter().any(|s| s.step.id == id);
+    if let Some(id) = requested {
+        return match taken(id) {
+            true => Err(OpError::StepIdTaken(id.to_string())),
+            false => Ok(id.to_string()),
+        };
+    }
+    let stem = format!("{}-{}", target, suffix);
+    if !taken(&stem) {
+        return Ok(stem);
+    }
+    Ok((2..)
+        .map(|n| format!("{}-{}", stem, n))
+        .find(|id| !taken(id))
+        .expect("unbounded range"))
+}
+
+fn push_step(
+    path: &mut Path,
+    id: String,
+    change: HashMap<String, ArtifactChange>,
+    config: &OpConfig,
+    intent: String,
+    rel: &str,
+    target: &str,
+) -> String {
+    let mut step = Step::new(id.clone(), config.actor.clone(), config.timestamp.clone())
+        .with_parent(path.path.head.clone())
+        .with_intent(intent);
+    step.change = change;
+    step.meta
+        .get_or_insert_with(StepMeta::default)
+        .refs
+        .push(Ref {
+            rel: rel.to_string(),
+            href: format!("toolpath:{}/{}", path.path.id, target),
+        });
+    path.steps.push(step);
+    path.path.head = id.clone();
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> OpConfig {
+        OpConfig {
+            actor: "human:alex".into(),
+            timestamp: "2026-01-29T12:00:00Z".into(),
+            ..Default::default()
+        }
+    }
+
+    /// s1 creates f.txt; s2 edits line 2; s3 edits line 5; s2a is a dead
+    /// end off s1 that edits line 4.
+    fn sample_path() -> Path {
+        let mut path = Path::new("p1", None, "s3");
+        path.steps = vec![
+            Step::new("s1", "human:alex", "2026-01-29T10:00:00Z").with_raw_change(
+                "f.txt",
+                "@@ -0,0 +1,5 @@\n+one\n+two\n+three\n+four\n+five\n",
+            ),
+            Step::new("s2", "agent:claude", "2026-01-29T10:01:00Z")
+                .with_parent("s1")
+                .with_raw_change("f.txt", "@@ -1,3 +1,3 @@\n one\n-two\n+TWO\n three\n")
+                .with_intent("Shout two"),
+            Step::new("s2a", "agent:claude", "2026-01-29T10:01:30Z")
+                .with_parent("s1")
+                .with_raw_change("f.txt", "@@ -3,3 +3,3 @@\n three\n-four\n+FOUR\n five\n"),
+            Step::new("s3", "human:alex", "2026-01-29T10:02:00Z")
+                .with_parent("s2")
+                .with_raw_change("f.txt", "@@ -4,2 +4,3 @@\n four\n five\n+six\n"),
+        ];
+        path
+    }
+
+    #[test]
+    fn test_content_at() {
+        let path = sample_path();
+        assert_eq!(
+            content_at(&path, "s3", "f.txt", None).unwrap(),
+            "one\nTWO\nthree\nfour\nfive\nsix\n"
+        );
+        assert_eq!(
+            content_at(&path, "s2a", "f.txt", None).unwrap(),
+            "one\ntwo\nthree\nFOUR\nfive\n"
+        );
+        assert_eq!(content_at(&path, "s3", "other.txt", None).unwrap(), "");
+    }
+
+    #[test]
+    fn test_content_at_with_base() {
+        let mut path = Path::new("p1", None, "s1");
+        path.steps.push(
+            Step::new("s1", "human:alex", "2026-01-29T10:00:00Z")
+                .with_raw_change("f.txt", "@@ -1 +1 @@\n-x\n+y\n"),
+        );
+        assert_eq!(
+            content_at(&path, "s1", "f.txt", Some("x\n")).unwrap(),
+            "y\n"
+        );
+        let err = content_at(&path, "s1", "f.txt", None).unwrap_err();
+        assert_eq!(err.step.as_deref(), Some("s1"));
+    }
+
+    #[test]
+    fn test_revert_step() {
+        let mut path = sample_path();
+        let id = revert_step(&mut path, "s2", &config()).unwrap();
+        assert_eq!(id, "s2-revert");
+        assert_eq!(path.path.head, "s2-revert");
+
+        let step = path.steps.last().unwrap();
+        assert_eq!(step.step.parents, vec!["s3"]);
+        assert_eq!(step.step.actor, "human:alex");
+        let meta = step.meta.as_ref().unwrap();
+        assert_eq!(meta.intent.as_deref(), Some("Revert s2: Shout two"));
+        assert_eq!(meta.refs[0].rel, "reverts");
+        assert_eq!(meta.refs[0].href, "toolpath:p1/s2");
+        assert_eq!(
+            step.change["f.txt"].raw.as_deref(),
+            Some("@@ -1,5 +1,5 @@\n one\n-TWO\n+two\n three\n four\n five\n")
+        );
+        assert_eq!(
+            content_at(&path, &id, "f.txt", None).unwrap(),
+            "one\ntwo\nthree\nfour\nfive\nsix\n"
+        );
+    }
+
+    #[test]
+    fn test_revert_conflict_leaves_path_unchanged() {
+        let mut path = sample_path();
+        // s4 rewrites the line s2 touched, so s2 can no longer be undone.
+        path.steps.push(
+            Step::new("s4", "human:alex", "2026-01-29T10:03:00Z")
+                .with_parent("s3")
+                .with_raw_change("f.txt", "@@ -2 +2 @@\n-TWO\n+Two\n"),
+        );
+        path.path.head = "s4".into();
+        let before = path.steps.len();
+
+        match revert_step(&mut path, "s2", &config()) {
+            Err(OpError::Conflicts(conflicts)) => {
+                assert_eq!(conflicts.len(), 1);
+                assert_eq!(conflicts[0].artifact, "f.txt");
+                assert!(conflicts[0].step.is_none());
+            }
+            other => panic!("expected conflicts, got {:?}", other),
+        }
+        assert_eq!(path.steps.len(), before);
+        assert_eq!(path.path.head, "s4");
+    }
+
+    #[test]
+    fn test_cherry_pick_dead_end() {
+        let mut path = sample_path();
+        let id = cherry_pick(&mut path, "s2a", &config()).unwrap();
+        assert_eq!(id, "s2a-pick");
+        assert_eq!(path.path.head, id);
+        let meta = path.steps.last().unwrap().meta.as_ref().unwrap();
+        assert_eq!(meta.refs[0].rel, "cherry-picked-from");
+        assert_eq!(meta.refs[0].href, "toolpath:p1/s2a");
+        assert_eq!(
+            content_at(&path, &id, "f.txt", None).unwrap(),
+            "one\nTWO\nthree\nFOUR\nfive\nsix\n"
+        );
+        // The original dead end is still a dead end.
+        let dead: Vec<&str> = query::dead_ends(&path.steps, &path.path.head)
+            .iter()
+            .map(|s| s.step.id.as_str())
+            .collect();
+        assert_eq!(dead, vec!["s2a"]);
+    }
+
+    #[test]
+    fn test_cherry_pick_active_step_rejected() {
+        let mut path = sample_path();
+        assert_eq!(
+            cherry_pick(&mut path, "s2", &config()),
+            Err(OpError::AlreadyOnHead("s2".into()))
+        );
+    }
+
+    #[test]
+    fn test_revert_dead_end_rejected() {
+        let mut path = sample_path();
+        assert_eq!(
+            revert_step(&mut path, "s2a", &config()),
+            Err(OpError::NotOnHead("s2a".into()))
+        );
+        assert_eq!(path.steps.len(), 4);
+    }
+
+    #[test]
+    fn test_missing_step() {
+        let mut path = sample_path();
+        assert_eq!(
+            revert_step(&mut path, "nope", &config()),
+            Err(OpError::StepNotFound("nope".into()))
+        );
+    }
+
+    #[test]
+    fn test_no_raw_changes() {
+        let mut path = sample_path();
+        path.steps
+            .push(Step::new("s5", "human:alex", "2026-01-29T10:04:00Z").with_parent("s3"));
+        path.path.head = "s5".into();
+        assert_eq!(
+            revert_step(&mut path, "s5", &config()),
+            Err(OpError::NoRawChanges("s5".into()))
+        );
+    }
+
+    #[test]
+    fn test_base_content_used() {
+        let mut path = Path::new("p1", None, "s1");
+        path.steps.push(
+            Step::new("s1", "human:alex", "2026-01-29T10:00:00Z")
+                .with_raw_change("f.txt", "@@ -1 +1 @@\n-x\n+y\n"),
+        );
+        assert!(matches!(
+            revert_step(&mut path.clone(), "s1", &config()),
+            Err(OpError::Conflicts(_))
+        ));
+
+        let mut config = config();
+        config.base.insert("f.txt".into(), "x\n".into());
+        config.step_id = Some("undo".into());
+        let id = revert_step(&mut path, "s1", &config).unwrap();
+        assert_eq!(id, "undo");
+        assert_eq!(
+            path.steps.last().unwrap().change["f.txt"].raw.as_deref(),
+            Some("@@ -1 +1 @@\n-y\n+x\n")
+        );
+    }
+
+    #[test]
+    fn test_unique_id_suffix() {
+        let mut path = sample_path();
+        revert_step(&mut path, "s2", &config()).unwrap();
+        let second = revert_step(&mut path, "s2-revert", &config()).unwrap();
+        assert_eq!(second, "s2-revert-revert");
+        path.steps
+            .push(Step::new("s2a-pick", "human:alex", "2026-01-29T10:03:00Z").with_parent("s1"));
+        let third = cherry_pick(&mut path, "s2a", &config()).unwrap();
+        assert_eq!(third, "s2a-pick-2");
+    }
+
+    #[test]
+    fn test_requested_id_taken() {
+        let mut path = sample_path();
+        let config = OpConfig {
+            step_id: Some("s1".into()),
+            ..config()
+        };
+        assert_eq!(
+            revert_step(&mut path, "s2", &config),
+            Err(OpError::StepIdTaken("s1".into()))
+        );
+        assert_eq!(path.steps.len(), 4);
+        assert_eq!(path.path.head, "s3");
+    }
+}
@@ -0,0 +1,1217 @@
+//! Parsed unified diffs.
+//!
+//! [`ArtifactChange::raw`](crate::types::ArtifactChange::raw) holds a unified
+//! diff as an opaque string. [`UnifiedDiff`] parses that string into hunks and
+//! lines so it can be inspected, applied to a base text, inverted, or composed
+//! with a later diff. Formatting a parsed diff reproduces the input byte for
+//! byte.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::Hash;
+use std::ops::Range;
+use std::str::FromStr;
+
+const NO_NEWLINE_MARKER: &str = "\\ No newline at end of file";
+
+/// A unified diff for a single artifact.
+///
+/// # Examples
+///
+/// ```
+/// use toolpath::v1::patch::UnifiedDiff;
+///
+/// let raw = "@@ -1,2 +1,2 @@\n fn main() {\n-    old();\n+    new();\n";
+/// let diff: UnifiedDiff = raw.parse().unwrap();
+/// assert_eq!(diff.to_string(), raw);
+///
+/// let stats = diff.stats();
+/// assert_eq!((stats.added, stats.removed), (1, 1));
+///
+/// let patched = diff.apply("fn main() {\n    old();\n}\n").unwrap();
+/// assert_eq!(patched, "fn main() {\n    new();\n}\n");
+///
+/// let restored = diff.invert().apply(&patched).unwrap();
+/// assert_eq!(restored, "fn main() {\n    old();\n}\n");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnifiedDiff {
+    /// Lines before the first hunk (`diff --git`, `---`, `+++`, …), verbatim.
+    pub header: Vec<String>,
+    pub hunks: Vec<Hunk>,
+    /// Whether the diff text ends with a newline.
+    pub trailing_newline: bool,
+}
+
+/// One `@@ -a,b +c,d @@` section of a diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    /// Text after the closing `@@` (e.g. `" fn main()"`), verbatim.
+    pub heading: String,
+    pub lines: Vec<Line>,
+    // Whether the header spelled out a length of 1 (`-3,1`) rather than
+    // omitting it (`-3`); kept so formatting is byte-exact.
+    old_len_explicit: bool,
+    new_len_explicit: bool,
+}
+
+/// A single line of a hunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub kind: LineKind,
+    /// Line content without the prefix character or newline.
+    pub text: String,
+    /// Followed by `\ No newline at end of file`.
+    pub no_newline: bool,
+}
+
+/// Whether a line is shared, added, or removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    Context,
+    Add,
+    Remove,
+}
+
+/// Line counts for a diff or hunk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffStats {
+    pub hunks: usize,
+    pub added: usize,
+    pub removed: usize,
+    pub context: usize,
+}
+
+/// Error raised while parsing, applying, or composing diffs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchError {
+    /// The diff text is malformed. `line` is 1-based.
+    Parse { line: usize, message: String },
+    /// A hunk's old-side lines were not found in the base text. `hunk` is
+    /// 0-based.
+    Conflict { hunk: usize, message: String },
+    /// Two diffs disagree about the intermediate text.
+    Compose { message: String },
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::Parse { line, message } => {
+                write!(f, "invalid diff at line {}: {}", line, message)
+            }
+            PatchError::Conflict { hunk, message } => {
+                write!(f, "hunk {} does not apply: {}", hunk + 1, message)
+            }
+            PatchError::Compose { message } => write!(f, "cannot compose diffs: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+// ============================================================================
+// Parsing and formatting
+// ============================================================================
+
+impl UnifiedDiff {
+    /// Parse a unified diff.
+    pub fn parse(text: &str) -> Result<Self, PatchError> {
+        let trailing_newline = text.ends_with('\n');
+        let body = text.strip_suffix('\n').unwrap_or(text);
+
+        let mut diff = UnifiedDiff {
+            trailing_newline,
+            ..Default::default()
+        };
+        if text.is_empty() {
+            return Ok(diff);
+        }
+
+        // Old- and new-side lines still expected by the current hunk.
+        let mut remaining = (0, 0);
+        for (idx, raw) in body.split('\n').enumerate() {
+            let lineno = idx + 1;
+            if raw.starts_with("@@ ") {
+                let hunk = parse_hunk_header(raw, lineno)?;
+                remaining = (hunk.old_len, hunk.new_len);
+                diff.hunks.push(hunk);
+                continue;
+            }
+            let Some(hunk) = diff.hunks.last_mut() else {
+                diff.header.push(raw.to_string());
+                continue;
+            };
+            let (kind, text) = match raw.as_bytes().first() {
+                // Editors often strip the space off blank context lines.
+                None if remaining.0 > 0 && remaining.1 > 0 => (LineKind::Context, ""),
+                Some(b' ') => (LineKind::Context, &raw[1..]),
+                Some(b'+') => (LineKind::Add, &raw[1..]),
+                Some(b'-') => (LineKind::Remove, &raw[1..]),
+                Some(b'\\') => {
+                    let last = hunk.lines.last_mut().ok_or_else(|| PatchError::Parse {
+                        line: lineno,
+                        message: "newline marker before any line".to_string(),
+                    })?;
+                    last.no_newline = true;
+                    continue;
+                }
+                _ => {
+                    return Err(PatchError::Parse {
+                        line: lineno,
+                        message: format!("unexpected line in hunk: {:?}", raw),
+                    });
+                }
+            };
+            if kind != LineKind::Add {
+                remaining.0 = remaining.0.saturating_sub(1);
+            }
+            if kind != LineKind::Remove {
+                remaining.1 = remaining.1.saturating_sub(1);
+            }
+            hunk.lines.push(Line {
+                kind,
+                text: text.to_string(),
+                no_newline: false,
+            });
+        }
+
+        Ok(diff)
+    }
+
+    /// Diff two texts line by line, with `context` unchanged lines around
+    /// each change. Identical texts produce an empty diff.
+    ///
+    /// ```
+    /// use toolpath::v1::patch::UnifiedDiff;
+    ///
+    /// let diff = UnifiedDiff::between("a\nb\nc\n", "a\nB\nc\n", 3);
+    /// assert_eq!(diff.to_string(), "@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n");
+    /// ```
+    pub fn between(old: &str, new: &str, context: usize) -> Self {
+        let old = split_lines(old);
+        let new = split_lines(new);
+        let ops = diff_ops(&old, &new);
+        let hunks = build_hunks(&ops, &old, &new, 0, 0, context);
+        UnifiedDiff {
+            header: Vec::new(),
+            trailing_newline: !hunks.is_empty(),
+            hunks,
+        }
+    }
+
+    /// A single hunk removing every line of `old` and adding every line of
+    /// `new`, without computing a line diff. Identical texts produce an
+    /// empty diff.
+    ///
+    /// ```
+    /// use toolpath::v1::patch::UnifiedDiff;
+    ///
+    /// let diff = UnifiedDiff::replacing("a\nb\n", "a\nc\n");
+    /// assert_eq!(diff.to_string(), "@@ -1,2 +1,2 @@\n-a\n-b\n+a\n+c\n");
+    /// ```
+    pub fn replacing(old: &str, new: &str) -> Self {
+        if old == new {
+            return Self::between(old, new, 0);
+        }
+        let old = split_lines(old);
+        let new = split_lines(new);
+        let ops: Vec<Op> = std::iter::repeat_n(Op::Delete, old.len())
+            .chain(std::iter::repeat_n(Op::Insert, new.len()))
+            .collect();
+        let hunks = build_hunks(&ops, &old, &new, 0, 0, 0);
+        UnifiedDiff {
+            header: Vec::new(),
+            trailing_newline: true,
+            hunks,
+        }
+    }
+
+    /// Whether the diff contains no hunks.
+    pub fn is_empty(&self) -> bool {
+        self.hunks.is_empty()
+    }
+
+    /// Line counts across all hunks.
+    pub fn stats(&self) -> DiffStats {
+        self.hunks.iter().fold(DiffStats::default(), |acc, h| {
+            let s = h.stats();
+            DiffStats {
+                hunks: acc.hunks + 1,
+                added: acc.added + s.added,
+                removed: acc.removed + s.removed,
+                context: acc.context + s.context,
+            }
+        })
+    }
+}
+
+impl FromStr for UnifiedDiff {
+    type Err = PatchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        UnifiedDiff::parse(s)
+    }
+}
+
+impl fmt::Display for UnifiedDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        let mut line = |f: &mut fmt::Formatter<'_>, args: fmt::Arguments<'_>| {
+            if !first {
+                f.write_str("\n")?;
+            }
+            first = false;
+            f.write_fmt(args)
+        };
+
+        for h in &self.header {
+            line(f, format_args!("{}", h))?;
+        }
+        for hunk in &self.hunks {
+            line(
+                f,
+                format_args!(
+                    "@@ -{} +{} @@{}",
+                    HunkRange(hunk.old_start, hunk.old_len, hunk.old_len_explicit),
+                    HunkRange(hunk.new_start, hunk.new_len, hunk.new_len_explicit),
+                    hunk.heading
+                ),
+            )?;
+            for l in &hunk.lines {
+                let prefix = match l.kind {
+                    LineKind::Context => ' ',
+                    LineKind::Add => '+',
+                    LineKind::Remove => '-',
+                };
+                line(f, format_args!("{}{}", prefix, l.text))?;
+                if l.no_newline {
+                    line(f, format_args!("{}", NO_NEWLINE_MARKER))?;
+                }
+            }
+        }
+        if self.trailing_newline {
+            f.write_str("\n")?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats a hunk range as `start,len`, or just `start` when the length is
+/// an implicit 1.
+struct HunkRange(usize, usize, bool);
+
+impl fmt::Display for HunkRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.1 == 1 && !self.2 {
+            write!(f, "{}", self.0)
+        } else {
+            write!(f, "{},{}", self.0, self.1)
+        }
+    }
+}
+
+fn parse_hunk_header(raw: &str, lineno: usize) -> Result<Hunk, PatchError> {
+    let err = |message: &str| PatchError::Parse {
+        line: lineno,
+        message: message.to_string(),
+    };
+    let rest = raw
+        .strip_prefix("@@ -")
+        .ok_or_else(|| err("expected `@@ -`"))?;
+    let (old, rest) = rest
+        .split_once(" +")
+        .ok_or_else(|| err("missing new range"))?;
+    let (new, heading) = rest
+        .split_once(" @@")
+        .ok_or_else(|| err("missing closing `@@`"))?;
+
+    let parse_range = |r: &str| -> Result<(usize, usize, bool), PatchError> {
+        let (start, len, explicit) = match r.split_once(',') {
+            Some((s, l)) => (s, l, true),
+            None => (r, "1", false),
+        };
+        let start = start.parse().map_err(|_| err("invalid range start"))?;
+        let len = len.parse().map_err(|_| err("invalid range length"))?;
+        Ok((start, len, explicit && len == 1))
+    };
+    let (old_start, old_len, old_len_explicit) = parse_range(old)?;
+    let (new_start, new_len, new_len_explicit) = parse_range(new)?;
+
+    Ok(Hunk {
+        old_start,
+        old_len,
+        new_start,
+        new_len,
+        heading: heading.to_string(),
+        lines: Vec::new(),
+        old_len_explicit,
+        new_len_explicit,
+    })
+}
+
+// ============================================================================
+// Hunks
+// ============================================================================
+
+impl Hunk {
+    /// Line counts for this hunk.
+    pub fn stats(&self) -> DiffStats {
+        let mut s = DiffStats {
+            hunks: 1,
+            ..Default::default()
+        };
+        for l in &self.lines {
+            match l.kind {
+                LineKind::Context => s.context += 1,
+                LineKind::Add => s.added += 1,
+                LineKind::Remove => s.removed += 1,
+            }
+        }
+        s
+    }
+
+    /// Lines present before the change (context and removals).
+    pub fn old_lines(&self) -> impl Iterator<Item = &Line> {
+        self.lines.iter().filter(|l| l.kind != LineKind::Add)
+    }
+
+    /// Lines present after the change (context and additions).
+    pub fn new_lines(&self) -> impl Iterator<Item = &Line> {
+        self.lines.iter().filter(|l| l.kind != LineKind::Remove)
+    }
+
+    /// 0-based line range covered on the old side, counted from the hunk's
+    /// lines rather than its header.
+    pub fn old_range(&self) -> Range<usize> {
+        side_range(self.old_start, self.old_lines().count())
+    }
+
+    /// 0-based line range covered on the new side.
+    pub fn new_range(&self) -> Range<usize> {
+        side_range(self.new_start, self.new_lines().count())
+    }
+}
+
+// An empty range starts *after* the named line (`-8,0` inserts after line 8).
+fn side_range(start: usize, len: usize) -> Range<usize> {
+    let begin = if len == 0 {
+        start
+    } else {
+        start.saturating_sub(1)
+    };
+    begin..begin + len
+}
+
+// ============================================================================
+// Apply / invert / compose
+// ============================================================================
+
+/// A line of text and whether it ends with a newline.
+type TextLine<'a> = (&'a str, bool);
+
+fn split_lines(text: &str) -> Vec<TextLine<'_>> {
+    if text.is_empty() {
+        return Vec::new();
+    }
+    let ends_nl = text.ends_with('\n');
+    let body = text.strip_suffix('\n').unwrap_or(text);
+    let mut lines: Vec<TextLine<'_>> = body.split('\n').map(|l| (l, true)).collect();
+    if let Some(last) = lines.last_mut() {
+        last.1 = ends_nl;
+    }
+    lines
+}
+
+fn join_lines<'a>(lines: impl IntoIterator<Item = TextLine<'a>>) -> String {
+    let mut out = String::new();
+    for (text, nl) in lines {
+        out.push_str(text);
+        if nl {
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn as_text_line(l: &Line) -> TextLine<'_> {
+    (l.text.as_str(), !l.no_newline)
+}
+
+impl UnifiedDiff {
+    /// Apply the diff to `original`, returning the patched text.
+    ///
+    /// Hunks are located at their stated position first, then by searching
+    /// outward, so a diff still applies after unrelated lines shift. A hunk
+    /// whose old-side lines cannot be found yields
+    /// [`PatchError::Conflict`].
+    pub fn apply(&self, original: &str) -> Result<String, PatchError> {
+        let orig = split_lines(original);
+        let mut out: Vec<TextLine<'_>> = Vec::with_capacity(orig.len());
+        let mut cursor = 0usize;
+        let mut offset = 0isize;
+
+        for (idx, hunk) in self.hunks.iter().enumerate() {
+            let old: Vec<&str> = hunk.old_lines().map(|l| l.text.as_str()).collect();
+            let expected = hunk.old_range().start as isize + offset;
+            let pos =
+                find_hunk(&orig, cursor, expected, &old).ok_or_else(|| PatchError::Conflict {
+                    hunk: idx,
+                    message: format!(
+                        "expected {} line(s) near line {} not found",
+                        old.len(),
+                        expected.max(0) + 1
+                    ),
+                })?;
+            offset = pos as isize - hunk.old_range().start as isize;
+
+            out.extend_from_slice(&orig[cursor..pos]);
+            out.extend(hunk.new_lines().map(as_text_line));
+            cursor = pos + old.len();
+        }
+        out.extend_from_slice(&orig[cursor..]);
+
+        Ok(join_lines(out))
+    }
+
+    /// The diff that undoes this one.
+    pub fn invert(&self) -> UnifiedDiff {
+        let mut header = self
+            .header
+            .iter()
+            .map(|h| {
+                if let Some(rest) = h.strip_prefix("--- ") {
+                    format!("+++ {}", rest)
+                } else if let Some(rest) = h.strip_prefix("+++ ") {
+                    format!("--- {}", rest)
+                } else {
+                    h.clone()
+                }
+            })
+            .collect::<Vec<_>>();
+        if let (Some(minus), Some(plus)) = (
+            header.iter().position(|h| h.starts_with("--- ")),
+            header.iter().position(|h| h.starts_with("+++ ")),
+        ) && plus < minus
+        {
+            header.swap(minus, plus);
+        }
+
+        let hunks = self
+            .hunks
+            .iter()
+            .map(|h| {
+                let mut lines = Vec::with_capacity(h.lines.len());
+                for (context, run) in h
+                    .lines
+                    .chunk_by(|a, b| (a.kind == LineKind::Context) == (b.kind == LineKind::Context))
+                    .map(|run| (run[0].kind == LineKind::Context, run))
+                {
+                    if context {
+                        lines.extend_from_slice(run);
+                    } else {
+                        lines.extend(invert_run(run));
+                    }
+                }
+                Hunk {
+                    old_start: h.new_start,
+                    old_len: h.new_len,
+                    new_start: h.old_start,
+                    new_len: h.old_len,
+                    heading: h.heading.clone(),
+                    lines,
+                    old_len_explicit: h.new_len_explicit,
+                    new_len_explicit: h.old_len_explicit,
+                }
+            })
+            .collect();
+
+        UnifiedDiff {
+            header,
+            hunks,
+            trailing_newline: self.trailing_newline,
+        }
+    }
+}
+
+/// Combine `a` (X → Y) and `b` (Y → Z) into a single diff X → Z.
+///
+/// No base text is needed: the lines both diffs show of Y must agree, and
+/// regions touched by either diff are recomputed. Header lines are taken
+/// from `a`.
+///
+/// ```
+/// use toolpath::v1::patch::{UnifiedDiff, compose};
+///
+/// let x = "one\ntwo\nthree\n";
+/// let y = "one\n2\nthree\n";
+/// let z = "one\n2\nthree\nfour\n";
+/// let a = UnifiedDiff::between(x, y, 3);
+/// let b = UnifiedDiff::between(y, z, 3);
+///
+/// let ab = compose(&a, &b).unwrap();
+/// assert_eq!(ab.apply(x).unwrap(), z);
+/// ```
+pub fn compose(a: &UnifiedDiff, b: &UnifiedDiff) -> Result<UnifiedDiff, PatchError> {
+    // Regions of the intermediate text Y touched by either diff.
+    let mut spans: Vec<Range<usize>> = a
+        .hunks
+        .iter()
+        .map(Hunk::new_range)
+        .chain(b.hunks.iter().map(Hunk::old_range))
+        .collect();
+    spans.sort_by_key(|r| (r.start, r.end));
+    let mut segments: Vec<Range<usize>> = Vec::new();
+    for r in spans {
+        match segments.last_mut() {
+            Some(last) if r.start <= last.end => last.end = last.end.max(r.end),
+            _ => segments.push(r),
+        }
+    }
+
+    let inside = |r: &Range<usize>, seg: &Range<usize>| r.start >= seg.start && r.end <= seg.end;
+    let mut hunks = Vec::new();
+
+    for seg in &segments {
+        // Reconstruct Y over the segment from both diffs' views of it.
+        let mut y: Vec<Option<TextLine<'_>>> = vec![None; seg.len()];
+        let views = a
+            .hunks
+            .iter()
+            .filter(|h| inside(&h.new_range(), seg))
+            .map(|h| {
+                (
+                    h.new_range(),
+                    h.new_lines().map(as_text_line).collect::<Vec<_>>(),
+                )
+            })
+            .chain(
+                b.hunks
+                    .iter()
+                    .filter(|h| inside(&h.old_range(), seg))
+                    .map(|h| (h.old_range(), h.old_lines().map(as_text_line).collect())),
+            );
+        for (range, lines) in views {
+            for (i, line) in range.zip(lines) {
+                let slot = &mut y[i - seg.start];
+                match slot {
+                    Some(existing) if *existing != line => {
+                        return Err(PatchError::Compose {
+                            message: format!(
+                                "intermediate line {} differs: {:?} vs {:?}",
+                                i + 1,
+                                existing.0,
+                                line.0
+                            ),
+                        });
+                    }
+                    _ => *slot = Some(line),
+                }
+            }
+        }
+        let y: Vec<TextLine<'_>> =
+            y.into_iter()
+                .collect::<Option<_>>()
+                .ok_or_else(|| PatchError::Compose {
+                    message: "intermediate text has gaps".to_string(),
+                })?;
+
+        // X = Y with a's hunks undone; Z = Y with b's hunks applied.
+        let x = rebuild(
+            &y,
+            seg.start,
+            a.hunks.iter().filter(|h| inside(&h.new_range(), seg)),
+            |h| (h.new_range(), h.old_lines().map(as_text_line).collect()),
+        );
+        let z = rebuild(
+            &y,
+            seg.start,
+            b.hunks.iter().filter(|h| inside(&h.old_range(), seg)),
+            |h| (h.old_range(), h.new_lines().map(as_text_line).collect()),
+        );
+
+        let shift = |hs: &[Hunk], before: fn(&Hunk) -> Range<usize>, sign: isize| {
+            hs.iter()
+                .filter(|h| before(h).end <= seg.start && !inside(&before(h), seg))
+                .map(|h| sign * (h.new_lines().count() as isize - h.old_lines().count() as isize))
+                .sum::<isize>()
+        };
+        let x_start = (seg.start as isize + shift(&a.hunks, Hunk::new_range, -1)) as usize;
+        let z_start = (seg.start as isize + shift(&b.hunks, Hunk::old_range, 1)) as usize;
+
+        let ops = diff_ops(&x, &z);
+        hunks.extend(build_hunks(&ops, &x, &z, x_start, z_start, 3));
+    }
+
+    Ok(UnifiedDiff {
+        header: a.header.clone(),
+        trailing_newline: !hunks.is_empty(),
+        hunks,
+    })
+}
+
+/// Replace each hunk's span of `y` (offset by `base`) with the lines
+/// `side` returns for it.
+fn rebuild<'a, 'h: 'a>(
+    y: &[TextLine<'a>],
+    base: usize,
+    hunks: impl Iterator<Item = &'h Hunk>,
+    side: impl Fn(&'h Hunk) -> (Range<usize>, Vec<TextLine<'a>>),
+) -> Vec<TextLine<'a>> {
+    let mut out = Vec::new();
+    let mut pos = 0;
+    for h in hunks {
+        let (range, lines) = side(h);
+        out.extend_from_slice(&y[pos..range.start - base]);
+        out.extend(lines);
+        pos = range.end - base;
+    }
+    out.extend_from_slice(&y[pos..]);
+    out
+}
+
+/// Invert a run of changed lines: the added lines become removals and the
+/// removed lines additions, each in their order. The run's pattern of kinds
+/// is reversed and flipped, so a run of removals then additions stays in
+/// that order and inverting twice gives back the original run.
+fn invert_run(run: &[Line]) -> Vec<Line> {
+    let mut removed = run.iter().filter(|l| l.kind == LineKind::Remove);
+    let mut added = run.iter().filter(|l| l.kind == LineKind::Add);
+    // Each reversed removal is the slot of one former removal, now an
+    // addition, and likewise for additions, so the iterators never run dry.
+    run.iter()
+        .rev()
+        .filter_map(|l| match l.kind {
+            LineKind::Remove => removed.next().map(|r| Line {
+                kind: LineKind::Add,
+                ..r.clone()
+            }),
+            _ => added.next().map(|a| Line {
+                kind: LineKind::Remove,
+                ..a.clone()
+            }),
+        })
+        .collect()
+}
+
+/// Within each run of changed lines, put removals before additions.
+fn removals_first(lines: Vec<Line>) -> Vec<Line> {
+    let mut out = Vec::with_capacity(lines.len());
+    let mut adds = Vec::new();
+    for l in lines {
+        match l.kind {
+            LineKind::Remove => out.push(l),
+            LineKind::Add => adds.push(l),
+            LineKind::Context => {
+                out.append(&mut adds);
+                out.push(l);
+            }
+        }
+    }
+    out.append(&mut adds);
+    out
+}
+
+/// Locate `old` in `lines` at or after `cursor`, preferring positions
+/// closest to `expected`.
+fn find_hunk(
+    lines: &[TextLine<'_>],
+    cursor: usize,
+    expected: isize,
+    old: &[&str],
+) -> Option<usize> {
+    if lines.len() < old.len() {
+        return None;
+    }
+    let last = lines.len() - old.len();
+    if cursor > last {
+        return None;
+    }
+    let matches = |pos: usize| {
+        lines[pos..pos + old.len()]
+            .iter()
+            .zip(old)
+            .all(|((text, _), want)| text == want)
+    };
+    let expected = expected.clamp(cursor as isize, last as isize) as usize;
+    (0..=last - cursor).find_map(|d| {
+        let below = expected.checked_sub(d).filter(|p| *p >= cursor);
+        let above = Some(expected + d).filter(|p| *p <= last);
+        below
+            .filter(|p| matches(*p))
+            .or(above.filter(|p| matches(*p)))
+    })
+}
+
+// ============================================================================
+// Line diff (Myers)
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Shortest edit script turning `a` into `b`.
+fn diff_ops<T: Eq + Hash>(a: &[T], b: &[T]) -> Vec<Op> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut ops = vec![Op::Equal; prefix];
+    ops.extend(matched_ops(a_mid, b_mid));
+    ops.extend(std::iter::repeat_n(Op::Equal, suffix));
+    ops
+}
+
+/// Lines found on only one side can't be part of a common subsequence, so
+/// run [`myers`] over the lines both sides share and map its script back,
+/// deleting and inserting everything else in the gaps between matches.
+/// Rewrites that keep only a few lines (blank ones, say) stay cheap.
+fn matched_ops<T: Eq + Hash>(a: &[T], b: &[T]) -> Vec<Op> {
+    let (in_a, in_b): (HashSet<&T>, HashSet<&T>) = (a.iter().collect(), b.iter().collect());
+    let a_idx: Vec<usize> = (0..a.len()).filter(|&i| in_b.contains(&a[i])).collect();
+    let b_idx: Vec<usize> = (0..b.len()).filter(|&j| in_a.contains(&b[j])).collect();
+    let a_kept: Vec<&T> = a_idx.iter().map(|&i| &a[i]).collect();
+    let b_kept: Vec<&T> = b_idx.iter().map(|&j| &b[j]).collect();
+
+    let mut ops = Vec::with_capacity(a.len() + b.len());
+    let (mut i, mut j) = (0, 0);
+    let (mut ri, mut rj) = (0, 0);
+    for op in myers(&a_kept, &b_kept) {
+        match op {
+            Op::Equal => {
+                let (ai, bj) = (a_idx[ri], b_idx[rj]);
+                ops.extend(std::iter::repeat_n(Op::Delete, ai - i));
+                ops.extend(std::iter::repeat_n(Op::Insert, bj - j));
+                ops.push(Op::Equal);
+                (i, j) = (ai + 1, bj + 1);
+                ri += 1;
+                rj += 1;
+            }
+            Op::Delete => ri += 1,
+            Op::Insert => rj += 1,
+        }
+    }
+    ops.extend(std::iter::repeat_n(Op::Delete, a.len() - i));
+    ops.extend(std::iter::repeat_n(Op::Insert, b.len() - j));
+    ops
+}
+
+/// Myers' algorithm in linear space (middle-snake divide and conquer), so
+/// large rewrites don't keep a copy of the frontier per edit.
+fn myers<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Op> {
+    let mut ops = Ops(Vec::with_capacity(a.len().max(b.len())));
+    let Ok(()) = similar::algorithms::myers::diff(&mut ops, a, 0..a.len(), b, 0..b.len());
+    ops.0
+}
+
+/// Collects [`similar`]'s callbacks as an edit script.
+struct Ops(Vec<Op>);
+
+impl similar::algorithms::DiffHook for Ops {
+    type Error = std::convert::Infallible;
+
+    fn equal(&mut self, _old: usize, _new: usize, len: usize) -> Result<(), Self::Error> {
+        self.0.extend(std::iter::repeat_n(Op::Equal, len));
+        Ok(())
+    }
+
+    fn delete(&mut self, _old: usize, len: usize, _new: usize) -> Result<(), Self::Error> {
+        self.0.extend(std::iter::repeat_n(Op::Delete, len));
+        Ok(())
+    }
+
+    fn insert(&mut self, _old: usize, _new: usize, len: usize) -> Result<(), Self::Error> {
+        self.0.extend(std::iter::repeat_n(Op::Insert, len));
+        Ok(())
+    }
+}
+
+/// Group an edit script into hunks with `context` lines around changes.
+/// `old_base` / `new_base` are the 0-based positions of `old[0]` / `new[0]`
+/// in their files.
+fn build_hunks(
+    ops: &[Op],
+    old: &[TextLine<'_>],
+    new: &[TextLine<'_>],
+    old_base: usize,
+    new_base: usize,
+    context: usize,
+) -> Vec<Hunk> {
+    // Position in old/new before each op.
+    let mut positions = Vec::with_capacity(ops.len() + 1);
+    let (mut i, mut j) = (0, 0);
+    for op in ops {
+        positions.push((i, j));
+        match op {
+            Op::Equal => {
+                i += 1;
+                j += 1;
+            }
+            Op::Delete => i += 1,
+            Op::Insert => j += 1,
+        }
+    }
+    positions.push((i, j));
+
+    let changes: Vec<usize> = (0..ops.len()).filter(|k| ops[*k] != Op::Equal).collect();
+    let mut groups: Vec<Range<usize>> = Vec::new();
+    for &k in &changes {
+        match groups.last_mut() {
+            Some(g) if k - g.end <= 2 * context => g.end = k + 1,
+            _ => groups.push(k..k + 1),
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|g| {
+            let start = g.start.saturating_sub(context);
+            let end = (g.end + context).min(ops.len());
+            let mut lines = Vec::new();
+            for k in start..end {
+                let (i, j) = positions[k];
+                let (kind, (text, nl)) = match ops[k] {
+                    Op::Equal => (LineKind::Context, new[j]),
+                    Op::Delete => (LineKind::Remove, old[i]),
+                    Op::Insert => (LineKind::Add, new[j]),
+                };
+                lines.push(Line {
+                    kind,
+                    text: text.to_string(),
+                    no_newline: !nl,
+                });
+            }
+            let (oi, nj) = positions[start];
+            let (oe, ne) = positions[end];
+            let (old_len, new_len) = (oe - oi, ne - nj);
+            Hunk {
+                old_start: old_base + oi + usize::from(old_len > 0),
+                old_len,
+                new_start: new_base + nj + usize::from(new_len > 0),
+                new_len,
+                heading: String::new(),
+                lines: removals_first(lines),
+                old_len_explicit: false,
+                new_len_explicit: false,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn examples_dir() -> std::path::PathBuf {
+        std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../examples")
+    }
+
+    fn collect_raw(value: &serde_json::Value, out: &mut Vec<String>) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (k, v) in map {
+                    match (k.as_str(), v) {
+                        ("raw", serde_json::Value::String(s)) => out.push(s.clone()),
+                        _ => collect_raw(v, out),
+                    }
+                }
+            }
+            serde_json::Value::Array(items) => items.iter().for_each(|v| collect_raw(v, out)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_examples_roundtrip() {
+        let mut checked = 0;
+        for entry in std::fs::read_dir(examples_dir()).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let value: serde_json::Value =
+                serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+            let mut raws = Vec::new();
+            collect_raw(&value, &mut raws);
+            for raw in raws {
+                match UnifiedDiff::parse(&raw) {
+                    Ok(diff) => {
+                        assert_eq!(diff.to_string(), raw, "roundtrip in {:?}", path);
+                        assert!(!diff.hunks.is_empty());
+                        checked += 1;
+                    }
+                    // Placeholder bodies like "...(merged changes)..." are
+                    // not diff lines.
+                    Err(PatchError::Parse { .. }) => assert!(raw.contains("\n..."), "{raw}"),
+                    Err(e) => panic!("{:?}: {}", path, e),
+                }
+            }
+        }
+        assert!(checked > 20);
+    }
+
+    #[test]
+    fn test_examples_invert_roundtrip() {
+        let mut checked = 0;
+        for entry in std::fs::read_dir(examples_dir()).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let value: serde_json::Value =
+                serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+            let mut raws = Vec::new();
+            collect_raw(&value, &mut raws);
+            for diff in raws.iter().filter_map(|raw| UnifiedDiff::parse(raw).ok()) {
+                assert_eq!(diff.invert().invert(), diff, "in {:?}", path);
+                checked += 1;
+            }
+        }
+        assert!(checked > 20);
+    }
+
+    #[test]
+    fn test_invert_twice_keeps_run_order() {
+        for raw in [
+            "@@ -1,3 +1,8 @@\n+use std::process;\n+\n fn main() {\n-    println!(\"Hello, world!\");\n+    let args: Vec<String> = std::env::args().collect();\n }",
+            "@@ -1,2 +1,2 @@\n+b\n-a\n c\n",
+            "@@ -1,3 +1,3 @@\n-a\n+x\n-b\n+y\n-c\n+z\n",
+        ] {
+            let diff = UnifiedDiff::parse(raw).unwrap();
+            assert_eq!(diff.invert().invert(), diff, "{raw}");
+        }
+        let diff = UnifiedDiff::parse("@@ -1,2 +1,2 @@\n+b\n-a\n c\n").unwrap();
+        assert_eq!(diff.invert().apply("b\nc\n").unwrap(), "a\nc\n");
+    }
+
+    #[test]
+    fn test_parse_blank_context_line() {
+        let raw = "@@ -1,3 +1,3 @@\n a\n\n-b\n+B\n";
+        let diff = UnifiedDiff::parse(raw).unwrap();
+        let line = &diff.hunks[0].lines[1];
+        assert_eq!((line.kind, line.text.as_str()), (LineKind::Context, ""));
+        assert_eq!(diff.apply("a\n\nb\n").unwrap(), "a\n\nB\n");
+        assert_eq!(diff.to_string(), "@@ -1,3 +1,3 @@\n a\n \n-b\n+B\n");
+
+        // Past the end of the hunk a blank line is still an error.
+        assert!(matches!(
+            UnifiedDiff::parse("@@ -1 +1 @@\n-a\n+b\n\n x\n"),
+            Err(PatchError::Parse { line: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_header_and_heading() {
+        let raw = "--- a/f.rs\n+++ b/f.rs\n@@ -3 +3,2 @@ fn main() {\n-a\n+b\n+c\n";
+        let diff = UnifiedDiff::parse(raw).unwrap();
+        assert_eq!(diff.header, vec!["--- a/f.rs", "+++ b/f.rs"]);
+        let h = &diff.hunks[0];
+        assert_eq!(
+            (h.old_start, h.old_len, h.new_start, h.new_len),
+            (3, 1, 3, 2)
+        );
+        assert_eq!(h.heading, " fn main() {");
+        assert_eq!(diff.to_string(), raw);
+    }
+
+    #[test]
+    fn test_parse_no_newline_marker() {
+        let raw = "@@ -1 +1 @@\n-a\n\\ No newline at end of file\n+a\n";
+        let diff = UnifiedDiff::parse(raw).unwrap();
+        assert!(diff.hunks[0].lines[0].no_newline);
+        assert!(!diff.hunks[0].lines[1].no_newline);
+        assert_eq!(diff.to_string(), raw);
+        assert_eq!(diff.apply("a").unwrap(), "a\n");
+        assert_eq!(diff.invert().apply("a\n").unwrap(), "a");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            UnifiedDiff::parse("@@ -1 +1 @@\n?what"),
+            Err(PatchError::Parse { line: 2, .. })
+        ));
+        assert!(UnifiedDiff::parse("@@ -x +1 @@\n").is_err());
+        assert!(UnifiedDiff::parse("@@ -1 +1\n").is_err());
+        assert!(UnifiedDiff::parse("@@ -1 +1 @@\n\\ No newline at end of file").is_err());
+    }
+
+    #[test]
+    fn test_empty() {
+        let diff = UnifiedDiff::parse("").unwrap();
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "");
+        assert_eq!(diff.apply("x\n").unwrap(), "x\n");
+    }
+
+    #[test]
+    fn test_stats() {
+        let diff =
+            UnifiedDiff::parse("@@ -1,2 +1,3 @@\n a\n-b\n+c\n+d\n@@ -9 +10 @@\n-x\n+y").unwrap();
+        assert_eq!(
+            diff.stats(),
+            DiffStats {
+                hunks: 2,
+                added: 3,
+                removed: 2,
+                context: 1
+            }
+        );
+        assert_eq!(diff.hunks[1].stats().added, 1);
+    }
+
+    #[test]
+    fn test_between_matches_gnu_format() {
+        let diff = UnifiedDiff::between("a\nb\nc\nd\n", "a\nc\nd\ne\n", 1);
+        assert_eq!(diff.to_string(), "@@ -1,4 +1,4 @@\n a\n-b\n c\n d\n+e\n");
+        let diff = UnifiedDiff::between("", "x\n", 3);
+        assert_eq!(diff.to_string(), "@@ -0,0 +1 @@\n+x\n");
+        assert!(UnifiedDiff::between("same\n", "same\n", 3).is_empty());
+    }
+
+    #[test]
+    fn test_between_apply_roundtrip() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+        let new = "1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\n11\neleven-and-a-half\n12";
+        let diff = UnifiedDiff::between(old, new, 3);
+        assert_eq!(diff.hunks.len(), 2);
+        assert_eq!(diff.apply(old).unwrap(), new);
+        assert_eq!(diff.invert().apply(new).unwrap(), old);
+        let reparsed = UnifiedDiff::parse(&diff.to_string()).unwrap();
+        assert_eq!(reparsed, diff);
+    }
+
+    #[test]
+    fn test_apply_with_offset() {
+        let diff = UnifiedDiff::parse("@@ -2,2 +2,2 @@\n b\n-c\n+C\n").unwrap();
+        // Two lines were inserted above the hunk since the diff was made.
+        let patched = diff.apply("x\ny\na\nb\nc\nd\n").unwrap();
+        assert_eq!(patched, "x\ny\na\nb\nC\nd\n");
+    }
+
+    #[test]
+    fn test_apply_conflict() {
+        let diff = UnifiedDiff::parse("@@ -1 +1 @@\n-missing\n+new\n").unwrap();
+        match diff.apply("a\nb\n") {
+            Err(PatchError::Conflict { hunk, .. }) => assert_eq!(hunk, 0),
+            other => panic!("expected conflict, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_apply_insert_only_hunk() {
+        let diff = UnifiedDiff::parse("@@ -2,0 +3,1 @@\n+inserted\n").unwrap();
+        assert_eq!(diff.apply("a\nb\nc\n").unwrap(), "a\nb\ninserted\nc\n");
+    }
+
+    #[test]
+    fn test_invert_swaps_header_and_orders_runs() {
+        let diff = UnifiedDiff::parse("--- a/f\n+++ b/f\n@@ -1 +1,2 @@\n-a\n+b\n+c\n").unwrap();
+        let inv = diff.invert();
+        assert_eq!(
+            inv.to_string(),
+            "--- b/f\n+++ a/f\n@@ -1,2 +1 @@\n-b\n-c\n+a\n"
+        );
+    }
+
+    #[test]
+    fn test_compose_overlapping() {
+        let x = "a\nb\nc\nd\ne\nf\ng\nh\n";
+        let y = "a\nB\nc\nd\ne\nf\ng\nh\n";
+        let z = "a\nB\nC\nd\ne\nf\ng\nH\n";
+        let a = UnifiedDiff::between(x, y, 1);
+        let b = UnifiedDiff::between(y, z, 1);
+        let ab = compose(&a, &b).unwrap();
+        assert_eq!(ab.apply(x).unwrap(), z);
+        assert_eq!(ab.invert().apply(z).unwrap(), x);
+    }
+
+    #[test]
+    fn test_compose_disjoint_with_shift() {
+        let x: String = (1..=30).map(|i| format!("{}\n", i)).collect();
+        let y = x.replacen("2\n", "2\n2.5\n2.6\n", 1);
+        let z = y.replacen("25\n", "twenty-five\n", 1);
+        let a = UnifiedDiff::between(&x, &y, 3);
+        let b = UnifiedDiff::between(&y, &z, 3);
+        let ab = compose(&a, &b).unwrap();
+        assert_eq!(ab.hunks.len(), 2);
+        assert_eq!(ab.apply(&x).unwrap(), z);
+    }
+
+    #[test]
+    fn test_compose_cancelling() {
+        let a = UnifiedDiff::between("a\nb\n", "a\nc\n", 3);
+        let ab = compose(&a, &a.invert()).unwrap();
+        assert!(ab.is_empty());
+    }
+
+    #[test]
+    fn test_compose_conflict() {
+        let a = UnifiedDiff::parse("@@ -1 +1 @@\n-a\n+b\n").unwrap();
+        let b = UnifiedDiff::parse("@@ -1 +1 @@\n-not-b\n+c\n").unwrap();
+        assert!(matches!(compose(&a, &b), Err(PatchError::Compose { .. })));
+    }
+
+    #[test]
+    fn test_myers_minimal() {
+        let a: Vec<char> = "abcabba".chars().collect();
+        let b: Vec<char> = "cbabac".chars().collect();
+        let ops = diff_ops(&a, &b);
+        let edits = ops.iter().filter(|o| **o != Op::Equal).count();
+        assert_eq!(edits, 5);
+    }
+
+    #[test]
+    fn test_replacing() {
+        let old = "a\nb\nc";
+        let new = "a\nB\nc\n";
+        let diff = UnifiedDiff::replacing(old, new);
+        assert_eq!(diff.hunks.len(), 1);
+        assert_eq!((diff.stats().added, diff.stats().removed), (3, 3));
+        assert_eq!(diff.apply(old).unwrap(), new);
+        assert_eq!(diff.invert().apply(new).unwrap(), old);
+        assert!(UnifiedDiff::replacing(old, old).is_empty());
+        assert_eq!(UnifiedDiff::replacing("", new).apply("").unwrap(), new);
+    }
+
+    #[test]
+    fn test_between_large_inputs() {
+        let lines: String = (0..20_000).map(|i| format!("line {}\n", i)).collect();
+        let diff = UnifiedDiff::between("", &lines, 3);
+        assert_eq!(diff.stats().added, 20_000);
+        assert_eq!(diff.apply("").unwrap(), lines);
+
+        let old: String = (0..8_000).map(|i| format!("old {}\n", i)).collect();
+        let new: String = (0..8_000).map(|i| format!("new {}\n", i)).collect();
+        let diff = UnifiedDiff::between(&old, &new, 3);
+        assert_eq!((diff.stats().added, diff.stats().removed), (8_000, 8_000));
+        assert_eq!(diff.apply(&old).unwrap(), new);
+        assert_eq!(diff.invert().apply(&new).unwrap(), old);
+
+        // A rewrite that keeps a blank line every 100 lines.
+        let mark = |prefix: &str| -> String {
+            (0..8_000)
+                .map(|i| match i % 100 {
+                    0 => "\n".to_string(),
+                    _ => format!("{} {}\n", prefix, i),
+                })
+                .collect()
+        };
+        let (old, new) = (mark("old"), mark("new"));
+        let diff = UnifiedDiff::between(&old, &new, 3);
+        assert_eq!(diff.apply(&old).unwrap(), new);
+    }
+}
@@ -0,0 +1,519 @@
+//! Removing or externalizing abandoned branches of a [`Path`].
+//!
+//! A *dead branch* is a connected group of [`dead_ends`](crate::query::dead_ends):
+//! steps that are not ancestors of `head`. [`prune`] drops matching branches
+//! outright; [`archive`] moves each one into its own Path, based on the step
+//! it forked from, and leaves a stub step in its place that links to the
+//! archive.
+
+use crate::ext::Extension;
+use crate::query;
+use crate::types::{Base, Path, PathIdentity, Ref, Step, StepMeta};
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+/// Which dead branches to remove. Every criterion that is set must match;
+/// with none set, every dead branch matches.
+#[derive(Debug, Clone, Default)]
+pub struct PruneConfig {
+    /// Only branches whose newest step is before this RFC 3339 timestamp.
+    pub older_than: Option<String>,
+    /// Only branches with fewer than this many steps.
+    pub smaller_than: Option<usize>,
+    /// Only branches whose every step has this actor kind (e.g. `"agent"`).
+    pub actor_kind: Option<String>,
+}
+
+/// A connected group of steps that are not ancestors of `head`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeadBranch {
+    /// First step of the branch.
+    pub root: String,
+    /// Active step the branch forked from; `None` for a parentless root.
+    pub fork: Option<String>,
+    /// Step IDs in the branch, in document order.
+    pub steps: Vec<String>,
+    /// Latest timestamp among the branch's steps.
+    pub newest: String,
+}
+
+/// Marker stored on the stub step that replaces an archived branch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Archived {
+    /// ID of the Path holding the archived steps.
+    pub path: String,
+    /// Number of steps archived.
+    pub steps: usize,
+}
+
+impl Extension for Archived {
+    const KEY: &'static str = "toolpath.archived";
+}
+
+/// Group a path's dead ends into branches, in document order of their roots.
+///
+/// Stub steps left by [`archive`] are not reported.
+pub fn dead_branches(path: &Path) -> Vec<DeadBranch> {
+    let dead: Vec<&Step> = query::dead_ends(&path.steps, &path.path.head)
+        .into_iter()
+        .filter(|s| !is_stub(s))
+        .collect();
+    let dead_ids: HashSet<&str> = dead.iter().map(|s| s.step.id.as_str()).collect();
+
+    // Union dead steps with their dead parents, wherever they are listed.
+    let index: HashMap<&str, usize> = dead
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (s.step.id.as_str(), i))
+        .collect();
+    let mut group: Vec<usize> = (0..dead.len()).collect();
+    fn find(group: &mut [usize], mut i: usize) -> usize {
+        while group[i] != i {
+            group[i] = group[group[i]];
+            i = group[i];
+        }
+        i
+    }
+    for (i, step) in dead.iter().enumerate() {
+        for parent in &step.step.parents {
+            if let Some(&j) = index.get(parent.as_str()) {
+                let (a, b) = (find(&mut group, i), find(&mut group, j));
+                group[a] = b;
+            }
+        }
+    }
+    let mut members: Vec<Vec<&Step>> = vec![Vec::new(); dead.len()];
+    for (i, step) in dead.iter().enumerate() {
+        members[find(&mut group, i)].push(step);
+    }
+
+    let order: HashMap<&str, usize> = path
+        .steps
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (s.step.id.as_str(), i))
+        .collect();
+    let mut branches: Vec<DeadBranch> = members
+        .into_iter()
+        .filter(|m| !m.is_empty())
+        .map(|mut steps| {
+            steps.sort_by_key(|s| order[s.step.id.as_str()]);
+            let root = steps
+                .iter()
+                .find(|s| !s.step.parents.iter().any(|p| dead_ids.contains(p.as_str())))
+                .unwrap_or(&steps[0]);
+            DeadBranch {
+                root: root.step.id.clone(),
+                fork: root.step.parents.first().cloned(),
+                newest: steps
+                    .iter()
+                    .map(|s| s.step.timestamp.as_str())
+                    .max_by(|a, b| compare_timestamps(a, b))
+                    .unwrap_or_default()
+                    .to_string(),
+                steps: steps.iter().map(|s| s.step.id.clone()).collect(),
+            }
+        })
+        .collect();
+    branches.sort_by_key(|b| order[b.root.as_str()]);
+    branches
+}
+
+/// Whether a branch satisfies every criterion set in `config`.
+pub fn matches(path: &Path, branch: &DeadBranch, config: &PruneConfig) -> bool {
+    if let Some(cutoff) = &config.older_than
+        && compare_timestamps(&branch.newest, cutoff) != Ordering::Less
+    {
+        return false;
+    }
+    if let Some(limit) = config.smaller_than
+        && branch.steps.len() >= limit
+    {
+        return false;
+    }
+    if let Some(kind) = &config.actor_kind {
+        let prefix = format!("{}:", kind);
+        let ids: HashSet<&str> = branch.steps.iter().map(String::as_str).collect();
+        return path
+            .steps
+            .iter()
+            .filter(|s| ids.contains(s.step.id.as_str()))
+            .all(|s| s.step.actor.starts_with(&prefix));
+    }
+    true
+}
+
+/// Drop every dead branch matching `config`. Returns the removed steps.
+///
+/// # Examples
+///
+/// ```
+/// use toolpath::v1::{Path, Step, prune};
+///
+/// let mut path = Path::new("p1", None, "s2");
+/// path.steps = vec![
+///     Step::new("s1", "human:alex", "2026-01-01T10:00:00Z"),
+///     Step::new("s1a", "agent:claude", "2026-01-01T10:01:00Z").with_parent("s1"),
+///     Step::new("s2", "human:alex", "2026-01-01T10:02:00Z").with_parent("s1"),
+/// ];
+///
+/// let config = prune::PruneConfig {
+///     actor_kind: Some("agent".into()),
+///     ..Default::default()
+/// };
+/// let removed = prune::prune(&mut path, &config);
+/// assert_eq!(removed[0].step.id, "s1a");
+/// assert_eq!(path.steps.len(), 2);
+/// ```
+pub fn prune(path: &mut Path, config: &PruneConfig) -> Vec<Step> {
+    let doomed: HashSet<String> = dead_branches(path)
+        .into_iter()
+        .filter(|b| matches(path, b, config))
+        .flat_map(|b| b.steps)
+        .collect();
+    let (removed, kept) = std::mem::take(&mut path.steps)
+        .into_iter()
+        .partition(|s| doomed.contains(&s.step.id));
+    path.steps = kept;
+    removed
+}
+
+/// Move every dead branch matching `config` into its own Path and return
+/// those paths.
+///
+/// Each archived path is named `<path-id>-<root>`, is based on
+/// `toolpath:<path-id>/<fork>`, and keeps the original step IDs. Each of the
+/// branch's root steps (one per fork it joins) is replaced by a stub with
+/// the same ID and parents,
+/// carrying an [`Archived`] marker and an `archived` ref to
+/// `<archive_url>#<archived-path-id>` — the same form as a Graph `$ref`.
+///
+/// # Examples
+///
+/// ```
+/// use toolpath::v1::{Path, Step, ext::Extension, prune};
+///
+/// let mut path = Path::new("p1", None, "s2");
+/// path.steps = vec![
+///     Step::new("s1", "human:alex", "2026-01-01T10:00:00Z"),
+///     Step::new("s1a", "agent:claude", "2026-01-01T10:01:00Z").with_parent("s1"),
+///     Step::new("s1b", "agent:claude", "2026-01-01T10:01:30Z").with_parent("s1a"),
+///     Step::new("s2", "human:alex", "2026-01-01T10:02:00Z").with_parent("s1"),
+/// ];
+///
+/// let archived = prune::archive(&mut path, &Default::default(), "dead-ends.json");
+/// assert_eq!(archived[0].path.id, "p1-s1a");
+/// assert_eq!(archived[0].steps.len(), 2);
+///
+/// // s1a is now a stub; s1b lives only in the archive.
+/// assert_eq!(path.steps.len(), 3);
+/// let stub = path.steps.iter().find(|s| s.step.id == "s1a").unwrap();
+/// assert_eq!(stub.meta.as_ref().unwrap().refs[0].href, "dead-ends.json#p1-s1a");
+/// ```
+pub fn archive(path: &mut Path, config: &PruneConfig, archive_url: &str) -> Vec<Path> {
+    let branches: Vec<DeadBranch> = dead_branches(path)
+        .into_iter()
+        .filter(|b| matches(path, b, config))
+        .collect();
+
+    let mut archived = Vec::new();
+    let mut stubs: HashMap<String, Step> = HashMap::new();
+    let mut moved: HashSet<String> = HashSet::new();
+    for branch in branches {
+        let ids: HashSet<&str> = branch.steps.iter().map(String::as_str).collect();
+        let steps: Vec<Step> = path
+            .steps
+            .iter()
+            .filter(|s| ids.contains(s.step.id.as_str()))
+            .cloned()
+            .collect();
+        let archive_id = format!("{}-{}", path.path.id, branch.root);
+        let head = steps
+            .iter()
+            .max_by(|a, b| compare_timestamps(&a.step.timestamp, &b.step.timestamp))
+            .map(|s| s.step.id.clone())
+            .unwrap_or_else(|| branch.root.clone());
+
+        // A branch joined from several forks has a root for each; every one
+        // is stubbed so no fork is left with a dangling child.
+        let roots: Vec<&Step> = steps
+            .iter()
+            .filter(|s| {
+                s.step.id == branch.root || !s.step.parents.iter().any(|p| ids.contains(p.as_str()))
+            })
+            .collect();
+        for root in roots {
+            let mut stub = Step::new(
+                root.step.id.clone(),
+                root.step.actor.clone(),
+                root.step.timestamp.clone(),
+            )
+            .with_intent(format!("Archived {} dead-end step(s)", steps.len()));
+            stub.step.parents = root.step.parents.clone();
+            let meta = stub.meta.get_or_insert_with(StepMeta::default);
+            meta.refs.push(Ref {
+                rel: "archived".to_string(),
+                href: format!("{}#{}", archive_url, archive_id),
+            });
+            // Serializing a plain struct of strings cannot fail.
+            let _ = meta.set_ext(Archived {
+                path: archive_id.clone(),
+                steps: steps.len(),
+            });
+            stubs.insert(root.step.id.clone(), stub);
+        }
+
+        moved.extend(branch.steps.iter().cloned());
+        archived.push(Path {
+            version: None,
+            path: PathIdentity {
+                id: archive_id,
+                base: branch
+                    .fork
+                    .as_ref()
+                    .map(|fork| Base::toolpath(path.path.id.clone(), fork.clone())),
+                head,
+            },
+            steps,
+            meta: None,
+        });
+    }
+
+    path.steps = std::mem::take(&mut path.steps)
+        .into_iter()
+        .filter_map(|s| match stubs.remove(&s.step.id) {
+            Some(stub) => Some(stub),
+            None if moved.contains(&s.step.id) => None,
+            None => Some(s),
+        })
+        .collect();
+    archived
+}
+
+/// Order two timestamps by the instant they denote, so offsets other than
+/// `Z` compare correctly; as strings when either isn't RFC 3339.
+fn compare_timestamps(a: &str, b: &str) -> Ordering {
+    match (
+        DateTime::parse_from_rfc3339(a),
+        DateTime::parse_from_rfc3339(b),
+    ) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+fn is_stub(step: &Step) -> bool {
+    step.meta
+        .as_ref()
+        .is_some_and(|m| m.extra.contains_key(Archived::KEY))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// s1 → s2 → s3 (head). s1 → a1 → a2 is an old agent branch;
+    /// s2 → h1 is a recent human branch.
+    fn sample_path() -> Path {
+        let mut path = Path::new("p1", None, "s3");
+        path.steps = vec![
+            Step::new("s1", "human:alex", "2026-01-01T10:00:00Z"),
+            Step::new("a1", "agent:claude", "2026-01-01T10:01:00Z").with_parent("s1"),
+            Step::new("a2", "agent:claude", "2026-01-01T10:02:00Z").with_parent("a1"),
+            Step::new("s2", "human:alex", "2026-01-02T10:00:00Z").with_parent("s1"),
+            Step::new("h1", "human:bob", "2026-03-01T10:00:00Z").with_parent("s2"),
+            Step::new("s3", "human:alex", "2026-03-02T10:00:00Z").with_parent("s2"),
+        ];
+        path
+    }
+
+    fn ids(steps: &[Step]) -> Vec<&str> {
+        steps.iter().map(|s| s.step.id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_dead_branches() {
+        let branches = dead_branches(&sample_path());
+        assert_eq!(branches.len(), 2);
+        assert_eq!(
+            branches[0],
+            DeadBranch {
+                root: "a1".into(),
+                fork: Some("s1".into()),
+                steps: vec!["a1".into(), "a2".into()],
+                newest: "2026-01-01T10:02:00Z".into(),
+            }
+        );
+        assert_eq!(branches[1].root, "h1");
+        assert_eq!(branches[1].fork.as_deref(), Some("s2"));
+    }
+
+    #[test]
+    fn test_dead_branches_joins_merged_subtrees() {
+        let mut path = sample_path();
+        path.steps.push(
+            Step::new("m1", "human:bob", "2026-03-03T10:00:00Z")
+                .with_parent("a2")
+                .with_parent("h1"),
+        );
+        let branches = dead_branches(&path);
+        assert_eq!(branches.len(), 1);
+        assert_eq!(branches[0].root, "a1");
+        assert_eq!(branches[0].steps, vec!["a1", "a2", "h1", "m1"]);
+    }
+
+    #[test]
+    fn test_prune_older_than() {
+        let mut path = sample_path();
+        let config = PruneConfig {
+            older_than: Some("2026-02-01T00:00:00Z".into()),
+            ..Default::default()
+        };
+        let removed = prune(&mut path, &config);
+        assert_eq!(ids(&removed), vec!["a1", "a2"]);
+        assert_eq!(ids(&path.steps), vec!["s1", "s2", "h1", "s3"]);
+    }
+
+    #[test]
+    fn test_prune_older_than_compares_instants() {
+        let mut path = sample_path();
+        // 11:30 at +02:00 is 09:30Z, before a2 at 10:02Z.
+        let config = PruneConfig {
+            older_than: Some("2026-01-01T11:30:00+02:00".into()),
+            ..Default::default()
+        };
+        assert!(prune(&mut path, &config).is_empty());
+
+        // a2 moves to 09:02Z, so a1 at 10:01Z is the branch's newest step.
+        path.steps[2].step.timestamp = "2026-01-01T11:02:00+02:00".into();
+        let config = PruneConfig {
+            older_than: Some("2026-01-01T10:01:30Z".into()),
+            ..Default::default()
+        };
+        assert_eq!(ids(&prune(&mut path, &config)), vec!["a1", "a2"]);
+    }
+
+    #[test]
+    fn test_prune_smaller_than() {
+        let mut path = sample_path();
+        let config = PruneConfig {
+            smaller_than: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(ids(&prune(&mut path, &config)), vec!["h1"]);
+    }
+
+    #[test]
+    fn test_prune_actor_kind() {
+        let mut path = sample_path();
+        let config = PruneConfig {
+            actor_kind: Some("human".into()),
+            ..Default::default()
+        };
+        assert_eq!(ids(&prune(&mut path, &config)), vec!["h1"]);
+    }
+
+    #[test]
+    fn test_prune_criteria_combine() {
+        let mut path = sample_path();
+        let config = PruneConfig {
+            older_than: Some("2026-02-01T00:00:00Z".into()),
+            actor_kind: Some("human".into()),
+            ..Default::default()
+        };
+        assert!(prune(&mut path, &config).is_empty());
+        assert_eq!(path.steps.len(), 6);
+    }
+
+    #[test]
+    fn test_prune_never_touches_active_steps() {
+        let mut path = sample_path();
+        prune(&mut path, &PruneConfig::default());
+        assert_eq!(ids(&path.steps), vec!["s1", "s2", "s3"]);
+    }
+
+    #[test]
+    fn test_archive() {
+        let mut path = sample_path();
+        let config = PruneConfig {
+            older_than: Some("2026-02-01T00:00:00Z".into()),
+            ..Default::default()
+        };
+        let archived = archive(&mut path, &config, "dead-ends.json");
+        assert_eq!(archived.len(), 1);
+
+        let arch = &archived[0];
+        assert_eq!(arch.path.id, "p1-a1");
+        assert_eq!(arch.path.head, "a2");
+        assert_eq!(arch.path.base.as_ref().unwrap().uri, "toolpath:p1/s1");
+        assert_eq!(ids(&arch.steps), vec!["a1", "a2"]);
+
+        assert_eq!(ids(&path.steps), vec!["s1", "a1", "s2", "h1", "s3"]);
+        let stub = &path.steps[1];
+        assert_eq!(stub.step.parents, vec!["s1"]);
+        assert!(stub.change.is_empty());
+        let meta = stub.meta.as_ref().unwrap();
+        assert_eq!(meta.refs[0].rel, "archived");
+        assert_eq!(meta.refs[0].href, "dead-ends.json#p1-a1");
+        assert_eq!(
+            meta.get_ext::<Archived>().unwrap(),
+            Some(Archived {
+                path: "p1-a1".into(),
+                steps: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn test_archive_child_listed_before_root() {
+        let mut path = sample_path();
+        path.steps.swap(1, 2);
+        let branches = dead_branches(&path);
+        assert_eq!(branches[0].root, "a1");
+        assert_eq!(branches[0].steps, vec!["a2", "a1"]);
+
+        let archived = archive(&mut path, &PruneConfig::default(), "dead-ends.json");
+        assert_eq!(archived[0].path.id, "p1-a1");
+        assert_eq!(ids(&path.steps), vec!["s1", "a1", "s2", "h1", "s3"]);
+        assert_eq!(path.steps[1].step.parents, vec!["s1"]);
+    }
+
+    #[test]
+    fn test_archive_stubs_every_root() {
+        let mut path = Path::new("p1", None, "s2");
+        path.steps = vec![
+            Step::new("s1", "human:alex", "2026-01-01T10:00:00Z"),
+            Step::new("s2", "human:alex", "2026-01-01T10:01:00Z").with_parent("s1"),
+            Step::new("a1", "agent:claude", "2026-01-01T10:02:00Z").with_parent("s1"),
+            Step::new("b1", "agent:claude", "2026-01-01T10:03:00Z").with_parent("s2"),
+            Step::new("m1", "agent:claude", "2026-01-01T10:04:00Z")
+                .with_parent("a1")
+                .with_parent("b1"),
+        ];
+
+        let archived = archive(&mut path, &PruneConfig::default(), "dead-ends.json");
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].steps.len(), 3);
+        assert_eq!(ids(&path.steps), vec!["s1", "s2", "a1", "b1"]);
+        for stub in &path.steps[2..] {
+            assert!(is_stub(stub));
+            assert_eq!(
+                stub.meta.as_ref().unwrap().refs[0].href,
+                "dead-ends.json#p1-a1"
+            );
+        }
+        assert_eq!(path.steps[2].step.parents, vec!["s1"]);
+        assert_eq!(path.steps[3].step.parents, vec!["s2"]);
+    }
+
+    #[test]
+    fn test_stubs_are_not_archived_again() {
+        let mut path = sample_path();
+        archive(&mut path, &PruneConfig::default(), "a.json");
+        assert!(dead_branches(&path).is_empty());
+        assert!(archive(&mut path, &PruneConfig::default(), "b.json").is_empty());
+        assert_eq!(path.steps.len(), 5);
+    }
+}
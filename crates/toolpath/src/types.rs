@@ -1,6 +1,11 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// The current Toolpath format version, written to the `version` field of
+/// the outermost [`Graph`], [`Path`] or [`Step`] by
+/// [`Document::stamp_version`].
+pub const FORMAT_VERSION: &str = "1.0.0";
+
 /// A Toolpath document — either a [`Step`], [`Path`], or [`Graph`].
 ///
 /// `Document` is externally tagged: the top-level JSON object has a single key
@@ -67,6 +72,11 @@ pub enum Document {
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Graph {
+    /// Format version of this document (see [`FORMAT_VERSION`]). Only
+    /// meaningful on the outermost object; absent in documents written
+    /// before versioning was introduced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
     pub graph: GraphIdentity,
     pub paths: Vec<PathOrRef>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -144,6 +154,11 @@ pub struct PathRef {
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Path {
+    /// Format version of this document (see [`FORMAT_VERSION`]). Only
+    /// meaningful on the outermost object; absent in documents written
+    /// before versioning was introduced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
     pub path: PathIdentity,
     pub steps: Vec<Step>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -231,6 +246,11 @@ pub struct PathMeta {
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Step {
+    /// Format version of this document (see [`FORMAT_VERSION`]). Only
+    /// meaningful on the outermost object; absent in documents written
+    /// before versioning was introduced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
     pub step: StepIdentity,
     pub change: HashMap<String, ArtifactChange>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -388,12 +408,32 @@ impl Document {
     pub fn to_json_pretty(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string_pretty(self)
     }
+
+    /// The format version declared by the outermost object, if any
+    pub fn version(&self) -> Option<&str> {
+        match self {
+            Document::Graph(g) => g.version.as_deref(),
+            Document::Path(p) => p.version.as_deref(),
+            Document::Step(s) => s.version.as_deref(),
+        }
+    }
+
+    /// Mark the document as written in the current [`FORMAT_VERSION`]
+    pub fn stamp_version(&mut self) {
+        let version = Some(FORMAT_VERSION.to_string());
+        match self {
+            Document::Graph(g) => g.version = version,
+            Document::Path(p) => p.version = version,
+            Document::Step(s) => s.version = version,
+        }
+    }
 }
 
 impl Graph {
     /// Create a new graph with the given ID
     pub fn new(id: impl Into<String>) -> Self {
         Self {
+            version: None,
             graph: GraphIdentity { id: id.into() },
             paths: Vec::new(),
             meta: None,
@@ -405,6 +445,7 @@ impl Path {
     /// Create a new path with the given ID, base, and head
     pub fn new(id: impl Into<String>, base: Option<Base>, head: impl Into<String>) -> Self {
         Self {
+            version: None,
             path: PathIdentity {
                 id: id.into(),
                 base,
@@ -442,6 +483,7 @@ impl Step {
         timestamp: impl Into<String>,
     ) -> Self {
         Self {
+            version: None,
             step: StepIdentity {
                 id: id.into(),
                 parents: Vec::new(),
@@ -562,6 +604,7 @@ mod tests {
         let step =
             Step::new("s1", "human:alex", "2026-01-01T00:00:00Z").with_raw_change("f.rs", "@@");
         let path = Path {
+            version: None,
             path: PathIdentity {
                 id: "p1".into(),
                 base: Some(Base::vcs("github:org/repo", "abc")),
@@ -605,6 +648,18 @@ mod tests {
         assert!(json.contains("\"Step\""));
     }
 
+    #[test]
+    fn test_document_stamp_version() {
+        let mut doc = Document::Path(Path::new("p1", None, "s1"));
+        assert_eq!(doc.version(), None);
+        doc.stamp_version();
+        assert_eq!(doc.version(), Some(FORMAT_VERSION));
+        let json = doc.to_json().unwrap();
+        assert!(json.starts_with(r#"{"Path":{"version":"1.0.0","#));
+        let parsed = Document::from_json(&json).unwrap();
+        assert_eq!(parsed.version(), Some(FORMAT_VERSION));
+    }
+
     #[test]
     fn test_document_from_json_invalid() {
         let result = Document::from_json("not json");
@@ -725,6 +780,7 @@ mod tests {
     #[test]
     fn test_graph_meta_default_skips_empty() {
         let g = Graph {
+            version: None,
             graph: GraphIdentity { id: "g1".into() },
             paths: vec![],
             meta: Some(GraphMeta::default()),
@@ -738,6 +794,7 @@ mod tests {
     #[test]
     fn test_step_meta_with_refs() {
         let step = Step {
+            version: None,
             step: StepIdentity {
                 id: "s1".into(),
                 parents: vec![],